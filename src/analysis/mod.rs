@@ -1 +1,3 @@
-pub mod topology;
\ No newline at end of file
+pub mod relaxation;
+pub mod report;
+pub mod topology;
@@ -0,0 +1,316 @@
+use crate::core::structure::{AtomProperty, Crystal, PROP_LAYER};
+use crate::io::poscar::species_order;
+use anyhow::{bail, Result};
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Options for `analyze_relaxation`.
+#[derive(Debug, Clone)]
+pub struct RelaxationOptions {
+    /// Interplanar spacing of the slab's planes (Å). When `None`, the mean ideal spacing
+    /// between consecutive layers is used.
+    pub d_hkl: Option<f64>,
+    /// Ideal heights closer than this (Å) belong to the same atomic plane.
+    pub plane_tolerance: f64,
+}
+
+impl Default for RelaxationOptions {
+    fn default() -> Self {
+        Self { d_hkl: None, plane_tolerance: 0.1 }
+    }
+}
+
+/// Mean motion of one slab layer along the normal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerRelaxation {
+    pub layer: usize,
+    pub n_atoms: usize,
+    /// Mean height along the normal before and after relaxation (Å).
+    pub ideal_height: f64,
+    pub relaxed_height: f64,
+    /// Mean and largest (absolute) displacement along the normal (Å).
+    pub mean_dz: f64,
+    pub max_abs_dz: f64,
+}
+
+/// Distance between the mean heights of two consecutive layers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlayerSpacing {
+    pub lower: usize,
+    pub upper: usize,
+    pub ideal: f64,
+    pub relaxed: f64,
+    /// Change in percent of `d_hkl`.
+    pub change_percent: f64,
+}
+
+/// Atoms that were coplanar in the ideal slab.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaneRelaxation {
+    pub layer: usize,
+    /// Elements in the plane, e.g. "Mg O".
+    pub elements: String,
+    pub n_atoms: usize,
+    pub ideal_height: f64,
+    pub relaxed_height: f64,
+    /// Spread of the relaxed heights (max - min, Å).
+    pub rumpling: f64,
+}
+
+/// Comparison of a relaxed slab with the ideal slab it was started from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelaxationReport {
+    pub n_atoms: usize,
+    pub d_hkl: f64,
+    /// Root-mean-square and largest displacement over all atoms (Å).
+    pub rms_displacement: f64,
+    pub max_displacement: f64,
+    /// Index (in the ideal slab) of the atom that moved most.
+    pub max_displacement_atom: usize,
+    pub layers: Vec<LayerRelaxation>,
+    pub spacings: Vec<InterlayerSpacing>,
+    pub planes: Vec<PlaneRelaxation>,
+}
+
+/// Position in `ideal` of each atom of `relaxed`: the same order, or the species-grouped order
+/// of a POSCAR written from `ideal`.
+pub fn match_atoms(ideal: &Crystal, relaxed: &Crystal) -> Result<Vec<usize>> {
+    if ideal.atoms.len() != relaxed.atoms.len() {
+        bail!("The relaxed structure has {} atoms, the ideal slab {}", relaxed.atoms.len(), ideal.atoms.len());
+    }
+    let same_elements = |order: &[usize]| order.iter().zip(&relaxed.atoms).all(|(&i, a)| ideal.atoms[i].element == a.element);
+
+    let identity: Vec<usize> = (0..ideal.atoms.len()).collect();
+    if same_elements(&identity) {
+        return Ok(identity);
+    }
+    let grouped = species_order(&ideal.atoms);
+    if same_elements(&grouped) {
+        return Ok(grouped);
+    }
+    let k = identity.iter().position(|&i| ideal.atoms[i].element != relaxed.atoms[i].element).unwrap_or(0);
+    bail!(
+        "Atoms do not match by index: atom {} is {} in the ideal slab but {} in the relaxed structure",
+        k + 1, ideal.atoms[k].element, relaxed.atoms[k].element
+    )
+}
+
+/// Compares a relaxed slab with the ideal one, atom by atom (see `match_atoms`).
+///
+/// Heights are measured along the normal of each cell's a-b plane, so the two files may use
+/// different Cartesian orientations; displacements are fractional shifts in the ideal cell,
+/// taking the nearest image so that atoms that crossed a cell boundary are handled.
+///
+/// Layers are taken from the `layer` property written by the generator; without it, atoms are
+/// binned into `d_hkl` thick layers from the bottom of the slab, or each plane is its own layer
+/// if `d_hkl` is unknown.
+pub fn analyze_relaxation(ideal: &Crystal, relaxed: &Crystal, options: &RelaxationOptions) -> Result<RelaxationReport> {
+    let order = match_atoms(ideal, relaxed)?;
+    let n = ideal.atoms.len();
+    if n == 0 {
+        bail!("The ideal slab has no atoms.");
+    }
+
+    let ideal_normal = surface_normal(&ideal.lattice.matrix);
+    let relaxed_normal = surface_normal(&relaxed.lattice.matrix);
+
+    // 1. Per-atom heights and displacements, indexed like `ideal`
+    let mut ideal_z = vec![0.0; n];
+    let mut relaxed_z = vec![0.0; n];
+    let mut displacement = vec![0.0; n];
+    for (atom, &i) in relaxed.atoms.iter().zip(&order) {
+        let f_ideal = ideal.atoms[i].fractional_coords;
+        let f_relaxed = atom.fractional_coords - (atom.fractional_coords - f_ideal).map(f64::round);
+        ideal_z[i] = ideal.lattice.to_cartesian(&f_ideal).dot(&ideal_normal);
+        relaxed_z[i] = relaxed.lattice.to_cartesian(&f_relaxed).dot(&relaxed_normal);
+        displacement[i] = ideal.lattice.to_cartesian(&(f_relaxed - f_ideal)).norm();
+    }
+
+    // 2. Planes: ideal heights within the tolerance
+    let mut by_height: Vec<usize> = (0..n).collect();
+    by_height.sort_by(|&i, &j| ideal_z[i].total_cmp(&ideal_z[j]));
+    let mut plane_of = vec![0usize; n];
+    let mut n_planes = 0;
+    for (k, &i) in by_height.iter().enumerate() {
+        if k > 0 && ideal_z[i] - ideal_z[by_height[k - 1]] > options.plane_tolerance {
+            n_planes += 1;
+        }
+        plane_of[i] = n_planes;
+    }
+    let n_planes = n_planes + 1;
+
+    // 3. Layers
+    let stored: Option<Vec<usize>> = ideal
+        .atoms
+        .iter()
+        .map(|a| a.property(PROP_LAYER).and_then(AtomProperty::as_i64).map(|l| l.max(0) as usize))
+        .collect();
+    let bottom = ideal_z.iter().copied().fold(f64::INFINITY, f64::min);
+    let layer_of: Vec<usize> = match (stored, options.d_hkl) {
+        (Some(layers), _) => layers,
+        (None, Some(d)) => ideal_z.iter().map(|z| ((z - bottom) / d + 1e-6).floor() as usize).collect(),
+        (None, None) => plane_of.clone(),
+    };
+    let n_layers = layer_of.iter().max().map_or(0, |m| m + 1);
+
+    let mean = |members: &[usize], values: &[f64]| members.iter().map(|&i| values[i]).sum::<f64>() / members.len() as f64;
+
+    let mut layers = Vec::new();
+    for layer in 0..n_layers {
+        let members: Vec<usize> = (0..n).filter(|&i| layer_of[i] == layer).collect();
+        if members.is_empty() {
+            continue;
+        }
+        let dz: Vec<f64> = members.iter().map(|&i| relaxed_z[i] - ideal_z[i]).collect();
+        layers.push(LayerRelaxation {
+            layer,
+            n_atoms: members.len(),
+            ideal_height: mean(&members, &ideal_z),
+            relaxed_height: mean(&members, &relaxed_z),
+            mean_dz: dz.iter().sum::<f64>() / dz.len() as f64,
+            max_abs_dz: dz.iter().fold(0.0, |m: f64, v| m.max(v.abs())),
+        });
+    }
+
+    let ideal_spacings: Vec<f64> = layers.windows(2).map(|w| w[1].ideal_height - w[0].ideal_height).collect();
+    let d_hkl = match options.d_hkl {
+        Some(d) => d,
+        None if !ideal_spacings.is_empty() => ideal_spacings.iter().sum::<f64>() / ideal_spacings.len() as f64,
+        None => 0.0,
+    };
+    let spacings = layers
+        .windows(2)
+        .map(|w| {
+            let ideal = w[1].ideal_height - w[0].ideal_height;
+            let relaxed = w[1].relaxed_height - w[0].relaxed_height;
+            InterlayerSpacing {
+                lower: w[0].layer,
+                upper: w[1].layer,
+                ideal,
+                relaxed,
+                change_percent: if d_hkl > 0.0 { 100.0 * (relaxed - ideal) / d_hkl } else { 0.0 },
+            }
+        })
+        .collect();
+
+    let mut planes = Vec::with_capacity(n_planes);
+    for plane in 0..n_planes {
+        let members: Vec<usize> = (0..n).filter(|&i| plane_of[i] == plane).collect();
+        let mut elements: Vec<&str> = members.iter().map(|&i| ideal.atoms[i].element.as_str()).collect();
+        elements.sort_unstable();
+        elements.dedup();
+        let heights = members.iter().map(|&i| relaxed_z[i]);
+        let (low, high) = heights.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), z| (lo.min(z), hi.max(z)));
+        planes.push(PlaneRelaxation {
+            layer: layer_of[members[0]],
+            elements: elements.join(" "),
+            n_atoms: members.len(),
+            ideal_height: mean(&members, &ideal_z),
+            relaxed_height: mean(&members, &relaxed_z),
+            rumpling: high - low,
+        });
+    }
+
+    let (max_displacement_atom, max_displacement) = displacement
+        .iter()
+        .copied()
+        .enumerate()
+        .fold((0, 0.0), |best, (i, d)| if d > best.1 { (i, d) } else { best });
+
+    Ok(RelaxationReport {
+        n_atoms: n,
+        d_hkl,
+        rms_displacement: (displacement.iter().map(|d| d * d).sum::<f64>() / n as f64).sqrt(),
+        max_displacement,
+        max_displacement_atom,
+        layers,
+        spacings,
+        planes,
+    })
+}
+
+/// Unit normal of the a-b plane, on the side of c.
+fn surface_normal(matrix: &Matrix3<f64>) -> Vector3<f64> {
+    let normal: Vector3<f64> = matrix.column(0).cross(&matrix.column(1)).normalize();
+    if normal.dot(&matrix.column(2)) < 0.0 { -normal } else { normal }
+}
+
+impl fmt::Display for RelaxationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- Relaxation Report ---")?;
+        writeln!(f, "• Atoms:           {}", self.n_atoms)?;
+        writeln!(f, "• d_hkl:           {:.4} Å", self.d_hkl)?;
+        writeln!(f, "• RMS displacement: {:.4} Å", self.rms_displacement)?;
+        writeln!(f, "• Max displacement: {:.4} Å (atom {})", self.max_displacement, self.max_displacement_atom + 1)?;
+
+        writeln!(f, "\nLayer  Atoms   z ideal (Å)  z relaxed (Å)   mean Δz (Å)  max |Δz| (Å)")?;
+        for l in &self.layers {
+            writeln!(
+                f,
+                "{:>5} {:>6} {:>13.4} {:>14.4} {:>13.4} {:>13.4}",
+                l.layer, l.n_atoms, l.ideal_height, l.relaxed_height, l.mean_dz, l.max_abs_dz
+            )?;
+        }
+
+        writeln!(f, "\nLayers   d ideal (Å)  d relaxed (Å)  Δd (% of d_hkl)")?;
+        for s in &self.spacings {
+            writeln!(
+                f,
+                "{:>3}-{:<3} {:>12.4} {:>14.4} {:>16.2}",
+                s.lower, s.upper, s.ideal, s.relaxed, s.change_percent
+            )?;
+        }
+
+        writeln!(f, "\nLayer  Elements     Atoms   z ideal (Å)  z relaxed (Å)  rumpling (Å)")?;
+        for p in &self.planes {
+            writeln!(
+                f,
+                "{:>5}  {:<11} {:>6} {:>13.4} {:>14.4} {:>13.4}",
+                p.layer, p.elements, p.n_atoms, p.ideal_height, p.relaxed_height, p.rumpling
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType, Lattice};
+
+    #[test]
+    fn test_layers_spacings_and_rumpling() {
+        // Two rock-salt layers 2 Å apart in a 4 x 4 x 20 Å cell, written O-first by a POSCAR
+        let lattice = Lattice::new(Matrix3::from_diagonal(&Vector3::new(4.0, 4.0, 20.0))).unwrap();
+        let site = |element: &str, x: f64, z: f64, layer: i64| {
+            let mut atom = Atom::new(element, Vector3::new(x, x, z / 20.0), ComponentType::Unknown);
+            atom.set_property(PROP_LAYER, layer);
+            atom
+        };
+        let ideal = Crystal {
+            lattice: lattice.clone(),
+            atoms: vec![site("Mg", 0.0, 2.0, 0), site("O", 0.5, 2.0, 0), site("Mg", 0.5, 4.0, 1), site("O", 0.0, 4.0, 1)],
+        };
+        // Top layer: Mg moves down 0.1 Å, O moves up 0.1 Å; the bottom Mg crosses x = 0
+        let moved = |element: &str, x: f64, z: f64| Atom::new(element, Vector3::new(x, x, z / 20.0), ComponentType::Unknown);
+        let relaxed = Crystal {
+            lattice,
+            atoms: vec![moved("Mg", 0.97, 2.0), moved("Mg", 0.5, 3.9), moved("O", 0.5, 2.0), moved("O", 0.0, 4.1)],
+        };
+
+        let report = analyze_relaxation(&ideal, &relaxed, &RelaxationOptions { d_hkl: Some(2.0), ..Default::default() }).unwrap();
+        assert_eq!(report.layers.len(), 2);
+        assert!((report.layers[1].mean_dz).abs() < 1e-9);
+        assert!((report.layers[1].max_abs_dz - 0.1).abs() < 1e-9);
+        assert!(report.spacings[0].change_percent.abs() < 1e-9);
+        assert_eq!(report.planes[1].elements, "Mg O");
+        assert!((report.planes[1].rumpling - 0.2).abs() < 1e-9);
+        assert!((report.max_displacement - 0.12 * 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(report.max_displacement_atom, 0);
+
+        let mut mismatched = ideal.clone();
+        mismatched.atoms[0].element = "O".to_string();
+        assert!(match_atoms(&ideal, &mismatched).is_err());
+    }
+}
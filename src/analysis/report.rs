@@ -0,0 +1,68 @@
+use crate::analysis::topology::SafeCut;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Outcome of one pipeline phase ("Chemistry", "Topology", "Physics").
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseReport {
+    pub name: String,
+    pub message: String,
+}
+
+/// Structured summary of a `generate_surface` run. `Display` gives the text report printed by
+/// the CLI; serde gives the JSON written by `--report-json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SurfaceReport {
+    pub miller_indices: [i32; 3],
+    /// Interplanar spacing (Å).
+    pub d_hkl: f64,
+    /// Requested material thickness (Å).
+    pub requested_thickness: f64,
+    pub n_layers: usize,
+    /// Material thickness after rounding to whole layers (Å).
+    pub material_thickness: f64,
+    /// Vacuum (Å).
+    pub vacuum: f64,
+    /// Cut offset along the normal (Å).
+    pub cut_offset: f64,
+    /// Candidate cuts ranked by the void analysis, best first (empty if the offset was given).
+    pub safe_cuts: Vec<SafeCut>,
+    pub n_atoms: usize,
+    pub phases: Vec<PhaseReport>,
+    pub warnings: Vec<String>,
+}
+
+impl SurfaceReport {
+    /// Records the outcome of a phase.
+    pub fn add_phase(&mut self, name: &str, message: impl Into<String>) {
+        self.phases.push(PhaseReport { name: name.to_string(), message: message.into() });
+    }
+
+    /// Message of the named phase, if it ran.
+    pub fn phase(&self, name: &str) -> Option<&str> {
+        self.phases.iter().find(|p| p.name == name).map(|p| p.message.as_str())
+    }
+}
+
+impl fmt::Display for SurfaceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for warning in &self.warnings {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+        if let Some(chemistry) = self.phase("Chemistry") {
+            writeln!(f, "--- Chemistry ---\n{}", chemistry)?;
+        }
+        let [h, k, l] = self.miller_indices;
+        writeln!(f, "\n--- Surface Generation Report ---")?;
+        writeln!(f, "• Plane:           ({} {} {})", h, k, l)?;
+        writeln!(f, "• Interplanar Spacing: {:.4} Å", self.d_hkl)?;
+        writeln!(f, "• Quantization:    Requested {:.2} Å → {} Full Layers", self.requested_thickness, self.n_layers)?;
+        writeln!(f, "• Final Thickness: {:.4} Å (Material) + {:.2} Å (Vacuum)", self.material_thickness, self.vacuum)?;
+        writeln!(f, "• Cut Offset:      {:.4} Å", self.cut_offset)?;
+        if let Some(supercell) = self.phase("Supercell") {
+            writeln!(f, "• Supercell:       {}", supercell)?;
+        }
+        writeln!(f, "• Atoms:           {}", self.n_atoms)?;
+        write!(f, "• Physics:         {}", self.phase("Physics").unwrap_or("not run"))
+    }
+}
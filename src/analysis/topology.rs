@@ -1,9 +1,13 @@
-use crate::core::structure::{Atom, Crystal};
+use crate::core::elements;
+use crate::core::structure::Crystal;
 use nalgebra::Vector3;
-use std::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+/// Van der Waals radius (Å) assumed for elements without a tabulated one.
+const DEFAULT_VDW_RADIUS: f64 = 1.80;
 
 /// Represents a potential slice plane through the crystal.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SafeCut {
     /// The Z-coordinate (along the surface normal) where the cut should occur.
     pub offset_z: f64,
@@ -11,14 +15,38 @@ pub struct SafeCut {
     pub gap_size: f64,
     /// A heuristic score (0.0 to 1.0) indicating how "clean" this cut is.
     pub quality_score: f64,
+    /// Planar-averaged electron density at the cut (e/Å³), when ranked by a density profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub density: Option<f64>,
+}
+
+/// Planar-averaged density along a surface normal, on a uniform periodic grid.
+#[derive(Debug, Clone)]
+pub struct DensityProfile {
+    /// Density (e/Å³) at `z = i * period / values.len()`.
+    pub values: Vec<f64>,
+    /// Repeat distance along the normal (Å).
+    pub period: f64,
+}
+
+impl DensityProfile {
+    /// Grid spacing along the normal (Å).
+    pub fn spacing(&self) -> f64 {
+        self.period / self.values.len() as f64
+    }
 }
 
+/// Density minima shallower than this fraction of the profile's range are treated as noise.
+const MIN_PROMINENCE: f64 = 0.05;
+
 /// A rigorous 1D density analyzer for determining optimal slab terminations.
 pub struct VoidCrawler {
     /// List of (center_z, radius) for every atom, projected onto the normal.
     projections: Vec<(f64, f64)>,
     /// The repeat distance along the normal direction.
     periodicity: f64,
+    /// Electron density along the normal; when set, cuts are ranked by its minima.
+    density: Option<DensityProfile>,
 }
 
 impl VoidCrawler {
@@ -68,14 +96,26 @@ impl VoidCrawler {
         Self {
             projections,
             periodicity,
+            density: None,
         }
     }
 
+    /// Ranks cuts by the minima of a planar-averaged electron density (e.g. from a CHGCAR)
+    /// instead of hard-sphere gaps. The profile must be sampled along the same normal.
+    pub fn with_density(mut self, profile: DensityProfile) -> Self {
+        self.density = Some(profile);
+        self
+    }
+
     /// Finds the largest gaps in the atomic density.
     /// Returns a list of safe offsets sorted by gap size (best/largest gap first).
+    /// With a density profile, returns its minima sorted by density (lowest first).
     pub fn find_safe_offsets(&self) -> Vec<SafeCut> {
+        if let Some(profile) = &self.density {
+            return Self::density_minima(profile);
+        }
         if self.projections.is_empty() {
-            return vec![SafeCut { offset_z: 0.0, gap_size: 10.0, quality_score: 1.0 }];
+            return vec![SafeCut { offset_z: 0.0, gap_size: 10.0, quality_score: 1.0, density: None }];
         }
 
         // 1. Merge Intervals (The "Sweep Line" Algorithm)
@@ -128,6 +168,7 @@ impl VoidCrawler {
                         offset_z: mid_point,
                         gap_size,
                         quality_score: score,
+                        density: None,
                     });
                 }
             }
@@ -139,30 +180,83 @@ impl VoidCrawler {
         cuts
     }
 
-    /// Returns the Van der Waals radius for a given element.
-    /// Data Source: Alvarez, S. (2013). Dalton Trans., 42, 8617-8636.
-    fn get_vdw_radius(element: &str) -> f64 {
-        match element {
-            // Period 1
-            "H" => 1.20, "He" => 1.40,
-            // Period 2
-            "Li" => 1.82, "Be" => 1.53, "B" => 1.92, "C" => 1.70, 
-            "N" => 1.55, "O" => 1.52, "F" => 1.47, "Ne" => 1.54,
-            // Period 3
-            "Na" => 2.27, "Mg" => 1.73, "Al" => 1.84, "Si" => 2.10, 
-            "P" => 1.80, "S" => 1.80, "Cl" => 1.75, "Ar" => 1.88,
-            // Period 4
-            "K" => 2.75, "Ca" => 2.31, "Sc" => 2.11, "Ti" => 2.00, "V" => 2.00, "Cr" => 2.00,
-            "Mn" => 2.00, "Fe" => 2.00, "Co" => 2.00, "Ni" => 1.63, "Cu" => 1.40, "Zn" => 1.39,
-            "Ga" => 1.87, "Ge" => 2.11, "As" => 1.85, "Se" => 1.90, "Br" => 1.85, "Kr" => 2.02,
-            // Period 5
-            "Rb" => 3.03, "Sr" => 2.49, "Pd" => 1.63, "Ag" => 1.72, "Cd" => 1.58,
-            "In" => 1.93, "Sn" => 2.17, "Sb" => 2.06, "Te" => 2.06, "I" => 1.98, "Xe" => 2.16,
-            // Period 6
-            "Cs" => 3.43, "Ba" => 2.68, "Pt" => 1.75, "Au" => 1.66, "Hg" => 1.55,
-            "Tl" => 1.96, "Pb" => 2.02, "Bi" => 2.07, "Po" => 1.97, "At" => 2.02, "Rn" => 2.20,
-            // Default fallback
-            _ => 1.80, 
+    /// Local minima of a periodic density profile, refined by a parabola through the three
+    /// nearest samples. `gap_size` is the width of the basin below the midpoint between the
+    /// minimum and the mean density; `quality_score` is `1 - ρ_min / ρ_mean`.
+    fn density_minima(profile: &DensityProfile) -> Vec<SafeCut> {
+        let values = &profile.values;
+        let n = values.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let spacing = profile.spacing();
+        let at = |i: isize| values[i.rem_euclid(n as isize) as usize];
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+
+        let mut cuts = Vec::new();
+        for i in 0..n as isize {
+            let (prev, value, next) = (at(i - 1), at(i), at(i + 1));
+            let is_minimum = if n < 3 { value == min } else { value < prev && value <= next };
+            if !is_minimum || Self::prominence(values, i as usize) < MIN_PROMINENCE * (max - min) {
+                continue;
+            }
+
+            // Parabolic refinement of the position and value
+            let curvature = prev - 2.0 * value + next;
+            let shift = if n >= 3 && curvature > 0.0 { (0.5 * (prev - next) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+            let density = value - 0.25 * (prev - next) * shift;
+
+            let threshold = density + 0.5 * (mean - density);
+            let mut width = 1;
+            for direction in [-1, 1] {
+                let mut step = 1;
+                while width < n && at(i + direction * step) < threshold {
+                    width += 1;
+                    step += 1;
+                }
+            }
+
+            cuts.push(SafeCut {
+                offset_z: ((i as f64 + shift) * spacing).rem_euclid(profile.period),
+                gap_size: width as f64 * spacing,
+                quality_score: if mean > 0.0 { (1.0 - density / mean).clamp(0.0, 1.0) } else { 0.0 },
+                density: Some(density),
+            });
         }
+
+        let density = |cut: &SafeCut| cut.density.unwrap_or(f64::INFINITY);
+        cuts.sort_by(|a, b| density(a).total_cmp(&density(b)).then(b.gap_size.total_cmp(&a.gap_size)));
+        cuts
+    }
+
+    /// Height of the lowest barrier separating sample `i` from a lower sample (or from itself,
+    /// for the global minimum), walking the periodic profile in both directions.
+    fn prominence(values: &[f64], i: usize) -> f64 {
+        let n = values.len();
+        let value = values[i];
+        let barrier = |direction: isize| {
+            let mut highest = value;
+            for step in 1..n as isize {
+                let v = values[(i as isize + direction * step).rem_euclid(n as isize) as usize];
+                if v < value {
+                    return Some(highest);
+                }
+                highest = highest.max(v);
+            }
+            None
+        };
+        match (barrier(-1), barrier(1)) {
+            (Some(left), Some(right)) => left.min(right) - value,
+            (Some(side), None) | (None, Some(side)) => side - value,
+            (None, None) => values.iter().copied().fold(value, f64::max) - value,
+        }
+    }
+
+    /// Van der Waals radius (Å) of an element, from the periodic table (Alvarez 2013);
+    /// `DEFAULT_VDW_RADIUS` when not tabulated.
+    fn get_vdw_radius(element: &str) -> f64 {
+        elements::element(element).and_then(|e| e.vdw_radius).unwrap_or(DEFAULT_VDW_RADIUS)
     }
 }
\ No newline at end of file
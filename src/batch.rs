@@ -0,0 +1,328 @@
+use crate::core::bonding::BondingRule;
+use crate::core::connectivity::MoleculeFinder;
+use crate::core::structure::{Crystal, Molecule};
+use crate::io::format::{FormatRegistry, StructureFrame};
+use crate::io::parser::{self, CifOptions};
+use crate::io::writer::{self, CifWriterOptions};
+use crate::{generate_surface_with_provenance, SurfaceConfig};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Cut offsets closer than this (Å) give the same termination.
+const SAME_OFFSET: f64 = 1e-3;
+
+/// Settings shared by every slab of a batch.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub miller_indices: Vec<[i32; 3]>,
+    pub thickness: f64,
+    pub vacuum: f64,
+    pub reconstruct: bool,
+    /// Number of terminations per plane, taken from the best-ranked safe cuts.
+    pub terminations: usize,
+    /// Which atom pairs are bonded (molecule detection and the CIF bond loops).
+    pub bonding: BondingRule,
+    /// How CIF inputs are read (disorder resolution, ...).
+    pub cif: CifOptions,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            miller_indices: vec![[1, 0, 0]],
+            thickness: 15.0,
+            vacuum: 15.0,
+            reconstruct: false,
+            terminations: 1,
+            bonding: BondingRule::default(),
+            cif: CifOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    Ok,
+    Error,
+}
+
+/// One row of the batch manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub input: String,
+    /// Structure name: file stem, plus the data block and disorder group where needed.
+    pub structure: String,
+    /// Miller indices as written in the output tree ("1-10"); empty if the input failed.
+    pub hkl: String,
+    /// Termination name ("t0" is the best-ranked cut); empty if no slab was made.
+    pub termination: String,
+    pub status: BatchStatus,
+    pub output: Option<String>,
+    pub n_atoms: Option<usize>,
+    pub cut_offset: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl ManifestEntry {
+    fn failure(input: &Path, structure: &str, hkl: &str, error: &anyhow::Error) -> Self {
+        Self {
+            input: input.display().to_string(),
+            structure: structure.to_string(),
+            hkl: hkl.to_string(),
+            termination: String::new(),
+            status: BatchStatus::Error,
+            output: None,
+            n_atoms: None,
+            cut_offset: None,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+}
+
+/// Parses Miller indices: "1 1 0", "1,-1,0" or the compact "110".
+pub fn parse_miller(text: &str) -> Result<[i32; 3]> {
+    let trimmed = text.trim();
+    let parts: Vec<&str> = if trimmed.len() == 3 && trimmed.chars().all(|c| c.is_ascii_digit()) {
+        (0..3).map(|i| &trimmed[i..i + 1]).collect()
+    } else {
+        trimmed.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()).collect()
+    };
+    let indices: Vec<i32> = parts
+        .iter()
+        .map(|p| p.parse::<i32>())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| anyhow!("Invalid Miller indices '{}': expected e.g. \"1 1 0\" or \"1,-1,0\"", text))?;
+    match indices[..] {
+        [h, k, l] if (h, k, l) != (0, 0, 0) => Ok([h, k, l]),
+        _ => bail!("Invalid Miller indices '{}': expected three integers, not all zero", text),
+    }
+}
+
+/// Directory name of a plane: "100", "1-10"; indices of two or more digits are separated, "10_0_1".
+pub fn miller_dir_name(miller: [i32; 3]) -> String {
+    let parts = miller.map(|i| i.to_string());
+    if miller.iter().all(|i| i.abs() < 10) {
+        parts.concat()
+    } else {
+        parts.join("_")
+    }
+}
+
+/// Keeps file names portable: anything but letters, digits, '-', '_' and '.' becomes '_'.
+fn sanitize(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' }).collect()
+}
+
+/// Expands the batch input: a directory (every file a reader recognises by name) or a glob.
+pub fn collect_inputs(pattern: &str, registry: &FormatRegistry) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let mut inputs = Vec::new();
+    if path.is_dir() {
+        for entry in fs::read_dir(path).with_context(|| format!("Could not read directory {:?}", path))? {
+            let file = entry?.path();
+            if file.is_file() && registry.detect_reader_by_name(&file).is_some() {
+                inputs.push(file);
+            }
+        }
+    } else {
+        for entry in glob::glob(pattern).with_context(|| format!("Invalid glob pattern '{}'", pattern))? {
+            let file = entry?;
+            if file.is_file() {
+                inputs.push(file);
+            }
+        }
+    }
+    inputs.sort();
+    if inputs.is_empty() {
+        bail!("No structure files match '{}'", pattern);
+    }
+    Ok(inputs)
+}
+
+/// The named structures of one input file. A bad data block of a multi-block CIF becomes an
+/// `Err` entry of its own, so the other blocks still run.
+fn read_structures(input: &Path, registry: &FormatRegistry, options: &BatchOptions) -> Result<Vec<(String, Result<Crystal>)>> {
+    let stem = sanitize(input.file_stem().and_then(|s| s.to_str()).unwrap_or("structure"));
+    let contents = fs::read_to_string(input).with_context(|| format!("Could not read structure file: {:?}", input))?;
+    let reader = registry.detect_reader(Some(input), &contents)?;
+
+    let blocks: Vec<(Option<String>, Result<Vec<StructureFrame>>)> = if reader.name() == "cif" {
+        parser::blocks_from_cif_str(&contents, &options.cif)?
+            .into_iter()
+            .map(|block| (Some(block.name), block.configurations.map(|c| c.into_iter().map(StructureFrame::from).collect())))
+            .collect()
+    } else {
+        vec![(None, reader.read_str(&contents))]
+    };
+
+    let multi_block = blocks.len() > 1;
+    let mut structures = Vec::new();
+    for (block, frames) in blocks {
+        let name = match (&block, multi_block) {
+            (Some(block), true) => format!("{}_{}", stem, sanitize(block)),
+            _ => stem.clone(),
+        };
+        match frames {
+            Err(e) => structures.push((name, Err(e))),
+            Ok(frames) => {
+                let split = frames.len() > 1;
+                for (index, frame) in frames.into_iter().enumerate() {
+                    let name = match (split, frame.disorder_group) {
+                        (false, _) => name.clone(),
+                        (true, Some(group)) => format!("{}_group{}", name, group),
+                        (true, None) => format!("{}_conf{}", name, index + 1),
+                    };
+                    structures.push((name, Ok(frame.crystal)));
+                }
+            }
+        }
+    }
+    Ok(structures)
+}
+
+/// Cuts one structure along one plane: up to `options.terminations` slabs.
+fn run_plane(
+    crystal: &Crystal,
+    molecules: &[Molecule],
+    input: &Path,
+    structure: &str,
+    miller: [i32; 3],
+    output_root: &Path,
+    options: &BatchOptions,
+) -> Result<Vec<ManifestEntry>> {
+    let hkl = miller_dir_name(miller);
+    let directory = output_root.join(structure).join(&hkl);
+    fs::create_dir_all(&directory).with_context(|| format!("Could not create {:?}", directory))?;
+
+    let mut config = SurfaceConfig {
+        miller_indices: miller,
+        thickness: options.thickness,
+        vacuum: options.vacuum,
+        reconstruct: options.reconstruct,
+        bonding: options.bonding.clone(),
+        input_cif_path: Some(input.to_path_buf()),
+        ..Default::default()
+    };
+
+    let mut entries = Vec::new();
+    let mut offsets: Vec<f64> = Vec::new();
+    let mut candidates = Vec::new();
+    for rank in 0..options.terminations.max(1) {
+        // The first slab ranks the cuts; the others reuse that ranking
+        if rank > 0 {
+            let next = candidates.iter().copied().find(|&z: &f64| offsets.iter().all(|o| (o - z).abs() > SAME_OFFSET));
+            let Some(offset) = next else { break };
+            config.offset = Some(offset);
+        }
+        let generated = generate_surface_with_provenance(&mut crystal.clone(), molecules, &config)?;
+        if rank == 0 {
+            candidates = generated.report.safe_cuts.iter().map(|c| c.offset_z).collect();
+        }
+        offsets.push(generated.report.cut_offset);
+
+        let termination = format!("t{}", rank);
+        let output = directory.join(format!("{}.cif", termination));
+        let writer_options = CifWriterOptions { bonding: Some(options.bonding.clone()), provenance: Some(generated.provenance) };
+        writer::to_cif_with_options(&generated.slab, &output, &writer_options)?;
+
+        entries.push(ManifestEntry {
+            input: input.display().to_string(),
+            structure: structure.to_string(),
+            hkl: hkl.clone(),
+            termination,
+            status: BatchStatus::Ok,
+            output: Some(output.display().to_string()),
+            n_atoms: Some(generated.slab.atoms.len()),
+            cut_offset: Some(generated.report.cut_offset),
+            error: None,
+        });
+    }
+    Ok(entries)
+}
+
+/// Runs every input × plane and returns the manifest. Slabs go to
+/// `<output_root>/<structure>/<hkl>/<termination>.cif`. Failures are recorded, never returned,
+/// so one bad structure does not stop the batch; `on_entry` sees each entry as it is made.
+pub fn run_batch(
+    inputs: &[PathBuf],
+    output_root: &Path,
+    options: &BatchOptions,
+    registry: &FormatRegistry,
+    on_entry: &mut dyn FnMut(&ManifestEntry),
+) -> Vec<ManifestEntry> {
+    let mut manifest = Vec::new();
+    let mut record = |entry: ManifestEntry, manifest: &mut Vec<ManifestEntry>| {
+        on_entry(&entry);
+        manifest.push(entry);
+    };
+
+    for input in inputs {
+        let stem = sanitize(input.file_stem().and_then(|s| s.to_str()).unwrap_or("structure"));
+        let structures = match read_structures(input, registry, options) {
+            Ok(structures) => structures,
+            Err(e) => {
+                record(ManifestEntry::failure(input, &stem, "", &e), &mut manifest);
+                continue;
+            }
+        };
+        for (structure, crystal) in structures {
+            let finder = MoleculeFinder::with_rule(options.bonding.clone());
+            let (crystal, molecules) = match crystal.and_then(|c| finder.find_discrete_molecules(&c).map(|m| (c, m))) {
+                Ok(found) => found,
+                Err(e) => {
+                    record(ManifestEntry::failure(input, &structure, "", &e), &mut manifest);
+                    continue;
+                }
+            };
+            for &miller in &options.miller_indices {
+                match run_plane(&crystal, &molecules, input, &structure, miller, output_root, options) {
+                    Ok(entries) => entries.into_iter().for_each(|entry| record(entry, &mut manifest)),
+                    Err(e) => record(ManifestEntry::failure(input, &structure, &miller_dir_name(miller), &e), &mut manifest),
+                }
+            }
+        }
+    }
+    manifest
+}
+
+/// Writes the manifest as CSV (one row per entry, header included).
+pub fn write_manifest_csv(entries: &[ManifestEntry], path: &Path) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    for entry in entries {
+        writer.serialize(entry)?;
+    }
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes the manifest as a JSON array.
+pub fn write_manifest_json(entries: &[ManifestEntry], path: &Path) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, entries)?;
+    writeln!(writer)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_miller_parsing_and_names() {
+        assert_eq!(parse_miller("1 1 0").unwrap(), [1, 1, 0]);
+        assert_eq!(parse_miller("1,-1,0").unwrap(), [1, -1, 0]);
+        assert_eq!(parse_miller("111").unwrap(), [1, 1, 1]);
+        assert!(parse_miller("0 0 0").is_err());
+        assert!(parse_miller("1 1").is_err());
+
+        assert_eq!(miller_dir_name([1, -1, 0]), "1-10");
+        assert_eq!(miller_dir_name([10, 0, 1]), "10_0_1");
+        assert_eq!(sanitize("MOF 5/a"), "MOF_5_a");
+    }
+}
@@ -1,13 +1,16 @@
-use crate::core::structure::{Atom, Crystal, ComponentType, Lattice};
+use crate::core::neighbors::CellList;
+use crate::core::structure::{Crystal, ComponentType, Lattice};
 use mofid_rust::types::MofArtifacts;
 use std::fs;
 use std::path::Path;
 use glob::glob;
 use nalgebra::Vector3;
-use anyhow::{Result, Context, anyhow};
-use std::collections::HashMap;
+use anyhow::{Result, Context};
 use crate::io::parser;
 
+/// Fragment atoms closer than this to a bulk atom (Å) are the same site (loose to handle rounding).
+const MATCH_TOLERANCE: f64 = 0.5;
+
 /// Engine for mapping semantic identities (Node/Linker) onto geometric atoms.
 pub struct SemanticTagger;
 
@@ -25,9 +28,9 @@ impl SemanticTagger {
         let mut linker_count = 0;
 
         // 1. Build Spatial Index for Fast Lookup
-        // We create a map from (grid_i, grid_j, grid_k) -> Vec<atom_index>
+        // A periodic cell list over the bulk, so fragments unwrapped outside the cell still match.
         // This reduces matching from O(N*M) to O(M).
-        let spatial_index = SpatialIndex::new(crystal, 2.0); // 2.0 Angstrom cell size
+        let spatial_index = SpatialIndex::new(crystal, MATCH_TOLERANCE);
 
         // 2. Load and Tag Nodes (SBUs)
         //
@@ -41,15 +44,13 @@ impl SemanticTagger {
             .context("Invalid glob pattern for nodes")?
             .collect();
         if !node_xyz_paths.is_empty() {
-            for entry in node_xyz_paths {
-                if let Ok(path) = entry {
-                    let coords = match Self::parse_fragment_xyz(&path) {
-                        Ok(c) => c,
-                        Err(_) => continue,
-                    };
-                    if Self::apply_tag(crystal, &spatial_index, &coords, ComponentType::MetalNode) {
-                        node_count += 1;
-                    }
+            for path in node_xyz_paths.into_iter().flatten() {
+                let coords = match Self::parse_fragment_xyz(&path) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                if Self::apply_tag(crystal, &spatial_index, &coords, ComponentType::MetalNode) {
+                    node_count += 1;
                 }
             }
         } else {
@@ -73,15 +74,13 @@ impl SemanticTagger {
             .context("Invalid glob pattern for linkers")?
             .collect();
         if !linker_xyz_paths.is_empty() {
-            for entry in linker_xyz_paths {
-                if let Ok(path) = entry {
-                    let coords = match Self::parse_fragment_xyz(&path) {
-                        Ok(c) => c,
-                        Err(_) => continue,
-                    };
-                    if Self::apply_tag(crystal, &spatial_index, &coords, ComponentType::OrganicLinker) {
-                        linker_count += 1;
-                    }
+            for path in linker_xyz_paths.into_iter().flatten() {
+                let coords = match Self::parse_fragment_xyz(&path) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                if Self::apply_tag(crystal, &spatial_index, &coords, ComponentType::OrganicLinker) {
+                    linker_count += 1;
                 }
             }
         } else {
@@ -121,16 +120,11 @@ impl SemanticTagger {
         fragment_coords: &[Vector3<f64>], 
         tag: ComponentType
     ) -> bool {
-        let tolerance_sq = 0.5_f64.powi(2); // 0.5 Angstrom squared tolerance (loose to handle rounding)
         let mut matched_any = false;
 
         for frag_pos_cart in fragment_coords {
-            // Convert Fragment (Cartesian) -> Fractional for PBC check
-            let frag_pos_frac = crystal.lattice.to_fractional(frag_pos_cart);
-
-            // Query the spatial index for nearby bulk atoms
-            // We need to check the exact grid cell and neighbors because of edge cases
-            let candidates = index.query(frag_pos_cart);
+            // Query the spatial index for bulk atoms with an image within the tolerance
+            let candidates = index.query(frag_pos_cart, MATCH_TOLERANCE);
 
             for &atom_idx in &candidates {
                 let atom = &mut crystal.atoms[atom_idx];
@@ -141,17 +135,9 @@ impl SemanticTagger {
                     continue;
                 }
 
-                // Distance Check with PBC
-                let dist_vec = crystal.lattice.get_shortest_distance_vector(
-                    &atom.fractional_coords,
-                    &frag_pos_frac
-                );
-
-                if dist_vec.norm_squared() < tolerance_sq {
-                    atom.component_type = tag;
-                    matched_any = true;
-                    // We keep searching in case of overlaps, but practically we found our atom.
-                }
+                atom.component_type = tag;
+                matched_any = true;
+                // We keep searching in case of overlaps, but practically we found our atom.
             }
         }
         matched_any
@@ -200,47 +186,31 @@ impl SemanticTagger {
 // SPATIAL ACCELERATION
 // ============================================================================
 
-/// A simple spatial hash grid to accelerate atom lookups.
+/// Periodic lookup of bulk atoms near Cartesian positions.
 struct SpatialIndex {
-    cell_size: f64,
-    grid: HashMap<(i32, i32, i32), Vec<usize>>,
+    lattice: Lattice,
+    cells: CellList,
 }
 
 impl SpatialIndex {
-    fn new(crystal: &Crystal, cell_size: f64) -> Self {
-        let mut grid = HashMap::new();
-        for (i, atom) in crystal.atoms.iter().enumerate() {
-            let cart = crystal.lattice.to_cartesian(&atom.fractional_coords);
-            let key = Self::get_key(&cart, cell_size);
-            grid.entry(key).or_insert_with(Vec::new).push(i);
+    fn new(crystal: &Crystal, radius: f64) -> Self {
+        let positions: Vec<Vector3<f64>> = crystal.atoms.iter().map(|a| a.fractional_coords).collect();
+        Self {
+            lattice: crystal.lattice.clone(),
+            cells: CellList::new(&crystal.lattice, &positions, radius),
         }
-        Self { cell_size, grid }
     }
 
-    fn get_key(pos: &Vector3<f64>, cell_size: f64) -> (i32, i32, i32) {
-        (
-            (pos.x / cell_size).floor() as i32,
-            (pos.y / cell_size).floor() as i32,
-            (pos.z / cell_size).floor() as i32,
-        )
-    }
-
-    /// Returns candidate atom indices near the query position.
-    fn query(&self, pos: &Vector3<f64>) -> Vec<usize> {
-        let (cx, cy, cz) = Self::get_key(pos, self.cell_size);
-        let mut candidates = Vec::new();
-
-        // Check 3x3x3 neighborhood to account for boundary conditions
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                for dz in -1..=1 {
-                    let key = (cx + dx, cy + dy, cz + dz);
-                    if let Some(indices) = self.grid.get(&key) {
-                        candidates.extend_from_slice(indices);
-                    }
-                }
-            }
-        }
+    /// Returns the atoms with an image closer than `radius` to the query position.
+    fn query(&self, pos: &Vector3<f64>, radius: f64) -> Vec<usize> {
+        let mut candidates: Vec<usize> = self
+            .cells
+            .query(&self.lattice.to_fractional(pos), radius)
+            .iter()
+            .map(|n| n.index)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
         candidates
     }
-}
\ No newline at end of file
+}
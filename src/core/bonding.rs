@@ -0,0 +1,133 @@
+use crate::core::elements;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+
+/// Covalent radius (Å) assumed for elements without a tabulated one.
+const DEFAULT_RADIUS: f64 = 1.5;
+
+/// Decides which atom pairs are bonded: `d < r_i + r_j + tolerance`, with covalent radii.
+///
+/// Pairs involving a metal use `metal_tolerance`, a tighter margin since metal radii are already
+/// large. It does not separate the metals of a cluster: with the default 0.3 Å, Zr–Zr bonds up to
+/// 3.8 Å and Cu–Cu up to 2.94 Å, so the metals of a Zr6 node (≈3.5 Å) or a Cu paddlewheel (≈2.6 Å)
+/// are bonded to each other. `pair_cutoffs` replace the rule for specific element pairs (e.g.
+/// "Zr-Zr=3.0" to break the node's metal–metal bonds).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondingRule {
+    /// Added to the sum of covalent radii (Å).
+    pub tolerance: f64,
+    /// Added to the sum of covalent radii when either atom is a metal (Å).
+    pub metal_tolerance: f64,
+    /// Fixed cutoffs (Å) for element pairs, keyed in alphabetical order (see `with_pair_cutoff`).
+    pub pair_cutoffs: BTreeMap<(String, String), f64>,
+    /// Bond every other pair closer than this (Å) whatever the elements, instead of using radii.
+    pub uniform_cutoff: Option<f64>,
+}
+
+impl Default for BondingRule {
+    fn default() -> Self {
+        Self { tolerance: 0.4, metal_tolerance: 0.3, pair_cutoffs: BTreeMap::new(), uniform_cutoff: None }
+    }
+}
+
+impl BondingRule {
+    /// The element-blind rule: every pair closer than `cutoff` (Å) is bonded.
+    pub fn uniform(cutoff: f64) -> Self {
+        Self { uniform_cutoff: Some(cutoff), ..Self::default() }
+    }
+
+    /// Adds (or replaces) a fixed cutoff for one element pair, in either order and any letter case.
+    /// Fails if either symbol is not an element.
+    pub fn with_pair_cutoff(mut self, a: &str, b: &str, cutoff: f64) -> Result<Self> {
+        let symbol = |s: &str| elements::normalize_symbol(s).ok_or_else(|| anyhow!("Unknown element '{}' in a bond cutoff", s));
+        self.pair_cutoffs.insert(pair_key(symbol(a)?, symbol(b)?), cutoff);
+        Ok(self)
+    }
+
+    /// Longest distance (Å) at which two elements count as bonded.
+    pub fn cutoff(&self, a: &str, b: &str) -> f64 {
+        if !self.pair_cutoffs.is_empty() {
+            let symbol = |s| elements::normalize_symbol(s).unwrap_or(s);
+            if let Some(&cutoff) = self.pair_cutoffs.get(&pair_key(symbol(a), symbol(b))) {
+                return cutoff;
+            }
+        }
+        if let Some(cutoff) = self.uniform_cutoff {
+            return cutoff;
+        }
+        let radius = |e: &str| elements::covalent_radius(e).unwrap_or(DEFAULT_RADIUS);
+        let tolerance = if elements::is_metal(a) || elements::is_metal(b) { self.metal_tolerance } else { self.tolerance };
+        radius(a) + radius(b) + tolerance
+    }
+
+    pub fn is_bonded(&self, a: &str, b: &str, distance: f64) -> bool {
+        distance < self.cutoff(a, b)
+    }
+
+    /// Largest cutoff between any two of `elements` (the neighbour search radius).
+    pub fn max_cutoff(&self, elements: &[&str]) -> f64 {
+        let mut distinct: Vec<&str> = elements.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+        let mut max: f64 = 0.0;
+        for (i, a) in distinct.iter().enumerate() {
+            for b in &distinct[i..] {
+                max = max.max(self.cutoff(a, b));
+            }
+        }
+        max
+    }
+}
+
+fn pair_key(a: &str, b: &str) -> (String, String) {
+    if a <= b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+}
+
+/// Parses a pair cutoff "Zr-O=2.6" (symbols in any case) into `("Zr", "O", 2.6)`.
+pub fn parse_pair_cutoff(text: &str) -> Result<(String, String, f64)> {
+    let (pair, cutoff) = text.split_once('=').ok_or_else(|| anyhow!("Expected A-B=distance, got '{}'", text))?;
+    let (a, b) = pair.split_once('-').ok_or_else(|| anyhow!("Expected A-B=distance, got '{}'", text))?;
+    let (a, b) = (a.trim(), b.trim());
+    if a.is_empty() || b.is_empty() {
+        return Err(anyhow!("Missing element in '{}'", text));
+    }
+    let symbol = |s: &str| elements::normalize_symbol(s).ok_or_else(|| anyhow!("Unknown element '{}' in '{}'", s, text));
+    let (a, b) = (symbol(a)?, symbol(b)?);
+    let cutoff: f64 = cutoff.trim().parse().with_context(|| format!("Invalid distance in '{}'", text))?;
+    if cutoff <= 0.0 {
+        return Err(anyhow!("Bond cutoff must be positive in '{}'", text));
+    }
+    Ok((a.to_string(), b.to_string(), cutoff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covalent_rule_with_overrides() {
+        let rule = BondingRule::default();
+        // Zr-O in a UiO-66 node (2.2 Å) bonds; H...H contacts (1.9 Å) do not
+        assert!(rule.is_bonded("Zr", "O", 2.25));
+        assert!(rule.is_bonded("Cu", "O", 1.97));
+        assert!(rule.is_bonded("C", "H", 1.09));
+        assert!(!rule.is_bonded("H", "H", 1.9));
+        assert!((rule.cutoff("C", "C") - 1.92).abs() < 1e-9);
+        assert!((rule.cutoff("Zn", "N") - (1.22 + 0.71 + 0.3)).abs() < 1e-9);
+
+        // The metal tolerance does not keep node metals apart
+        assert!(rule.is_bonded("Zr", "Zr", 3.5));
+        assert!(!rule.clone().with_pair_cutoff("Zr", "Zr", 3.0).unwrap().is_bonded("Zr", "Zr", 3.5));
+
+        let rule = rule.with_pair_cutoff("O", "Zr", 2.0).unwrap();
+        assert!(!rule.is_bonded("Zr", "O", 2.25));
+        assert_eq!(rule.max_cutoff(&["O", "Zr", "O"]), rule.cutoff("Zr", "Zr"));
+
+        assert_eq!(parse_pair_cutoff("Zr-O=2.6").unwrap(), ("Zr".to_string(), "O".to_string(), 2.6));
+        assert_eq!(parse_pair_cutoff("ZR-o=2.6").unwrap(), ("Zr".to_string(), "O".to_string(), 2.6));
+        assert!(parse_pair_cutoff("Zx-O=2.6").is_err());
+        assert!(parse_pair_cutoff("Zr=2.6").is_err());
+        assert!(parse_pair_cutoff("Zr-O=-1").is_err());
+        assert!(BondingRule::uniform(2.0).is_bonded("H", "H", 1.9));
+    }
+}
@@ -1,8 +1,12 @@
-use crate::core::structure::{Crystal, Molecule};
+use crate::core::bonding::BondingRule;
+use crate::core::neighbors::{Neighbor, NeighborList};
+use crate::core::structure::{CifRepresentable, Crystal, Molecule};
 use petgraph::graph::{NodeIndex, UnGraph};
-use petgraph::visit::Bfs;
+use petgraph::visit::{Bfs, EdgeRef};
 use nalgebra::Vector3;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use anyhow::Result; // Defensive error handling
 
 // ============================================================================
@@ -12,44 +16,106 @@ use anyhow::Result; // Defensive error handling
 /// Represents the connectivity of atoms within the crystal.
 /// Used to identify distinct molecules or clusters.
 pub struct GraphRepresentation {
-    /// Undirected graph where nodes are atom indices and edges represent bonds.
-    pub graph: UnGraph<usize, ()>,
+    /// Undirected graph where nodes are atom indices and edges represent bonds: the quotient
+    /// graph of the periodic structure. Each edge carries the lattice translation of the
+    /// higher-index atom's image bonded to the lower-index atom (of the partner image, for loops).
+    pub graph: UnGraph<usize, Vector3<i32>>,
 }
 
 impl GraphRepresentation {
-    /// Builds the connectivity graph using the Minimum Image Convention (MIC).
-    /// Atoms closer than `cutoff` are considered bonded.
-    ///
-    /// # Complexity
-    /// O(N^2) currently. For systems > 10,000 atoms, a Cell List or KD-Tree should be used.
-    pub fn from_crystal(crystal: &Crystal, cutoff: f64) -> Self {
-        let num_atoms = crystal.atoms.len();
-        let mut graph = UnGraph::<usize, ()>::with_capacity(num_atoms, num_atoms * 3);
-        
+    /// Builds the connectivity graph from a periodic neighbour list.
+    /// Atoms closer than `cutoff` are considered bonded, whatever their elements.
+    pub fn from_crystal<T: CifRepresentable>(crystal: &T, cutoff: f64) -> Self {
+        Self::from_neighbors(&NeighborList::from_structure(crystal, cutoff))
+    }
+
+    /// Builds the connectivity graph with an element-aware bonding rule.
+    pub fn from_crystal_with_rule<T: CifRepresentable>(crystal: &T, rule: &BondingRule) -> Self {
+        let elements: Vec<&str> = crystal.atoms().iter().map(|a| a.element.as_str()).collect();
+        let neighbors = NeighborList::from_structure(crystal, rule.max_cutoff(&elements));
+        Self::build(&neighbors, |i, n| rule.is_bonded(elements[i], elements[n.index], n.distance))
+    }
+
+    /// Builds the graph from an existing neighbour list (every listed pair is a bond).
+    pub fn from_neighbors(neighbors: &NeighborList) -> Self {
+        Self::build(neighbors, |_, _| true)
+    }
+
+    fn build(neighbors: &NeighborList, bonded: impl Fn(usize, &Neighbor) -> bool) -> Self {
+        let num_atoms = neighbors.len();
+        let mut graph = UnGraph::<usize, Vector3<i32>>::with_capacity(num_atoms, num_atoms * 3);
+
         // Add all nodes first to maintain index mapping
         let node_indices: Vec<NodeIndex> = (0..num_atoms)
             .map(|i| graph.add_node(i))
             .collect();
-            
-        let cutoff_sq = cutoff.powi(2);
-
-        // Iterate pairwise to find bonds
-        for i in 0..num_atoms {
-            for j in (i + 1)..num_atoms {
-                // Calculate distance considering Periodic Boundary Conditions
-                let dist_vec = crystal.lattice.get_shortest_distance_vector(
-                    &crystal.atoms[i].fractional_coords,
-                    &crystal.atoms[j].fractional_coords,
-                );
-
-                if dist_vec.norm_squared() < cutoff_sq {
-                    graph.add_edge(node_indices[i], node_indices[j], ());
-                }
+
+        // One edge per bonded image: a pair bonded through two images (a chain through a short
+        // cell) gets two edges, and an atom bonded to its own image gets a loop
+        for (i, n) in neighbors.pairs() {
+            if bonded(i, n) {
+                graph.add_edge(node_indices[i], node_indices[n.index], n.image);
             }
         }
         Self { graph }
     }
 
+    /// Bonds as `(i, j, image)` with `i <= j`, sorted; atom `j` is bonded at `fractional_coords + image`.
+    pub fn bonds(&self) -> Vec<(usize, usize, Vector3<i32>)> {
+        let mut bonds: Vec<(usize, usize, Vector3<i32>)> = self
+            .graph
+            .edge_references()
+            .map(|e| (self.graph[e.source()], self.graph[e.target()], *e.weight()))
+            .map(|(i, j, image)| if i <= j { (i, j, image) } else { (j, i, -image) })
+            .collect();
+        bonds.sort_unstable_by_key(|&(i, j, image)| (i, j, image.x, image.y, image.z));
+        bonds
+    }
+
+    /// Connected components with their periodicity: walking the graph from one atom, every
+    /// bond that closes a loop onto a different image of an atom adds a lattice translation
+    /// under which the component repeats, and the rank of those translations is its dimension.
+    pub fn components(&self) -> Vec<Component> {
+        let mut offsets: Vec<Option<Vector3<i32>>> = vec![None; self.graph.node_count()];
+        let mut components = Vec::new();
+        for start in self.graph.node_indices() {
+            if offsets[start.index()].is_some() {
+                continue;
+            }
+            offsets[start.index()] = Some(Vector3::zeros());
+            let mut component = Component { atoms: Vec::new(), translations: Vec::new() };
+            let mut queue = VecDeque::from([start]);
+            while let Some(current) = queue.pop_front() {
+                component.atoms.push(self.graph[current]);
+                let here = offsets[current.index()].unwrap_or_default();
+                for edge in self.graph.edges(current) {
+                    let image = if self.graph[current] < self.graph[edge.target()] { *edge.weight() } else { -edge.weight() };
+                    match offsets[edge.target().index()] {
+                        Some(there) => component.add_translation(here + image - there),
+                        None => {
+                            offsets[edge.target().index()] = Some(here + image);
+                            queue.push_back(edge.target());
+                        }
+                    }
+                }
+            }
+            component.atoms.sort_unstable();
+            components.push(component);
+        }
+        components
+    }
+
+    /// Highest dimensionality of any component (`Molecule` for an empty structure).
+    pub fn dimensionality(&self) -> Dimensionality {
+        self.components().iter().map(Component::dimensionality).max().unwrap_or(Dimensionality::Molecule)
+    }
+
+    /// Whether some component bonds to its own periodic image (a chain, layer or framework
+    /// rather than a finite molecule).
+    pub fn is_extended(&self) -> bool {
+        self.dimensionality() != Dimensionality::Molecule
+    }
+
     /// Finds all connected components (subgraphs) in the graph.
     /// Returns a list of vectors, where each vector contains the atom indices of a molecule.
     pub fn find_connected_components(&self) -> Vec<Vec<usize>> {
@@ -81,18 +147,100 @@ impl GraphRepresentation {
     }
 }
 
+// ============================================================================
+// PERIODIC COMPONENTS
+// ============================================================================
+
+/// How many independent lattice directions a bonded component extends along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dimensionality {
+    /// 0D: a finite molecule or cluster.
+    Molecule,
+    /// 1D: a chain or rod.
+    Chain,
+    /// 2D: a layer (graphite, MOF nanosheets).
+    Layer,
+    /// 3D: a framework or ionic solid.
+    Framework,
+}
+
+impl Dimensionality {
+    fn from_rank(rank: usize) -> Self {
+        match rank {
+            0 => Self::Molecule,
+            1 => Self::Chain,
+            2 => Self::Layer,
+            _ => Self::Framework,
+        }
+    }
+
+    /// Number of periodic directions (0 to 3).
+    pub fn rank(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for Dimensionality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Molecule => "molecule",
+            Self::Chain => "chain",
+            Self::Layer => "layer",
+            Self::Framework => "framework",
+        };
+        write!(f, "{}D {}", self.rank(), name)
+    }
+}
+
+/// A connected component of the bond graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    /// Atom indices, sorted.
+    pub atoms: Vec<usize>,
+    /// Linearly independent lattice translations mapping the component onto itself
+    /// (one per periodic direction).
+    pub translations: Vec<Vector3<i32>>,
+}
+
+impl Component {
+    pub fn dimensionality(&self) -> Dimensionality {
+        Dimensionality::from_rank(self.translations.len())
+    }
+
+    /// Keeps `t` if it is independent of the translations found so far (exact integer test).
+    fn add_translation(&mut self, t: Vector3<i32>) {
+        let t = t.cast::<i64>();
+        let basis: Vec<Vector3<i64>> = self.translations.iter().map(|b| b.cast::<i64>()).collect();
+        let independent = match basis.as_slice() {
+            [] => t != Vector3::zeros(),
+            [a] => a.cross(&t) != Vector3::zeros(),
+            [a, b] => a.cross(b).dot(&t) != 0,
+            _ => false,
+        };
+        if independent {
+            self.translations.push(t.map(|x| x as i32));
+        }
+    }
+}
+
 // ============================================================================
 // MOLECULE FINDER
 // ============================================================================
 
 /// Engine for detecting molecules in a periodic crystal.
 pub struct MoleculeFinder {
-    bond_cutoff: f64,
+    rule: BondingRule,
 }
 
 impl MoleculeFinder {
+    /// Bonds every pair closer than `cutoff` (Å), whatever the elements.
     pub fn new(cutoff: f64) -> Self {
-        Self { bond_cutoff: cutoff }
+        Self { rule: BondingRule::uniform(cutoff) }
+    }
+
+    /// Bonds pairs by an element-aware rule (covalent radii plus tolerance).
+    pub fn with_rule(rule: BondingRule) -> Self {
+        Self { rule }
     }
 
     /// Primary entry point: Finds molecules and returns them as robust `Molecule` objects.
@@ -101,8 +249,31 @@ impl MoleculeFinder {
         Ok(molecules)
     }
 
+    /// Molecules for the slab populator: none when any part of the structure is bonded into
+    /// an extended network (ionic solid, framework), which can only be cut atom by atom.
+    pub fn find_discrete_molecules(&self, crystal: &Crystal) -> Result<Vec<Molecule>> {
+        let crystal_graph = GraphRepresentation::from_crystal_with_rule(crystal, &self.rule);
+        let components = crystal_graph.components();
+        if components.iter().any(|c| c.dimensionality() != Dimensionality::Molecule) {
+            return Ok(Vec::new());
+        }
+        Ok(Self::unwrap_components(crystal, &crystal_graph, components).0)
+    }
+
+    /// Bonded components of the structure with their periodicity.
+    pub fn components(&self, crystal: &Crystal) -> Vec<Component> {
+        GraphRepresentation::from_crystal_with_rule(crystal, &self.rule).components()
+    }
+
+    /// Highest dimensionality of any bonded component: whether the structure is molecular,
+    /// chain-like, layered or a framework.
+    pub fn dimensionality(&self, crystal: &Crystal) -> Dimensionality {
+        GraphRepresentation::from_crystal_with_rule(crystal, &self.rule).dimensionality()
+    }
+
     /// Advanced entry point: Returns molecules AND a set of atom indices that were assigned.
-    /// Useful for debugging (finding "orphan" atoms).
+    /// Useful for debugging (finding "orphan" atoms). Only 0D components become molecules:
+    /// atoms of chains, layers and frameworks are left unassigned.
     pub fn find_molecules_with_indices(
         &self,
         crystal: &Crystal,
@@ -112,10 +283,23 @@ impl MoleculeFinder {
         }
 
         // 1. Build Graph
-        let crystal_graph = GraphRepresentation::from_crystal(crystal, self.bond_cutoff);
-        
-        // 2. Find Components (Indices only)
-        let components = crystal_graph.find_connected_components();
+        let crystal_graph = GraphRepresentation::from_crystal_with_rule(crystal, &self.rule);
+        let components = crystal_graph.components();
+        Ok(Self::unwrap_components(crystal, &crystal_graph, components))
+    }
+
+    /// Unwraps the 0D components into molecules (with the set of atoms assigned to them).
+    fn unwrap_components(
+        crystal: &Crystal,
+        crystal_graph: &GraphRepresentation,
+        components: Vec<Component>,
+    ) -> (Vec<Molecule>, HashSet<usize>) {
+        // 2. Keep the finite components (an extended component cannot be unwrapped)
+        let components: Vec<Vec<usize>> = components
+            .into_iter()
+            .filter(|c| c.dimensionality() == Dimensionality::Molecule)
+            .map(|c| c.atoms)
+            .collect();
 
         let mut molecules = Vec::with_capacity(components.len());
         let mut assigned_indices = HashSet::new();
@@ -146,22 +330,24 @@ impl MoleculeFinder {
             while let Some(current_idx) = queue.pop_front() {
                 let current_pos = reassembled_atoms[&current_idx]; // Should always exist
                 
-                for neighbor in crystal_graph.graph.neighbors(NodeIndex::new(current_idx)) {
-                    let neighbor_idx = *crystal_graph.graph.node_weight(neighbor).unwrap();
+                for edge in crystal_graph.graph.edges(NodeIndex::new(current_idx)) {
+                    let neighbor_idx = crystal_graph.graph[edge.target()];
                     
                     // Only process if part of this component (graph ensures this) 
                     // and not yet visited in this reconstruction pass
-                    if !reassembled_atoms.contains_key(&neighbor_idx) {
-                        // Crucial Step: Get the shortest vector across PBC
-                        let shortest_vec = crystal.lattice.get_shortest_distance_vector(
-                            &original_atoms[current_idx].fractional_coords,
-                            &original_atoms[neighbor_idx].fractional_coords,
+                    if let Entry::Vacant(slot) = reassembled_atoms.entry(neighbor_idx) {
+                        // Crucial Step: Follow the bond to the image it was found at
+                        // (edge weights are stored from the lower to the higher index)
+                        let image = if current_idx < neighbor_idx { *edge.weight() } else { -edge.weight() };
+                        let shortest_vec = crystal.lattice.to_cartesian(
+                            &(original_atoms[neighbor_idx].fractional_coords + image.cast::<f64>()
+                                - original_atoms[current_idx].fractional_coords),
                         );
                         
                         // Construct neighbor position relative to current
                         let neighbor_pos = current_pos + shortest_vec;
                         
-                        reassembled_atoms.insert(neighbor_idx, neighbor_pos);
+                        slot.insert(neighbor_pos);
                         queue.push_back(neighbor_idx);
                     }
                 }
@@ -183,6 +369,7 @@ impl MoleculeFinder {
 
             let mut final_com = Vector3::zeros();
             let mut atoms_cart = Vec::with_capacity(indices.len());
+            let mut bulk_indices = Vec::with_capacity(indices.len());
 
             // Apply final shift
             for &idx in &indices {
@@ -192,16 +379,82 @@ impl MoleculeFinder {
                     let final_pos = pos + shift_cart;
                     final_com += final_pos;
                     atoms_cart.push((original_atoms[idx].element.clone(), final_pos));
+                    bulk_indices.push(idx);
                 }
             }
             final_com /= atoms_cart.len() as f64;
 
             molecules.push(Molecule {
                 atoms: atoms_cart,
+                indices: bulk_indices,
                 center_of_mass: final_com,
             });
         }
 
-        Ok((molecules, assigned_indices))
+        (molecules, assigned_indices)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType, Lattice};
+
+    fn crystal(a: f64, b: f64, c: f64, positions: &[[f64; 3]]) -> Crystal {
+        Crystal {
+            lattice: Lattice::from_parameters(a, b, c, 90.0, 90.0, 90.0).unwrap(),
+            atoms: positions.iter().map(|&p| Atom::new("C".to_string(), Vector3::from(p), ComponentType::Unknown)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_component_dimensionality() {
+        let finder = MoleculeFinder::new(1.6);
+        // A dimer straddling the cell boundary is still a finite molecule
+        let dimer = crystal(10.0, 10.0, 10.0, &[[0.02, 0.5, 0.5], [0.95, 0.5, 0.5]]);
+        assert_eq!(finder.dimensionality(&dimer), Dimensionality::Molecule);
+        assert_eq!(finder.find_molecules(&dimer).unwrap().len(), 1);
+
+        // One atom bonded to its images along a, then a and b, then all three axes
+        let chain = crystal(1.5, 10.0, 10.0, &[[0.0, 0.5, 0.5]]);
+        let layer = crystal(1.5, 1.5, 10.0, &[[0.0, 0.0, 0.5]]);
+        let framework = crystal(1.5, 1.5, 1.5, &[[0.0, 0.0, 0.0]]);
+        assert_eq!(finder.dimensionality(&chain), Dimensionality::Chain);
+        assert_eq!(finder.dimensionality(&layer), Dimensionality::Layer);
+        assert_eq!(finder.dimensionality(&framework), Dimensionality::Framework);
+        assert!(finder.find_molecules(&framework).unwrap().is_empty());
+
+        // A zigzag chain through two atoms along a diagonal translation, plus a guest dimer
+        let mixed = crystal(2.0, 2.0, 12.0, &[[0.0, 0.0, 0.2], [0.5, 0.5, 0.2], [0.5, 0.5, 0.7], [0.5, 0.5, 0.8]]);
+        let components = finder.components(&mixed);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].atoms, vec![0, 1]);
+        assert_eq!(components[0].dimensionality(), Dimensionality::Layer);
+        assert_eq!(components[1].dimensionality(), Dimensionality::Molecule);
+        assert_eq!(finder.find_molecules(&mixed).unwrap().len(), 1);
+        assert!(finder.find_discrete_molecules(&mixed).unwrap().is_empty());
+        assert_eq!(Dimensionality::Layer.to_string(), "2D layer");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_covalent_bonding_joins_ionic_network() {
+        let lattice = Lattice::from_parameters(4.2316, 4.2316, 4.2316, 90.0, 90.0, 90.0).unwrap();
+        let mut rock_salt = Crystal { lattice, atoms: Vec::new() };
+        for t in [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]] {
+            rock_salt.atoms.push(Atom::new("Mg", Vector3::from(t), ComponentType::Unknown));
+            rock_salt.atoms.push(Atom::new("O", Vector3::from(t).map(|x| (x + 0.5) % 1.0), ComponentType::Unknown));
+        }
+
+        // Mg-O is 2.1 Å: the fixed 2.0 Å cutoff sees isolated ions, the covalent radii one network
+        let isolated = MoleculeFinder::new(2.0).find_molecules(&rock_salt).unwrap();
+        assert_eq!(isolated.len(), rock_salt.atoms.len());
+        // ... a 3D framework, so no molecule at all, and cut atom by atom
+        let finder = MoleculeFinder::with_rule(BondingRule::default());
+        assert!(finder.find_molecules(&rock_salt).unwrap().is_empty());
+        assert_eq!(finder.dimensionality(&rock_salt), Dimensionality::Framework);
+        assert!(finder.find_discrete_molecules(&rock_salt).unwrap().is_empty());
+
+        // A pair override below the Mg-O distance splits it again, whatever the symbols' case
+        let rule = BondingRule::default().with_pair_cutoff("O", "Mg", 2.0).unwrap().with_pair_cutoff("mg", "MG", 2.0).unwrap();
+        assert_eq!(MoleculeFinder::with_rule(rule).find_molecules(&rock_salt).unwrap().len(), rock_salt.atoms.len());
+    }
+}
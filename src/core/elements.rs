@@ -0,0 +1,243 @@
+// ============================================================================
+// PERIODIC TABLE
+// ============================================================================
+
+/// Tabulated data for one element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Element {
+    pub number: u8,
+    pub symbol: &'static str,
+    /// Standard atomic weight (g/mol); mass number of the longest-lived isotope for
+    /// radioactive elements.
+    pub mass: f64,
+    /// Single-bond covalent radius (Å), Cordero et al., Dalton Trans. 2008 (low-spin Mn, Fe, Co;
+    /// sp3 C).
+    pub covalent_radius: Option<f64>,
+    /// Van der Waals radius (Å), Alvarez, Dalton Trans. 2013.
+    pub vdw_radius: Option<f64>,
+    /// Shannon effective ionic radius (Å, six-coordinate where tabulated) of the first
+    /// oxidation state in `oxidation_states`.
+    pub ionic_radius: Option<f64>,
+    /// Pauling electronegativity.
+    pub electronegativity: Option<f64>,
+    /// Common oxidation states, most common first.
+    pub oxidation_states: &'static [i8],
+    /// Metals; metalloids (B, Si, Ge, As, Sb, Te) and the noble gases are not.
+    pub metal: bool,
+}
+
+impl Element {
+    /// Formal ionic charge (e): the most common oxidation state, 0 for the noble gases.
+    pub fn formal_charge(&self) -> f64 {
+        self.oxidation_states.first().copied().unwrap_or(0) as f64
+    }
+}
+
+/// Shorthand for the table below; non-positive values mean "not tabulated".
+#[allow(clippy::too_many_arguments)]
+const fn el(
+    number: u8,
+    symbol: &'static str,
+    mass: f64,
+    covalent: f64,
+    vdw: f64,
+    ionic: f64,
+    electronegativity: f64,
+    oxidation_states: &'static [i8],
+    metal: bool,
+) -> Element {
+    const fn given(x: f64) -> Option<f64> {
+        if x > 0.0 { Some(x) } else { None }
+    }
+    Element {
+        number,
+        symbol,
+        mass,
+        covalent_radius: given(covalent),
+        vdw_radius: given(vdw),
+        ionic_radius: given(ionic),
+        electronegativity: given(electronegativity),
+        oxidation_states,
+        metal,
+    }
+}
+
+const M: bool = true;
+const N: bool = false;
+
+/// Every element, indexed by atomic number - 1.
+/// Columns: Z, symbol, mass, covalent, vdW and ionic radius, electronegativity, oxidation states, metal.
+pub const ELEMENTS: [Element; 118] = [
+    el(1, "H", 1.008, 0.31, 1.20, 0.0, 2.20, &[1, -1], N),
+    el(2, "He", 4.0026, 0.28, 1.43, 0.0, 0.0, &[], N),
+    el(3, "Li", 6.94, 1.28, 2.12, 0.76, 0.98, &[1], M),
+    el(4, "Be", 9.0122, 0.96, 1.98, 0.45, 1.57, &[2], M),
+    el(5, "B", 10.81, 0.84, 1.91, 0.27, 2.04, &[3], N),
+    el(6, "C", 12.011, 0.76, 1.77, 0.16, 2.55, &[4, -4], N),
+    el(7, "N", 14.007, 0.71, 1.66, 1.46, 3.04, &[-3, 3, 5], N),
+    el(8, "O", 15.999, 0.66, 1.50, 1.40, 3.44, &[-2], N),
+    el(9, "F", 18.998, 0.57, 1.46, 1.33, 3.98, &[-1], N),
+    el(10, "Ne", 20.180, 0.58, 1.58, 0.0, 0.0, &[], N),
+    el(11, "Na", 22.990, 1.66, 2.50, 1.02, 0.93, &[1], M),
+    el(12, "Mg", 24.305, 1.41, 2.51, 0.72, 1.31, &[2], M),
+    el(13, "Al", 26.982, 1.21, 2.25, 0.535, 1.61, &[3], M),
+    el(14, "Si", 28.085, 1.11, 2.19, 0.40, 1.90, &[4, -4], N),
+    el(15, "P", 30.974, 1.07, 1.90, 0.38, 2.19, &[5, 3, -3], N),
+    el(16, "S", 32.06, 1.05, 1.89, 1.84, 2.58, &[-2, 2, 4, 6], N),
+    el(17, "Cl", 35.45, 1.02, 1.82, 1.81, 3.16, &[-1, 1, 3, 5, 7], N),
+    el(18, "Ar", 39.948, 1.06, 1.83, 0.0, 0.0, &[], N),
+    el(19, "K", 39.098, 2.03, 2.73, 1.38, 0.82, &[1], M),
+    el(20, "Ca", 40.078, 1.76, 2.62, 1.00, 1.00, &[2], M),
+    el(21, "Sc", 44.956, 1.70, 2.58, 0.745, 1.36, &[3], M),
+    el(22, "Ti", 47.867, 1.60, 2.46, 0.605, 1.54, &[4, 3], M),
+    el(23, "V", 50.942, 1.53, 2.42, 0.54, 1.63, &[5, 3, 4], M),
+    el(24, "Cr", 51.996, 1.39, 2.45, 0.615, 1.66, &[3, 6], M),
+    el(25, "Mn", 54.938, 1.39, 2.45, 0.83, 1.55, &[2, 3, 4, 7], M),
+    el(26, "Fe", 55.845, 1.32, 2.44, 0.78, 1.83, &[2, 3], M),
+    el(27, "Co", 58.933, 1.26, 2.40, 0.745, 1.88, &[2, 3], M),
+    el(28, "Ni", 58.693, 1.24, 2.40, 0.69, 1.91, &[2], M),
+    el(29, "Cu", 63.546, 1.32, 2.38, 0.73, 1.90, &[2, 1], M),
+    el(30, "Zn", 65.38, 1.22, 2.39, 0.74, 1.65, &[2], M),
+    el(31, "Ga", 69.723, 1.22, 2.32, 0.62, 1.81, &[3], M),
+    el(32, "Ge", 72.630, 1.20, 2.29, 0.53, 2.01, &[4, -4], N),
+    el(33, "As", 74.922, 1.19, 1.88, 0.46, 2.18, &[5, 3, -3], N),
+    el(34, "Se", 78.971, 1.20, 1.82, 1.98, 2.55, &[-2, 2, 4, 6], N),
+    el(35, "Br", 79.904, 1.20, 1.86, 1.96, 2.96, &[-1, 1, 3, 5], N),
+    el(36, "Kr", 83.798, 1.16, 2.25, 0.0, 3.00, &[], N),
+    el(37, "Rb", 85.468, 2.20, 3.21, 1.52, 0.82, &[1], M),
+    el(38, "Sr", 87.62, 1.95, 2.84, 1.18, 0.95, &[2], M),
+    el(39, "Y", 88.906, 1.90, 2.75, 0.90, 1.22, &[3], M),
+    el(40, "Zr", 91.224, 1.75, 2.52, 0.72, 1.33, &[4], M),
+    el(41, "Nb", 92.906, 1.64, 2.56, 0.64, 1.6, &[5, 3], M),
+    el(42, "Mo", 95.95, 1.54, 2.45, 0.59, 2.16, &[6, 4], M),
+    el(43, "Tc", 98.0, 1.47, 2.44, 0.645, 1.9, &[4, 7], M),
+    el(44, "Ru", 101.07, 1.46, 2.46, 0.62, 2.2, &[4, 3], M),
+    el(45, "Rh", 102.91, 1.42, 2.44, 0.665, 2.28, &[3], M),
+    el(46, "Pd", 106.42, 1.39, 2.15, 0.86, 2.20, &[2, 4], M),
+    el(47, "Ag", 107.87, 1.45, 2.53, 1.15, 1.93, &[1], M),
+    el(48, "Cd", 112.41, 1.44, 2.49, 0.95, 1.69, &[2], M),
+    el(49, "In", 114.82, 1.42, 2.43, 0.80, 1.78, &[3], M),
+    el(50, "Sn", 118.71, 1.39, 2.42, 0.69, 1.96, &[4, 2], M),
+    el(51, "Sb", 121.76, 1.39, 2.47, 0.76, 2.05, &[3, 5, -3], N),
+    el(52, "Te", 127.60, 1.38, 1.99, 2.21, 2.1, &[-2, 2, 4, 6], N),
+    el(53, "I", 126.90, 1.39, 2.04, 2.20, 2.66, &[-1, 1, 3, 5, 7], N),
+    el(54, "Xe", 131.29, 1.40, 2.06, 0.0, 2.6, &[], N),
+    el(55, "Cs", 132.91, 2.44, 3.48, 1.67, 0.79, &[1], M),
+    el(56, "Ba", 137.33, 2.15, 3.03, 1.35, 0.89, &[2], M),
+    el(57, "La", 138.91, 2.07, 2.98, 1.032, 1.10, &[3], M),
+    el(58, "Ce", 140.12, 2.04, 2.88, 1.01, 1.12, &[3, 4], M),
+    el(59, "Pr", 140.91, 2.03, 2.92, 0.99, 1.13, &[3], M),
+    el(60, "Nd", 144.24, 2.01, 2.95, 0.983, 1.14, &[3], M),
+    el(61, "Pm", 145.0, 1.99, 0.0, 0.97, 1.13, &[3], M),
+    el(62, "Sm", 150.36, 1.98, 2.90, 0.958, 1.17, &[3, 2], M),
+    el(63, "Eu", 151.96, 1.98, 2.87, 0.947, 1.2, &[3, 2], M),
+    el(64, "Gd", 157.25, 1.96, 2.83, 0.938, 1.2, &[3], M),
+    el(65, "Tb", 158.93, 1.94, 2.79, 0.923, 1.1, &[3], M),
+    el(66, "Dy", 162.50, 1.92, 2.87, 0.912, 1.22, &[3], M),
+    el(67, "Ho", 164.93, 1.92, 2.81, 0.901, 1.23, &[3], M),
+    el(68, "Er", 167.26, 1.89, 2.83, 0.89, 1.24, &[3], M),
+    el(69, "Tm", 168.93, 1.90, 2.79, 0.88, 1.25, &[3], M),
+    el(70, "Yb", 173.05, 1.87, 2.80, 0.868, 1.1, &[3, 2], M),
+    el(71, "Lu", 174.97, 1.87, 2.74, 0.861, 1.27, &[3], M),
+    el(72, "Hf", 178.49, 1.75, 2.63, 0.71, 1.3, &[4], M),
+    el(73, "Ta", 180.95, 1.70, 2.53, 0.64, 1.5, &[5], M),
+    el(74, "W", 183.84, 1.62, 2.57, 0.60, 2.36, &[6, 4], M),
+    el(75, "Re", 186.21, 1.51, 2.49, 0.63, 1.9, &[4, 7], M),
+    el(76, "Os", 190.23, 1.44, 2.48, 0.63, 2.2, &[4], M),
+    el(77, "Ir", 192.22, 1.41, 2.41, 0.625, 2.20, &[4, 3], M),
+    el(78, "Pt", 195.08, 1.36, 2.29, 0.80, 2.28, &[2, 4], M),
+    el(79, "Au", 196.97, 1.36, 2.32, 0.85, 2.54, &[3, 1], M),
+    el(80, "Hg", 200.59, 1.32, 2.45, 1.02, 2.00, &[2, 1], M),
+    el(81, "Tl", 204.38, 1.45, 2.47, 1.50, 1.62, &[1, 3], M),
+    el(82, "Pb", 207.2, 1.46, 2.60, 1.19, 2.33, &[2, 4], M),
+    el(83, "Bi", 208.98, 1.48, 2.54, 1.03, 2.02, &[3], M),
+    el(84, "Po", 209.0, 1.40, 0.0, 0.94, 2.0, &[4, 2, -2], M),
+    el(85, "At", 210.0, 1.50, 0.0, 0.0, 2.2, &[-1, 1], M),
+    el(86, "Rn", 222.0, 1.50, 0.0, 0.0, 2.2, &[], N),
+    el(87, "Fr", 223.0, 2.60, 0.0, 1.80, 0.7, &[1], M),
+    el(88, "Ra", 226.0, 2.21, 0.0, 1.48, 0.9, &[2], M),
+    el(89, "Ac", 227.0, 2.15, 2.8, 1.12, 1.1, &[3], M),
+    el(90, "Th", 232.04, 2.06, 2.93, 0.94, 1.3, &[4], M),
+    el(91, "Pa", 231.04, 2.00, 2.88, 0.78, 1.5, &[5], M),
+    el(92, "U", 238.03, 1.96, 2.71, 0.73, 1.38, &[6, 4], M),
+    el(93, "Np", 237.0, 1.90, 2.82, 0.75, 1.36, &[5], M),
+    el(94, "Pu", 244.0, 1.87, 2.81, 0.86, 1.28, &[4], M),
+    el(95, "Am", 243.0, 1.80, 2.83, 0.975, 1.13, &[3], M),
+    el(96, "Cm", 247.0, 1.69, 3.05, 0.97, 1.28, &[3], M),
+    el(97, "Bk", 247.0, 0.0, 3.4, 0.96, 1.3, &[3], M),
+    el(98, "Cf", 251.0, 0.0, 3.05, 0.95, 1.3, &[3], M),
+    el(99, "Es", 252.0, 0.0, 2.7, 0.0, 1.3, &[3], M),
+    el(100, "Fm", 257.0, 0.0, 0.0, 0.0, 1.3, &[3], M),
+    el(101, "Md", 258.0, 0.0, 0.0, 0.0, 1.3, &[3], M),
+    el(102, "No", 259.0, 0.0, 0.0, 0.0, 1.3, &[2], M),
+    el(103, "Lr", 266.0, 0.0, 0.0, 0.0, 0.0, &[3], M),
+    el(104, "Rf", 267.0, 0.0, 0.0, 0.0, 0.0, &[4], M),
+    el(105, "Db", 268.0, 0.0, 0.0, 0.0, 0.0, &[5], M),
+    el(106, "Sg", 269.0, 0.0, 0.0, 0.0, 0.0, &[6], M),
+    el(107, "Bh", 270.0, 0.0, 0.0, 0.0, 0.0, &[7], M),
+    el(108, "Hs", 277.0, 0.0, 0.0, 0.0, 0.0, &[8], M),
+    el(109, "Mt", 278.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(110, "Ds", 281.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(111, "Rg", 282.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(112, "Cn", 285.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(113, "Nh", 286.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(114, "Fl", 289.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(115, "Mc", 290.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(116, "Lv", 293.0, 0.0, 0.0, 0.0, 0.0, &[], M),
+    el(117, "Ts", 294.0, 0.0, 0.0, 0.0, 0.0, &[], N),
+    el(118, "Og", 294.0, 0.0, 0.0, 0.0, 0.0, &[], N),
+];
+
+// ============================================================================
+// LOOKUP
+// ============================================================================
+
+/// Element with this exact symbol ("Zn").
+pub fn element(symbol: &str) -> Option<&'static Element> {
+    ELEMENTS.iter().find(|e| e.symbol == symbol)
+}
+
+/// Element with this atomic number (30 -> Zn).
+pub fn from_number(number: usize) -> Option<&'static Element> {
+    number.checked_sub(1).and_then(|i| ELEMENTS.get(i))
+}
+
+/// Canonical symbol for a symbol in any letter case ("CL", "cl" -> "Cl"); `None` if it is not an element.
+pub fn normalize_symbol(text: &str) -> Option<&'static str> {
+    let text = text.trim();
+    ELEMENTS.iter().find(|e| e.symbol.eq_ignore_ascii_case(text)).map(|e| e.symbol)
+}
+
+/// Covalent radius of an element symbol ("Zr" -> 1.75 Å), if tabulated.
+pub fn covalent_radius(symbol: &str) -> Option<f64> {
+    element(symbol).and_then(|e| e.covalent_radius)
+}
+
+/// Whether an element is a metal (unknown symbols are not).
+pub fn is_metal(symbol: &str) -> bool {
+    element(symbol).is_some_and(|e| e.metal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_is_ordered_and_consistent() {
+        for (i, e) in ELEMENTS.iter().enumerate() {
+            assert_eq!(e.number as usize, i + 1, "{}", e.symbol);
+            assert!(i == 0 || e.mass > ELEMENTS[i - 1].mass - 2.0, "{}", e.symbol);
+            // The ionic radius belongs to the first oxidation state
+            assert!(e.ionic_radius.is_none() || !e.oxidation_states.is_empty(), "{}", e.symbol);
+        }
+        assert_eq!(from_number(40).map(|e| e.symbol), Some("Zr"));
+        assert_eq!(element("Cl").map(|e| e.number), Some(17));
+        assert_eq!(normalize_symbol("CL"), Some("Cl"));
+        assert_eq!(normalize_symbol("zn"), Some("Zn"));
+        assert_eq!(normalize_symbol("Xx"), None);
+        assert!(is_metal("Zr") && !is_metal("Si") && !is_metal("Xx"));
+        assert_eq!(element("O").unwrap().formal_charge(), -2.0);
+        assert_eq!(element("Ti").unwrap().formal_charge(), 4.0);
+        assert_eq!(element("Ar").unwrap().formal_charge(), 0.0);
+    }
+}
@@ -1,2 +1,8 @@
 pub mod structure;
-pub mod connectivity;
\ No newline at end of file
+pub mod elements;
+pub mod bonding;
+pub mod connectivity;
+pub mod primitive;
+pub mod neighbors;
+pub mod spacegroup;
+pub mod symmetry;
\ No newline at end of file
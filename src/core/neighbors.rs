@@ -0,0 +1,261 @@
+use crate::core::structure::{CifRepresentable, Lattice};
+use crate::math::lll;
+use nalgebra::{Matrix3, Vector3};
+
+// ============================================================================
+// NEIGHBOR RECORD
+// ============================================================================
+
+/// One periodic image of an atom found near a query point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Neighbor {
+    /// Index of the atom in the structure.
+    pub index: usize,
+    /// Lattice translation of the image: it sits at `fractional_coords + image`.
+    pub image: Vector3<i32>,
+    /// Cartesian vector from the query point to the image (Å).
+    pub vector: Vector3<f64>,
+    pub distance: f64,
+}
+
+// ============================================================================
+// CELL LIST
+// ============================================================================
+
+/// Atoms binned on a grid over the LLL-reduced cell.
+///
+/// Queries return every image within the radius, so skewed cells and radii larger than half
+/// the cell are handled exactly. Bins are at least `cutoff` wide (perpendicular to their faces),
+/// so a query at the build cutoff only visits the 27 surrounding bins.
+#[derive(Debug, Clone)]
+pub struct CellList {
+    /// Reduced basis (lattice vectors as columns), spanning the same lattice as the input.
+    reduced: Matrix3<f64>,
+    /// Original fractional coordinates -> reduced fractional coordinates.
+    to_reduced: Matrix3<f64>,
+    /// Reduced translations -> original translations.
+    from_reduced: Matrix3<i32>,
+    /// Distances between opposite faces of the reduced cell (Å).
+    heights: Vector3<f64>,
+    bins: [usize; 3],
+    cells: Vec<Vec<Binned>>,
+}
+
+/// An atom in a bin: Cartesian position of its reduced coordinates wrapped into [0, 1), and the
+/// translation removed by wrapping.
+#[derive(Debug, Clone)]
+struct Binned {
+    index: usize,
+    cart: Vector3<f64>,
+    shift: Vector3<i32>,
+}
+
+impl CellList {
+    /// Bins `positions` (fractional coordinates of `lattice`, wrapped or not).
+    pub fn new(lattice: &Lattice, positions: &[Vector3<f64>], cutoff: f64) -> Self {
+        let (reduced, to_reduced, from_reduced) = reduce(lattice);
+        let inverse = reduced.try_inverse().unwrap_or_else(Matrix3::identity);
+        let heights = Vector3::from_fn(|k, _| 1.0 / inverse.row(k).norm());
+
+        // Aim for about one atom per bin when the cutoff is small compared to the atom spacing
+        let spacing = (reduced.determinant().abs() / positions.len().max(1) as f64).cbrt();
+        let width = cutoff.max(spacing);
+        let bins = [0, 1, 2].map(|k| ((heights[k] / width).floor() as usize).max(1));
+
+        let mut cells = vec![Vec::new(); bins[0] * bins[1] * bins[2]];
+        for (i, frac) in positions.iter().enumerate() {
+            let r = to_reduced * frac;
+            let shift = r.map(f64::floor);
+            let wrapped = r - shift;
+            let bin = [0, 1, 2].map(|k| ((wrapped[k] * bins[k] as f64) as usize).min(bins[k] - 1));
+            cells[(bin[0] * bins[1] + bin[1]) * bins[2] + bin[2]].push(Binned {
+                index: i,
+                cart: reduced * wrapped,
+                shift: shift.map(|x| x as i32),
+            });
+        }
+
+        Self { reduced, to_reduced, from_reduced, heights, bins, cells }
+    }
+
+    /// Every image of every atom closer than `radius` to `point` (fractional coordinates),
+    /// including the atom sitting at `point` itself.
+    pub fn query(&self, point: &Vector3<f64>, radius: f64) -> Vec<Neighbor> {
+        let r = self.to_reduced * point;
+        let centre = [0, 1, 2].map(|k| (r[k] * self.bins[k] as f64).floor() as i64);
+        let span = [0, 1, 2].map(|k| (radius * self.bins[k] as f64 / self.heights[k]).floor() as i64 + 1);
+        let radius_sq = radius * radius;
+
+        let mut found = Vec::new();
+        for c0 in centre[0] - span[0]..=centre[0] + span[0] {
+            for c1 in centre[1] - span[1]..=centre[1] + span[1] {
+                for c2 in centre[2] - span[2]..=centre[2] + span[2] {
+                    let c = [c0, c1, c2];
+                    let bin = [0, 1, 2].map(|k| c[k].rem_euclid(self.bins[k] as i64) as usize);
+                    let translation = Vector3::from_fn(|k, _| c[k].div_euclid(self.bins[k] as i64) as i32);
+                    let cell = &self.cells[(bin[0] * self.bins[1] + bin[1]) * self.bins[2] + bin[2]];
+                    let offset = self.reduced * (translation.cast::<f64>() - r);
+
+                    for atom in cell {
+                        let vector = atom.cart + offset;
+                        let distance_sq = vector.norm_squared();
+                        if distance_sq < radius_sq {
+                            found.push(Neighbor {
+                                index: atom.index,
+                                image: self.from_reduced * (translation - atom.shift),
+                                vector,
+                                distance: distance_sq.sqrt(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// LLL-reduces the lattice, returning the reduced basis and the integer change of basis both ways.
+/// Falls back to the original cell if the reduction does not round to a unimodular matrix.
+fn reduce(lattice: &Lattice) -> (Matrix3<f64>, Matrix3<f64>, Matrix3<i32>) {
+    let identity = (lattice.matrix, Matrix3::identity(), Matrix3::identity());
+    let Some(inverse) = lattice.matrix.try_inverse() else { return identity };
+
+    let change = (inverse * lll::lll_reduce(lattice.matrix)).map(f64::round);
+    if (change.determinant().abs() - 1.0).abs() > 1e-6 {
+        return identity;
+    }
+    let Some(back) = change.try_inverse() else { return identity };
+    (lattice.matrix * change, back.map(f64::round), change.map(|x| x as i32))
+}
+
+// ============================================================================
+// NEIGHBOR LIST
+// ============================================================================
+
+/// All neighbours within a cutoff of every atom of a periodic structure.
+///
+/// An atom's own periodic images count as neighbours when the cell is shorter than the cutoff.
+#[derive(Debug, Clone)]
+pub struct NeighborList {
+    cutoff: f64,
+    neighbors: Vec<Vec<Neighbor>>,
+}
+
+impl NeighborList {
+    pub fn new(lattice: &Lattice, positions: &[Vector3<f64>], cutoff: f64) -> Self {
+        let cells = CellList::new(lattice, positions, cutoff);
+        let neighbors = positions
+            .iter()
+            .enumerate()
+            .map(|(i, frac)| {
+                let mut found: Vec<Neighbor> = cells
+                    .query(frac, cutoff)
+                    .into_iter()
+                    .filter(|n| n.index != i || n.image != Vector3::zeros())
+                    .collect();
+                found.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.index.cmp(&b.index)));
+                found
+            })
+            .collect();
+        Self { cutoff, neighbors }
+    }
+
+    pub fn from_structure<T: CifRepresentable>(structure: &T, cutoff: f64) -> Self {
+        let positions: Vec<Vector3<f64>> = structure.atoms().iter().map(|a| a.fractional_coords).collect();
+        Self::new(structure.lattice(), &positions, cutoff)
+    }
+
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    /// Number of atoms.
+    pub fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbors.is_empty()
+    }
+
+    /// Neighbours of atom `i`, nearest first.
+    pub fn neighbors(&self, i: usize) -> &[Neighbor] {
+        &self.neighbors[i]
+    }
+
+    /// Every neighbouring pair once, as `(i, neighbor)` with `i <= neighbor.index`
+    /// (for self-images, only the translation that is lexicographically positive).
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, &Neighbor)> {
+        self.neighbors.iter().enumerate().flat_map(|(i, found)| {
+            found.iter().filter(move |n| {
+                n.index > i || (n.index == i && n.image.iter().find(|&&t| t != 0).is_some_and(|&t| t > 0))
+            }).map(move |n| (i, n))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skewed_cell_matches_brute_force() {
+        // Strongly sheared cell: b = 5a + small, c leans over both
+        let lattice = Lattice::new(Matrix3::new(
+            3.0, 15.2, 7.1,
+            0.0, 2.9, 4.3,
+            0.0, 0.0, 3.1,
+        )).unwrap();
+        let positions = vec![
+            Vector3::new(0.1, 0.2, 0.3),
+            Vector3::new(0.9, 0.75, 0.05),
+            Vector3::new(0.5, 0.5, 0.5),
+            Vector3::new(1.3, -0.4, 0.95),
+        ];
+        let cutoff = 4.5;
+        let list = NeighborList::new(&lattice, &positions, cutoff);
+
+        for (i, fi) in positions.iter().enumerate() {
+            let mut expected = Vec::new();
+            for (j, fj) in positions.iter().enumerate() {
+                for t0 in -12..=12 {
+                    for t1 in -12..=12 {
+                        for t2 in -12..=12 {
+                            let t = Vector3::new(t0, t1, t2);
+                            if i == j && t == Vector3::zeros() { continue; }
+                            let d = lattice.to_cartesian(&(fj + t.cast::<f64>() - fi)).norm();
+                            if d < cutoff {
+                                expected.push((j, t));
+                            }
+                        }
+                    }
+                }
+            }
+            let mut got: Vec<(usize, Vector3<i32>)> = list.neighbors(i).iter().map(|n| (n.index, n.image)).collect();
+            let key = |p: &(usize, Vector3<i32>)| (p.0, p.1.x, p.1.y, p.1.z);
+            expected.sort_by_key(key);
+            got.sort_by_key(key);
+            assert_eq!(got, expected, "neighbours of atom {}", i);
+        }
+
+        // The minimum image agrees with the nearest neighbour of the pair
+        let (image, vector) = lattice.minimum_image(&positions[0], &positions[1]);
+        let nearest = list.neighbors(0).iter().find(|n| n.index == 1).unwrap();
+        assert_eq!(image, nearest.image);
+        assert!((vector.norm() - nearest.distance).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cutoff_beyond_half_cell_finds_every_image() {
+        // Simple cubic, a = 2 Å: a 2.5 Å cutoff reaches only the 6 face images (2 Å)
+        let lattice = Lattice::from_parameters(2.0, 2.0, 2.0, 90.0, 90.0, 90.0).unwrap();
+        let list = NeighborList::new(&lattice, &[Vector3::new(0.5, 0.5, 0.5)], 2.5);
+
+        assert_eq!(list.neighbors(0).len(), 6);
+        // 2.9 Å also reaches the 12 edge images (2√2 ≈ 2.83 Å), but not the corners (2√3 ≈ 3.46 Å)
+        let list = NeighborList::new(&lattice, &[Vector3::new(0.5, 0.5, 0.5)], 2.9);
+        assert_eq!(list.neighbors(0).len(), 18);
+        assert_eq!(list.pairs().count(), 9);
+    }
+}
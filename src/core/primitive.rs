@@ -0,0 +1,222 @@
+use crate::core::neighbors::CellList;
+use crate::core::spacegroup::wrap_fractional;
+use crate::core::structure::{Atom, Crystal, Lattice};
+use crate::math::integer_basis::gcd;
+use crate::math::reduction;
+use nalgebra::{Matrix3, Vector3};
+use anyhow::{anyhow, bail, Result};
+
+/// Default symmetry tolerance (Å): how far an atom may sit from the image of another under a
+/// translation or symmetry operation and still count as the same site.
+pub const DEFAULT_SYMPREC: f64 = 0.01;
+
+/// Relative tolerance of the Niggli reduction applied to found cells.
+const NIGGLI_TOLERANCE: f64 = 1e-5;
+
+// ============================================================================
+// CELL CHANGE
+// ============================================================================
+
+/// A structure re-expressed in a smaller or better-shaped cell of the same crystal.
+#[derive(Debug, Clone)]
+pub struct ReducedCell {
+    pub crystal: Crystal,
+    /// Columns: the new lattice vectors in the input cell's lattice coordinates. Fractional
+    /// entries appear for the primitive cell of a centred one (fcc: (0, ½, ½), ...).
+    pub transformation: Matrix3<f64>,
+}
+
+impl ReducedCell {
+    /// Miller indices, in the new cell, of a plane given in the input cell.
+    pub fn miller_to_reduced(&self, hkl: [i32; 3]) -> [i32; 3] {
+        transform_miller(hkl, &self.transformation)
+    }
+
+    /// Miller indices, in the input cell, of a plane given in the new cell.
+    pub fn miller_to_input(&self, hkl: [i32; 3]) -> [i32; 3] {
+        let inverse = self.transformation.try_inverse().unwrap_or_else(Matrix3::identity);
+        transform_miller(hkl, &inverse)
+    }
+}
+
+/// Miller indices of the same planes after the cell change `new basis = old basis · P`:
+/// h' = Pᵀ·h, scaled to the smallest integers.
+pub fn transform_miller(hkl: [i32; 3], transformation: &Matrix3<f64>) -> [i32; 3] {
+    let h = transformation.transpose() * Vector3::from(hkl).cast::<f64>();
+    let scaled = (1..=1000)
+        .map(|m| h * m as f64)
+        .find(|v| v.iter().all(|x| (x - x.round()).abs() < 1e-6))
+        .unwrap_or(h)
+        .map(|x| x.round() as i32);
+    let divisor = gcd(gcd(scaled.x, scaled.y), scaled.z).max(1);
+    [scaled.x / divisor, scaled.y / divisor, scaled.z / divisor]
+}
+
+// ============================================================================
+// PRIMITIVE CELL
+// ============================================================================
+
+/// Lattice translations smaller than the cell (fractional, in [0, 1), zero excluded) that map
+/// every atom onto an atom of the same element within `symprec` (Å): the centring vectors.
+pub fn pure_translations(crystal: &Crystal, symprec: f64) -> Vec<Vector3<f64>> {
+    let atoms = &crystal.atoms;
+    let positions: Vec<Vector3<f64>> = atoms.iter().map(|a| a.fractional_coords).collect();
+    let cells = CellList::new(&crystal.lattice, &positions, symprec);
+    let matches = |i: usize, t: &Vector3<f64>| {
+        cells.query(&(positions[i] + t), symprec).iter().any(|n| atoms[n.index].element == atoms[i].element)
+    };
+
+    // Candidates: vectors from one atom of the rarest element to the others of that element
+    let Some(reference) = rarest_element_atom(atoms) else { return Vec::new() };
+    let mut translations: Vec<Vector3<f64>> = Vec::new();
+    for (j, atom) in atoms.iter().enumerate() {
+        if atom.element != atoms[reference].element {
+            continue;
+        }
+        let t = wrap_fractional(&(positions[j] - positions[reference]));
+        let is_zero = crystal.lattice.to_cartesian(&t.map(|x| x - x.round())).norm() < symprec;
+        let seen = translations.iter().any(|s| {
+            let d = t - s;
+            crystal.lattice.to_cartesian(&d.map(|x| x - x.round())).norm() < symprec
+        });
+        if !is_zero && !seen && (0..atoms.len()).all(|i| matches(i, &t)) {
+            translations.push(t);
+        }
+    }
+    translations
+}
+
+pub(crate) fn rarest_element_atom(atoms: &[Atom]) -> Option<usize> {
+    (0..atoms.len()).min_by_key(|&i| atoms.iter().filter(|a| a.element == atoms[i].element).count())
+}
+
+/// Niggli-reduced primitive cell: the lattice generated by the cell and its pure translations,
+/// with one atom per translation orbit. A structure that is already primitive is only reduced.
+pub fn find_primitive(crystal: &Crystal, symprec: f64) -> Result<ReducedCell> {
+    let translations = pure_translations(crystal, symprec);
+    let n_points = translations.len() + 1;
+    if !crystal.atoms.len().is_multiple_of(n_points) {
+        bail!(
+            "{} pure translations do not divide the {} atoms; try a smaller tolerance.",
+            translations.len(), crystal.atoms.len()
+        );
+    }
+
+    // Basis of the lattice spanned by the cell vectors and the translations, whose coordinates
+    // are multiples of 1/n (snapped to remove the noise of rounded CIF coordinates)
+    let scale = n_points as i64;
+    let mut generators: Vec<Vector3<i64>> = translations.iter().map(|t| t.map(|x| (x * scale as f64).round() as i64)).collect();
+    generators.extend([Vector3::x(), Vector3::y(), Vector3::z()].map(|e| e * scale));
+    let basis = lattice_basis(generators).cast::<f64>() / scale as f64;
+
+    let reduce = reduction::niggli_reduce(&(crystal.lattice.matrix * basis), NIGGLI_TOLERANCE)?;
+    let transformation = basis * reduce.cast::<f64>();
+    let lattice = Lattice::new(crystal.lattice.matrix * transformation).map_err(|e| anyhow!(e))?;
+
+    // One atom per orbit of the pure translations; every orbit must hold exactly n atoms (a
+    // repeated input site, or a tolerance that merges neighbours, gives a larger one)
+    let to_new = transformation.try_inverse().ok_or_else(|| anyhow!("Singular cell transformation."))?;
+    let mut atoms: Vec<Atom> = Vec::with_capacity(crystal.atoms.len() / n_points);
+    let mut orbit_sizes: Vec<usize> = Vec::with_capacity(crystal.atoms.len() / n_points);
+    for atom in &crystal.atoms {
+        let frac = wrap_fractional(&(to_new * atom.fractional_coords));
+        let duplicate = atoms.iter().position(|kept| {
+            kept.element == atom.element && lattice.minimum_image(&kept.fractional_coords, &frac).1.norm() < symprec
+        });
+        match duplicate {
+            Some(k) => orbit_sizes[k] += 1,
+            None => {
+                atoms.push(Atom { fractional_coords: frac, ..atom.clone() });
+                orbit_sizes.push(1);
+            }
+        }
+    }
+    if orbit_sizes.iter().any(|&size| size != n_points) {
+        bail!(
+            "Sites of the primitive cell absorb {} to {} atoms instead of {}; the input may repeat a site or the tolerance be too loose.",
+            orbit_sizes.iter().min().unwrap_or(&0), orbit_sizes.iter().max().unwrap_or(&0), n_points
+        );
+    }
+
+    Ok(ReducedCell { crystal: Crystal { lattice, atoms }, transformation })
+}
+
+/// Basis (columns, positive determinant) of the integer lattice spanned by `generators`, by
+/// column reduction to Hermite normal form. The generators must span all three dimensions.
+fn lattice_basis(mut generators: Vec<Vector3<i64>>) -> Matrix3<i64> {
+    for row in 0..3 {
+        // Euclid on this row across the remaining columns, until only the pivot is non-zero
+        loop {
+            let pivot = (row..generators.len())
+                .filter(|&j| generators[j][row] != 0)
+                .min_by_key(|&j| generators[j][row].abs())
+                .expect("generators span the lattice");
+            generators.swap(row, pivot);
+            let pivot = generators[row];
+            let mut reduced = true;
+            for column in &mut generators[row + 1..] {
+                *column -= pivot * (column[row] / pivot[row]);
+                reduced &= column[row] == 0;
+            }
+            if reduced {
+                break;
+            }
+        }
+        if generators[row][row] < 0 {
+            generators[row] = -generators[row];
+        }
+    }
+    Matrix3::from_columns(&generators[..3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::ComponentType;
+
+    #[test]
+    fn test_primitive_of_centred_cells() {
+        let atom = |element: &str, p: [f64; 3]| Atom::new(element, Vector3::from(p), ComponentType::Unknown);
+        let cubic = Lattice::from_parameters(4.0, 4.0, 4.0, 90.0, 90.0, 90.0).unwrap();
+
+        // Body-centred iron: two atoms -> one, half the volume
+        let bcc = Crystal { lattice: cubic.clone(), atoms: vec![atom("Fe", [0.0, 0.0, 0.0]), atom("Fe", [0.5, 0.5, 0.5])] };
+        let primitive = find_primitive(&bcc, DEFAULT_SYMPREC).unwrap();
+        assert_eq!(primitive.crystal.atoms.len(), 1);
+        assert!((primitive.crystal.lattice.matrix.determinant() - 32.0).abs() < 1e-9);
+
+        // Rock salt: eight atoms -> two, with the fcc translations; (100) maps to a (011)-type
+        // plane and back
+        let mut atoms = Vec::new();
+        for t in [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]] {
+            atoms.push(atom("Mg", t));
+            atoms.push(atom("O", [t[0] + 0.5, t[1], t[2]].map(|x: f64| x % 1.0)));
+        }
+        let rocksalt = Crystal { lattice: cubic, atoms };
+        assert_eq!(pure_translations(&rocksalt, DEFAULT_SYMPREC).len(), 3);
+        let primitive = find_primitive(&rocksalt, DEFAULT_SYMPREC).unwrap();
+        assert_eq!(primitive.crystal.atoms.len(), 2);
+        let (a, b, c, alpha, beta, gamma) = primitive.crystal.lattice.to_parameters();
+        for length in [a, b, c] {
+            assert!((length - 4.0 / 2f64.sqrt()).abs() < 1e-9);
+        }
+        for angle in [alpha, beta, gamma] {
+            assert!((angle - 60.0).abs() < 1e-6 || (angle - 120.0).abs() < 1e-6);
+        }
+        let hkl = primitive.miller_to_reduced([1, 0, 0]);
+        assert_eq!(hkl.iter().map(|h| h.abs()).sum::<i32>(), 2);
+        assert_eq!(primitive.miller_to_input(hkl), [1, 0, 0]);
+
+        // A primitive structure keeps its atoms
+        let two = Crystal {
+            lattice: Lattice::from_parameters(4.0, 4.0, 4.0, 90.0, 90.0, 90.0).unwrap(),
+            atoms: vec![atom("Cs", [0.0, 0.0, 0.0]), atom("Cl", [0.5, 0.5, 0.5])],
+        };
+        assert_eq!(find_primitive(&two, DEFAULT_SYMPREC).unwrap().crystal.atoms.len(), 2);
+
+        // Every site given twice: orbits of 2n atoms are refused
+        let mut doubled = bcc.clone();
+        doubled.atoms.extend(bcc.atoms.clone());
+        assert!(find_primitive(&doubled, DEFAULT_SYMPREC).is_err());
+    }
+}
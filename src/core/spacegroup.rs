@@ -0,0 +1,826 @@
+use nalgebra::{Matrix3, Vector3};
+use anyhow::{Result, anyhow, bail};
+
+// ============================================================================
+// SYMMETRY OPERATIONS
+// ============================================================================
+
+/// A crystallographic symmetry operation (W, w) acting on fractional coordinates:
+/// x' = W·x + w.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymOp {
+    /// Integer rotation part, expressed in the lattice basis.
+    pub rotation: Matrix3<i32>,
+    /// Fractional translation part.
+    pub translation: Vector3<f64>,
+}
+
+impl SymOp {
+    pub fn identity() -> Self {
+        Self { rotation: Matrix3::identity(), translation: Vector3::zeros() }
+    }
+
+    /// Applies the operation to a fractional position (no wrapping).
+    pub fn apply(&self, frac: &Vector3<f64>) -> Vector3<f64> {
+        self.rotation.map(|v| v as f64) * frac + self.translation
+    }
+
+    /// Parses a Jones-faithful symbol such as `-y+1/2, x-y, z+0.25`.
+    pub fn from_xyz(symbol: &str) -> Result<Self> {
+        let parts: Vec<&str> = symbol.split(',').collect();
+        if parts.len() != 3 {
+            bail!("Symmetry operator '{}' does not have three components", symbol);
+        }
+
+        let mut rotation = Matrix3::zeros();
+        let mut translation = Vector3::zeros();
+
+        for (row, part) in parts.iter().enumerate() {
+            let (coeffs, shift) = parse_xyz_component(part)
+                .ok_or_else(|| anyhow!("Cannot parse symmetry operator '{}'", symbol))?;
+            for col in 0..3 {
+                rotation[(row, col)] = coeffs[col];
+            }
+            translation[row] = shift;
+        }
+
+        Ok(Self { rotation, translation })
+    }
+}
+
+/// Parses one component of an `x,y,z` operator into integer coefficients and a shift.
+fn parse_xyz_component(s: &str) -> Option<([i32; 3], f64)> {
+    let mut coeffs = [0i32; 3];
+    let mut shift = 0.0;
+
+    let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.is_empty() { return None; }
+
+    let mut i = 0;
+    while i < chars.len() {
+        // Optional sign
+        let mut sign = 1.0;
+        if chars[i] == '+' || chars[i] == '-' {
+            if chars[i] == '-' { sign = -1.0; }
+            i += 1;
+        }
+
+        // Optional numeric literal (integer, decimal or fraction)
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '/') {
+            i += 1;
+        }
+        let number: Option<f64> = if i > start {
+            let literal: String = chars[start..i].iter().collect();
+            Some(parse_fraction(&literal)?)
+        } else {
+            None
+        };
+
+        // Optional axis letter; a number directly in front of it is a coefficient
+        if i < chars.len() {
+            let axis = match chars[i].to_ascii_lowercase() {
+                'x' => Some(0),
+                'y' => Some(1),
+                'z' => Some(2),
+                _ => None,
+            };
+            if let Some(axis) = axis {
+                let factor = number.unwrap_or(1.0) * sign;
+                coeffs[axis] += factor.round() as i32;
+                i += 1;
+                continue;
+            }
+            if chars[i] != '+' && chars[i] != '-' {
+                return None;
+            }
+        }
+
+        match number {
+            Some(n) => shift += sign * n,
+            None => return None,
+        }
+    }
+
+    Some((coeffs, shift))
+}
+
+fn parse_fraction(s: &str) -> Option<f64> {
+    if let Some((num, den)) = s.split_once('/') {
+        let n: f64 = num.parse().ok()?;
+        let d: f64 = den.parse().ok()?;
+        if d == 0.0 { return None; }
+        Some(n / d)
+    } else {
+        s.parse().ok()
+    }
+}
+
+/// Removes enclosing CIF quotes. Only a matching pair is stripped, since `"` is also a
+/// legitimate axis symbol inside Hall symbols (`P 3 2"`).
+fn strip_quotes(s: &str) -> &str {
+    let s = s.trim();
+    let quoted = s.len() >= 2
+        && ((s.starts_with('\'') && s.ends_with('\'')) || (s.starts_with('"') && s.ends_with('"')));
+    if quoted { s[1..s.len() - 1].trim() } else { s }
+}
+
+/// Wraps a fractional coordinate into [0, 1), guarding against `rem_euclid` returning 1.0
+/// for tiny negative inputs.
+pub fn wrap_fractional(frac: &Vector3<f64>) -> Vector3<f64> {
+    frac.map(|x| {
+        let w = x.rem_euclid(1.0);
+        if w >= 1.0 - 1e-12 { 0.0 } else { w }
+    })
+}
+
+// ============================================================================
+// HALL SYMBOL DECODER
+// ============================================================================
+
+/// Internal exact representation: translations are stored in units of 1/12.
+#[derive(Clone, PartialEq, Eq)]
+struct ExactOp {
+    rotation: Matrix3<i32>,
+    translation: Vector3<i32>,
+}
+
+impl ExactOp {
+    fn new(rotation: Matrix3<i32>, translation: Vector3<i32>) -> Self {
+        Self { rotation, translation: translation.map(|t| t.rem_euclid(12)) }
+    }
+
+    fn compose(&self, other: &ExactOp) -> ExactOp {
+        ExactOp::new(
+            self.rotation * other.rotation,
+            self.rotation * other.translation + self.translation,
+        )
+    }
+
+    fn to_symop(&self) -> SymOp {
+        SymOp {
+            rotation: self.rotation,
+            translation: self.translation.map(|t| t as f64 / 12.0),
+        }
+    }
+}
+
+/// Rotation matrices for the principal axis `z`; other axes are obtained by permutation.
+fn principal_rotation(order: i32) -> Option<Matrix3<i32>> {
+    match order {
+        1 => Some(Matrix3::identity()),
+        2 => Some(Matrix3::new(-1, 0, 0, 0, -1, 0, 0, 0, 1)),
+        3 => Some(Matrix3::new(0, -1, 0, 1, -1, 0, 0, 0, 1)),
+        4 => Some(Matrix3::new(0, -1, 0, 1, 0, 0, 0, 0, 1)),
+        6 => Some(Matrix3::new(1, -1, 0, 1, 0, 0, 0, 0, 1)),
+        _ => None,
+    }
+}
+
+/// Re-expresses a matrix defined for the `z` axis so that it acts about `axis` (0 = x, 1 = y).
+fn permute_axis(m: &Matrix3<i32>, axis: usize) -> Matrix3<i32> {
+    // role(i): which z-frame index the coordinate i plays.
+    let role = |i: usize| (i + 3 - axis + 2) % 3;
+    Matrix3::from_fn(|i, j| m[(role(i), role(j))])
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AxisKind {
+    Principal,
+    Prime,
+    DoublePrime,
+    BodyDiagonal,
+}
+
+/// Decodes a Hall symbol (e.g. `-P 2ac 2n`, `P 31 2c (0 0 1)`) into the full list of
+/// operations, including lattice centering translations.
+pub fn operations_from_hall(hall: &str) -> Result<Vec<SymOp>> {
+    let hall = strip_quotes(hall);
+
+    // Split off the optional change-of-basis (origin shift) part.
+    let (body, shift) = match hall.find('(') {
+        Some(pos) => {
+            let inner = hall[pos + 1..].trim_end_matches(')').trim();
+            let values: Vec<i32> = inner
+                .split_whitespace()
+                .map(|v| v.parse::<i32>())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| anyhow!("Unsupported change-of-basis in Hall symbol '{}'", hall))?;
+            if values.len() != 3 {
+                bail!("Unsupported change-of-basis in Hall symbol '{}'", hall);
+            }
+            (&hall[..pos], Some(Vector3::new(values[0], values[1], values[2])))
+        }
+        None => (hall, None),
+    };
+
+    let mut tokens = body.split_whitespace();
+    let lattice_token = tokens.next().ok_or_else(|| anyhow!("Empty Hall symbol"))?;
+
+    let (centrosymmetric, lattice_letter) = match lattice_token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, lattice_token),
+    };
+
+    let centering: Vec<Vector3<i32>> = match lattice_letter.to_ascii_uppercase().as_str() {
+        "P" => vec![],
+        "A" => vec![Vector3::new(0, 6, 6)],
+        "B" => vec![Vector3::new(6, 0, 6)],
+        "C" => vec![Vector3::new(6, 6, 0)],
+        "I" => vec![Vector3::new(6, 6, 6)],
+        "R" => vec![Vector3::new(8, 4, 4), Vector3::new(4, 8, 8)],
+        "F" => vec![Vector3::new(0, 6, 6), Vector3::new(6, 0, 6), Vector3::new(6, 6, 0)],
+        other => bail!("Unknown lattice symbol '{}' in Hall symbol '{}'", other, hall),
+    };
+
+    let mut generators: Vec<ExactOp> = centering
+        .iter()
+        .map(|c| ExactOp::new(Matrix3::identity(), *c))
+        .collect();
+    if centrosymmetric {
+        generators.push(ExactOp::new(-Matrix3::<i32>::identity(), Vector3::zeros()));
+    }
+
+    let mut previous_order = 0;
+    let mut previous_axis = 2usize;
+
+    for (position, token) in tokens.enumerate() {
+        let chars: Vec<char> = token.chars().collect();
+        let mut idx = 0;
+
+        let improper = chars.first() == Some(&'-');
+        if improper { idx += 1; }
+
+        let order = chars
+            .get(idx)
+            .and_then(|c| c.to_digit(10))
+            .ok_or_else(|| anyhow!("Bad matrix symbol '{}' in Hall symbol '{}'", token, hall))?
+            as i32;
+        idx += 1;
+
+        let mut axis: Option<usize> = None;
+        let mut kind = AxisKind::Principal;
+        let mut screw = 0;
+        let mut translation = Vector3::<i32>::zeros();
+
+        for &c in &chars[idx..] {
+            match c {
+                'x' => axis = Some(0),
+                'y' => axis = Some(1),
+                'z' => axis = Some(2),
+                '\'' => kind = AxisKind::Prime,
+                '"' => kind = AxisKind::DoublePrime,
+                '*' => kind = AxisKind::BodyDiagonal,
+                '1'..='5' => screw = c.to_digit(10).unwrap() as i32,
+                'a' => translation += Vector3::new(6, 0, 0),
+                'b' => translation += Vector3::new(0, 6, 0),
+                'c' => translation += Vector3::new(0, 0, 6),
+                'n' => translation += Vector3::new(6, 6, 6),
+                'u' => translation += Vector3::new(3, 0, 0),
+                'v' => translation += Vector3::new(0, 3, 0),
+                'w' => translation += Vector3::new(0, 0, 3),
+                'd' => translation += Vector3::new(3, 3, 3),
+                _ => bail!("Bad matrix symbol '{}' in Hall symbol '{}'", token, hall),
+            }
+        }
+
+        // Default axis rules (Hall, 1981).
+        if axis.is_none() && kind == AxisKind::Principal {
+            match position {
+                0 => axis = Some(2),
+                1 if order == 2 => {
+                    if previous_order == 2 || previous_order == 4 {
+                        axis = Some(0);
+                    } else if previous_order == 3 || previous_order == 6 {
+                        kind = AxisKind::Prime;
+                    }
+                }
+                2 if order == 3 => kind = AxisKind::BodyDiagonal,
+                _ => {}
+            }
+        }
+
+        let rotation_z = match kind {
+            AxisKind::Principal => principal_rotation(order),
+            AxisKind::Prime if order == 2 => Some(Matrix3::new(0, -1, 0, -1, 0, 0, 0, 0, -1)),
+            AxisKind::DoublePrime if order == 2 => Some(Matrix3::new(0, 1, 0, 1, 0, 0, 0, 0, -1)),
+            AxisKind::BodyDiagonal if order == 3 => Some(Matrix3::new(0, 0, 1, 1, 0, 0, 0, 1, 0)),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("Unsupported rotation '{}' in Hall symbol '{}'", token, hall))?;
+
+        // Primed axes are defined relative to the preceding axis.
+        let frame_axis = match kind {
+            AxisKind::Principal => axis.unwrap_or(2),
+            _ => previous_axis,
+        };
+        let mut rotation = if frame_axis == 2 { rotation_z } else { permute_axis(&rotation_z, frame_axis) };
+        if improper { rotation = -rotation; }
+
+        if screw > 0 && kind == AxisKind::Principal {
+            translation[frame_axis] += 12 * screw / order;
+        }
+
+        generators.push(ExactOp::new(rotation, translation));
+        previous_order = order;
+        if kind == AxisKind::Principal {
+            previous_axis = frame_axis;
+        }
+    }
+
+    let mut ops = close_group(&generators);
+
+    if let Some(v) = shift {
+        ops = ops
+            .into_iter()
+            .map(|op| {
+                let t = op.translation + v - op.rotation * v;
+                ExactOp::new(op.rotation, t)
+            })
+            .collect();
+    }
+
+    Ok(ops.iter().map(ExactOp::to_symop).collect())
+}
+
+/// Generates the full group (modulo lattice translations) from a set of generators.
+fn close_group(generators: &[ExactOp]) -> Vec<ExactOp> {
+    let mut group = vec![ExactOp::new(Matrix3::identity(), Vector3::zeros())];
+    let mut frontier = group.clone();
+
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for op in &frontier {
+            for gen in generators {
+                let product = gen.compose(op);
+                if !group.contains(&product) {
+                    group.push(product.clone());
+                    next.push(product);
+                }
+            }
+        }
+        frontier = next;
+    }
+    group
+}
+
+// ============================================================================
+// SPACE GROUP TABLE
+// ============================================================================
+
+/// One setting of a space group: (IT number, Hermann–Mauguin symbol, Hall symbol, setting code).
+///
+/// The setting code is empty for unique settings, `"1"`/`"2"` for origin choices and
+/// `"H"`/`"R"` for hexagonal/rhombohedral axes of the R groups.
+pub type SpaceGroupSetting = (u16, &'static str, &'static str, &'static str);
+
+/// Standard settings of the 230 space groups (ITA conventions; monoclinic b-unique, cell choice 1).
+pub const SPACE_GROUPS: &[SpaceGroupSetting] = &[
+    (1, "P 1", "P 1", ""),
+    (2, "P -1", "-P 1", ""),
+    (3, "P 2", "P 2y", ""),
+    (4, "P 21", "P 2yb", ""),
+    (5, "C 2", "C 2y", ""),
+    (6, "P m", "P -2y", ""),
+    (7, "P c", "P -2yc", ""),
+    (8, "C m", "C -2y", ""),
+    (9, "C c", "C -2yc", ""),
+    (10, "P 2/m", "-P 2y", ""),
+    (11, "P 21/m", "-P 2yb", ""),
+    (12, "C 2/m", "-C 2y", ""),
+    (13, "P 2/c", "-P 2yc", ""),
+    (14, "P 21/c", "-P 2ybc", ""),
+    (15, "C 2/c", "-C 2yc", ""),
+    (16, "P 2 2 2", "P 2 2", ""),
+    (17, "P 2 2 21", "P 2c 2", ""),
+    (18, "P 21 21 2", "P 2 2ab", ""),
+    (19, "P 21 21 21", "P 2ac 2ab", ""),
+    (20, "C 2 2 21", "C 2c 2", ""),
+    (21, "C 2 2 2", "C 2 2", ""),
+    (22, "F 2 2 2", "F 2 2", ""),
+    (23, "I 2 2 2", "I 2 2", ""),
+    (24, "I 21 21 21", "I 2b 2c", ""),
+    (25, "P m m 2", "P 2 -2", ""),
+    (26, "P m c 21", "P 2c -2", ""),
+    (27, "P c c 2", "P 2 -2c", ""),
+    (28, "P m a 2", "P 2 -2a", ""),
+    (29, "P c a 21", "P 2c -2ac", ""),
+    (30, "P n c 2", "P 2 -2bc", ""),
+    (31, "P m n 21", "P 2ac -2", ""),
+    (32, "P b a 2", "P 2 -2ab", ""),
+    (33, "P n a 21", "P 2c -2n", ""),
+    (34, "P n n 2", "P 2 -2n", ""),
+    (35, "C m m 2", "C 2 -2", ""),
+    (36, "C m c 21", "C 2c -2", ""),
+    (37, "C c c 2", "C 2 -2c", ""),
+    (38, "A m m 2", "A 2 -2", ""),
+    (39, "A e m 2", "A 2 -2c", ""),
+    (40, "A m a 2", "A 2 -2a", ""),
+    (41, "A e a 2", "A 2 -2ac", ""),
+    (42, "F m m 2", "F 2 -2", ""),
+    (43, "F d d 2", "F 2 -2d", ""),
+    (44, "I m m 2", "I 2 -2", ""),
+    (45, "I b a 2", "I 2 -2c", ""),
+    (46, "I m a 2", "I 2 -2a", ""),
+    (47, "P m m m", "-P 2 2", ""),
+    (48, "P n n n", "P 2 2 -1n", "1"),
+    (48, "P n n n", "-P 2ab 2bc", "2"),
+    (49, "P c c m", "-P 2 2c", ""),
+    (50, "P b a n", "P 2 2 -1ab", "1"),
+    (50, "P b a n", "-P 2ab 2b", "2"),
+    (51, "P m m a", "-P 2a 2a", ""),
+    (52, "P n n a", "-P 2a 2bc", ""),
+    (53, "P m n a", "-P 2ac 2", ""),
+    (54, "P c c a", "-P 2a 2ac", ""),
+    (55, "P b a m", "-P 2 2ab", ""),
+    (56, "P c c n", "-P 2ab 2ac", ""),
+    (57, "P b c m", "-P 2c 2b", ""),
+    (58, "P n n m", "-P 2 2n", ""),
+    (59, "P m m n", "P 2 2ab -1ab", "1"),
+    (59, "P m m n", "-P 2ab 2a", "2"),
+    (60, "P b c n", "-P 2n 2ab", ""),
+    (61, "P b c a", "-P 2ac 2ab", ""),
+    (62, "P n m a", "-P 2ac 2n", ""),
+    (63, "C m c m", "-C 2c 2", ""),
+    (64, "C m c e", "-C 2bc 2", ""),
+    (65, "C m m m", "-C 2 2", ""),
+    (66, "C c c m", "-C 2 2c", ""),
+    (67, "C m m e", "-C 2b 2", ""),
+    (68, "C c c e", "C 2 2 -1bc", "1"),
+    (68, "C c c e", "-C 2b 2bc", "2"),
+    (69, "F m m m", "-F 2 2", ""),
+    (70, "F d d d", "F 2 2 -1d", "1"),
+    (70, "F d d d", "-F 2uv 2vw", "2"),
+    (71, "I m m m", "-I 2 2", ""),
+    (72, "I b a m", "-I 2 2c", ""),
+    (73, "I b c a", "-I 2b 2c", ""),
+    (74, "I m m a", "-I 2b 2", ""),
+    (75, "P 4", "P 4", ""),
+    (76, "P 41", "P 4w", ""),
+    (77, "P 42", "P 4c", ""),
+    (78, "P 43", "P 4cw", ""),
+    (79, "I 4", "I 4", ""),
+    (80, "I 41", "I 4bw", ""),
+    (81, "P -4", "P -4", ""),
+    (82, "I -4", "I -4", ""),
+    (83, "P 4/m", "-P 4", ""),
+    (84, "P 42/m", "-P 4c", ""),
+    (85, "P 4/n", "P 4ab -1ab", "1"),
+    (85, "P 4/n", "-P 4a", "2"),
+    (86, "P 42/n", "P 4n -1n", "1"),
+    (86, "P 42/n", "-P 4bc", "2"),
+    (87, "I 4/m", "-I 4", ""),
+    (88, "I 41/a", "I 4bw -1bw", "1"),
+    (88, "I 41/a", "-I 4ad", "2"),
+    (89, "P 4 2 2", "P 4 2", ""),
+    (90, "P 4 21 2", "P 4ab 2ab", ""),
+    (91, "P 41 2 2", "P 4w 2c", ""),
+    (92, "P 41 21 2", "P 4abw 2nw", ""),
+    (93, "P 42 2 2", "P 4c 2", ""),
+    (94, "P 42 21 2", "P 4n 2n", ""),
+    (95, "P 43 2 2", "P 4cw 2c", ""),
+    (96, "P 43 21 2", "P 4nw 2abw", ""),
+    (97, "I 4 2 2", "I 4 2", ""),
+    (98, "I 41 2 2", "I 4bw 2bw", ""),
+    (99, "P 4 m m", "P 4 -2", ""),
+    (100, "P 4 b m", "P 4 -2ab", ""),
+    (101, "P 42 c m", "P 4c -2c", ""),
+    (102, "P 42 n m", "P 4n -2n", ""),
+    (103, "P 4 c c", "P 4 -2c", ""),
+    (104, "P 4 n c", "P 4 -2n", ""),
+    (105, "P 42 m c", "P 4c -2", ""),
+    (106, "P 42 b c", "P 4c -2ab", ""),
+    (107, "I 4 m m", "I 4 -2", ""),
+    (108, "I 4 c m", "I 4 -2c", ""),
+    (109, "I 41 m d", "I 4bw -2", ""),
+    (110, "I 41 c d", "I 4bw -2c", ""),
+    (111, "P -4 2 m", "P -4 2", ""),
+    (112, "P -4 2 c", "P -4 2c", ""),
+    (113, "P -4 21 m", "P -4 2ab", ""),
+    (114, "P -4 21 c", "P -4 2n", ""),
+    (115, "P -4 m 2", "P -4 -2", ""),
+    (116, "P -4 c 2", "P -4 -2c", ""),
+    (117, "P -4 b 2", "P -4 -2ab", ""),
+    (118, "P -4 n 2", "P -4 -2n", ""),
+    (119, "I -4 m 2", "I -4 -2", ""),
+    (120, "I -4 c 2", "I -4 -2c", ""),
+    (121, "I -4 2 m", "I -4 2", ""),
+    (122, "I -4 2 d", "I -4 2bw", ""),
+    (123, "P 4/m m m", "-P 4 2", ""),
+    (124, "P 4/m c c", "-P 4 2c", ""),
+    (125, "P 4/n b m", "P 4 2 -1ab", "1"),
+    (125, "P 4/n b m", "-P 4a 2b", "2"),
+    (126, "P 4/n n c", "P 4 2 -1n", "1"),
+    (126, "P 4/n n c", "-P 4a 2bc", "2"),
+    (127, "P 4/m b m", "-P 4 2ab", ""),
+    (128, "P 4/m n c", "-P 4 2n", ""),
+    (129, "P 4/n m m", "P 4ab 2ab -1ab", "1"),
+    (129, "P 4/n m m", "-P 4a 2a", "2"),
+    (130, "P 4/n c c", "P 4ab 2n -1ab", "1"),
+    (130, "P 4/n c c", "-P 4a 2ac", "2"),
+    (131, "P 42/m m c", "-P 4c 2", ""),
+    (132, "P 42/m c m", "-P 4c 2c", ""),
+    (133, "P 42/n b c", "P 4n 2c -1n", "1"),
+    (133, "P 42/n b c", "-P 4ac 2b", "2"),
+    (134, "P 42/n n m", "P 4n 2 -1n", "1"),
+    (134, "P 42/n n m", "-P 4ac 2bc", "2"),
+    (135, "P 42/m b c", "-P 4c 2ab", ""),
+    (136, "P 42/m n m", "-P 4n 2n", ""),
+    (137, "P 42/n m c", "P 4n 2n -1n", "1"),
+    (137, "P 42/n m c", "-P 4ac 2a", "2"),
+    (138, "P 42/n c m", "P 4n 2ab -1n", "1"),
+    (138, "P 42/n c m", "-P 4ac 2ac", "2"),
+    (139, "I 4/m m m", "-I 4 2", ""),
+    (140, "I 4/m c m", "-I 4 2c", ""),
+    (141, "I 41/a m d", "I 4bw 2bw -1bw", "1"),
+    (141, "I 41/a m d", "-I 4bd 2", "2"),
+    (142, "I 41/a c d", "I 4bw 2aw -1bw", "1"),
+    (142, "I 41/a c d", "-I 4bd 2c", "2"),
+    (143, "P 3", "P 3", ""),
+    (144, "P 31", "P 31", ""),
+    (145, "P 32", "P 32", ""),
+    (146, "R 3", "R 3", "H"),
+    (146, "R 3", "P 3*", "R"),
+    (147, "P -3", "-P 3", ""),
+    (148, "R -3", "-R 3", "H"),
+    (148, "R -3", "-P 3*", "R"),
+    (149, "P 3 1 2", "P 3 2", ""),
+    (150, "P 3 2 1", "P 3 2\"", ""),
+    (151, "P 31 1 2", "P 31 2c (0 0 1)", ""),
+    (152, "P 31 2 1", "P 31 2\"", ""),
+    (153, "P 32 1 2", "P 32 2c (0 0 -1)", ""),
+    (154, "P 32 2 1", "P 32 2\"", ""),
+    (155, "R 3 2", "R 3 2\"", "H"),
+    (155, "R 3 2", "P 3* 2", "R"),
+    (156, "P 3 m 1", "P 3 -2\"", ""),
+    (157, "P 3 1 m", "P 3 -2", ""),
+    (158, "P 3 c 1", "P 3 -2\"c", ""),
+    (159, "P 3 1 c", "P 3 -2c", ""),
+    (160, "R 3 m", "R 3 -2\"", "H"),
+    (160, "R 3 m", "P 3* -2", "R"),
+    (161, "R 3 c", "R 3 -2\"c", "H"),
+    (161, "R 3 c", "P 3* -2n", "R"),
+    (162, "P -3 1 m", "-P 3 2", ""),
+    (163, "P -3 1 c", "-P 3 2c", ""),
+    (164, "P -3 m 1", "-P 3 2\"", ""),
+    (165, "P -3 c 1", "-P 3 2\"c", ""),
+    (166, "R -3 m", "-R 3 2\"", "H"),
+    (166, "R -3 m", "-P 3* 2", "R"),
+    (167, "R -3 c", "-R 3 2\"c", "H"),
+    (167, "R -3 c", "-P 3* 2n", "R"),
+    (168, "P 6", "P 6", ""),
+    (169, "P 61", "P 61", ""),
+    (170, "P 65", "P 65", ""),
+    (171, "P 62", "P 62", ""),
+    (172, "P 64", "P 64", ""),
+    (173, "P 63", "P 6c", ""),
+    (174, "P -6", "P -6", ""),
+    (175, "P 6/m", "-P 6", ""),
+    (176, "P 63/m", "-P 6c", ""),
+    (177, "P 6 2 2", "P 6 2", ""),
+    (178, "P 61 2 2", "P 61 2 (0 0 -1)", ""),
+    (179, "P 65 2 2", "P 65 2 (0 0 1)", ""),
+    (180, "P 62 2 2", "P 62 2c (0 0 1)", ""),
+    (181, "P 64 2 2", "P 64 2c (0 0 -1)", ""),
+    (182, "P 63 2 2", "P 6c 2c", ""),
+    (183, "P 6 m m", "P 6 -2", ""),
+    (184, "P 6 c c", "P 6 -2c", ""),
+    (185, "P 63 c m", "P 6c -2", ""),
+    (186, "P 63 m c", "P 6c -2c", ""),
+    (187, "P -6 m 2", "P -6 2", ""),
+    (188, "P -6 c 2", "P -6c 2", ""),
+    (189, "P -6 2 m", "P -6 -2", ""),
+    (190, "P -6 2 c", "P -6c -2c", ""),
+    (191, "P 6/m m m", "-P 6 2", ""),
+    (192, "P 6/m c c", "-P 6 2c", ""),
+    (193, "P 63/m c m", "-P 6c 2", ""),
+    (194, "P 63/m m c", "-P 6c 2c", ""),
+    (195, "P 2 3", "P 2 2 3", ""),
+    (196, "F 2 3", "F 2 2 3", ""),
+    (197, "I 2 3", "I 2 2 3", ""),
+    (198, "P 21 3", "P 2ac 2ab 3", ""),
+    (199, "I 21 3", "I 2b 2c 3", ""),
+    (200, "P m -3", "-P 2 2 3", ""),
+    (201, "P n -3", "P 2 2 3 -1n", "1"),
+    (201, "P n -3", "-P 2ab 2bc 3", "2"),
+    (202, "F m -3", "-F 2 2 3", ""),
+    (203, "F d -3", "F 2 2 3 -1d", "1"),
+    (203, "F d -3", "-F 2uv 2vw 3", "2"),
+    (204, "I m -3", "-I 2 2 3", ""),
+    (205, "P a -3", "-P 2ac 2ab 3", ""),
+    (206, "I a -3", "-I 2b 2c 3", ""),
+    (207, "P 4 3 2", "P 4 2 3", ""),
+    (208, "P 42 3 2", "P 4n 2 3", ""),
+    (209, "F 4 3 2", "F 4 2 3", ""),
+    (210, "F 41 3 2", "F 4d 2 3", ""),
+    (211, "I 4 3 2", "I 4 2 3", ""),
+    (212, "P 43 3 2", "P 4acd 2ab 3", ""),
+    (213, "P 41 3 2", "P 4bd 2ab 3", ""),
+    (214, "I 41 3 2", "I 4bd 2c 3", ""),
+    (215, "P -4 3 m", "P -4 2 3", ""),
+    (216, "F -4 3 m", "F -4 2 3", ""),
+    (217, "I -4 3 m", "I -4 2 3", ""),
+    (218, "P -4 3 n", "P -4n 2 3", ""),
+    (219, "F -4 3 c", "F -4c 2 3", ""),
+    (220, "I -4 3 d", "I -4bd 2c 3", ""),
+    (221, "P m -3 m", "-P 4 2 3", ""),
+    (222, "P n -3 n", "P 4 2 3 -1n", "1"),
+    (222, "P n -3 n", "-P 4a 2bc 3", "2"),
+    (223, "P m -3 n", "-P 4n 2 3", ""),
+    (224, "P n -3 m", "P 4n 2 3 -1n", "1"),
+    (224, "P n -3 m", "-P 4bc 2bc 3", "2"),
+    (225, "F m -3 m", "-F 4 2 3", ""),
+    (226, "F m -3 c", "-F 4c 2 3", ""),
+    (227, "F d -3 m", "F 4d 2 3 -1d", "1"),
+    (227, "F d -3 m", "-F 4vw 2vw 3", "2"),
+    (228, "F d -3 c", "F 4d 2 3 -1cd", "1"),
+    (228, "F d -3 c", "-F 4cvw 2vw 3", "2"),
+    (229, "I m -3 m", "-I 4 2 3", ""),
+    (230, "I a -3 d", "-I 4bd 2c 3", ""),
+];
+
+/// Older or alternative H-M names that still appear in deposited CIFs.
+const SYMBOL_ALIASES: &[(&str, &str)] = &[
+    ("Abm2", "Aem2"),
+    ("Aba2", "Aea2"),
+    ("Cmca", "Cmce"),
+    ("Cmma", "Cmme"),
+    ("Ccca", "Ccce"),
+];
+
+/// Normalises an H-M symbol for comparison: drops whitespace, quotes and underscores.
+fn normalize_hm(symbol: &str) -> String {
+    let s: String = symbol
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '\'' && *c != '"')
+        .collect();
+
+    for (alias, canonical) in SYMBOL_ALIASES {
+        if s.eq_ignore_ascii_case(alias) {
+            return canonical.to_string();
+        }
+    }
+    s
+}
+
+/// Short form of a full b-unique monoclinic symbol (`P 1 21/c 1` -> `P21/c`), when the table
+/// stores that short form. c- and a-unique symbols (`P 1 1 21`, `B 1 1 2/m`, `P 21 1 1`) are
+/// other settings, which the table does not hold, and are left alone.
+fn short_monoclinic(key: &str) -> Option<String> {
+    let axis = key.get(2..key.len().checked_sub(1)?)?;
+    if key.get(1..2)? != "1" || !key.ends_with('1') || axis.is_empty() || axis.starts_with('1') {
+        return None;
+    }
+    let short = format!("{}{}", &key[..1], axis);
+    SPACE_GROUPS
+        .iter()
+        .any(|entry| (3..=15).contains(&entry.0) && normalize_hm(entry.1).eq_ignore_ascii_case(&short))
+        .then_some(short)
+}
+
+/// Looks up a table setting by Hermann–Mauguin symbol.
+///
+/// Accepts suffixes selecting the setting: `:1`/`:2` (origin choice, `S`/`Z` as used by ICSD)
+/// and `:H`/`:R` (R-group axes). Without a suffix, origin choice 2 and hexagonal axes are
+/// assumed, which matches what most CIF writers emit.
+pub fn find_by_hm(symbol: &str) -> Option<&'static SpaceGroupSetting> {
+    let symbol = strip_quotes(symbol);
+
+    let (name, requested) = match symbol.rsplit_once(':') {
+        Some((name, setting)) => (name, Some(setting.trim().to_ascii_uppercase())),
+        None => {
+            // ICSD style trailing " S" / " Z"
+            let trimmed = symbol.trim_end();
+            if let Some(name) = trimmed.strip_suffix(" S") {
+                (name, Some("1".to_string()))
+            } else if let Some(name) = trimmed.strip_suffix(" Z") {
+                (name, Some("2".to_string()))
+            } else {
+                (symbol, None)
+            }
+        }
+    };
+
+    let key = normalize_hm(name);
+    let key = short_monoclinic(&key).unwrap_or(key);
+    let candidates: Vec<&SpaceGroupSetting> = SPACE_GROUPS
+        .iter()
+        .filter(|entry| normalize_hm(entry.1).eq_ignore_ascii_case(&key))
+        .collect();
+
+    select_setting(&candidates, requested.as_deref())
+}
+
+/// Looks up the default table setting for an International Tables number.
+pub fn find_by_number(number: u16) -> Option<&'static SpaceGroupSetting> {
+    let candidates: Vec<&SpaceGroupSetting> = SPACE_GROUPS
+        .iter()
+        .filter(|entry| entry.0 == number)
+        .collect();
+    select_setting(&candidates, None)
+}
+
+fn select_setting(
+    candidates: &[&'static SpaceGroupSetting],
+    requested: Option<&str>,
+) -> Option<&'static SpaceGroupSetting> {
+    if let Some(code) = requested {
+        if let Some(entry) = candidates.iter().find(|e| e.3 == code) {
+            return Some(entry);
+        }
+    }
+    candidates
+        .iter()
+        .find(|e| e.3.is_empty() || e.3 == "2" || e.3 == "H")
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_group_order(number: u16) -> usize {
+        match number {
+            1 => 1,
+            2..=9 => 2,
+            10..=46 => 4,
+            47..=74 => 8,
+            75..=82 => 4,
+            83..=122 => 8,
+            123..=142 => 16,
+            143..=146 => 3,
+            147..=161 => 6,
+            162..=167 => 12,
+            168..=174 => 6,
+            175..=190 => 12,
+            191..=194 => 24,
+            195..=199 => 12,
+            200..=220 => 24,
+            _ => 48,
+        }
+    }
+
+    #[test]
+    fn test_table_group_orders() {
+        for &(number, hm, hall, setting) in SPACE_GROUPS {
+            let ops = operations_from_hall(hall).unwrap();
+            let centering = match (hm.as_bytes()[0], setting) {
+                (b'R', "R") | (b'P', _) => 1,
+                (b'R', _) => 3,
+                (b'F', _) => 4,
+                _ => 2,
+            };
+            assert_eq!(
+                ops.len(),
+                point_group_order(number) * centering,
+                "Wrong order for {} ({}) from Hall '{}'",
+                number, hm, hall
+            );
+        }
+    }
+
+    #[test]
+    fn test_hall_matches_pnma_operators() {
+        let expected = [
+            "x,y,z", "-x+1/2,-y,z+1/2", "-x,y+1/2,-z", "x+1/2,-y+1/2,-z+1/2",
+            "-x,-y,-z", "x+1/2,y,-z+1/2", "x,-y+1/2,z", "-x+1/2,y+1/2,z+1/2",
+        ];
+        let ops = operations_from_hall("-P 2ac 2n").unwrap();
+        assert_eq!(ops.len(), expected.len());
+        for symbol in expected {
+            let op = SymOp::from_xyz(symbol).unwrap();
+            assert!(
+                ops.iter().any(|o| o.rotation == op.rotation
+                    && (o.translation - op.translation).norm() < 1e-9),
+                "Missing operator {}", symbol
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_xyz_variants() {
+        let op = SymOp::from_xyz("1/2+X, -y+0.5 , z-x").unwrap();
+        assert_eq!(op.rotation, Matrix3::new(1, 0, 0, 0, -1, 0, -1, 0, 1));
+        assert!((op.translation - Vector3::new(0.5, 0.5, 0.0)).norm() < 1e-12);
+        assert!(SymOp::from_xyz("x, y").is_err());
+    }
+
+    #[test]
+    fn test_hm_lookup() {
+        assert_eq!(find_by_hm("'Fm-3m    '").unwrap().0, 225);
+        assert_eq!(find_by_hm("P 1 21/c 1").unwrap().0, 14);
+        assert_eq!(find_by_hm("F d -3 m:1").unwrap().2, "F 4d 2 3 -1d");
+        assert_eq!(find_by_hm("Cmca").unwrap().0, 64);
+        assert_eq!(find_by_hm("R -3 m :R").unwrap().2, "-P 3* 2");
+        assert!(find_by_hm("Q 7").is_none());
+
+        // Full b-unique monoclinic symbols reduce to the table's short form
+        assert_eq!(find_by_hm("C 1 2/c 1").unwrap().0, 15);
+        assert_eq!(find_by_hm("P121/c1").unwrap().0, 14);
+        assert_eq!(find_by_hm("P 1 m 1").unwrap().0, 6);
+        // c- and a-unique settings are not in the table and must not match another group
+        for symbol in ["P 1 1 21", "P 1 1 2", "B 1 1 2/m", "P 1 1 21/b", "P 21 1 1", "P 2/m 1 1", "A 1 1 2/a"] {
+            assert!(find_by_hm(symbol).is_none(), "{}", symbol);
+        }
+    }
+}
@@ -1,5 +1,9 @@
-use nalgebra::{Matrix3, Vector3};
-use std::fmt;
+use crate::core::primitive::{self, ReducedCell};
+use crate::core::symmetry::{self, SymmetryDataset};
+use crate::math::reduction;
+use anyhow::{anyhow, bail, Result};
+use nalgebra::{Matrix2, Matrix3, Vector3};
+use std::collections::{BTreeMap, HashSet};
 
 // ============================================================================
 // TRAITS
@@ -33,6 +37,119 @@ pub enum ComponentType {
     Adsorbate,
 }
 
+impl ComponentType {
+    pub const ALL: [ComponentType; 5] = [
+        ComponentType::Unknown,
+        ComponentType::MetalNode,
+        ComponentType::OrganicLinker,
+        ComponentType::Solvent,
+        ComponentType::Adsorbate,
+    ];
+
+    /// Stable name used in output files ("MetalNode", ...).
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComponentType::Unknown => "Unknown",
+            ComponentType::MetalNode => "MetalNode",
+            ComponentType::OrganicLinker => "OrganicLinker",
+            ComponentType::Solvent => "Solvent",
+            ComponentType::Adsorbate => "Adsorbate",
+        }
+    }
+
+    /// Inverse of `name` (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A value in an atom's property map (see `Atom::properties`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtomProperty {
+    Float(f64),
+    Int(i64),
+    Text(String),
+    Bool(bool),
+    Vector(Vector3<f64>),
+}
+
+impl AtomProperty {
+    /// Numeric value; integers are widened to `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            AtomProperty::Float(x) => Some(*x),
+            AtomProperty::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            AtomProperty::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AtomProperty::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            AtomProperty::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<&Vector3<f64>> {
+        match self {
+            AtomProperty::Vector(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl From<f64> for AtomProperty {
+    fn from(x: f64) -> Self { AtomProperty::Float(x) }
+}
+impl From<i64> for AtomProperty {
+    fn from(i: i64) -> Self { AtomProperty::Int(i) }
+}
+impl From<&str> for AtomProperty {
+    fn from(s: &str) -> Self { AtomProperty::Text(s.to_string()) }
+}
+impl From<String> for AtomProperty {
+    fn from(s: String) -> Self { AtomProperty::Text(s) }
+}
+impl From<bool> for AtomProperty {
+    fn from(b: bool) -> Self { AtomProperty::Bool(b) }
+}
+impl From<Vector3<f64>> for AtomProperty {
+    fn from(v: Vector3<f64>) -> Self { AtomProperty::Vector(v) }
+}
+
+/// Property key for the site label from the source file (e.g. "Zn1", "O2A").
+pub const PROP_LABEL: &str = "label";
+/// Property key for the partial (force-field) charge in e.
+pub const PROP_CHARGE: &str = "charge";
+/// Property key for the magnetic moment in μB, as a vector along the crystal axes.
+pub const PROP_MAGMOM: &str = "magmom";
+/// Property key for VASP selective dynamics flags, as written in a POSCAR ("T T F").
+pub const PROP_SELECTIVE_DYNAMICS: &str = "selective_dynamics";
+/// Property key for the slab layer an atom belongs to (0 = bottom layer).
+pub const PROP_LAYER: &str = "layer";
+/// Property key for the index of the bulk atom a slab atom was cut from.
+pub const PROP_BULK_INDEX: &str = "bulk_index";
+/// Property key for the bulk lattice translation of a slab atom, as a vector of integers: the
+/// slab atom is the bulk atom's image at `fractional_coords + image` (before centring the slab).
+pub const PROP_BULK_IMAGE: &str = "bulk_image";
+
+/// Bohr radius (Å), the length unit of cube files and QE output.
+pub const BOHR_RADIUS: f64 = 0.529177210903;
+
 // ============================================================================
 // DATA STRUCTURES
 // ============================================================================
@@ -90,12 +207,57 @@ impl Lattice {
         (a, b, c, alpha, beta, gamma)
     }
 
+    /// Cartesian vector from `f1` to the nearest periodic image of `f2` (minimum image convention).
     pub fn get_shortest_distance_vector(&self, f1: &Vector3<f64>, f2: &Vector3<f64>) -> Vector3<f64> {
-        let mut d = f2 - f1;
-        d.x -= d.x.round();
-        d.y -= d.y.round();
-        d.z -= d.z.round();
-        self.to_cartesian(&d)
+        self.minimum_image(f1, f2).1
+    }
+
+    /// Nearest periodic image of `f2` seen from `f1`: the lattice translation applied to `f2` and
+    /// the Cartesian vector from `f1` to it.
+    ///
+    /// Rounding the fractional difference is only a first guess; every translation that could
+    /// still be shorter is checked, which keeps the result exact for strongly skewed cells.
+    pub fn minimum_image(&self, f1: &Vector3<f64>, f2: &Vector3<f64>) -> (Vector3<i32>, Vector3<f64>) {
+        let d = f2 - f1;
+        let mut best = d.map(|x| -x.round());
+        let mut best_sq = self.to_cartesian(&(d + best)).norm_squared();
+
+        // A vector of length r has |fractional component k| <= r * |reciprocal column k|
+        let radius = best_sq.sqrt();
+        let range = |k: usize| {
+            let reach = radius * self.reciprocal_matrix.column(k).norm();
+            ((-d[k] - reach).ceil() as i64)..=((-d[k] + reach).floor() as i64)
+        };
+        for t0 in range(0) {
+            for t1 in range(1) {
+                for t2 in range(2) {
+                    let t = Vector3::new(t0 as f64, t1 as f64, t2 as f64);
+                    let length_sq = self.to_cartesian(&(d + t)).norm_squared();
+                    if length_sq < best_sq - 1e-12 {
+                        best = t;
+                        best_sq = length_sq;
+                    }
+                }
+            }
+        }
+        (best.map(|x| x as i32), self.to_cartesian(&(d + best)))
+    }
+
+    /// Niggli-reduced cell of the same lattice and the transformation to it
+    /// (`reduced.matrix = matrix · P`, det P = +1). `tolerance` is relative to V^(2/3).
+    pub fn niggli_reduce(&self, tolerance: f64) -> Result<(Lattice, Matrix3<i32>)> {
+        let p = reduction::niggli_reduce(&self.matrix, tolerance)?;
+        Ok((self.transformed(&p)?, p))
+    }
+
+    /// Delaunay-reduced cell of the same lattice and the transformation to it, as `niggli_reduce`.
+    pub fn delaunay_reduce(&self, tolerance: f64) -> Result<(Lattice, Matrix3<i32>)> {
+        let p = reduction::delaunay_reduce(&self.matrix, tolerance)?;
+        Ok((self.transformed(&p)?, p))
+    }
+
+    fn transformed(&self, p: &Matrix3<i32>) -> Result<Lattice> {
+        Lattice::new(self.matrix * p.cast::<f64>()).map_err(|e| anyhow!(e))
     }
 }
 
@@ -105,6 +267,58 @@ pub struct Atom {
     pub fractional_coords: Vector3<f64>,
     /// Semantic tag identifying the atom's role in the framework.
     pub component_type: ComponentType,
+    /// Site occupancy as given in the source file (1.0 for fully occupied sites).
+    pub occupancy: f64,
+    /// CIF `_atom_site_disorder_group`; `None` for ordered sites.
+    /// Negative groups mark disorder about a special position (the site overlaps its own images).
+    pub disorder_group: Option<i32>,
+    /// Free-form per-atom data carried through the pipeline (label, charge, magmom, ...).
+    pub properties: BTreeMap<String, AtomProperty>,
+}
+
+impl Atom {
+    /// Creates a fully occupied, ordered atom.
+    pub fn new(element: impl Into<String>, fractional_coords: Vector3<f64>, component_type: ComponentType) -> Self {
+        Self {
+            element: element.into(),
+            fractional_coords,
+            component_type,
+            occupancy: 1.0,
+            disorder_group: None,
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn property(&self, key: &str) -> Option<&AtomProperty> {
+        self.properties.get(key)
+    }
+
+    pub fn set_property(&mut self, key: &str, value: impl Into<AtomProperty>) {
+        self.properties.insert(key.to_string(), value.into());
+    }
+
+    /// Site label from the source file, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.property(PROP_LABEL).and_then(AtomProperty::as_str)
+    }
+
+    /// Partial charge from the source file, if any.
+    pub fn charge(&self) -> Option<f64> {
+        self.property(PROP_CHARGE).and_then(AtomProperty::as_f64)
+    }
+
+    /// Magnetic moment (μB, crystal-axis components), if any.
+    pub fn magmom(&self) -> Option<&Vector3<f64>> {
+        self.property(PROP_MAGMOM).and_then(AtomProperty::as_vector)
+    }
+
+    /// For a slab atom, the bulk atom it was cut from and the lattice translation of that
+    /// atom's image (`PROP_BULK_INDEX`, `PROP_BULK_IMAGE`).
+    pub fn bulk_site(&self) -> Option<(usize, Vector3<i32>)> {
+        let index = self.property(PROP_BULK_INDEX).and_then(AtomProperty::as_i64)?;
+        let image = self.property(PROP_BULK_IMAGE).and_then(AtomProperty::as_vector)?;
+        Some((usize::try_from(index).ok()?, image.map(|x| x.round() as i32)))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -118,8 +332,201 @@ impl CifRepresentable for Crystal {
     fn atoms(&self) -> &Vec<Atom> { &self.atoms }
 }
 
+impl Crystal {
+    /// The same structure in the cell spanned by the columns of `transformation`: the new
+    /// lattice vectors in this cell's lattice coordinates (`diag(2, 2, 1)` is a 2×2×1
+    /// supercell, columns (2, 1, 0) and (-1, 1, 0) a √3×√3 cell of a hexagonal lattice).
+    ///
+    /// Every lattice translation inside the new cell is enumerated exactly and the atoms are
+    /// wrapped into it; the result must hold |det| × N distinct atoms. The determinant must be
+    /// positive: a negative one gives a left-handed cell, which a CIF (lengths and angles only)
+    /// would write as its mirror image. Negating a column gives the same cell right-handed.
+    pub fn transform(&self, transformation: &Matrix3<i32>) -> Result<Crystal> {
+        Ok(self.transform_with_images(transformation, true)?.0)
+    }
+
+    /// `nx × ny × nz` supercell.
+    pub fn make_supercell(&self, nx: u32, ny: u32, nz: u32) -> Result<Crystal> {
+        self.transform(&Matrix3::from_diagonal(&Vector3::new(nx as i32, ny as i32, nz as i32)))
+    }
+
+    /// Niggli-reduced primitive cell, with the transformation from this cell (see
+    /// `primitive::find_primitive`). `symprec` is the site tolerance (Å).
+    pub fn find_primitive(&self, symprec: f64) -> Result<ReducedCell> {
+        primitive::find_primitive(self, symprec)
+    }
+
+    /// Space group, standardizing transformation and site symmetry of the structure (see
+    /// `symmetry::analyze`). `symprec` is the site tolerance (Å).
+    pub fn symmetry(&self, symprec: f64) -> Result<SymmetryDataset> {
+        symmetry::analyze(self, symprec)
+    }
+
+    /// In-plane transformation of a slab: `in_plane` acts on a and b as `transform` does, while
+    /// c (layers plus vacuum) and the atoms' heights are kept.
+    pub fn transform_in_plane(&self, in_plane: &Matrix2<i32>) -> Result<Crystal> {
+        Ok(self.transform_with_images(&embed_in_plane(in_plane), false)?.0)
+    }
+
+    /// `transform`, also returning the translation (in this cell's lattice coordinates) that
+    /// carries each original atom onto each new one: new atom `k` is `atoms[k % N]` moved by
+    /// `images[k]`. Without `wrap_c` the third fractional coordinate is left as it is.
+    pub(crate) fn transform_with_images(
+        &self,
+        transformation: &Matrix3<i32>,
+        wrap_c: bool,
+    ) -> Result<(Crystal, Vec<Vector3<i32>>)> {
+        let p = transformation.cast::<f64>();
+        let (_, det) = adjugate(transformation);
+        if det <= 0 {
+            bail!("Transformation must have a positive determinant (got {}); negate a column for a right-handed cell.", det);
+        }
+        let lattice = self.lattice.transformed(transformation)?;
+        let inverse = p.try_inverse().unwrap_or_else(Matrix3::zeros);
+
+        let translations = cell_translations(transformation);
+        let mut atoms = Vec::with_capacity(translations.len() * self.atoms.len());
+        let mut images = Vec::with_capacity(atoms.capacity());
+        for t in &translations {
+            for atom in &self.atoms {
+                let frac = inverse * (atom.fractional_coords + t.cast::<f64>());
+                let mut wrap = frac.map(|x| x.floor());
+                let mut wrapped = frac - wrap;
+                for k in 0..3 {
+                    if k == 2 && !wrap_c {
+                        wrap[k] = 0.0;
+                        wrapped[k] = frac[k];
+                    } else if wrapped[k] >= 1.0 - 1e-12 {
+                        wrap[k] += 1.0;
+                        wrapped[k] = 0.0;
+                    }
+                }
+                let wrap = wrap.map(|x| x as i32);
+                atoms.push(Atom { fractional_coords: wrapped, ..atom.clone() });
+                images.push(t - transformation * wrap);
+            }
+        }
+
+        // Atoms landing on the same site mean the input repeats sites or the wrapping went wrong
+        let sites: HashSet<[i64; 3]> = atoms
+            .iter()
+            .map(|a| {
+                [0, 1, 2].map(|k| {
+                    let x = (a.fractional_coords[k] * 1e6).round() as i64;
+                    if k < 2 || wrap_c { x.rem_euclid(1_000_000) } else { x }
+                })
+            })
+            .collect();
+        let expected = det as usize * self.atoms.len();
+        if sites.len() != expected {
+            bail!("The new cell holds {} distinct atoms, expected {} (|det| = {} × {}).", sites.len(), expected, det, self.atoms.len());
+        }
+        Ok((Crystal { lattice, atoms }, images))
+    }
+}
+
+/// Adjugate and determinant of an integer matrix, in exact arithmetic.
+fn adjugate(m: &Matrix3<i32>) -> (Matrix3<i64>, i64) {
+    let m = m.cast::<i64>();
+    // Entry (i, j) is the cofactor of m[(j, i)]
+    let adjugate = Matrix3::from_fn(|i, j| {
+        let (r0, r1) = ((j + 1) % 3, (j + 2) % 3);
+        let (c0, c1) = ((i + 1) % 3, (i + 2) % 3);
+        m[(r0, c0)] * m[(r1, c1)] - m[(r0, c1)] * m[(r1, c0)]
+    });
+    let det = (0..3).map(|k| m[(0, k)] * adjugate[(k, 0)]).sum();
+    (adjugate, det)
+}
+
+/// The 3×3 transformation acting on a and b only.
+pub(crate) fn embed_in_plane(in_plane: &Matrix2<i32>) -> Matrix3<i32> {
+    Matrix3::new(
+        in_plane[(0, 0)], in_plane[(0, 1)], 0,
+        in_plane[(1, 0)], in_plane[(1, 1)], 0,
+        0, 0, 1,
+    )
+}
+
+/// Integer points `t` of the old lattice inside the cell spanned by the columns of
+/// `transformation` (`P⁻¹ t` in [0, 1)³), tested exactly with the adjugate: `adj(P) t` in [0, det)³.
+pub(crate) fn cell_translations(transformation: &Matrix3<i32>) -> Vec<Vector3<i32>> {
+    let p = transformation.cast::<i64>();
+    let (adjugate, det) = adjugate(transformation);
+
+    // Bounding box of the new cell's corners
+    let mut lower = Vector3::repeat(i64::MAX);
+    let mut upper = Vector3::repeat(i64::MIN);
+    for corner in 0..8 {
+        let c = Vector3::new((corner & 1) as i64, ((corner >> 1) & 1) as i64, ((corner >> 2) & 1) as i64);
+        let point = p * c;
+        lower = lower.inf(&point);
+        upper = upper.sup(&point);
+    }
+
+    let mut translations = Vec::new();
+    for t0 in lower.x..=upper.x {
+        for t1 in lower.y..=upper.y {
+            for t2 in lower.z..=upper.z {
+                let t = Vector3::new(t0, t1, t2);
+                if (adjugate * t).iter().all(|&x| (0..det).contains(&x)) {
+                    translations.push(t.map(|x| x as i32));
+                }
+            }
+        }
+    }
+    translations
+}
+
+/// A discrete molecule of a periodic structure, unwrapped into one piece (Cartesian, Å).
 #[derive(Debug, Clone)]
 pub struct Molecule {
     pub atoms: Vec<(String, Vector3<f64>)>,
+    /// Index in the bulk structure of each entry of `atoms`.
+    pub indices: Vec<usize>,
     pub center_of_mass: Vector3<f64>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crystal(lattice: Lattice, positions: &[[f64; 3]]) -> Crystal {
+        Crystal {
+            lattice,
+            atoms: positions.iter().map(|&p| Atom::new("Mg", Vector3::from(p), ComponentType::Unknown)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_supercell_and_transform() {
+        let cubic = crystal(Lattice::from_parameters(4.2, 4.2, 4.2, 90.0, 90.0, 90.0).unwrap(), &[[0.0, 0.0, 0.0], [0.5, 0.5, 0.5]]);
+        let supercell = cubic.make_supercell(2, 3, 1).unwrap();
+        assert_eq!(supercell.atoms.len(), 12);
+        assert!((supercell.lattice.matrix.determinant() - 6.0 * cubic.lattice.matrix.determinant()).abs() < 1e-6);
+        assert!(supercell.atoms.iter().all(|a| a.fractional_coords.iter().all(|x| (0.0..1.0).contains(x))));
+
+        // √3×√3 on a hexagonal lattice: three distinct copies of the atom
+        let hexagonal = crystal(Lattice::from_parameters(3.0, 3.0, 5.0, 90.0, 90.0, 120.0).unwrap(), &[[0.1, 0.2, 0.3]]);
+        let root3 = hexagonal.transform_in_plane(&Matrix2::new(2, -1, 1, 1)).unwrap();
+        let (a, b, _, _, _, gamma) = root3.lattice.to_parameters();
+        assert!((a - 3.0 * 3f64.sqrt()).abs() < 1e-9 && (b - a).abs() < 1e-9 && (gamma - 120.0).abs() < 1e-9);
+        assert_eq!(root3.atoms.len(), 3);
+        for (i, p) in root3.atoms.iter().enumerate() {
+            assert!((p.fractional_coords.z - 0.3).abs() < 1e-12);
+            for q in &root3.atoms[i + 1..] {
+                assert!(root3.lattice.minimum_image(&p.fractional_coords, &q.fractional_coords).1.norm() > 1.0);
+            }
+        }
+
+        // A sheared cell still gets exactly det translations; singular and left-handed cells fail
+        let sheared = cubic.transform(&Matrix3::new(1, 2, 3, 0, 1, 4, 1, 0, 5)).unwrap();
+        assert_eq!(sheared.atoms.len(), 2 * 10);
+        assert!(cubic.transform(&Matrix3::new(1, 1, 0, 1, 1, 0, 0, 0, 1)).is_err());
+        assert!(cubic.make_supercell(1, 1, 0).is_err());
+        assert!(cubic.transform(&Matrix3::new(0, 1, 0, 1, 0, 0, 0, 0, 1)).is_err());
+
+        // A repeated site cannot give |det| × N distinct atoms
+        let repeated = crystal(cubic.lattice.clone(), &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]);
+        assert!(repeated.make_supercell(2, 1, 1).is_err());
+    }
+}
@@ -0,0 +1,826 @@
+use crate::core::neighbors::CellList;
+use crate::core::primitive::{find_primitive, rarest_element_atom};
+use crate::core::spacegroup::{operations_from_hall, wrap_fractional, SpaceGroupSetting, SymOp, SPACE_GROUPS};
+use crate::core::structure::{cell_translations, Crystal, Lattice};
+use crate::math::integer_basis::gcd;
+use crate::math::reduction::integer_determinant;
+use nalgebra::{Matrix3, Vector3};
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Lattice vectors searched (coefficients in -RANGE..=RANGE of the reduced primitive basis)
+/// for images of the basis vectors and for conventional axes.
+const SEARCH_RANGE: i32 = 3;
+
+/// Rotation types in a fixed order: improper -6, -4, -3, -2 (mirror), -1, then proper 1 to 6.
+const ROTATION_TYPES: [i32; 10] = [-6, -4, -3, -2, -1, 1, 2, 3, 4, 6];
+
+// ============================================================================
+// DATASET
+// ============================================================================
+
+/// The seven crystal systems, from the space-group number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrystalSystem {
+    Triclinic,
+    Monoclinic,
+    Orthorhombic,
+    Tetragonal,
+    Trigonal,
+    Hexagonal,
+    Cubic,
+}
+
+impl CrystalSystem {
+    pub fn from_number(number: u16) -> Self {
+        match number {
+            0..=2 => Self::Triclinic,
+            3..=15 => Self::Monoclinic,
+            16..=74 => Self::Orthorhombic,
+            75..=142 => Self::Tetragonal,
+            143..=167 => Self::Trigonal,
+            168..=194 => Self::Hexagonal,
+            _ => Self::Cubic,
+        }
+    }
+}
+
+impl fmt::Display for CrystalSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Triclinic => "triclinic",
+            Self::Monoclinic => "monoclinic",
+            Self::Orthorhombic => "orthorhombic",
+            Self::Tetragonal => "tetragonal",
+            Self::Trigonal => "trigonal",
+            Self::Hexagonal => "hexagonal",
+            Self::Cubic => "cubic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Space group of a structure and the symmetry of its sites (see `Crystal::symmetry`).
+#[derive(Debug, Clone)]
+pub struct SymmetryDataset {
+    /// International Tables number (1-230).
+    pub number: u16,
+    /// Hermann–Mauguin symbol of the standard setting, e.g. "P 21/c".
+    pub international: String,
+    /// Hall symbol of the standard setting.
+    pub hall: String,
+    /// Point-group symbol, e.g. "2/m".
+    pub point_group: String,
+    /// Operations in the input cell's lattice coordinates, including the pure translations of
+    /// a non-primitive cell. Operations that do not map the input lattice onto itself (possible
+    /// for skewed supercells) are left out.
+    pub operations: Vec<SymOp>,
+    /// Columns: the conventional standard cell vectors in the input cell's lattice coordinates.
+    pub transformation: Matrix3<f64>,
+    /// Standard origin: a position `x` of the input cell is at `transformation⁻¹·x + origin_shift`
+    /// in the standard cell.
+    pub origin_shift: Vector3<f64>,
+    /// For each atom, the index of the first atom of its orbit.
+    pub equivalent_atoms: Vec<usize>,
+    /// For each atom, the point-group symbol of its site-symmetry group ("1" for general positions).
+    pub site_symmetries: Vec<String>,
+    /// For each atom, the multiplicity of its Wyckoff position in the conventional cell.
+    // TODO: Wyckoff letters. Site symmetry and multiplicity do not fix the letter (several
+    // positions can share both), so this needs the International Tables' Wyckoff list for every
+    // setting in `SPACE_GROUPS`, matched against each orbit's coordinates.
+    pub multiplicities: Vec<usize>,
+}
+
+impl SymmetryDataset {
+    pub fn crystal_system(&self) -> CrystalSystem {
+        CrystalSystem::from_number(self.number)
+    }
+
+    /// Lattice of the conventional standard cell.
+    pub fn standard_lattice(&self, lattice: &Lattice) -> Result<Lattice> {
+        Lattice::new(lattice.matrix * self.transformation).map_err(|e| anyhow!(e))
+    }
+
+    /// Miller indices (input cell) of the planes equivalent to `hkl` by symmetry, sorted.
+    pub fn equivalent_planes(&self, hkl: [i32; 3]) -> Vec<[i32; 3]> {
+        let h = Vector3::from(hkl);
+        let mut planes: Vec<[i32; 3]> = self.operations.iter().map(|op| (op.rotation.transpose() * h).into()).collect();
+        planes.sort_unstable();
+        planes.dedup();
+        planes
+    }
+}
+
+impl fmt::Display for SymmetryDataset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "• Space group:   {} (No. {}), Hall '{}'", self.international, self.number, self.hall)?;
+        writeln!(f, "• Point group:   {} ({})", self.point_group, self.crystal_system())?;
+        writeln!(f, "• Operations:    {} in the input cell", self.operations.len())?;
+        let p = &self.transformation;
+        writeln!(
+            f,
+            "• Standard cell: a = ({:.3}, {:.3}, {:.3}), b = ({:.3}, {:.3}, {:.3}), c = ({:.3}, {:.3}, {:.3}), origin ({:.4}, {:.4}, {:.4})",
+            p[(0, 0)], p[(1, 0)], p[(2, 0)], p[(0, 1)], p[(1, 1)], p[(2, 1)], p[(0, 2)], p[(1, 2)], p[(2, 2)],
+            self.origin_shift.x, self.origin_shift.y, self.origin_shift.z
+        )?;
+        let orbits: Vec<usize> = (0..self.equivalent_atoms.len()).filter(|&i| self.equivalent_atoms[i] == i).collect();
+        writeln!(f, "• Sites:         {} orbits (Wyckoff letters are not assigned yet)", orbits.len())?;
+        for i in orbits {
+            let size = self.equivalent_atoms.iter().filter(|&&r| r == i).count();
+            writeln!(
+                f,
+                "  - atom {:>4}: site symmetry {:<6} multiplicity {:>3} ({} atoms in the input cell)",
+                i, self.site_symmetries[i], self.multiplicities[i], size
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ANALYSIS
+// ============================================================================
+
+/// An operation of the primitive cell and the atom each atom is carried onto.
+struct Operation {
+    rotation: Matrix3<i32>,
+    translation: Vector3<f64>,
+    mapping: Vec<usize>,
+}
+
+/// A default setting of the table with its operations (one per rotation in the primitive cell
+/// once centring translations are dropped) and point group.
+struct TableGroup {
+    setting: &'static SpaceGroupSetting,
+    operations: Vec<SymOp>,
+    signature: [usize; 10],
+}
+
+/// Space group and site symmetry of `crystal`, with `symprec` (Å) the largest displacement of
+/// an atom from the image of another under an operation.
+///
+/// The structure is reduced to its Niggli-reduced primitive cell. Candidate rotations are the
+/// integer matrices that preserve its metric; each is kept when a translation maps every atom
+/// onto one of the same element. Conventional axes follow from the rotation axes, and the
+/// operations are matched against every default setting of the space-group table, solving for
+/// the origin shift.
+pub fn analyze(crystal: &Crystal, symprec: f64) -> Result<SymmetryDataset> {
+    if crystal.atoms.is_empty() {
+        bail!("Cannot analyse the symmetry of a structure without atoms.");
+    }
+    let reduced = find_primitive(crystal, symprec)?;
+    let primitive = &reduced.crystal;
+    let operations = crystal_operations(primitive, &lattice_rotations(&primitive.lattice, symprec), symprec);
+    let rotations: Vec<Matrix3<i32>> = operations.iter().map(|op| op.rotation).collect();
+
+    let table = table_groups()?;
+    let signature = rotation_signature(&rotations);
+    let candidates: Vec<&TableGroup> = table.iter().filter(|g| g.signature == signature).collect();
+    let system = candidates
+        .first()
+        .map(|g| CrystalSystem::from_number(g.setting.0))
+        .ok_or_else(|| anyhow!("The {} operations found do not form a crystallographic point group; try another tolerance.", operations.len()))?;
+
+    // Shortest cells first; among equally short ones, the closest to the input axes
+    let mut cells = conventional_cells(&primitive.lattice, &rotations, system);
+    let edges = |c: &Matrix3<i32>| (primitive.lattice.matrix * c.cast::<f64>()).column_iter().map(|v| v.norm()).sum::<f64>();
+    let deviation = |c: &Matrix3<i32>| (reduced.transformation * c.cast::<f64>() - Matrix3::identity()).norm();
+    cells.sort_by(|x, y| {
+        let key = |c: &Matrix3<i32>| ((edges(c) / symprec).round(), deviation(c));
+        key(x).partial_cmp(&key(y)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    // First conventional cell in which the operations are a table setting
+    let mut identified = None;
+    'cells: for cell in cells {
+        for group in &candidates {
+            if let Some(origin) = match_setting(&primitive.lattice, &operations, &cell, group, symprec) {
+                identified = Some((cell, *group, origin));
+                break 'cells;
+            }
+        }
+    }
+    let (cell, group, origin) = identified.ok_or_else(|| {
+        anyhow!("Could not match the {} operations to a space-group setting; try another tolerance.", operations.len())
+    })?;
+    let &(number, international, hall, _) = group.setting;
+    let n_centring = integer_determinant(&cell) as usize;
+    let cell_f = cell.cast::<f64>();
+
+    // Sites of the primitive cell: orbit representatives and site-symmetry groups
+    let n_primitive = primitive.atoms.len();
+    let orbit_of: Vec<usize> = (0..n_primitive).map(|i| operations.iter().map(|op| op.mapping[i]).min().unwrap_or(i)).collect();
+    let site_groups: Vec<Vec<Matrix3<i32>>> = (0..n_primitive)
+        .map(|i| operations.iter().filter(|op| op.mapping[i] == i).map(|op| op.rotation).collect())
+        .collect();
+
+    // Input atoms -> primitive sites
+    let to_primitive = reduced.transformation.try_inverse().ok_or_else(|| anyhow!("Singular cell transformation."))?;
+    let primitive_positions: Vec<Vector3<f64>> = primitive.atoms.iter().map(|a| a.fractional_coords).collect();
+    let cells = CellList::new(&primitive.lattice, &primitive_positions, symprec);
+    let mut sites = Vec::with_capacity(crystal.atoms.len());
+    for atom in &crystal.atoms {
+        let site = cells
+            .query(&(to_primitive * atom.fractional_coords), symprec)
+            .into_iter()
+            .filter(|n| primitive.atoms[n.index].element == atom.element)
+            .min_by(|x, y| x.distance.total_cmp(&y.distance))
+            .ok_or_else(|| anyhow!("Atom {} has no site in the primitive cell.", atom.element))?;
+        sites.push(site.index);
+    }
+    let equivalent_atoms: Vec<usize> = sites
+        .iter()
+        .map(|&s| sites.iter().position(|&t| orbit_of[t] == orbit_of[s]).unwrap_or(0))
+        .collect();
+    let site_symmetries = sites.iter().map(|&s| point_group_symbol_of(table, &site_groups[s])).collect();
+    let multiplicities = sites.iter().map(|&s| n_centring * operations.len() / site_groups[s].len()).collect();
+
+    Ok(SymmetryDataset {
+        number,
+        international: international.to_string(),
+        hall: hall.to_string(),
+        point_group: point_group_symbol(international),
+        operations: input_cell_operations(&operations, &reduced.transformation),
+        transformation: (reduced.transformation * cell_f).map(tidy),
+        origin_shift: wrap_fractional(&(cell_f.try_inverse().unwrap_or_else(Matrix3::identity) * origin)).map(tidy),
+        equivalent_atoms,
+        site_symmetries,
+        multiplicities,
+    })
+}
+
+/// Rounds away floating-point residue (and negative zeros) from rational coefficients.
+fn tidy(x: f64) -> f64 {
+    let x = (x * 1e9).round() / 1e9;
+    if x == 0.0 { 0.0 } else { x }
+}
+
+// ============================================================================
+// OPERATIONS OF THE STRUCTURE
+// ============================================================================
+
+/// Integer matrices (in the lattice basis) that preserve the metric within `symprec`: images of
+/// the basis vectors are lattice vectors of the same lengths and mutual angles.
+fn lattice_rotations(lattice: &Lattice, symprec: f64) -> Vec<Matrix3<i32>> {
+    let m = lattice.matrix;
+    let metric = m.transpose() * m;
+    let vectors = search_vectors();
+    let images: Vec<Vec<Vector3<i32>>> = (0..3)
+        .map(|k| {
+            let length = metric[(k, k)].sqrt();
+            vectors.iter().filter(|v| ((m * v.cast::<f64>()).norm() - length).abs() < symprec).copied().collect()
+        })
+        .collect();
+
+    let mut rotations = Vec::new();
+    for u in &images[0] {
+        for v in &images[1] {
+            for w in &images[2] {
+                let rotation = Matrix3::from_columns(&[*u, *v, *w]);
+                if integer_determinant(&rotation).abs() != 1 {
+                    continue;
+                }
+                let image = m * rotation.cast::<f64>();
+                let image_metric = image.transpose() * image;
+                let preserved = (0..3).all(|i| {
+                    (0..3).all(|j| {
+                        let scale = metric[(i, i)].sqrt() + metric[(j, j)].sqrt();
+                        (image_metric[(i, j)] - metric[(i, j)]).abs() < symprec * scale
+                    })
+                });
+                if preserved {
+                    rotations.push(rotation);
+                }
+            }
+        }
+    }
+    rotations
+}
+
+/// Lattice rotations that, with some translation, map every atom onto an atom of the same
+/// element. `crystal` must be primitive, so each rotation has at most one translation.
+fn crystal_operations(crystal: &Crystal, rotations: &[Matrix3<i32>], symprec: f64) -> Vec<Operation> {
+    let atoms = &crystal.atoms;
+    let positions: Vec<Vector3<f64>> = atoms.iter().map(|a| a.fractional_coords).collect();
+    let cells = CellList::new(&crystal.lattice, &positions, symprec);
+    let Some(reference) = rarest_element_atom(atoms) else { return Vec::new() };
+
+    let mut operations = Vec::new();
+    for rotation in rotations {
+        let w = rotation.cast::<f64>();
+        for (j, atom) in atoms.iter().enumerate() {
+            if atom.element != atoms[reference].element {
+                continue;
+            }
+            let translation = positions[j] - w * positions[reference];
+
+            // Image of every atom; the mean residual refines the translation
+            let mut mapping = Vec::with_capacity(atoms.len());
+            let mut residual = Vector3::zeros();
+            for (i, atom) in atoms.iter().enumerate() {
+                let nearest = cells
+                    .query(&(w * positions[i] + translation), symprec)
+                    .into_iter()
+                    .filter(|n| atoms[n.index].element == atom.element)
+                    .min_by(|x, y| x.distance.total_cmp(&y.distance));
+                match nearest {
+                    Some(n) => {
+                        mapping.push(n.index);
+                        residual += crystal.lattice.to_fractional(&n.vector);
+                    }
+                    None => break,
+                }
+            }
+            if mapping.len() == atoms.len() {
+                let translation = wrap_fractional(&(translation + residual / atoms.len() as f64));
+                operations.push(Operation { rotation: *rotation, translation, mapping });
+                break;
+            }
+        }
+    }
+    // Identity first
+    operations.sort_by_key(|op| op.rotation != Matrix3::identity());
+    operations
+}
+
+/// The operations in the input cell's lattice coordinates (`x_input = transformation · x_primitive`),
+/// combined with every lattice translation of the primitive cell inside the input cell.
+fn input_cell_operations(operations: &[Operation], transformation: &Matrix3<f64>) -> Vec<SymOp> {
+    let to_input = transformation;
+    let Some(to_primitive) = transformation.try_inverse() else { return Vec::new() };
+    let primitive_in_input = to_primitive.map(|x| x.round() as i32);
+    let translations: Vec<Vector3<f64>> = cell_translations(&primitive_in_input)
+        .iter()
+        .map(|t| wrap_fractional(&(to_input * t.cast::<f64>())))
+        .collect();
+
+    let mut result = Vec::with_capacity(operations.len() * translations.len());
+    for op in operations {
+        let rotation = to_input * op.rotation.cast::<f64>() * to_primitive;
+        if rotation.iter().any(|x| (x - x.round()).abs() > 1e-6) {
+            continue;
+        }
+        let rotation = rotation.map(|x| x.round() as i32);
+        for t in &translations {
+            let translation = wrap_fractional(&(to_input * op.translation + t));
+            result.push(SymOp { rotation, translation });
+        }
+    }
+    result
+}
+
+// ============================================================================
+// POINT GROUPS
+// ============================================================================
+
+/// Rotation type from the determinant and trace: 1, 2, 3, 4, 6 for proper rotations and
+/// -1, -2 (mirror), -3, -4, -6 for improper ones; 0 if not crystallographic.
+fn rotation_type(rotation: &Matrix3<i32>) -> i32 {
+    match (integer_determinant(rotation), rotation.trace()) {
+        (1, 3) => 1,
+        (1, -1) => 2,
+        (1, 0) => 3,
+        (1, 1) => 4,
+        (1, 2) => 6,
+        (-1, -3) => -1,
+        (-1, 1) => -2,
+        (-1, 0) => -3,
+        (-1, -1) => -4,
+        (-1, -2) => -6,
+        _ => 0,
+    }
+}
+
+/// Number of rotations of each type (in `ROTATION_TYPES` order), which identifies the point group.
+fn rotation_signature(rotations: &[Matrix3<i32>]) -> [usize; 10] {
+    let mut counts = [0; 10];
+    for rotation in rotations {
+        if let Some(k) = ROTATION_TYPES.iter().position(|&t| t == rotation_type(rotation)) {
+            counts[k] += 1;
+        }
+    }
+    counts
+}
+
+/// Point-group symbol from a Hermann–Mauguin symbol: screw axes become rotations, glides become
+/// mirrors and placeholder "1" axes are dropped ("P 21/c" -> "2/m", "P -3 1 m" -> "-3m").
+fn point_group_symbol(international: &str) -> String {
+    let element = |part: &str| -> String {
+        match part.strip_prefix('-') {
+            Some(rest) => format!("-{}", &rest[..1]),
+            None if part.starts_with(|c: char| c.is_ascii_digit()) => part[..1].to_string(),
+            None => "m".to_string(),
+        }
+    };
+    let parts: Vec<String> = international
+        .split_whitespace()
+        .skip(1)
+        .map(|part| part.split('/').map(element).collect::<Vec<_>>().join("/"))
+        .collect();
+    let symbol: String = if parts.len() > 1 {
+        parts.iter().filter(|p| p.as_str() != "1").map(String::as_str).collect()
+    } else {
+        parts.concat()
+    };
+    match symbol.as_str() {
+        "-4m2" => "-42m".to_string(),
+        "-62m" => "-6m2".to_string(),
+        _ => symbol,
+    }
+}
+
+/// Point-group symbol of a group of rotations, looked up by signature in the table.
+fn point_group_symbol_of(table: &[TableGroup], rotations: &[Matrix3<i32>]) -> String {
+    let signature = rotation_signature(rotations);
+    table
+        .iter()
+        .find(|g| g.signature == signature)
+        .map(|g| point_group_symbol(g.setting.1))
+        .unwrap_or_else(|| "?".to_string())
+}
+
+/// Default settings of the space-group table (origin choice 2, hexagonal axes) with their
+/// operations and point groups. The Hall symbols are decoded once and cached.
+fn table_groups() -> Result<&'static [TableGroup]> {
+    static TABLE: OnceLock<std::result::Result<Vec<TableGroup>, String>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        SPACE_GROUPS
+            .iter()
+            .filter(|e| e.3.is_empty() || e.3 == "2" || e.3 == "H")
+            .map(|setting| {
+                let operations = operations_from_hall(setting.2).map_err(|e| e.to_string())?;
+                let mut rotations: Vec<Matrix3<i32>> = operations.iter().map(|op| op.rotation).collect();
+                rotations.sort_by_key(|r| r.as_slice().to_vec());
+                rotations.dedup();
+                let signature = rotation_signature(&rotations);
+                Ok(TableGroup { setting, operations, signature })
+            })
+            .collect()
+    });
+    table.as_deref().map_err(|e| anyhow!("Space-group table: {}", e))
+}
+
+// ============================================================================
+// CONVENTIONAL CELL
+// ============================================================================
+
+fn search_vectors() -> Vec<Vector3<i32>> {
+    let range = -SEARCH_RANGE..=SEARCH_RANGE;
+    let mut vectors = Vec::new();
+    for x in range.clone() {
+        for y in range.clone() {
+            for z in range.clone() {
+                if (x, y, z) != (0, 0, 0) {
+                    vectors.push(Vector3::new(x, y, z));
+                }
+            }
+        }
+    }
+    vectors
+}
+
+/// Shortest lattice vector along the axis of a rotation (of its proper part), sign fixed so that
+/// the first non-zero coefficient is positive.
+fn rotation_axis(rotation: &Matrix3<i32>) -> Option<Vector3<i32>> {
+    let proper = rotation * integer_determinant(rotation) as i32;
+    let fixed = proper - Matrix3::identity();
+    let rows = [fixed.row(0).transpose(), fixed.row(1).transpose(), fixed.row(2).transpose()];
+    let axis = [(0, 1), (0, 2), (1, 2)]
+        .iter()
+        .map(|&(i, j)| rows[i].cross(&rows[j]))
+        .find(|v| *v != Vector3::zeros())?;
+    let divisor = gcd(gcd(axis.x, axis.y), axis.z).abs();
+    let axis = axis / divisor;
+    let sign = axis.iter().find(|&&x| x != 0).map_or(1, |x| x.signum());
+    Some(axis * sign)
+}
+
+/// Candidate conventional cells (columns in the primitive basis, positive determinant), built
+/// from the rotation axes of the crystal system and sorted by total edge length.
+fn conventional_cells(lattice: &Lattice, rotations: &[Matrix3<i32>], system: CrystalSystem) -> Vec<Matrix3<i32>> {
+    let length = |v: &Vector3<i32>| (lattice.matrix * v.cast::<f64>()).norm();
+    let of_type = |types: &[i32]| -> Vec<Matrix3<i32>> {
+        rotations.iter().filter(|r| types.contains(&rotation_type(r))).copied().collect()
+    };
+    let distinct_axes = |rotations: Vec<Matrix3<i32>>| -> Vec<Vector3<i32>> {
+        let mut axes: Vec<Vector3<i32>> = rotations.iter().filter_map(rotation_axis).collect();
+        axes.sort_by_key(|v| v.as_slice().to_vec());
+        axes.dedup();
+        axes
+    };
+    // Lattice vectors perpendicular to an axis: the orbit sum under its rotation vanishes
+    let perpendicular = |rotation: &Matrix3<i32>, order: u32| -> Vec<Vector3<i32>> {
+        let mut vectors: Vec<Vector3<i32>> = search_vectors()
+            .into_iter()
+            .filter(|u| {
+                let mut image = *u;
+                let mut orbit_sum = Vector3::zeros();
+                for _ in 0..order {
+                    orbit_sum += image;
+                    image = rotation * image;
+                }
+                orbit_sum == Vector3::zeros()
+            })
+            .collect();
+        vectors.sort_by(|u, v| length(u).total_cmp(&length(v)));
+        vectors
+    };
+
+    let mut cells: Vec<Matrix3<i32>> = Vec::new();
+    match system {
+        CrystalSystem::Triclinic => cells.push(Matrix3::identity()),
+        CrystalSystem::Monoclinic => {
+            let Some(two) = of_type(&[2, -2]).first().map(|r| r * integer_determinant(r) as i32) else { return cells };
+            let Some(b) = rotation_axis(&two) else { return cells };
+            let plane = perpendicular(&two, 2);
+            let Some(&u) = plane.first() else { return cells };
+            let Some(&v) = plane.iter().find(|v| u.cross(v) != Vector3::zeros()) else { return cells };
+            // Bases of the plane lattice with small coefficients on its two shortest vectors
+            let coefficients: [i32; 3] = [-1, 0, 1];
+            for &p in &coefficients {
+                for &q in &coefficients {
+                    for &r in &coefficients {
+                        for &s in &coefficients {
+                            if (p * s - q * r).abs() == 1 {
+                                cells.push(Matrix3::from_columns(&[u * p + v * q, b, u * r + v * s]));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        CrystalSystem::Orthorhombic => cells.extend(axis_permutations(&distinct_axes(of_type(&[2, -2])))),
+        CrystalSystem::Tetragonal => {
+            let Some(four) = of_type(&[4, -4]).first().map(|r| r * integer_determinant(r) as i32) else { return cells };
+            let Some(c) = rotation_axis(&four) else { return cells };
+            for a in perpendicular(&four, 4).into_iter().take(8) {
+                cells.push(Matrix3::from_columns(&[a, four * a, c]));
+                cells.push(Matrix3::from_columns(&[a, -(four * a), c]));
+            }
+        }
+        CrystalSystem::Trigonal | CrystalSystem::Hexagonal => {
+            let Some(r) = of_type(&[3, -3, 6, -6]).first().map(|r| r * integer_determinant(r) as i32) else { return cells };
+            let three = if rotation_type(&r) == 6 { r * r } else { r };
+            let Some(c) = rotation_axis(&three) else { return cells };
+            for a in perpendicular(&three, 3).into_iter().take(12) {
+                cells.push(Matrix3::from_columns(&[a, three * a, c]));
+                cells.push(Matrix3::from_columns(&[a, three * three * a, c]));
+            }
+        }
+        CrystalSystem::Cubic => {
+            let fours = of_type(&[4, -4]);
+            let axes = if fours.is_empty() { distinct_axes(of_type(&[2])) } else { distinct_axes(fours) };
+            cells.extend(axis_permutations(&axes));
+        }
+    }
+
+    // Axes reversed in pairs give the same cell in other orientations
+    let flips = [Vector3::new(1, 1, 1), Vector3::new(-1, -1, 1), Vector3::new(-1, 1, -1), Vector3::new(1, -1, -1)];
+    let mut cells: Vec<Matrix3<i32>> = cells.iter().flat_map(|c| flips.iter().map(move |f| c * Matrix3::from_diagonal(f))).collect();
+    cells.retain(|cell| (1..=4).contains(&integer_determinant(cell)));
+    cells.sort_by_key(|c| c.as_slice().to_vec());
+    cells.dedup();
+    cells
+}
+
+/// The six orderings of three axes as cell vectors, the last one flipped where needed for a
+/// right-handed cell.
+fn axis_permutations(axes: &[Vector3<i32>]) -> Vec<Matrix3<i32>> {
+    if axes.len() != 3 {
+        return Vec::new();
+    }
+    [[0, 1, 2], [1, 2, 0], [2, 0, 1], [1, 0, 2], [0, 2, 1], [2, 1, 0]]
+        .iter()
+        .map(|&[i, j, k]| {
+            let cell = Matrix3::from_columns(&[axes[i], axes[j], axes[k]]);
+            if integer_determinant(&cell) < 0 { Matrix3::from_columns(&[axes[i], axes[j], -axes[k]]) } else { cell }
+        })
+        .collect()
+}
+
+// ============================================================================
+// IDENTIFICATION
+// ============================================================================
+
+/// Origin shift (primitive coordinates) under which `operations` are the table group's
+/// operations in the conventional cell `cell`, if any.
+fn match_setting(
+    lattice: &Lattice,
+    operations: &[Operation],
+    cell: &Matrix3<i32>,
+    group: &TableGroup,
+    symprec: f64,
+) -> Option<Vector3<f64>> {
+    let n_centring = integer_determinant(cell) as usize;
+    if group.operations.len() != operations.len() * n_centring || centring_points(group.setting.1) != n_centring {
+        return None;
+    }
+
+    // Table operations in the primitive basis; centring translations become lattice vectors
+    let to_primitive = cell.cast::<f64>();
+    let from_primitive = to_primitive.try_inverse()?;
+    let mut table: Vec<(Matrix3<i32>, Vector3<f64>)> = Vec::with_capacity(operations.len());
+    for op in &group.operations {
+        let rotation = to_primitive * op.rotation.cast::<f64>() * from_primitive;
+        if rotation.iter().any(|x| (x - x.round()).abs() > 1e-6) {
+            return None;
+        }
+        let rotation = rotation.map(|x| x.round() as i32);
+        let translation = to_primitive * op.translation;
+        match table.iter().find(|(r, _)| *r == rotation) {
+            // Operations differing by a centring translation must coincide in the primitive cell
+            Some((_, s)) => {
+                if (translation - s).iter().any(|x| (x - x.round()).abs() > 1e-6) {
+                    return None;
+                }
+            }
+            None => table.push((rotation, translation)),
+        }
+    }
+
+    // Pair by rotation: t ≡ s + (W - I)·p
+    let mut pairs = Vec::with_capacity(operations.len());
+    for op in operations {
+        let (_, s) = table.iter().find(|(r, _)| *r == op.rotation)?;
+        pairs.push((op.rotation, op.translation - s));
+    }
+
+    let residual = |p: &Vector3<f64>| {
+        pairs
+            .iter()
+            .map(|(w, d)| {
+                let r = (w - Matrix3::identity()).cast::<f64>() * p - d;
+                lattice.to_cartesian(&r.map(|x| x - x.round())).norm()
+            })
+            .fold(0.0, f64::max)
+    };
+    // Of the valid origins (equivalent under the group's normalizer), the smallest shift
+    let shortest = |p: Vector3<f64>| p + lattice.minimum_image(&Vector3::zeros(), &p).0.cast::<f64>();
+    origin_candidates(&pairs)
+        .into_iter()
+        .filter(|p| residual(p) < 3.0 * symprec)
+        .map(shortest)
+        .min_by(|p, q| lattice.to_cartesian(p).norm().total_cmp(&lattice.to_cartesian(q).norm()))
+}
+
+/// Lattice points of the conventional cell from the centring letter of an H-M symbol
+/// (R groups in hexagonal axes).
+fn centring_points(international: &str) -> usize {
+    match international.as_bytes().first() {
+        Some(b'A' | b'B' | b'C' | b'I') => 2,
+        Some(b'R') => 3,
+        Some(b'F') => 4,
+        _ => 1,
+    }
+}
+
+/// Solutions `p` (mod lattice translations) of `(W - I)·p ≡ d (mod 1)` for every pair `(W, d)`,
+/// by diagonalising the stacked integer system with unimodular row and column operations.
+/// Free directions (polar axes) are set to zero; consistency is left to the caller.
+fn origin_candidates(pairs: &[(Matrix3<i32>, Vector3<f64>)]) -> Vec<Vector3<f64>> {
+    let mut a: Vec<[i64; 3]> = Vec::with_capacity(3 * pairs.len());
+    let mut b: Vec<f64> = Vec::with_capacity(3 * pairs.len());
+    for (w, d) in pairs {
+        let m = (w - Matrix3::identity()).cast::<i64>();
+        for i in 0..3 {
+            a.push([m[(i, 0)], m[(i, 1)], m[(i, 2)]]);
+            b.push(d[i]);
+        }
+    }
+    // p = V·y, with the system for y diagonal
+    let mut v = Matrix3::<i64>::identity();
+    let mut rank = 0;
+    while rank < 3 {
+        let k = rank;
+        let pivot = (k..a.len())
+            .flat_map(|i| (k..3).map(move |j| (i, j)))
+            .filter(|&(i, j)| a[i][j] != 0)
+            .min_by_key(|&(i, j)| a[i][j].abs());
+        let Some((i, j)) = pivot else { break };
+        a.swap(k, i);
+        b.swap(k, i);
+        for row in a.iter_mut() {
+            row.swap(k, j);
+        }
+        v.swap_columns(k, j);
+
+        let mut clean = true;
+        for i in k + 1..a.len() {
+            let q = a[i][k] / a[k][k];
+            if q != 0 {
+                let pivot_row = a[k];
+                for (x, p) in a[i].iter_mut().zip(pivot_row) {
+                    *x -= q * p;
+                }
+                b[i] -= q as f64 * b[k];
+            }
+            clean &= a[i][k] == 0;
+        }
+        for j in k + 1..3 {
+            let q = a[k][j] / a[k][k];
+            if q != 0 {
+                for row in a.iter_mut() {
+                    row[j] -= q * row[k];
+                }
+                for r in 0..3 {
+                    v[(r, j)] -= q * v[(r, k)];
+                }
+            }
+            clean &= a[k][j] == 0;
+        }
+        if clean {
+            rank += 1;
+        }
+    }
+
+    // d_k·y_k ≡ b_k: |d_k| solutions per diagonal entry
+    let mut solutions = vec![Vector3::zeros()];
+    for k in 0..rank {
+        let (d, bk) = (a[k][k], b[k]);
+        solutions = solutions
+            .into_iter()
+            .flat_map(|y: Vector3<f64>| {
+                (0..d.abs()).map(move |n| {
+                    let mut y = y;
+                    y[k] = (bk + n as f64) / d as f64;
+                    y
+                })
+            })
+            .collect();
+    }
+    solutions.into_iter().map(|y| v.cast::<f64>() * y).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType};
+
+    fn crystal(lattice: Lattice, sites: &[(&str, [f64; 3])]) -> Crystal {
+        let atoms = sites.iter().map(|(e, p)| Atom::new(*e, Vector3::from(*p), ComponentType::Unknown)).collect();
+        Crystal { lattice, atoms }
+    }
+
+    #[test]
+    fn test_space_groups_of_simple_structures() {
+        // Rock salt in a skewed primitive setting: Fm-3m, both sites m-3m
+        let cubic = Lattice::from_parameters(4.2, 4.2, 4.2, 90.0, 90.0, 90.0).unwrap();
+        let mut rock_salt = crystal(cubic, &[("Mg", [0.0, 0.0, 0.0]), ("O", [0.5, 0.0, 0.0])]);
+        for t in [[0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]] {
+            rock_salt.atoms.push(Atom::new("Mg", Vector3::from(t), ComponentType::Unknown));
+            rock_salt.atoms.push(Atom::new("O", wrap_fractional(&(Vector3::from(t) + Vector3::new(0.5, 0.0, 0.0))), ComponentType::Unknown));
+        }
+        let skewed = rock_salt.transform(&Matrix3::new(1, 1, 0, 0, 1, 0, 0, 1, 1)).unwrap();
+        let dataset = analyze(&skewed, 0.01).unwrap();
+        assert_eq!((dataset.number, dataset.international.as_str()), (225, "F m -3 m"));
+        assert_eq!(dataset.point_group, "m-3m");
+        assert_eq!(dataset.operations.len(), 4 * 48);
+        assert!(dataset.site_symmetries.iter().all(|s| s == "m-3m"));
+        assert!(dataset.multiplicities.iter().all(|&m| m == 4));
+        assert_eq!(dataset.equivalent_atoms.iter().filter(|&&r| r == dataset.equivalent_atoms[0]).count(), 4);
+        let standard = dataset.standard_lattice(&skewed.lattice).unwrap();
+        let (a, b, c, alpha, beta, gamma) = standard.to_parameters();
+        assert!([a, b, c].iter().all(|x| (x - 4.2).abs() < 1e-6));
+        assert!([alpha, beta, gamma].iter().all(|x| (x - 90.0).abs() < 1e-6));
+        // (100) of the skewed cell is a {110} plane
+        assert_eq!(dataset.equivalent_planes([1, 0, 0]).len(), 12);
+        assert_eq!(analyze(&rock_salt, 0.01).unwrap().equivalent_planes([1, 0, 0]).len(), 6);
+
+        // Rutile: P42/mnm with Ti on 2a (mmm) and O on 4f (mm2)
+        let u = 0.305;
+        let rutile = crystal(
+            Lattice::from_parameters(4.594, 4.594, 2.959, 90.0, 90.0, 90.0).unwrap(),
+            &[
+                ("Ti", [0.0, 0.0, 0.0]), ("Ti", [0.5, 0.5, 0.5]),
+                ("O", [u, u, 0.0]), ("O", [1.0 - u, 1.0 - u, 0.0]),
+                ("O", [0.5 + u, 0.5 - u, 0.5]), ("O", [0.5 - u, 0.5 + u, 0.5]),
+            ],
+        );
+        let dataset = analyze(&rutile, 0.01).unwrap();
+        assert_eq!(dataset.number, 136);
+        assert_eq!(dataset.site_symmetries, ["mmm", "mmm", "mm2", "mm2", "mm2", "mm2"]);
+        assert_eq!(dataset.multiplicities, [2, 2, 4, 4, 4, 4]);
+
+        // Wurtzite, shifted along the polar axis: P63mc
+        let shift = 0.137;
+        let wurtzite = crystal(
+            Lattice::from_parameters(3.25, 3.25, 5.21, 90.0, 90.0, 120.0).unwrap(),
+            &[
+                ("Zn", [1.0 / 3.0, 2.0 / 3.0, shift]), ("Zn", [2.0 / 3.0, 1.0 / 3.0, 0.5 + shift]),
+                ("O", [1.0 / 3.0, 2.0 / 3.0, 0.382 + shift]), ("O", [2.0 / 3.0, 1.0 / 3.0, 0.882 + shift]),
+            ],
+        );
+        let dataset = analyze(&wurtzite, 0.01).unwrap();
+        assert_eq!((dataset.number, dataset.point_group.as_str()), (186, "6mm"));
+        assert_eq!(dataset.site_symmetries[0], "3m");
+
+        // A distorted structure keeps only the identity
+        let triclinic = crystal(
+            Lattice::from_parameters(5.0, 6.0, 7.0, 80.0, 85.0, 95.0).unwrap(),
+            &[("C", [0.1, 0.2, 0.3]), ("N", [0.4, 0.1, 0.8]), ("O", [0.7, 0.6, 0.2])],
+        );
+        let dataset = analyze(&triclinic, 0.01).unwrap();
+        assert_eq!((dataset.number, dataset.international.as_str(), dataset.operations.len()), (1, "P 1", 1));
+    }
+}
@@ -0,0 +1,443 @@
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashMap;
+
+// ============================================================================
+// DOCUMENT MODEL
+// ============================================================================
+
+/// A single CIF value together with the line it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CifValue {
+    pub text: String,
+    /// 1-based line number of the start of the value.
+    pub line: usize,
+    /// True for quoted strings and text fields, which are never treated as `?` / `.`.
+    pub quoted: bool,
+}
+
+impl CifValue {
+    /// True for the unquoted CIF placeholders `?` (unknown) and `.` (inapplicable).
+    pub fn is_missing(&self) -> bool {
+        !self.quoted && (self.text == "?" || self.text == ".")
+    }
+
+    /// Parses a numeric value, removing a trailing standard uncertainty: "1.234(5)" -> 1.234.
+    pub fn as_f64(&self, tag: &str) -> Result<f64> {
+        let clean = self.text.split('(').next().unwrap_or(&self.text);
+        clean.trim().parse::<f64>().map_err(|_| {
+            anyhow!("line {}: {}: cannot parse '{}' as a number", self.line, tag, self.text)
+        })
+    }
+}
+
+/// A `loop_` construct: a table of values under a list of tags.
+#[derive(Debug, Clone)]
+pub struct CifLoop {
+    /// Lower-cased tag names, in column order.
+    pub tags: Vec<String>,
+    pub rows: Vec<Vec<CifValue>>,
+}
+
+impl CifLoop {
+    /// Column index of a tag (case-insensitive).
+    pub fn column(&self, tag: &str) -> Option<usize> {
+        let tag = tag.to_ascii_lowercase();
+        self.tags.iter().position(|t| *t == tag)
+    }
+}
+
+/// A `data_` block: key-value items plus loops.
+#[derive(Debug, Clone, Default)]
+pub struct CifBlock {
+    pub name: String,
+    /// Line of the `data_` header.
+    pub line: usize,
+    items: HashMap<String, CifValue>,
+    pub loops: Vec<CifLoop>,
+}
+
+impl CifBlock {
+    /// Looks up a non-looped item (case-insensitive tag).
+    pub fn get(&self, tag: &str) -> Option<&CifValue> {
+        self.items.get(&tag.to_ascii_lowercase())
+    }
+
+    /// Finds the loop containing `tag`, if any.
+    pub fn find_loop(&self, tag: &str) -> Option<&CifLoop> {
+        self.loops.iter().find(|l| l.column(tag).is_some())
+    }
+
+    /// All values of a tag, whether it is a single item or a loop column.
+    pub fn values(&self, tag: &str) -> Vec<&CifValue> {
+        if let Some(value) = self.get(tag) {
+            return vec![value];
+        }
+        match self.find_loop(tag) {
+            Some(l) => {
+                let col = l.column(tag).unwrap();
+                l.rows.iter().map(|row| &row[col]).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+// ============================================================================
+// TOKENIZER
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Data(String),
+    Global,
+    Loop,
+    Save(String),
+    Stop,
+    Tag(String),
+    Value(CifValue),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    /// CIF 2.0 changes quoting rules and adds triple quotes, lists and tables.
+    cif2: bool,
+}
+
+impl Lexer {
+    fn new(source: &str) -> Self {
+        let cif2 = source.trim_start_matches('\u{feff}').starts_with("#\\#CIF_2.0");
+        Self { chars: source.chars().collect(), pos: 0, line: 1, cif2 }
+    }
+
+    fn peek(&self) -> Option<char> { self.chars.get(self.pos).copied() }
+    fn peek_at(&self, offset: usize) -> Option<char> { self.chars.get(self.pos + offset).copied() }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied()?;
+        self.pos += 1;
+        if c == '\n' { self.line += 1; }
+        Some(c)
+    }
+
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.bump();
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.chars[self.pos - 1] == '\n' || self.chars[self.pos - 1] == '\r'
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '#' {
+                while let Some(c) = self.peek() {
+                    if c == '\n' { break; }
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        self.skip_whitespace_and_comments();
+        let line = self.line;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        // Semicolon text field: ';' in column 1 up to the next line starting with ';'
+        if c == ';' && self.at_line_start() {
+            self.bump();
+            let mut text = String::new();
+            loop {
+                match self.bump() {
+                    None => bail!("line {}: unterminated text field", line),
+                    Some('\n') => {
+                        if self.peek() == Some(';') {
+                            self.bump();
+                            break;
+                        }
+                        text.push('\n');
+                    }
+                    Some('\r') => {}
+                    Some(ch) => text.push(ch),
+                }
+            }
+            return Ok(Some(Token {
+                kind: TokenKind::Value(CifValue { text: text.trim().to_string(), line, quoted: true }),
+                line,
+            }));
+        }
+
+        if c == '\'' || c == '"' {
+            let text = self.read_quoted(c, line)?;
+            return Ok(Some(Token {
+                kind: TokenKind::Value(CifValue { text, line, quoted: true }),
+                line,
+            }));
+        }
+
+        if self.cif2 && (c == '[' || c == '{') {
+            let text = self.read_bracketed(line)?;
+            return Ok(Some(Token {
+                kind: TokenKind::Value(CifValue { text, line, quoted: true }),
+                line,
+            }));
+        }
+
+        // Bare word: tag, reserved word or unquoted value
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() { break; }
+            if self.cif2 && matches!(c, '[' | ']' | '{' | '}') { break; }
+            word.push(c);
+            self.bump();
+        }
+
+        let lower = word.to_ascii_lowercase();
+        let kind = if word.starts_with('_') {
+            TokenKind::Tag(lower)
+        } else if lower.starts_with("data_") {
+            TokenKind::Data(word[5..].to_string())
+        } else if lower == "loop_" {
+            TokenKind::Loop
+        } else if lower == "global_" {
+            TokenKind::Global
+        } else if lower.starts_with("save_") {
+            TokenKind::Save(word[5..].to_string())
+        } else if lower == "stop_" {
+            TokenKind::Stop
+        } else {
+            TokenKind::Value(CifValue { text: word, line, quoted: false })
+        };
+        Ok(Some(Token { kind, line }))
+    }
+
+    /// Reads a quoted string. In CIF 1.1 a quote only closes the string when followed by
+    /// whitespace, so `'O'Brien'` is a single value; CIF 2.0 closes at the first match.
+    fn read_quoted(&mut self, quote: char, line: usize) -> Result<String> {
+        // Triple-quoted: may span lines, so every character goes through `bump` to count them
+        if self.cif2 && self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote) {
+            self.bump_n(3);
+            let mut text = String::new();
+            loop {
+                if self.peek() == Some(quote) && self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote) {
+                    self.bump_n(3);
+                    return Ok(text);
+                }
+                match self.bump() {
+                    None => bail!("line {}: unterminated triple-quoted string", line),
+                    Some('\r') => {}
+                    Some(ch) => text.push(ch),
+                }
+            }
+        }
+
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') | Some('\r') => {
+                    bail!("line {}: unterminated quoted string {}{}", line, quote, text)
+                }
+                Some(ch) if ch == quote => {
+                    let next = self.peek_at(1);
+                    if self.cif2 || next.is_none() || next.is_some_and(char::is_whitespace) {
+                        self.bump();
+                        return Ok(text);
+                    }
+                    text.push(ch);
+                    self.bump();
+                }
+                Some(ch) => {
+                    text.push(ch);
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    /// Reads a CIF 2.0 list or table as raw text (brackets included).
+    fn read_bracketed(&mut self, line: usize) -> Result<String> {
+        let mut depth = 0;
+        let mut text = String::new();
+        while let Some(c) = self.bump() {
+            text.push(c);
+            match c {
+                '[' | '{' => depth += 1,
+                ']' | '}' => {
+                    depth -= 1;
+                    if depth == 0 { return Ok(text); }
+                }
+                _ => {}
+            }
+        }
+        bail!("line {}: unterminated list or table", line)
+    }
+}
+
+// ============================================================================
+// GRAMMAR
+// ============================================================================
+
+/// Parses CIF 1.1 / 2.0 text into its data blocks.
+///
+/// Handles quoting, semicolon text fields, comments anywhere, loops whose rows wrap over
+/// several lines and multiple `data_` blocks. Save frames (dictionary definitions) are skipped.
+pub fn parse_document(source: &str) -> Result<Vec<CifBlock>> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+
+    let mut blocks: Vec<CifBlock> = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match &token.kind {
+            TokenKind::Data(name) => {
+                blocks.push(CifBlock { name: name.clone(), line: token.line, ..Default::default() });
+                i += 1;
+            }
+            TokenKind::Global => {
+                blocks.push(CifBlock { name: "global_".to_string(), line: token.line, ..Default::default() });
+                i += 1;
+            }
+            TokenKind::Save(name) if !name.is_empty() => {
+                // Skip the whole frame
+                i += 1;
+                while i < tokens.len() && !matches!(&tokens[i].kind, TokenKind::Save(n) if n.is_empty()) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            TokenKind::Save(_) | TokenKind::Stop => i += 1,
+            TokenKind::Tag(tag) => {
+                let block = current_block(&mut blocks, token.line);
+                match tokens.get(i + 1).map(|t| &t.kind) {
+                    Some(TokenKind::Value(value)) => {
+                        block.items.entry(tag.clone()).or_insert_with(|| value.clone());
+                        i += 2;
+                    }
+                    _ => bail!("line {}: tag '{}' has no value", token.line, tag),
+                }
+            }
+            TokenKind::Loop => {
+                let loop_line = token.line;
+                i += 1;
+
+                let mut tags = Vec::new();
+                while let Some(TokenKind::Tag(tag)) = tokens.get(i).map(|t| &t.kind) {
+                    tags.push(tag.clone());
+                    i += 1;
+                }
+                if tags.is_empty() {
+                    bail!("line {}: loop_ without tags", loop_line);
+                }
+
+                let mut values = Vec::new();
+                while let Some(TokenKind::Value(value)) = tokens.get(i).map(|t| &t.kind) {
+                    values.push(value.clone());
+                    i += 1;
+                }
+                if values.len() % tags.len() != 0 {
+                    bail!(
+                        "line {}: loop starting with '{}' has {} values, not a multiple of its {} tags",
+                        loop_line, tags[0], values.len(), tags.len()
+                    );
+                }
+
+                let rows = values.chunks(tags.len()).map(|c| c.to_vec()).collect();
+                current_block(&mut blocks, loop_line).loops.push(CifLoop { tags, rows });
+            }
+            TokenKind::Value(value) => {
+                bail!("line {}: unexpected value '{}' outside of a tag or loop", value.line, value.text)
+            }
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Returns the open block, creating an anonymous one for files that omit `data_`.
+fn current_block(blocks: &mut Vec<CifBlock>, line: usize) -> &mut CifBlock {
+    if blocks.is_empty() {
+        blocks.push(CifBlock { line, ..Default::default() });
+    }
+    blocks.last_mut().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotes_text_fields_and_wrapped_loops() {
+        let source = "\
+data_test
+_symmetry_space_group_name_H-M 'Fm-3m    '
+_audit_creation_method
+;
+Multi-line
+text
+;
+_publ_author_name 'O'Brien, T.'
+loop_
+_atom_site_label _atom_site_fract_x  # comment inside a loop
+_atom_site_fract_y
+Mg1 0.0
+0.0 O1 0.5(2) # wrapped row
+0.5
+";
+        let blocks = parse_document(source).unwrap();
+        assert_eq!(blocks.len(), 1);
+        let block = &blocks[0];
+        assert_eq!(block.get("_symmetry_space_group_name_h-m").unwrap().text, "Fm-3m    ");
+        assert_eq!(block.get("_audit_creation_method").unwrap().text, "Multi-line\ntext");
+        assert_eq!(block.get("_publ_author_name").unwrap().text, "O'Brien, T.");
+
+        let atoms = block.find_loop("_atom_site_fract_x").unwrap();
+        assert_eq!(atoms.rows.len(), 2);
+        assert_eq!(atoms.rows[1][0].text, "O1");
+        assert_eq!(atoms.rows[1][1].as_f64("_atom_site_fract_x").unwrap(), 0.5);
+        assert_eq!(atoms.rows[1][1].line, 13);
+    }
+
+    #[test]
+    fn test_multiple_blocks_and_errors() {
+        let blocks = parse_document("data_a\n_cell_length_a 1\ndata_b\n_cell_length_a 2\n").unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].name, "b");
+
+        let err = parse_document("data_a\nloop_\n_x\n_y\n1 2 3\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        // Lines inside a multi-line triple-quoted value are counted
+        let source = "#\\#CIF_2.0\ndata_a\n_title '''one\ntwo\nthree'''\nloop_\n_x\n_y\n1 2 3\n";
+        let err = parse_document(source).unwrap_err();
+        assert!(err.to_string().contains("line 6"), "{}", err);
+        let err = parse_document("#\\#CIF_2.0\ndata_a\n_title \"\"\"one\ntwo\"\"\"\n_x 'open\n").unwrap_err();
+        assert!(err.to_string().contains("line 5: unterminated"), "{}", err);
+
+        let value = CifValue { text: "1.2.3".into(), line: 7, quoted: false };
+        let err = value.as_f64("_cell_length_a").unwrap_err();
+        assert!(err.to_string().contains("line 7: _cell_length_a"));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::core::structure::{AtomProperty, CifRepresentable, PROP_SELECTIVE_DYNAMICS};
+use crate::synthesis::builder::SlabGeometry;
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Options for `write_cp2k`.
+#[derive(Debug, Clone)]
+pub struct Cp2kOptions {
+    pub project: String,
+    /// `RUN_TYPE` in `&GLOBAL` ("ENERGY", "GEO_OPT", ...).
+    pub run_type: String,
+    /// Basis set and pseudopotential names used for every `&KIND`.
+    pub basis_set: String,
+    pub potential: String,
+    /// Target k-point spacing (Å⁻¹, 2π included); Γ-only when the mesh is 1×1×1.
+    pub k_spacing: f64,
+    /// Add `SURFACE_DIPOLE_CORRECTION` along the slab normal.
+    pub dipole_correction: bool,
+    /// Per-atom "free to move" flags. When `None`, atoms whose stored `selective_dynamics`
+    /// flags are all "F" are fixed.
+    pub selective_dynamics: Option<Vec<bool>>,
+}
+
+impl Default for Cp2kOptions {
+    fn default() -> Self {
+        Self {
+            project: "slab".to_string(),
+            run_type: "GEO_OPT".to_string(),
+            basis_set: "DZVP-MOLOPT-SR-GTH".to_string(),
+            potential: "GTH-PBE".to_string(),
+            k_spacing: 0.25,
+            dipole_correction: true,
+            selective_dynamics: None,
+        }
+    }
+}
+
+/// Writes a CP2K input file for a slab to disk.
+pub fn to_cp2k<T: CifRepresentable>(
+    structure: &T,
+    geometry: &SlabGeometry,
+    path: &Path,
+    options: &Cp2kOptions,
+) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_cp2k(structure, geometry, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a CP2K input for a slab to any writer.
+///
+/// The cell is given as `ABC`/`ALPHA_BETA_GAMMA`, which CP2K orients with a along x and b in
+/// the xy plane; the slab normal (third vector, orthogonal to the surface) is then z, as
+/// `SURF_DIP_DIR` requires. A left-handed cell has a and b swapped so the structure is not
+/// mirrored. Coordinates are written `SCALED`.
+pub fn write_cp2k<T: CifRepresentable, W: Write>(
+    structure: &T,
+    geometry: &SlabGeometry,
+    mut writer: W,
+    options: &Cp2kOptions,
+) -> Result<()> {
+    let atoms = structure.atoms();
+    let lattice = structure.lattice();
+    if atoms.is_empty() {
+        bail!("Cannot write an empty structure to a CP2K input.");
+    }
+    if options.k_spacing <= 0.0 {
+        bail!("k-point spacing must be positive, got {}", options.k_spacing);
+    }
+
+    // 1-based indices of atoms held fixed
+    let fixed: Vec<usize> = match &options.selective_dynamics {
+        Some(free) => {
+            if free.len() != atoms.len() {
+                bail!("{} selective dynamics flags for {} atoms", free.len(), atoms.len());
+            }
+            (0..atoms.len()).filter(|&i| !free[i]).map(|i| i + 1).collect()
+        }
+        None => (0..atoms.len())
+            .filter(|&i| {
+                atoms[i]
+                    .property(PROP_SELECTIVE_DYNAMICS)
+                    .and_then(AtomProperty::as_str)
+                    .is_some_and(|flags| flags.split_whitespace().all(|t| t == "F"))
+            })
+            .map(|i| i + 1)
+            .collect(),
+    };
+
+    let mut kinds: Vec<&str> = Vec::new();
+    for atom in atoms.iter() {
+        if !kinds.contains(&atom.element.as_str()) {
+            kinds.push(&atom.element);
+        }
+    }
+
+    // Handedness: swapping a and b (and x and y) keeps the structure's chirality
+    let swap = lattice.matrix.determinant() < 0.0;
+    let (mut a, mut b, c, mut alpha, mut beta, gamma) = lattice.to_parameters();
+    if swap {
+        std::mem::swap(&mut a, &mut b);
+        std::mem::swap(&mut alpha, &mut beta);
+    }
+
+    // 1. Global
+    writeln!(writer, "&GLOBAL")?;
+    writeln!(writer, "  PROJECT {}", options.project)?;
+    writeln!(writer, "  RUN_TYPE {}", options.run_type)?;
+    writeln!(writer, "&END GLOBAL")?;
+    writeln!(writer)?;
+
+    // 2. Force evaluation
+    writeln!(writer, "&FORCE_EVAL")?;
+    writeln!(writer, "  METHOD Quickstep")?;
+    writeln!(writer, "  &DFT")?;
+    writeln!(writer, "    BASIS_SET_FILE_NAME BASIS_MOLOPT")?;
+    writeln!(writer, "    POTENTIAL_FILE_NAME GTH_POTENTIALS")?;
+    if options.dipole_correction {
+        writeln!(writer, "    SURFACE_DIPOLE_CORRECTION .TRUE.")?;
+        writeln!(writer, "    SURF_DIP_DIR Z")?;
+    }
+    let [n1, n2, n3] = geometry.kpoint_mesh(options.k_spacing);
+    let [n1, n2] = if swap { [n2, n1] } else { [n1, n2] };
+    if n1 * n2 * n3 > 1 {
+        writeln!(writer, "    &KPOINTS")?;
+        writeln!(writer, "      SCHEME MONKHORST-PACK {} {} {}", n1, n2, n3)?;
+        writeln!(writer, "    &END KPOINTS")?;
+    }
+    writeln!(writer, "    &XC")?;
+    writeln!(writer, "      &XC_FUNCTIONAL PBE")?;
+    writeln!(writer, "      &END XC_FUNCTIONAL")?;
+    writeln!(writer, "    &END XC")?;
+    writeln!(writer, "  &END DFT")?;
+
+    writeln!(writer, "  &SUBSYS")?;
+    writeln!(writer, "    &CELL")?;
+    writeln!(writer, "      ABC [angstrom] {:.10} {:.10} {:.10}", a, b, c)?;
+    writeln!(writer, "      ALPHA_BETA_GAMMA [deg] {:.8} {:.8} {:.8}", alpha, beta, gamma)?;
+    writeln!(writer, "      PERIODIC XYZ")?;
+    writeln!(writer, "    &END CELL")?;
+    writeln!(writer, "    &COORD")?;
+    writeln!(writer, "      SCALED")?;
+    for atom in atoms.iter() {
+        let p = atom.fractional_coords;
+        let (x, y) = if swap { (p.y, p.x) } else { (p.x, p.y) };
+        writeln!(writer, "      {:<3} {:>14.10} {:>14.10} {:>14.10}", atom.element, x, y, p.z)?;
+    }
+    writeln!(writer, "    &END COORD")?;
+    for kind in &kinds {
+        writeln!(writer, "    &KIND {}", kind)?;
+        writeln!(writer, "      BASIS_SET {}", options.basis_set)?;
+        writeln!(writer, "      POTENTIAL {}", options.potential)?;
+        writeln!(writer, "    &END KIND")?;
+    }
+    writeln!(writer, "  &END SUBSYS")?;
+    writeln!(writer, "&END FORCE_EVAL")?;
+
+    // 3. Constraints
+    if !fixed.is_empty() {
+        let list: Vec<String> = fixed.iter().map(|i| i.to_string()).collect();
+        writeln!(writer)?;
+        writeln!(writer, "&MOTION")?;
+        writeln!(writer, "  &CONSTRAINT")?;
+        writeln!(writer, "    &FIXED_ATOMS")?;
+        // Keep lines short; CP2K concatenates repeated LIST keywords
+        for chunk in list.chunks(20) {
+            writeln!(writer, "      LIST {}", chunk.join(" "))?;
+        }
+        writeln!(writer, "    &END FIXED_ATOMS")?;
+        writeln!(writer, "  &END CONSTRAINT")?;
+        writeln!(writer, "&END MOTION")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType, Crystal, Lattice};
+    use nalgebra::{Matrix3, Vector3};
+
+    #[test]
+    fn test_left_handed_slab_is_swapped_and_fixed_atoms_listed() {
+        // b before a: left-handed
+        let basis = Matrix3::new(
+            0.0, 3.0, 0.0,
+            4.0, 0.0, 0.0,
+            0.0, 0.0, 30.0,
+        );
+        let geometry = SlabGeometry { basis, d_hkl: 2.0, n_layers: 2, vacuum_thickness: 26.0 };
+        let atoms = vec![
+            Atom::new("Zn", Vector3::new(0.1, 0.2, 0.4), ComponentType::MetalNode),
+            Atom::new("O", Vector3::new(0.5, 0.5, 0.5), ComponentType::OrganicLinker),
+        ];
+        let slab = Crystal { lattice: Lattice::new(basis).unwrap(), atoms };
+        let options = Cp2kOptions { selective_dynamics: Some(vec![false, true]), ..Default::default() };
+
+        let mut buffer = Vec::new();
+        write_cp2k(&slab, &geometry, &mut buffer, &options).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.contains("ABC [angstrom] 3.0000000000 4.0000000000 30.0000000000"));
+        assert!(text.contains("Zn    0.2000000000   0.1000000000   0.4000000000"));
+        assert!(text.contains("SCHEME MONKHORST-PACK 9 7 1"));
+        assert!(text.contains("SURF_DIP_DIR Z"));
+        assert!(text.contains("LIST 1\n"));
+    }
+}
@@ -0,0 +1,385 @@
+use crate::core::elements;
+use crate::core::structure::{
+    Atom, AtomProperty, CifRepresentable, ComponentType, Crystal, Lattice, BOHR_RADIUS, PROP_SELECTIVE_DYNAMICS,
+};
+use crate::io::parser::infer_element;
+use crate::synthesis::builder::SlabGeometry;
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Options for `write_espresso`.
+#[derive(Debug, Clone)]
+pub struct EspressoOptions {
+    /// `calculation` in `&CONTROL` ("scf", "relax", "vc-relax", ...).
+    pub calculation: String,
+    pub prefix: String,
+    pub pseudo_dir: String,
+    /// Pseudopotential file per element; missing elements default to "<El>.UPF".
+    pub pseudopotentials: BTreeMap<String, String>,
+    /// Wavefunction cutoff (Ry).
+    pub ecutwfc: f64,
+    /// Charge-density cutoff (Ry); QE's default (4 × ecutwfc) when `None`.
+    pub ecutrho: Option<f64>,
+    /// Target k-point spacing (Å⁻¹, 2π included).
+    pub k_spacing: f64,
+    /// Add a sawtooth dipole correction along the slab normal (`tefield`/`dipfield`).
+    pub dipole_correction: bool,
+    /// Per-atom "free to move" flags. When `None`, per-axis flags stored in the
+    /// `selective_dynamics` property are written if every atom has them.
+    pub selective_dynamics: Option<Vec<bool>>,
+}
+
+impl Default for EspressoOptions {
+    fn default() -> Self {
+        Self {
+            calculation: "relax".to_string(),
+            prefix: "slab".to_string(),
+            pseudo_dir: "./".to_string(),
+            pseudopotentials: BTreeMap::new(),
+            ecutwfc: 40.0,
+            ecutrho: None,
+            k_spacing: 0.25,
+            dipole_correction: true,
+            selective_dynamics: None,
+        }
+    }
+}
+
+/// Writes a `pw.x` input file for a slab to disk.
+pub fn to_espresso<T: CifRepresentable>(
+    structure: &T,
+    geometry: &SlabGeometry,
+    path: &Path,
+    options: &EspressoOptions,
+) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_espresso(structure, geometry, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a `pw.x` input for a slab to any writer.
+///
+/// The cell is written as-is (`CELL_PARAMETERS angstrom`) with `ATOMIC_POSITIONS crystal`.
+/// The slab normal is the third lattice vector, so the dipole correction uses `edir = 3` and
+/// the k-point mesh has a single point along it.
+pub fn write_espresso<T: CifRepresentable, W: Write>(
+    structure: &T,
+    geometry: &SlabGeometry,
+    mut writer: W,
+    options: &EspressoOptions,
+) -> Result<()> {
+    let atoms = structure.atoms();
+    let lattice = structure.lattice();
+    if atoms.is_empty() {
+        bail!("Cannot write an empty structure to a pw.x input.");
+    }
+    if options.k_spacing <= 0.0 {
+        bail!("k-point spacing must be positive, got {}", options.k_spacing);
+    }
+
+    // Per-axis if_pos flags (1 = free); explicit flags win over stored ones
+    let if_pos: Option<Vec<String>> = match &options.selective_dynamics {
+        Some(free) => {
+            if free.len() != atoms.len() {
+                bail!("{} selective dynamics flags for {} atoms", free.len(), atoms.len());
+            }
+            Some(free.iter().map(|&f| if f { "1 1 1" } else { "0 0 0" }.to_string()).collect())
+        }
+        None => atoms
+            .iter()
+            .map(|a| {
+                let flags = a.property(PROP_SELECTIVE_DYNAMICS).and_then(AtomProperty::as_str)?;
+                Some(flags.split_whitespace().map(|t| if t == "F" { "0" } else { "1" }).collect::<Vec<_>>().join(" "))
+            })
+            .collect(),
+    };
+
+    let mut species: Vec<&str> = Vec::new();
+    for atom in atoms.iter() {
+        if !species.contains(&atom.element.as_str()) {
+            species.push(&atom.element);
+        }
+    }
+
+    // 1. Namelists
+    let ions = matches!(options.calculation.as_str(), "relax" | "md" | "vc-relax" | "vc-md");
+    let cell = options.calculation.starts_with("vc-");
+
+    writeln!(writer, "&CONTROL")?;
+    writeln!(writer, "  calculation = '{}'", options.calculation)?;
+    writeln!(writer, "  prefix = '{}'", options.prefix)?;
+    writeln!(writer, "  pseudo_dir = '{}'", options.pseudo_dir)?;
+    if options.dipole_correction {
+        writeln!(writer, "  tefield = .true.")?;
+        writeln!(writer, "  dipfield = .true.")?;
+    }
+    writeln!(writer, "/")?;
+
+    writeln!(writer, "&SYSTEM")?;
+    writeln!(writer, "  ibrav = 0")?;
+    writeln!(writer, "  nat = {}", atoms.len())?;
+    writeln!(writer, "  ntyp = {}", species.len())?;
+    writeln!(writer, "  ecutwfc = {:.1}", options.ecutwfc)?;
+    if let Some(ecutrho) = options.ecutrho {
+        writeln!(writer, "  ecutrho = {:.1}", ecutrho)?;
+    }
+    if options.dipole_correction {
+        let (emaxpos, eopreg) = sawtooth_region(atoms);
+        writeln!(writer, "  edir = 3")?;
+        writeln!(writer, "  emaxpos = {:.4}", emaxpos)?;
+        writeln!(writer, "  eopreg = {:.4}", eopreg)?;
+        writeln!(writer, "  eamp = 0.0")?;
+    }
+    writeln!(writer, "/")?;
+
+    writeln!(writer, "&ELECTRONS")?;
+    writeln!(writer, "/")?;
+    if ions {
+        writeln!(writer, "&IONS")?;
+        writeln!(writer, "/")?;
+    }
+    if cell {
+        // Only the in-plane cell may change; the vacuum stays fixed
+        writeln!(writer, "&CELL")?;
+        writeln!(writer, "  cell_dofree = '2Dxy'")?;
+        writeln!(writer, "/")?;
+    }
+
+    // 2. Cards
+    writeln!(writer, "\nATOMIC_SPECIES")?;
+    for element in &species {
+        let mass = elements::element(element).map(|e| e.mass).ok_or_else(|| anyhow!("No atomic mass known for element '{}'", element))?;
+        let pseudo = options.pseudopotentials.get(*element).cloned().unwrap_or_else(|| format!("{}.UPF", element));
+        writeln!(writer, "  {:<3} {:>10.4}  {}", element, mass, pseudo)?;
+    }
+
+    writeln!(writer, "\nCELL_PARAMETERS angstrom")?;
+    for column in lattice.matrix.column_iter() {
+        writeln!(writer, "  {:>16.10} {:>16.10} {:>16.10}", column.x, column.y, column.z)?;
+    }
+
+    writeln!(writer, "\nATOMIC_POSITIONS crystal")?;
+    for (i, atom) in atoms.iter().enumerate() {
+        let p = atom.fractional_coords;
+        write!(writer, "  {:<3} {:>14.10} {:>14.10} {:>14.10}", atom.element, p.x, p.y, p.z)?;
+        if let Some(flags) = &if_pos {
+            write!(writer, "  {}", flags[i])?;
+        }
+        writeln!(writer)?;
+    }
+
+    let [n1, n2, n3] = geometry.kpoint_mesh(options.k_spacing);
+    writeln!(writer, "\nK_POINTS automatic")?;
+    writeln!(writer, "  {} {} {} 0 0 0", n1, n2, n3)?;
+
+    Ok(())
+}
+
+/// Places QE's sawtooth ramp (`emaxpos`, `eopreg`, fractions of c) in the middle of the
+/// largest gap between atoms along c, i.e. the vacuum.
+fn sawtooth_region(atoms: &[Atom]) -> (f64, f64) {
+    let mut z: Vec<f64> = atoms.iter().map(|a| a.fractional_coords.z.rem_euclid(1.0)).collect();
+    z.sort_by(|a, b| a.total_cmp(b));
+
+    // Largest gap, including the one across the cell boundary
+    let mut gap = (z[0] + 1.0 - z[z.len() - 1], z[z.len() - 1]);
+    for pair in z.windows(2) {
+        if pair[1] - pair[0] > gap.0 {
+            gap = (pair[1] - pair[0], pair[0]);
+        }
+    }
+    let (width, start) = gap;
+
+    let eopreg = (width / 2.0).min(0.1);
+    let emaxpos = (start + width / 2.0 - eopreg / 2.0).rem_euclid(1.0);
+    (emaxpos, eopreg)
+}
+
+// ============================================================================
+// OUTPUT READER
+// ============================================================================
+
+/// Reads the last geometry of a `pw.x` output file.
+pub fn from_espresso_output(path: &Path) -> Result<Crystal> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read pw.x output: {:?}", path))?;
+    from_espresso_output_str(&contents).with_context(|| format!("Failed to parse pw.x output: {:?}", path))
+}
+
+/// Parses `pw.x` output text. The cell is the last `CELL_PARAMETERS` block (vc-relax) or the
+/// initial `crystal axes`; positions are the last `ATOMIC_POSITIONS` block (relax) or the
+/// initial `positions (alat units)` table (scf). Species labels such as "Fe1" are mapped to
+/// elements.
+pub fn from_espresso_output_str(contents: &str) -> Result<Crystal> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let numbers = |text: &str| -> Vec<f64> {
+        text.split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '=')
+            .filter_map(|t| t.parse::<f64>().ok())
+            .collect()
+    };
+    let header_value = |key: &str| -> Option<f64> {
+        lines.iter().find_map(|l| l.split_once(key)).and_then(|(_, rest)| numbers(rest).first().copied())
+    };
+
+    let alat = header_value("lattice parameter (alat)").ok_or_else(|| anyhow!("pw.x output has no lattice parameter"))? * BOHR_RADIUS;
+    let n_atoms = header_value("number of atoms/cell").ok_or_else(|| anyhow!("pw.x output has no atom count"))? as usize;
+
+    // 1. Cell (columns in Å)
+    let block_unit = |header: &str| -> Result<f64> {
+        let lower = header.to_ascii_lowercase();
+        if lower.contains("alat") {
+            Ok(numbers(header.split_once("alat").map_or("", |(_, r)| r)).first().map_or(alat, |a| a * BOHR_RADIUS))
+        } else if lower.contains("bohr") {
+            Ok(BOHR_RADIUS)
+        } else if lower.contains("angstrom") {
+            Ok(1.0)
+        } else {
+            bail!("unsupported units in '{}'", header.trim())
+        }
+    };
+    let mut columns = Vec::with_capacity(3);
+    if let Some(start) = lines.iter().rposition(|l| l.trim_start().starts_with("CELL_PARAMETERS")) {
+        let unit = block_unit(lines[start])?;
+        for n in start + 1..start + 4 {
+            let v = numbers(lines.get(n).copied().unwrap_or(""));
+            if v.len() < 3 {
+                bail!("line {}: expected a cell vector", n + 1);
+            }
+            columns.push(Vector3::new(v[0], v[1], v[2]) * unit);
+        }
+    } else {
+        let start = lines
+            .iter()
+            .position(|l| l.contains("crystal axes:"))
+            .ok_or_else(|| anyhow!("pw.x output has no crystal axes"))?;
+        for n in start + 1..start + 4 {
+            let v = numbers(lines.get(n).and_then(|l| l.split_once('=')).map_or("", |(_, r)| r));
+            if v.len() < 3 {
+                bail!("line {}: expected a crystal axis", n + 1);
+            }
+            columns.push(Vector3::new(v[0], v[1], v[2]) * alat);
+        }
+    }
+    let lattice = Lattice::new(Matrix3::from_columns(&columns)).map_err(|e| anyhow!("pw.x lattice: {}", e))?;
+
+    // 2. Positions: (label, coordinates, Cartesian Å or fractional)
+    let mut sites: Vec<(String, Vector3<f64>)> = Vec::with_capacity(n_atoms);
+    let crystal_coordinates;
+    if let Some(start) = lines.iter().rposition(|l| l.trim_start().starts_with("ATOMIC_POSITIONS")) {
+        let header = lines[start].to_ascii_lowercase();
+        crystal_coordinates = header.contains("crystal");
+        let unit = if crystal_coordinates { 1.0 } else { block_unit(lines[start])? };
+        for n in start + 1..start + 1 + n_atoms {
+            let text = lines.get(n).copied().unwrap_or("");
+            let label = text.split_whitespace().next().unwrap_or("");
+            let v = numbers(text);
+            if label.is_empty() || v.len() < 3 {
+                bail!("line {}: expected an atomic position", n + 1);
+            }
+            sites.push((label.to_string(), Vector3::new(v[0], v[1], v[2]) * unit));
+        }
+    } else {
+        crystal_coordinates = false;
+        let start = lines
+            .iter()
+            .position(|l| l.contains("positions (alat units)"))
+            .ok_or_else(|| anyhow!("pw.x output has no atomic positions"))?;
+        for n in start + 1..start + 1 + n_atoms {
+            let text = lines.get(n).copied().unwrap_or("");
+            let label = text.split_whitespace().nth(1).unwrap_or("");
+            let v = numbers(text.split_once('=').map_or("", |(_, r)| r));
+            if label.is_empty() || v.len() < 3 {
+                bail!("line {}: expected an atomic position", n + 1);
+            }
+            sites.push((label.to_string(), Vector3::new(v[0], v[1], v[2]) * alat));
+        }
+    }
+
+    let atoms = sites
+        .into_iter()
+        .map(|(label, position)| {
+            let element = infer_element(&label).ok_or_else(|| anyhow!("species '{}' is not an element", label))?;
+            let frac = if crystal_coordinates { position } else { lattice.to_fractional(&position) };
+            Ok(Atom::new(element, frac, ComponentType::Unknown))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Crystal { lattice, atoms })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slab_input_with_dipole_and_mesh() {
+        let basis = Matrix3::new(
+            3.0, 0.0, 0.0,
+            0.0, 4.0, 0.0,
+            0.0, 0.0, 30.0,
+        );
+        let geometry = SlabGeometry { basis, d_hkl: 2.0, n_layers: 2, vacuum_thickness: 26.0 };
+        let mut bottom = Atom::new("Mg", Vector3::new(0.0, 0.0, 0.4), ComponentType::Unknown);
+        bottom.set_property(PROP_SELECTIVE_DYNAMICS, "F F F");
+        let mut top = Atom::new("O", Vector3::new(0.5, 0.5, 0.6), ComponentType::Unknown);
+        top.set_property(PROP_SELECTIVE_DYNAMICS, "T T F");
+        let slab = Crystal { lattice: Lattice::new(basis).unwrap(), atoms: vec![bottom, top] };
+
+        let mut buffer = Vec::new();
+        write_espresso(&slab, &geometry, &mut buffer, &EspressoOptions::default()).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        // 2π/3 / 0.25 -> 9, 2π/4 / 0.25 -> 7
+        assert!(text.contains("K_POINTS automatic\n  9 7 1 0 0 0"));
+        assert!(text.contains("&IONS"));
+        assert!(text.contains("edir = 3"));
+        // Vacuum runs from 0.6 through 1.0 to 1.4: ramp centred on 0.0
+        assert!(text.contains("emaxpos = 0.9500"));
+        assert!(text.contains("eopreg = 0.1000"));
+        assert!(text.contains("  Mg     24.3050  Mg.UPF"));
+        assert!(text.lines().any(|l| l.trim_start().starts_with("O ") && l.ends_with("1 1 0")));
+    }
+
+    #[test]
+    fn test_read_relax_output() {
+        let source = "\
+     Program PWSCF v.7.2 starts on  1Jan2024 at 10: 0: 0
+     lattice parameter (alat)  =       7.5589  a.u.
+     number of atoms/cell      =            2
+     crystal axes: (cart. coord. in units of alat)
+               a(1) = (   1.000000   0.000000   0.000000 )
+               a(2) = (   0.000000   1.000000   0.000000 )
+               a(3) = (   0.000000   0.000000   5.000000 )
+
+     site n.     atom                  positions (alat units)
+         1           Mg  tau(   1) = (   0.0000000   0.0000000   0.5000000  )
+         2           O1  tau(   2) = (   0.5000000   0.5000000   0.5000000  )
+
+ATOMIC_POSITIONS (crystal)
+Mg            0.0000000000        0.0000000000        0.1100000000
+O1            0.5000000000        0.5000000000        0.0950000000
+
+Begin final coordinates
+
+ATOMIC_POSITIONS (crystal)
+Mg            0.0000000000        0.0000000000        0.1050000000
+O1            0.5000000000        0.5000000000        0.0950000000
+End final coordinates
+";
+        let crystal = from_espresso_output_str(source).unwrap();
+        let alat = 7.5589 * BOHR_RADIUS;
+        assert!((crystal.lattice.matrix[(2, 2)] - 5.0 * alat).abs() < 1e-9);
+        assert_eq!(crystal.atoms[1].element, "O");
+        assert!((crystal.atoms[0].fractional_coords.z - 0.105).abs() < 1e-12);
+
+        // scf output: initial positions in alat units
+        let scf: String = source.split("\nATOMIC_POSITIONS").next().unwrap().to_string();
+        let initial = from_espresso_output_str(&scf).unwrap();
+        assert!((initial.atoms[1].fractional_coords - Vector3::new(0.5, 0.5, 0.1)).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::core::structure::{
+    Atom, AtomProperty, CifRepresentable, ComponentType, Crystal, Lattice, PROP_SELECTIVE_DYNAMICS,
+};
+use crate::io::parser::type_symbol_element;
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// One frame of an extended XYZ file: a structure plus its comment-line `key=value` info.
+#[derive(Debug, Clone)]
+pub struct ExtXyzFrame {
+    pub crystal: Crystal,
+    /// Frame-level metadata other than `Lattice`, `Properties` and `pbc`.
+    pub info: BTreeMap<String, String>,
+}
+
+impl ExtXyzFrame {
+    pub fn new(crystal: Crystal) -> Self {
+        Self { crystal, info: BTreeMap::new() }
+    }
+}
+
+/// Per-atom columns with a dedicated `Atom` field or an ASE name.
+const COLUMN_SPECIES: &str = "species";
+const COLUMN_POS: &str = "pos";
+const COLUMN_COMPONENT: &str = "component_type";
+const COLUMN_OCCUPANCY: &str = "occupancy";
+const COLUMN_DISORDER: &str = "disorder_group";
+/// ASE's name for per-axis "may move" flags, mapped to the `selective_dynamics` property.
+const COLUMN_MOVE_MASK: &str = "move_mask";
+
+// ============================================================================
+// READER
+// ============================================================================
+
+/// Reads every frame of an extended XYZ file.
+pub fn from_extxyz(path: &Path) -> Result<Vec<ExtXyzFrame>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read XYZ file: {:?}", path))?;
+    from_extxyz_str(&contents).with_context(|| format!("Failed to parse XYZ file: {:?}", path))
+}
+
+/// Parses extended XYZ text (one or more frames).
+///
+/// Each frame needs a `Lattice`; columns are described by `Properties` (default
+/// `species:S:1:pos:R:3`). Unknown columns become atom properties: `R:1` -> Float, `R:3` -> Vector,
+/// `I:1` -> Int, `L:1` -> Bool, anything else -> Text.
+pub fn from_extxyz_str(contents: &str) -> Result<Vec<ExtXyzFrame>> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut frames = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        let n_atoms: usize = lines[i]
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("line {}: expected an atom count, found '{}'", i + 1, lines[i].trim()))?;
+        let comment = lines.get(i + 1).ok_or_else(|| anyhow!("line {}: frame has no comment line", i + 1))?;
+        if i + 2 + n_atoms > lines.len() {
+            bail!("line {}: frame declares {} atoms but the file ends early", i + 1, n_atoms);
+        }
+
+        let frame = parse_frame(comment, &lines[i + 2..i + 2 + n_atoms], i + 3)
+            .with_context(|| format!("frame {} (line {})", frames.len() + 1, i + 1))?;
+        frames.push(frame);
+        i += 2 + n_atoms;
+    }
+
+    if frames.is_empty() {
+        bail!("No frames found in XYZ file.");
+    }
+    Ok(frames)
+}
+
+/// A column from the `Properties` spec: name, type code (S/R/I/L) and width.
+struct Column {
+    name: String,
+    kind: char,
+    width: usize,
+}
+
+fn parse_frame(comment: &str, atom_lines: &[&str], first_line: usize) -> Result<ExtXyzFrame> {
+    let mut info = parse_comment(comment)?;
+
+    // 1. Lattice (a, b, c vectors in order)
+    let lattice_text = info.remove("Lattice").ok_or_else(|| anyhow!("no Lattice= on the comment line"))?;
+    let values: Vec<f64> = lattice_text
+        .split_whitespace()
+        .map(|t| t.parse::<f64>().map_err(|_| anyhow!("Lattice: cannot parse '{}' as a number", t)))
+        .collect::<Result<_>>()?;
+    if values.len() != 9 {
+        bail!("Lattice needs 9 numbers, found {}", values.len());
+    }
+    let lattice = Lattice::new(Matrix3::from_column_slice(&values)).map_err(|e| anyhow!("Lattice: {}", e))?;
+    info.remove("pbc");
+
+    // 2. Column layout
+    let spec = info.remove("Properties").unwrap_or_else(|| "species:S:1:pos:R:3".to_string());
+    let fields: Vec<&str> = spec.split(':').collect();
+    if !fields.len().is_multiple_of(3) {
+        bail!("Properties '{}' is not a list of name:type:count triples", spec);
+    }
+    let columns: Vec<Column> = fields
+        .chunks(3)
+        .map(|c| {
+            let kind = c[1].chars().next().unwrap_or('S').to_ascii_uppercase();
+            let width = c[2].parse().map_err(|_| anyhow!("Properties: bad column count '{}'", c[2]))?;
+            Ok(Column { name: c[0].to_string(), kind, width })
+        })
+        .collect::<Result<_>>()?;
+    if !columns.iter().any(|c| c.name == COLUMN_SPECIES) || !columns.iter().any(|c| c.name == COLUMN_POS) {
+        bail!("Properties must include species and pos");
+    }
+
+    // 3. Atoms
+    let mut atoms = Vec::with_capacity(atom_lines.len());
+    for (k, line) in atom_lines.iter().enumerate() {
+        let n = first_line + k;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let mut atom = Atom::new("", Vector3::zeros(), ComponentType::Unknown);
+        let mut offset = 0;
+
+        for column in &columns {
+            let values = tokens
+                .get(offset..offset + column.width)
+                .ok_or_else(|| anyhow!("line {}: too few columns for '{}'", n, column.name))?;
+            offset += column.width;
+
+            let float = |t: &str| t.parse::<f64>().map_err(|_| anyhow!("line {}: {}: bad number '{}'", n, column.name, t));
+            let boolean = |t: &str| matches!(t, "T" | "True" | "true" | "1");
+
+            match (column.name.as_str(), column.kind, column.width) {
+                (COLUMN_SPECIES, _, 1) => {
+                    atom.element = type_symbol_element(values[0])
+                        .ok_or_else(|| anyhow!("line {}: '{}' is not an element", n, values[0]))?;
+                }
+                (COLUMN_POS, _, 3) => {
+                    let cart = Vector3::new(float(values[0])?, float(values[1])?, float(values[2])?);
+                    atom.fractional_coords = lattice.to_fractional(&cart);
+                }
+                (COLUMN_COMPONENT, 'S', 1) => {
+                    atom.component_type = ComponentType::from_name(values[0])
+                        .ok_or_else(|| anyhow!("line {}: unknown component_type '{}'", n, values[0]))?;
+                }
+                (COLUMN_OCCUPANCY, 'R', 1) => atom.occupancy = float(values[0])?,
+                (COLUMN_DISORDER, 'I', 1) => {
+                    let group: i32 = values[0].parse().map_err(|_| anyhow!("line {}: bad disorder group", n))?;
+                    atom.disorder_group = (group != 0).then_some(group);
+                }
+                (COLUMN_MOVE_MASK, 'L', 3) => {
+                    let flags: Vec<&str> = values.iter().map(|&t| if boolean(t) { "T" } else { "F" }).collect();
+                    atom.set_property(PROP_SELECTIVE_DYNAMICS, flags.join(" "));
+                }
+                (name, 'R', 1) => atom.set_property(name, float(values[0])?),
+                (name, 'R', 3) => {
+                    atom.set_property(name, Vector3::new(float(values[0])?, float(values[1])?, float(values[2])?))
+                }
+                (name, 'I', 1) => {
+                    let value: i64 = values[0].parse().map_err(|_| anyhow!("line {}: {}: bad integer", n, name))?;
+                    atom.set_property(name, value);
+                }
+                (name, 'L', 1) => atom.set_property(name, boolean(values[0])),
+                (name, _, _) => atom.set_property(name, values.join(" ")),
+            }
+        }
+        atoms.push(atom);
+    }
+
+    Ok(ExtXyzFrame { crystal: Crystal { lattice, atoms }, info })
+}
+
+/// Splits a comment line into `key=value` pairs; values may be "double quoted".
+/// A bare key without `=` is stored as "T", as ASE does.
+fn parse_comment(comment: &str) -> Result<BTreeMap<String, String>> {
+    let mut info = BTreeMap::new();
+    let mut chars = comment.trim().chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '=' && !c.is_whitespace()) {
+            key.push(c);
+        }
+        let mut value = "T".to_string();
+        if chars.next_if_eq(&'=').is_some() {
+            value.clear();
+            if chars.next_if_eq(&'"').is_some() {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => bail!("unterminated quote in value of '{}'", key),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+        info.insert(key, value);
+    }
+    Ok(info)
+}
+
+// ============================================================================
+// WRITER
+// ============================================================================
+
+/// Writes one or more frames to an extended XYZ file.
+pub fn to_extxyz(frames: &[ExtXyzFrame], path: &Path) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    for frame in frames {
+        write_extxyz_frame(&frame.crystal, &frame.info, &mut writer)?;
+    }
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Appends one structure as an extended XYZ frame to any writer.
+///
+/// Columns: species, Cartesian pos, component_type, then occupancy / disorder_group when any
+/// atom is partial or disordered, then every atom property (missing values are written as
+/// 0 / F / "_"). Whitespace inside text values is replaced by '_' so columns stay aligned.
+pub fn write_extxyz_frame<T: CifRepresentable, W: Write>(
+    structure: &T,
+    info: &BTreeMap<String, String>,
+    mut writer: W,
+) -> Result<()> {
+    let atoms = structure.atoms();
+    let lattice = structure.lattice();
+
+    // 1. Column layout
+    let has_occupancy = atoms.iter().any(|a| a.occupancy != 1.0);
+    let has_disorder = atoms.iter().any(|a| a.disorder_group.is_some());
+
+    // Property columns: first value seen decides the type
+    let mut property_columns: BTreeMap<&str, &AtomProperty> = BTreeMap::new();
+    for atom in atoms.iter() {
+        for (key, value) in &atom.properties {
+            property_columns.entry(key.as_str()).or_insert(value);
+        }
+    }
+
+    let mut spec = vec![format!("{}:S:1:{}:R:3:{}:S:1", COLUMN_SPECIES, COLUMN_POS, COLUMN_COMPONENT)];
+    if has_occupancy {
+        spec.push(format!("{}:R:1", COLUMN_OCCUPANCY));
+    }
+    if has_disorder {
+        spec.push(format!("{}:I:1", COLUMN_DISORDER));
+    }
+    for (&key, value) in &property_columns {
+        spec.push(match (key, value) {
+            (PROP_SELECTIVE_DYNAMICS, _) => format!("{}:L:3", COLUMN_MOVE_MASK),
+            (_, AtomProperty::Float(_)) => format!("{}:R:1", key),
+            (_, AtomProperty::Int(_)) => format!("{}:I:1", key),
+            (_, AtomProperty::Text(_)) => format!("{}:S:1", key),
+            (_, AtomProperty::Bool(_)) => format!("{}:L:1", key),
+            (_, AtomProperty::Vector(_)) => format!("{}:R:3", key),
+        });
+    }
+
+    // 2. Header
+    let m = &lattice.matrix;
+    writeln!(writer, "{}", atoms.len())?;
+    write!(
+        writer,
+        "Lattice=\"{:.8} {:.8} {:.8} {:.8} {:.8} {:.8} {:.8} {:.8} {:.8}\" Properties={} pbc=\"T T T\"",
+        m[(0, 0)], m[(1, 0)], m[(2, 0)],
+        m[(0, 1)], m[(1, 1)], m[(2, 1)],
+        m[(0, 2)], m[(1, 2)], m[(2, 2)],
+        spec.join(":")
+    )?;
+    for (key, value) in info {
+        if value.contains(char::is_whitespace) || value.is_empty() {
+            write!(writer, " {}=\"{}\"", key, value)?;
+        } else {
+            write!(writer, " {}={}", key, value)?;
+        }
+    }
+    writeln!(writer)?;
+
+    // 3. Atoms
+    for atom in atoms.iter() {
+        let pos = lattice.to_cartesian(&atom.fractional_coords);
+        write!(
+            writer,
+            "{:<3} {:>16.8} {:>16.8} {:>16.8} {}",
+            atom.element, pos.x, pos.y, pos.z, atom.component_type.name()
+        )?;
+        if has_occupancy {
+            write!(writer, " {:.4}", atom.occupancy)?;
+        }
+        if has_disorder {
+            write!(writer, " {}", atom.disorder_group.unwrap_or(0))?;
+        }
+        for (&key, template) in &property_columns {
+            let value = atom.property(key);
+            let text = match (key, template) {
+                (PROP_SELECTIVE_DYNAMICS, _) => {
+                    value.and_then(AtomProperty::as_str).unwrap_or("T T T").to_string()
+                }
+                (_, AtomProperty::Float(_)) => format!("{:.8}", value.and_then(AtomProperty::as_f64).unwrap_or(0.0)),
+                (_, AtomProperty::Int(_)) => value.and_then(AtomProperty::as_i64).unwrap_or(0).to_string(),
+                (_, AtomProperty::Text(_)) => {
+                    let s = value.and_then(AtomProperty::as_str).unwrap_or("_");
+                    if s.is_empty() { "_".to_string() } else { s.split_whitespace().collect::<Vec<_>>().join("_") }
+                }
+                (_, AtomProperty::Bool(_)) => {
+                    if value.and_then(AtomProperty::as_bool).unwrap_or(false) { "T" } else { "F" }.to_string()
+                }
+                (_, AtomProperty::Vector(_)) => {
+                    let v = value.and_then(AtomProperty::as_vector).copied().unwrap_or_else(Vector3::zeros);
+                    format!("{:.8} {:.8} {:.8}", v.x, v.y, v.z)
+                }
+            };
+            write!(writer, " {}", text)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{PROP_CHARGE, PROP_LABEL, PROP_LAYER};
+
+    #[test]
+    fn test_round_trip_multi_frame() {
+        let lattice = Lattice::from_parameters(5.0, 6.0, 20.0, 90.0, 90.0, 120.0).unwrap();
+        let mut zn = Atom::new("Zn", Vector3::new(0.1, 0.2, 0.3), ComponentType::MetalNode);
+        zn.set_property(PROP_LABEL, "Zn1");
+        zn.set_property(PROP_CHARGE, 1.25);
+        zn.set_property(PROP_LAYER, 2i64);
+        zn.set_property(PROP_SELECTIVE_DYNAMICS, "T T F");
+        zn.occupancy = 0.5;
+        let o = Atom::new("O", Vector3::new(0.6, 0.7, 0.8), ComponentType::OrganicLinker);
+
+        let mut first = ExtXyzFrame::new(Crystal { lattice: lattice.clone(), atoms: vec![zn, o] });
+        first.info.insert("miller".to_string(), "1 0 0".to_string());
+        let second = ExtXyzFrame::new(Crystal { lattice, atoms: vec![Atom::new("H", Vector3::zeros(), ComponentType::Unknown)] });
+
+        let mut buffer = Vec::new();
+        for frame in [&first, &second] {
+            write_extxyz_frame(&frame.crystal, &frame.info, &mut buffer).unwrap();
+        }
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("move_mask:L:3"));
+
+        let frames = from_extxyz_str(&text).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].info.get("miller").map(String::as_str), Some("1 0 0"));
+
+        let zn = &frames[0].crystal.atoms[0];
+        assert_eq!(zn.component_type, ComponentType::MetalNode);
+        assert_eq!(zn.label(), Some("Zn1"));
+        assert_eq!(zn.charge(), Some(1.25));
+        assert_eq!(zn.property(PROP_LAYER).and_then(AtomProperty::as_i64), Some(2));
+        assert_eq!(zn.property(PROP_SELECTIVE_DYNAMICS).and_then(AtomProperty::as_str), Some("T T F"));
+        assert_eq!(zn.occupancy, 0.5);
+        assert!((zn.fractional_coords - Vector3::new(0.1, 0.2, 0.3)).norm() < 1e-6);
+
+        // Missing properties are padded with defaults
+        let o = &frames[0].crystal.atoms[1];
+        assert_eq!(o.label(), Some("_"));
+        assert_eq!(frames[1].crystal.atoms[0].element, "H");
+    }
+
+    #[test]
+    fn test_read_ase_style_header() {
+        let source = "\
+2
+Lattice=\"4.0 0.0 0.0 0.0 4.0 0.0 0.0 0.0 4.0\" Properties=species:S:1:pos:R:3:forces:R:3 energy=-1.5 pbc=\"T T T\"
+Mg 0.0 0.0 0.0 0.1 0.2 0.3
+O  2.0 2.0 2.0 0.0 0.0 0.0
+";
+        let frames = from_extxyz_str(source).unwrap();
+        let crystal = &frames[0].crystal;
+        assert_eq!(frames[0].info.get("energy").map(String::as_str), Some("-1.5"));
+        assert!((crystal.atoms[1].fractional_coords - Vector3::repeat(0.5)).norm() < 1e-9);
+        assert_eq!(
+            crystal.atoms[0].property("forces").and_then(AtomProperty::as_vector),
+            Some(&Vector3::new(0.1, 0.2, 0.3))
+        );
+    }
+}
@@ -0,0 +1,709 @@
+use crate::core::bonding::BondingRule;
+use crate::core::structure::Crystal;
+use crate::io::cp2k::{self, Cp2kOptions};
+use crate::io::espresso::{self, EspressoOptions};
+use crate::io::extxyz;
+use crate::io::gulp::{self, GulpOptions};
+use crate::io::json;
+use crate::io::lammps::{self, LammpsOptions};
+use crate::io::parser::{self, CifConfiguration, CifOptions};
+use crate::io::pdb::{self, PdbOptions};
+use crate::io::poscar::{self, PoscarOptions};
+use crate::io::writer::{self, CifWriterOptions};
+use crate::io::vasp;
+use crate::io::xsf;
+use crate::io::provenance::SlabProvenance;
+use crate::synthesis::builder::SlabGeometry;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// ============================================================================
+// TRAITS
+// ============================================================================
+
+/// One structure read by a `StructureReader`: a frame, a data block or one ordered
+/// configuration of a disordered input.
+#[derive(Debug, Clone)]
+pub struct StructureFrame {
+    pub crystal: Crystal,
+    /// Name of the data block or frame it came from, for formats that name them.
+    pub name: Option<String>,
+    /// Disorder group it was built from, when the reader resolved disorder by groups.
+    pub disorder_group: Option<i32>,
+    /// What the reader removed to obtain an ordered structure, when it resolved disorder.
+    pub disorder_summary: Option<String>,
+}
+
+impl StructureFrame {
+    /// A frame holding only the structure.
+    pub fn new(crystal: Crystal) -> Self {
+        Self { crystal, name: None, disorder_group: None, disorder_summary: None }
+    }
+}
+
+impl From<CifConfiguration> for StructureFrame {
+    fn from(configuration: CifConfiguration) -> Self {
+        Self {
+            crystal: configuration.crystal,
+            name: configuration.block,
+            disorder_group: configuration.disorder.group,
+            disorder_summary: Some(configuration.disorder.summary()),
+        }
+    }
+}
+
+/// A structure file format that can be read.
+pub trait StructureReader {
+    /// Short name used to select the format explicitly ("cif", "poscar", ...).
+    fn name(&self) -> &str;
+    /// Whether a file name (without directories) belongs to this format.
+    fn matches_name(&self, file_name: &str) -> bool;
+    /// Whether the text looks like this format; used when the name does not decide.
+    fn sniff(&self, contents: &str) -> bool;
+    /// Parses the text into one or more structures (frames, data blocks or disorder configurations).
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>>;
+}
+
+/// A structure file format that can be written.
+pub trait StructureWriter {
+    /// Short name used to select the format explicitly ("cif", "poscar", ...).
+    fn name(&self) -> &str;
+    /// Whether a file name (without directories) belongs to this format.
+    fn matches_name(&self, file_name: &str) -> bool;
+    /// File names it is detected by ("*.cif", "POSCAR*"), for messages listing the formats.
+    fn file_patterns(&self) -> &[&str] {
+        &[]
+    }
+    /// Whether several structures share one file (one frame each) instead of one file apiece.
+    fn multi_frame(&self) -> bool {
+        false
+    }
+    /// Writes one structure (one frame for multi-frame formats).
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()>;
+}
+
+/// What a writer may know about a structure beyond its atoms and lattice.
+#[derive(Debug, Clone, Default)]
+pub struct WriteContext {
+    /// Geometry of the slab the structure was built from (layer spacing, k-point mesh).
+    pub geometry: Option<SlabGeometry>,
+    /// Relax this many layers at each surface and hold the rest fixed (needs `geometry`).
+    pub relax_layers: Option<usize>,
+    /// How the slab was cut from the bulk, for formats that can record it.
+    pub provenance: Option<SlabProvenance>,
+    /// Bonding rule for formats that write bonds (overrides the writer's own).
+    pub bonding: Option<BondingRule>,
+    /// Frame-level metadata (Miller indices, disorder group, ...).
+    pub info: BTreeMap<String, String>,
+}
+
+impl WriteContext {
+    /// Per-atom "free to move" flags from `relax_layers`, if requested.
+    pub fn relax_mask(&self, structure: &Crystal) -> Result<Option<Vec<bool>>> {
+        match self.relax_layers {
+            Some(layers) => Ok(Some(self.slab_geometry()?.surface_mask(&structure.atoms, layers))),
+            None => Ok(None),
+        }
+    }
+
+    fn slab_geometry(&self) -> Result<&SlabGeometry> {
+        self.geometry.as_ref().ok_or_else(|| anyhow!("This output needs the slab geometry."))
+    }
+}
+
+/// Lower-cased extension of a file name.
+fn extension(file_name: &str) -> Option<String> {
+    Path::new(file_name).extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase())
+}
+
+fn has_extension(file_name: &str, extensions: &[&str]) -> bool {
+    extension(file_name).is_some_and(|e| extensions.contains(&e.as_str()))
+}
+
+// ============================================================================
+// BUILT-IN FORMATS
+// ============================================================================
+
+/// CIF 1.1 / 2.0 (`.cif`), read with symmetry expansion and disorder resolution.
+#[derive(Debug, Clone, Default)]
+pub struct CifFormat {
+    pub options: CifOptions,
+    pub write_options: CifWriterOptions,
+}
+
+impl StructureReader for CifFormat {
+    fn name(&self) -> &str {
+        "cif"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["cif"])
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        contents.lines().any(|l| l.trim_start().get(..5).is_some_and(|t| t.eq_ignore_ascii_case("data_")))
+    }
+    /// Every data block; a multi-block file gives one or more configurations per block.
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        Ok(parser::all_configurations_from_cif_str(contents, &self.options)?.into_iter().map(StructureFrame::from).collect())
+    }
+}
+
+impl StructureWriter for CifFormat {
+    fn name(&self) -> &str {
+        "cif"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["cif"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.cif"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.write_options.clone();
+        if context.provenance.is_some() {
+            options.provenance = context.provenance.clone();
+        }
+        if options.bonding.is_some() && context.bonding.is_some() {
+            options.bonding = context.bonding.clone();
+        }
+        writer::write_cif_with_options(structure, out, &options)
+    }
+}
+
+/// VASP POSCAR / CONTCAR, recognised by name (POSCAR, CONTCAR, POSCAR_x) or a `.vasp` extension.
+#[derive(Debug, Clone, Default)]
+pub struct PoscarFormat {
+    pub options: PoscarOptions,
+}
+
+impl PoscarFormat {
+    fn is_poscar_name(file_name: &str) -> bool {
+        let name = file_name.to_ascii_uppercase();
+        name.starts_with("POSCAR") || name.starts_with("CONTCAR") || name.ends_with(".VASP")
+    }
+}
+
+impl StructureReader for PoscarFormat {
+    fn name(&self) -> &str {
+        "poscar"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        Self::is_poscar_name(file_name)
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        // Comment, 1 or 3 scale factors, then three lattice vectors
+        let mut lines = contents.lines().skip(1);
+        let floats = |line: Option<&str>| -> Option<usize> {
+            let tokens: Vec<&str> = line?.split_whitespace().collect();
+            tokens.iter().all(|t| t.parse::<f64>().is_ok()).then_some(tokens.len())
+        };
+        matches!(floats(lines.next()), Some(1) | Some(3)) && (0..3).all(|_| floats(lines.next()) == Some(3))
+    }
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        Ok(vec![StructureFrame::new(poscar::from_poscar_str(contents)?)])
+    }
+}
+
+impl StructureWriter for PoscarFormat {
+    fn name(&self) -> &str {
+        "poscar"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        Self::is_poscar_name(file_name)
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["POSCAR*", "CONTCAR*", "*.vasp"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.options.clone();
+        if let Some(mask) = context.relax_mask(structure)? {
+            options.selective_dynamics = Some(mask);
+        }
+        poscar::write_poscar(structure, out, &options)
+    }
+}
+
+/// Extended XYZ (`.xyz` / `.extxyz`); every frame is one structure.
+#[derive(Debug, Clone, Default)]
+pub struct ExtXyzFormat;
+
+impl StructureReader for ExtXyzFormat {
+    fn name(&self) -> &str {
+        "extxyz"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["xyz", "extxyz"])
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        let mut lines = contents.lines().skip_while(|l| l.trim().is_empty());
+        lines.next().is_some_and(|l| l.trim().parse::<usize>().is_ok())
+            && lines.next().is_some_and(|l| l.to_ascii_lowercase().contains("lattice="))
+    }
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        let frames = extxyz::from_extxyz_str(contents)?;
+        if frames.is_empty() {
+            bail!("No frames found in XYZ file.");
+        }
+        Ok(frames
+            .into_iter()
+            .map(|frame| StructureFrame::new(frame.crystal))
+            .collect())
+    }
+}
+
+impl StructureWriter for ExtXyzFormat {
+    fn name(&self) -> &str {
+        "extxyz"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["xyz", "extxyz"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.xyz", "*.extxyz"]
+    }
+    fn multi_frame(&self) -> bool {
+        true
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        extxyz::write_extxyz_frame(structure, &context.info, out)
+    }
+}
+
+/// LAMMPS data file (`.data` / `.lmp`).
+#[derive(Debug, Clone, Default)]
+pub struct LammpsFormat {
+    pub options: LammpsOptions,
+}
+
+impl StructureWriter for LammpsFormat {
+    fn name(&self) -> &str {
+        "lammps"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["data", "lmp"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.data", "*.lmp"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.options.clone();
+        if let Some(rule) = &context.bonding {
+            options.bonding = rule.clone();
+        }
+        lammps::write_lammps_data(structure, out, &options)
+    }
+}
+
+/// Quantum ESPRESSO `pw.x` input (`.pwi`); needs the slab geometry.
+#[derive(Debug, Clone, Default)]
+pub struct EspressoFormat {
+    pub options: EspressoOptions,
+}
+
+impl StructureWriter for EspressoFormat {
+    fn name(&self) -> &str {
+        "espresso"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["pwi"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.pwi"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.options.clone();
+        if let Some(mask) = context.relax_mask(structure)? {
+            options.selective_dynamics = Some(mask);
+        }
+        espresso::write_espresso(structure, context.slab_geometry()?, out, &options)
+    }
+}
+
+/// Quantum ESPRESSO `pw.x` output (`.pwo`, or any file with the PWSCF banner); the last geometry.
+#[derive(Debug, Clone, Default)]
+pub struct EspressoOutputFormat;
+
+impl StructureReader for EspressoOutputFormat {
+    fn name(&self) -> &str {
+        "espresso-out"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["pwo"])
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        contents.lines().take(20).any(|l| l.contains("Program PWSCF"))
+    }
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        let crystal = espresso::from_espresso_output_str(contents)?;
+        Ok(vec![StructureFrame::new(crystal)])
+    }
+}
+
+/// VASP `vasprun.xml`; the final geometry.
+#[derive(Debug, Clone, Default)]
+pub struct VasprunFormat;
+
+impl StructureReader for VasprunFormat {
+    fn name(&self) -> &str {
+        "vasprun"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        file_name.to_ascii_lowercase().starts_with("vasprun") && has_extension(file_name, &["xml"])
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        contents.trim_start().starts_with("<?xml") && contents.contains("<modeling>")
+    }
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        Ok(vec![StructureFrame::new(vasp::from_vasprun_str(contents)?)])
+    }
+}
+
+/// VASP `OUTCAR`; the last geometry.
+#[derive(Debug, Clone, Default)]
+pub struct OutcarFormat;
+
+impl StructureReader for OutcarFormat {
+    fn name(&self) -> &str {
+        "outcar"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        file_name.to_ascii_uppercase().starts_with("OUTCAR")
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        contents.trim_start().starts_with("vasp.") && contents.contains("ions per type")
+    }
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        Ok(vec![StructureFrame::new(vasp::from_outcar_str(contents)?)])
+    }
+}
+
+/// CP2K input (`.inp`); needs the slab geometry.
+#[derive(Debug, Clone, Default)]
+pub struct Cp2kFormat {
+    pub options: Cp2kOptions,
+}
+
+impl StructureWriter for Cp2kFormat {
+    fn name(&self) -> &str {
+        "cp2k"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["inp"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.inp"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.options.clone();
+        if let Some(mask) = context.relax_mask(structure)? {
+            options.selective_dynamics = Some(mask);
+        }
+        cp2k::write_cp2k(structure, context.slab_geometry()?, out, &options)
+    }
+}
+
+/// GULP input (`.gin`). With `relax_layers`, that many layers below the top surface form region 1.
+#[derive(Debug, Clone, Default)]
+pub struct GulpFormat {
+    pub options: GulpOptions,
+}
+
+impl StructureWriter for GulpFormat {
+    fn name(&self) -> &str {
+        "gulp"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["gin"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.gin"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.options.clone();
+        if let Some(layers) = context.relax_layers {
+            options.region1_depth = Some(layers as f64 * context.slab_geometry()?.d_hkl - 1e-3);
+        }
+        gulp::write_gulp(structure, out, &options)
+    }
+}
+
+/// pymatgen `Structure` JSON (`.json`), as written by `Structure.to(filename="x.json")`.
+#[derive(Debug, Clone, Default)]
+pub struct JsonFormat;
+
+impl StructureReader for JsonFormat {
+    fn name(&self) -> &str {
+        "json"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["json"])
+    }
+    fn sniff(&self, contents: &str) -> bool {
+        contents.trim_start().starts_with('{') && contents.contains("\"sites\"")
+    }
+    fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+        Ok(vec![StructureFrame::new(json::from_json_str(contents)?)])
+    }
+}
+
+impl StructureWriter for JsonFormat {
+    fn name(&self) -> &str {
+        "json"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["json"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.json"]
+    }
+    /// The frame metadata and the slab provenance go into `Structure.properties`.
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut properties: serde_json::Map<String, serde_json::Value> =
+            context.info.iter().map(|(k, v)| (k.clone(), v.as_str().into())).collect();
+        if let Some(provenance) = &context.provenance {
+            properties.insert("slab_provenance".to_string(), serde_json::to_value(provenance)?);
+        }
+        json::write_json(structure, &properties, out)
+    }
+}
+
+/// Protein Data Bank file (`.pdb`) with residues per component and explicit bonds.
+#[derive(Debug, Clone, Default)]
+pub struct PdbFormat {
+    pub options: PdbOptions,
+}
+
+impl StructureWriter for PdbFormat {
+    fn name(&self) -> &str {
+        "pdb"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["pdb"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.pdb"]
+    }
+    fn write(&self, structure: &Crystal, context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        let mut options = self.options.clone();
+        if let Some(rule) = &context.bonding {
+            options.bonding = rule.clone();
+        }
+        pdb::write_pdb(structure, out, &options)
+    }
+}
+
+/// XCrySDen structure file (`.xsf`).
+#[derive(Debug, Clone, Default)]
+pub struct XsfFormat;
+
+impl StructureWriter for XsfFormat {
+    fn name(&self) -> &str {
+        "xsf"
+    }
+    fn matches_name(&self, file_name: &str) -> bool {
+        has_extension(file_name, &["xsf"])
+    }
+    fn file_patterns(&self) -> &[&str] {
+        &["*.xsf"]
+    }
+    fn write(&self, structure: &Crystal, _context: &WriteContext, out: &mut dyn Write) -> Result<()> {
+        xsf::write_xsf(structure, out)
+    }
+}
+
+// ============================================================================
+// REGISTRY
+// ============================================================================
+
+/// Readers and writers keyed by name, file name and content.
+///
+/// Formats registered later take precedence during detection, and registering a format with
+/// an existing name replaces it, so library users can add or override formats.
+pub struct FormatRegistry {
+    readers: Vec<Box<dyn StructureReader>>,
+    writers: Vec<Box<dyn StructureWriter>>,
+}
+
+impl Default for FormatRegistry {
+    /// A registry with every built-in format.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        // Loosest sniffer first, so it is tried last
+        registry.register_reader(PoscarFormat::default());
+        registry.register_reader(OutcarFormat);
+        registry.register_reader(EspressoOutputFormat);
+        registry.register_reader(VasprunFormat);
+        registry.register_reader(ExtXyzFormat);
+        registry.register_reader(JsonFormat);
+        registry.register_reader(CifFormat::default());
+
+        registry.register_writer(JsonFormat);
+        registry.register_writer(XsfFormat);
+        registry.register_writer(PdbFormat::default());
+        registry.register_writer(GulpFormat::default());
+        registry.register_writer(Cp2kFormat::default());
+        registry.register_writer(EspressoFormat::default());
+        registry.register_writer(LammpsFormat::default());
+        registry.register_writer(ExtXyzFormat);
+        registry.register_writer(PoscarFormat::default());
+        registry.register_writer(CifFormat::default());
+        registry
+    }
+}
+
+impl FormatRegistry {
+    /// A registry without any formats.
+    pub fn empty() -> Self {
+        Self { readers: Vec::new(), writers: Vec::new() }
+    }
+
+    pub fn register_reader(&mut self, reader: impl StructureReader + 'static) {
+        self.readers.retain(|r| r.name() != reader.name());
+        self.readers.insert(0, Box::new(reader));
+    }
+
+    pub fn register_writer(&mut self, writer: impl StructureWriter + 'static) {
+        self.writers.retain(|w| w.name() != writer.name());
+        self.writers.insert(0, Box::new(writer));
+    }
+
+    pub fn reader(&self, name: &str) -> Option<&dyn StructureReader> {
+        self.readers.iter().find(|r| r.name().eq_ignore_ascii_case(name)).map(|r| r.as_ref())
+    }
+
+    pub fn writer(&self, name: &str) -> Option<&dyn StructureWriter> {
+        self.writers.iter().find(|w| w.name().eq_ignore_ascii_case(name)).map(|w| w.as_ref())
+    }
+
+    /// Picks a reader by file name, then by sniffing the contents.
+    pub fn detect_reader(&self, path: Option<&Path>, contents: &str) -> Result<&dyn StructureReader> {
+        let file_name = path.and_then(|p| p.file_name()).and_then(|n| n.to_str());
+        file_name
+            .and_then(|name| self.readers.iter().find(|r| r.matches_name(name)))
+            .or_else(|| self.readers.iter().find(|r| r.sniff(contents)))
+            .map(|r| r.as_ref())
+            .ok_or_else(|| anyhow!("Could not detect the structure format of {:?}", path.unwrap_or(Path::new("-"))))
+    }
+
+    /// Picks a reader by file name alone.
+    pub fn detect_reader_by_name(&self, path: &Path) -> Option<&dyn StructureReader> {
+        let name = path.file_name().and_then(|n| n.to_str())?;
+        self.readers.iter().find(|r| r.matches_name(name)).map(|r| r.as_ref())
+    }
+
+    /// Picks a writer by file name.
+    pub fn detect_writer(&self, path: &Path) -> Option<&dyn StructureWriter> {
+        let name = path.file_name().and_then(|n| n.to_str())?;
+        self.writers.iter().find(|w| w.matches_name(name)).map(|w| w.as_ref())
+    }
+
+    /// Writer names with the file names they are detected by: "cif (*.cif)", ...
+    pub fn writer_patterns(&self) -> Vec<String> {
+        self.writers
+            .iter()
+            .map(|w| match w.file_patterns() {
+                [] => w.name().to_string(),
+                patterns => format!("{} ({})", w.name(), patterns.join(", ")),
+            })
+            .collect()
+    }
+
+    /// Reads a structure file, detecting its format.
+    pub fn read_path(&self, path: &Path) -> Result<Vec<StructureFrame>> {
+        let contents = fs::read_to_string(path).with_context(|| format!("Could not read structure file: {:?}", path))?;
+        let reader = self.detect_reader(Some(path), &contents)?;
+        reader
+            .read_str(&contents)
+            .with_context(|| format!("Failed to parse {} file: {:?}", reader.name(), path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType, Lattice};
+    use nalgebra::Vector3;
+
+    /// Minimal user format: one "element x y z" line per atom in a 10 Å cube.
+    struct CubeFormat;
+
+    impl StructureReader for CubeFormat {
+        fn name(&self) -> &str {
+            "cube10"
+        }
+        fn matches_name(&self, file_name: &str) -> bool {
+            file_name.ends_with(".c10")
+        }
+        fn sniff(&self, _contents: &str) -> bool {
+            false
+        }
+        fn read_str(&self, contents: &str) -> Result<Vec<StructureFrame>> {
+            let lattice = Lattice::from_parameters(10.0, 10.0, 10.0, 90.0, 90.0, 90.0).map_err(|e| anyhow!(e))?;
+            let atoms = contents
+                .lines()
+                .map(|l| {
+                    let t: Vec<&str> = l.split_whitespace().collect();
+                    let p = Vector3::new(t[1].parse()?, t[2].parse()?, t[3].parse()?);
+                    Ok(Atom::new(t[0], p, ComponentType::Unknown))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(vec![StructureFrame::new(Crystal { lattice, atoms })])
+        }
+    }
+
+    #[test]
+    fn test_detection_by_name_and_content() {
+        let registry = FormatRegistry::default();
+        let poscar = "Mg O\n1.0\n4 0 0\n0 4 0\n0 0 4\nMg O\n1 1\nDirect\n0 0 0\n0.5 0.5 0.5\n";
+        let xyz = "1\nLattice=\"4 0 0 0 4 0 0 0 4\" Properties=species:S:1:pos:R:3\nMg 0 0 0\n";
+        let cif = "# comment\ndata_test\n_cell_length_a 4\n";
+
+        assert_eq!(registry.detect_reader(Some(Path::new("dir/CONTCAR")), "").unwrap().name(), "poscar");
+        assert_eq!(registry.detect_reader(Some(Path::new("a.CIF")), "").unwrap().name(), "cif");
+        assert_eq!(registry.detect_reader(None, poscar).unwrap().name(), "poscar");
+        assert_eq!(registry.detect_reader(None, xyz).unwrap().name(), "extxyz");
+        assert_eq!(registry.detect_reader(None, "{\"@class\": \"Structure\", \"sites\": []}").unwrap().name(), "json");
+        assert_eq!(registry.detect_reader(Some(Path::new("in.txt")), cif).unwrap().name(), "cif");
+        assert_eq!(registry.detect_reader(Some(Path::new("run/vasprun.xml")), "").unwrap().name(), "vasprun");
+        assert_eq!(registry.detect_reader(Some(Path::new("OUTCAR.relax")), "").unwrap().name(), "outcar");
+        assert_eq!(registry.detect_reader(None, "\n     Program PWSCF v.7.2 starts\n").unwrap().name(), "espresso-out");
+        assert!(registry.detect_reader(None, "hello").is_err());
+
+        assert_eq!(registry.detect_writer(Path::new("slab.xyz")).unwrap().name(), "extxyz");
+        assert_eq!(registry.detect_writer(Path::new("slab.gin")).unwrap().name(), "gulp");
+        assert!(registry.detect_writer(Path::new("slab.unknown")).is_none());
+        assert!(registry.writer_patterns().contains(&"poscar (POSCAR*, CONTCAR*, *.vasp)".to_string()));
+
+        let read = registry.detect_reader(None, poscar).unwrap().read_str(poscar).unwrap();
+        assert_eq!(read[0].crystal.atoms.len(), 2);
+        assert!(read[0].name.is_none() && read[0].disorder_summary.is_none());
+
+        // CIF blocks become named frames carrying the disorder resolution
+        let cif = "data_rocksalt\n_cell_length_a 4\n_cell_length_b 4\n_cell_length_c 4\n\
+                   _cell_angle_alpha 90\n_cell_angle_beta 90\n_cell_angle_gamma 90\n\
+                   loop_\n_atom_site_label\n_atom_site_fract_x\n_atom_site_fract_y\n_atom_site_fract_z\n\
+                   Mg1 0 0 0\nO1 0.5 0.5 0.5\n";
+        let read = registry.reader("cif").unwrap().read_str(cif).unwrap();
+        assert_eq!(read[0].name.as_deref(), Some("rocksalt"));
+        assert_eq!(read[0].disorder_summary.as_deref(), Some("No disordered sites dropped."));
+    }
+
+    #[test]
+    fn test_user_format_and_override() {
+        let mut registry = FormatRegistry::default();
+        registry.register_reader(CubeFormat);
+        let reader = registry.detect_reader(Some(Path::new("x.c10")), "").unwrap();
+        let read = reader.read_str("Zn 0.5 0.5 0.5\n").unwrap();
+        assert_eq!(read[0].crystal.atoms[0].element, "Zn");
+
+        // Replacing a built-in keeps one entry under that name
+        registry.register_writer(PoscarFormat { options: PoscarOptions { cartesian: true, ..Default::default() } });
+        assert_eq!(registry.writers.iter().filter(|w| w.name() == "poscar").count(), 1);
+        let mut buffer = Vec::new();
+        let writer = registry.writer("POSCAR").unwrap();
+        writer.write(&read[0].crystal, &WriteContext::default(), &mut buffer).unwrap();
+        assert!(String::from_utf8(buffer).unwrap().contains("Cartesian"));
+    }
+}
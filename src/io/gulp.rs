@@ -0,0 +1,203 @@
+use crate::core::elements::{self, Element};
+use crate::core::structure::{Atom, CifRepresentable};
+use anyhow::{bail, Context, Result};
+use nalgebra::{Matrix2, Matrix3, Vector2, Vector3};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Periodicity of a GULP input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GulpMode {
+    /// 3D cell (`cell` / `fractional`).
+    Bulk,
+    /// 2D slab (`svectors` / `sfractional`) with the surface normal along z; the vacuum is dropped.
+    Slab,
+}
+
+/// Options for `write_gulp`.
+#[derive(Debug, Clone)]
+pub struct GulpOptions {
+    pub mode: GulpMode,
+    /// First line of the input ("opti conp", "opti conv prop", ...).
+    pub keywords: String,
+    pub title: String,
+    /// Slab mode: atoms within this depth (Å) of the top surface form region 1, the rest region 2
+    /// (held at bulk positions). Everything is region 1 when `None`.
+    pub region1_depth: Option<f64>,
+    /// Potential library loaded with `library` (e.g. "catlow" for Buckingham oxide potentials).
+    pub library: Option<String>,
+    /// Slab mode: bulk energy per formula unit (eV) for `sbulkenergy`, giving the surface energy.
+    pub bulk_energy: Option<f64>,
+}
+
+impl Default for GulpOptions {
+    fn default() -> Self {
+        Self {
+            mode: GulpMode::Slab,
+            keywords: "opti conp".to_string(),
+            title: "slab".to_string(),
+            region1_depth: None,
+            library: None,
+            bulk_energy: None,
+        }
+    }
+}
+
+/// Writes a GULP input file to disk.
+pub fn to_gulp<T: CifRepresentable>(structure: &T, path: &Path, options: &GulpOptions) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_gulp(structure, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a GULP input to any writer.
+///
+/// Every atom is a core carrying the formal charge used by `IonicReconstructor` (one `species`
+/// entry per element). In slab mode the plane spanned by a and b is the surface: a lies along x,
+/// z is the height above the top surface (so every atom has z <= 0) and region 2 lies below
+/// region 1, as GULP requires.
+pub fn write_gulp<T: CifRepresentable, W: Write>(structure: &T, mut writer: W, options: &GulpOptions) -> Result<()> {
+    let atoms = structure.atoms();
+    let lattice = structure.lattice();
+    if atoms.is_empty() {
+        bail!("Cannot write an empty structure to a GULP input.");
+    }
+
+    let mut species: Vec<&str> = Vec::new();
+    for atom in atoms.iter() {
+        if !species.contains(&atom.element.as_str()) {
+            species.push(&atom.element);
+        }
+    }
+
+    // 1. Header
+    writeln!(writer, "{}", options.keywords)?;
+    writeln!(writer, "title")?;
+    writeln!(writer, "{}", options.title)?;
+    writeln!(writer, "end")?;
+
+    // 2. Structure
+    match options.mode {
+        GulpMode::Bulk => {
+            let (a, b, c, alpha, beta, gamma) = lattice.to_parameters();
+            writeln!(writer, "cell")?;
+            writeln!(writer, "  {:.6} {:.6} {:.6} {:.6} {:.6} {:.6}", a, b, c, alpha, beta, gamma)?;
+            writeln!(writer, "fractional")?;
+            for atom in atoms.iter() {
+                let p = atom.fractional_coords;
+                writeln!(writer, "{:<3} core {:>12.8} {:>12.8} {:>12.8}", atom.element, p.x, p.y, p.z)?;
+            }
+        }
+        GulpMode::Slab => {
+            let (surface, sites) = surface_frame(atoms, &lattice.matrix)?;
+            writeln!(writer, "svectors")?;
+            for column in surface.column_iter() {
+                writeln!(writer, "  {:>14.8} {:>14.8}", column.x, column.y)?;
+            }
+
+            let in_region1 = |z: f64| options.region1_depth.is_none_or(|depth| z > -depth);
+            for region in [1, 2] {
+                let members: Vec<usize> = (0..atoms.len()).filter(|&i| in_region1(sites[i].z) == (region == 1)).collect();
+                if members.is_empty() {
+                    continue;
+                }
+                writeln!(writer, "sfractional region {}", region)?;
+                for i in members {
+                    let s = sites[i];
+                    writeln!(writer, "{:<3} core {:>12.8} {:>12.8} {:>12.6}", atoms[i].element, s.x, s.y, s.z)?;
+                }
+            }
+        }
+    }
+
+    // 3. Charges and potentials
+    writeln!(writer, "species")?;
+    for element in &species {
+        writeln!(writer, "{:<3} core {:>8.4}", element, elements::element(element).map_or(0.0, Element::formal_charge))?;
+    }
+    if let Some(library) = &options.library {
+        writeln!(writer, "library {}", library)?;
+    }
+    if options.mode == GulpMode::Slab {
+        if let Some(energy) = options.bulk_energy {
+            writeln!(writer, "sbulkenergy {:.6}", energy)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Surface vectors (columns, 2D Cartesian) and per-atom (frac a, frac b, height below the top
+/// surface in Å). A left-handed a/b pair is swapped so that +z points out of the top surface.
+fn surface_frame(atoms: &[Atom], matrix: &Matrix3<f64>) -> Result<(Matrix2<f64>, Vec<Vector3<f64>>)> {
+    let (mut a, mut b, c) = (matrix.column(0).into_owned(), matrix.column(1).into_owned(), matrix.column(2));
+    let swap = a.cross(&b).dot(&c) < 0.0;
+    if swap {
+        std::mem::swap(&mut a, &mut b);
+    }
+    let normal = a.cross(&b).normalize();
+    let x = a.normalize();
+    let y = normal.cross(&x);
+
+    let surface = Matrix2::new(
+        a.dot(&x), b.dot(&x),
+        0.0, b.dot(&y),
+    );
+    let to_fractional = surface.try_inverse().context("Surface vectors are degenerate")?;
+
+    let positions: Vec<Vector3<f64>> = atoms.iter().map(|atom| matrix * atom.fractional_coords).collect();
+    let top = positions.iter().map(|r| r.dot(&normal)).fold(f64::NEG_INFINITY, f64::max);
+    let sites = positions
+        .iter()
+        .map(|r| {
+            let f = to_fractional * Vector2::new(r.dot(&x), r.dot(&y));
+            Vector3::new(f.x.rem_euclid(1.0), f.y.rem_euclid(1.0), r.dot(&normal) - top)
+        })
+        .collect();
+    Ok((surface, sites))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{ComponentType, Crystal, Lattice};
+
+    #[test]
+    fn test_slab_regions_and_species() {
+        // Sheared c: heights come from the normal, not from c
+        let basis = Matrix3::new(
+            4.0, 0.0, 2.0,
+            0.0, 4.0, 0.0,
+            0.0, 0.0, 20.0,
+        );
+        let lattice = Lattice::new(basis).unwrap();
+        let atoms = [("Mg", 0.0, 0.2), ("O", 0.5, 0.2), ("Mg", 0.5, 0.3), ("O", 0.0, 0.3)]
+            .iter()
+            .map(|&(e, x, z)| Atom::new(e, Vector3::new(x, x, z), ComponentType::Unknown))
+            .collect();
+        let slab = Crystal { lattice, atoms };
+        let options = GulpOptions {
+            region1_depth: Some(1.0),
+            library: Some("catlow".to_string()),
+            bulk_energy: Some(-41.07),
+            ..Default::default()
+        };
+
+        let mut buffer = Vec::new();
+        write_gulp(&slab, &mut buffer, &options).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.starts_with("opti conp\n"));
+        assert!(text.contains("svectors\n      4.00000000     0.00000000\n      0.00000000     4.00000000\n"));
+        let region1 = text.split("sfractional region 1\n").nth(1).unwrap();
+        let region2 = region1.split("sfractional region 2\n").nth(1).unwrap();
+        // The top plane (z = 6 Å) sits at 0; the plane 2 Å below is region 2. The shear along c
+        // moves the top Mg from x = 0.5 to 0.5 + 0.3 * 2 / 4 = 0.65.
+        assert!(region1.starts_with("Mg  core   0.65000000   0.50000000     0.000000\n"));
+        assert!(region2.starts_with("Mg  core   0.10000000   0.00000000    -2.000000\n"));
+        assert!(text.contains("species\nMg  core   2.0000\nO   core  -2.0000\nlibrary catlow\nsbulkenergy -41.070000"));
+    }
+}
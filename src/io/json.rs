@@ -0,0 +1,394 @@
+use crate::core::elements;
+use crate::core::structure::{
+    Atom, AtomProperty, ComponentType, Crystal, Lattice, PROP_LABEL, PROP_SELECTIVE_DYNAMICS,
+};
+use anyhow::{anyhow, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Site property holding the `ComponentType` name.
+pub const PROP_COMPONENT_TYPE: &str = "component_type";
+/// Site property holding the disorder group.
+pub const PROP_DISORDER_GROUP: &str = "disorder_group";
+
+// ============================================================================
+// PYMATGEN LAYOUT
+// ============================================================================
+//
+// `Crystal` and `Lattice` serialize to the dictionaries of pymatgen's `Structure.as_dict()` /
+// `Lattice.as_dict()`, so `Structure.from_dict(json.load(f))` reads our slabs directly:
+//
+//   {"@module": "pymatgen.core.structure", "@class": "Structure", "charge": 0,
+//    "lattice": {"matrix": [[ax, ay, az], ...], "pbc": [true, true, true], "a": ..., ...},
+//    "properties": {},
+//    "sites": [{"species": [{"element": "Zn", "occu": 1.0}], "abc": [...], "xyz": [...],
+//               "label": "Zn1", "properties": {"charge": 1.2, "component_type": "MetalNode"}}]}
+
+#[derive(Serialize, Deserialize)]
+struct StructureDict {
+    #[serde(rename = "@module", default)]
+    module: String,
+    #[serde(rename = "@class", default)]
+    class: String,
+    #[serde(default)]
+    charge: f64,
+    lattice: LatticeDict,
+    #[serde(default)]
+    properties: Map<String, Value>,
+    sites: Vec<SiteDict>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LatticeDict {
+    #[serde(rename = "@module", default)]
+    module: String,
+    #[serde(rename = "@class", default)]
+    class: String,
+    /// Rows are the lattice vectors.
+    matrix: [[f64; 3]; 3],
+    #[serde(default = "periodic")]
+    pbc: [bool; 3],
+    // Derived values, written for convenience and ignored when reading
+    #[serde(default)]
+    a: f64,
+    #[serde(default)]
+    b: f64,
+    #[serde(default)]
+    c: f64,
+    #[serde(default)]
+    alpha: f64,
+    #[serde(default)]
+    beta: f64,
+    #[serde(default)]
+    gamma: f64,
+    #[serde(default)]
+    volume: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SiteDict {
+    species: Vec<SpeciesDict>,
+    abc: [f64; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    xyz: Option<[f64; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default)]
+    properties: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpeciesDict {
+    element: String,
+    occu: f64,
+}
+
+fn periodic() -> [bool; 3] {
+    [true; 3]
+}
+
+fn rows(matrix: &Matrix3<f64>) -> [[f64; 3]; 3] {
+    std::array::from_fn(|i| std::array::from_fn(|j| matrix[(j, i)]))
+}
+
+impl From<&Lattice> for LatticeDict {
+    fn from(lattice: &Lattice) -> Self {
+        let (a, b, c, alpha, beta, gamma) = lattice.to_parameters();
+        Self {
+            module: "pymatgen.core.lattice".to_string(),
+            class: "Lattice".to_string(),
+            matrix: rows(&lattice.matrix),
+            pbc: periodic(),
+            a,
+            b,
+            c,
+            alpha,
+            beta,
+            gamma,
+            volume: lattice.matrix.determinant().abs(),
+        }
+    }
+}
+
+impl TryFrom<LatticeDict> for Lattice {
+    type Error = String;
+
+    fn try_from(dict: LatticeDict) -> std::result::Result<Self, String> {
+        let m = dict.matrix;
+        let matrix = Matrix3::from_columns(&[Vector3::from(m[0]), Vector3::from(m[1]), Vector3::from(m[2])]);
+        Lattice::new(matrix).map_err(str::to_string)
+    }
+}
+
+/// pymatgen site dictionary for one atom; `lattice` adds the Cartesian `xyz`.
+fn site_dict(atom: &Atom, lattice: Option<&Lattice>) -> SiteDict {
+    let mut properties = Map::new();
+    for (key, value) in &atom.properties {
+        if key == PROP_LABEL {
+            continue;
+        }
+        properties.insert(key.clone(), property_to_json(key, value));
+    }
+    properties.insert(PROP_COMPONENT_TYPE.to_string(), atom.component_type.name().into());
+    if let Some(group) = atom.disorder_group {
+        properties.insert(PROP_DISORDER_GROUP.to_string(), group.into());
+    }
+
+    SiteDict {
+        species: vec![SpeciesDict { element: atom.element.clone(), occu: atom.occupancy }],
+        abc: atom.fractional_coords.into(),
+        xyz: lattice.map(|l| l.to_cartesian(&atom.fractional_coords).into()),
+        // pymatgen labels every site, by default with its species
+        label: Some(atom.label().unwrap_or(&atom.element).to_string()),
+        properties,
+    }
+}
+
+/// Atoms of one site: a disordered pymatgen site (several species) gives one atom per species.
+fn site_atoms(site: SiteDict) -> std::result::Result<Vec<Atom>, String> {
+    if site.species.is_empty() {
+        return Err("site without species".to_string());
+    }
+    let single = site.species.len() == 1;
+    let mut atoms = Vec::with_capacity(site.species.len());
+    for species in site.species {
+        let element = elements::normalize_symbol(&species.element)
+            .ok_or_else(|| format!("'{}' is not an element", species.element))?;
+        let mut atom = Atom::new(element.to_string(), Vector3::from(site.abc), ComponentType::Unknown);
+        atom.occupancy = species.occu;
+        for (key, value) in &site.properties {
+            match key.as_str() {
+                PROP_COMPONENT_TYPE => {
+                    let name = value.as_str().unwrap_or_default();
+                    atom.component_type =
+                        ComponentType::from_name(name).ok_or_else(|| format!("unknown component_type '{}'", value))?;
+                }
+                PROP_DISORDER_GROUP => {
+                    let group = value.as_i64().ok_or_else(|| format!("disorder_group '{}' is not an integer", value))?;
+                    atom.disorder_group = Some(group as i32);
+                }
+                _ => atom.set_property(key, json_to_property(key, value)),
+            }
+        }
+        // The default pymatgen label is the species string; only real labels are kept
+        if let Some(label) = site.label.as_deref().filter(|&l| single && l != species.element) {
+            atom.set_property(PROP_LABEL, label);
+        }
+        atoms.push(atom);
+    }
+    Ok(atoms)
+}
+
+/// JSON value of a site property. Selective dynamics become pymatgen's `[bool, bool, bool]`.
+fn property_to_json(key: &str, value: &AtomProperty) -> Value {
+    match value {
+        AtomProperty::Text(flags) if key == PROP_SELECTIVE_DYNAMICS => {
+            flags.split_whitespace().map(|f| Value::Bool(f.eq_ignore_ascii_case("T"))).collect()
+        }
+        AtomProperty::Float(x) => (*x).into(),
+        AtomProperty::Int(i) => (*i).into(),
+        AtomProperty::Text(s) => s.as_str().into(),
+        AtomProperty::Bool(b) => (*b).into(),
+        AtomProperty::Vector(v) => v.iter().copied().collect(),
+    }
+}
+
+/// Inverse of `property_to_json`; values with no `AtomProperty` counterpart are kept as JSON text.
+fn json_to_property(key: &str, value: &Value) -> AtomProperty {
+    match value {
+        Value::Bool(b) => AtomProperty::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => AtomProperty::Int(i),
+            None => AtomProperty::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => AtomProperty::Text(s.clone()),
+        Value::Array(items) if items.len() == 3 && key == PROP_SELECTIVE_DYNAMICS && items.iter().all(Value::is_boolean) => {
+            let flags: Vec<&str> = items.iter().map(|f| if f.as_bool() == Some(true) { "T" } else { "F" }).collect();
+            AtomProperty::Text(flags.join(" "))
+        }
+        Value::Array(items) if items.len() == 3 && items.iter().all(Value::is_number) => {
+            AtomProperty::Vector(Vector3::from_fn(|i, _| items[i].as_f64().unwrap_or_default()))
+        }
+        other => AtomProperty::Text(other.to_string()),
+    }
+}
+
+fn structure_dict(crystal: &Crystal, properties: Map<String, Value>) -> StructureDict {
+    StructureDict {
+        module: "pymatgen.core.structure".to_string(),
+        class: "Structure".to_string(),
+        charge: 0.0,
+        lattice: LatticeDict::from(&crystal.lattice),
+        properties,
+        sites: crystal.atoms.iter().map(|atom| site_dict(atom, Some(&crystal.lattice))).collect(),
+    }
+}
+
+impl TryFrom<StructureDict> for Crystal {
+    type Error = String;
+
+    fn try_from(dict: StructureDict) -> std::result::Result<Self, String> {
+        let lattice = Lattice::try_from(dict.lattice)?;
+        let mut atoms = Vec::with_capacity(dict.sites.len());
+        for (i, site) in dict.sites.into_iter().enumerate() {
+            atoms.extend(site_atoms(site).map_err(|e| format!("site {}: {}", i + 1, e))?);
+        }
+        Ok(Crystal { lattice, atoms })
+    }
+}
+
+impl Serialize for Lattice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        LatticeDict::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Lattice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Lattice::try_from(LatticeDict::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// A single atom serializes as a pymatgen site without the Cartesian `xyz`.
+impl Serialize for Atom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        site_dict(self, None).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Atom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let mut atoms = site_atoms(SiteDict::deserialize(deserializer)?).map_err(D::Error::custom)?;
+        if atoms.len() != 1 {
+            return Err(D::Error::custom("a disordered site holds several atoms"));
+        }
+        Ok(atoms.remove(0))
+    }
+}
+
+impl Serialize for Crystal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        structure_dict(self, Map::new()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Crystal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Crystal::try_from(StructureDict::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+// ============================================================================
+// FILES
+// ============================================================================
+
+/// Writes a structure as pymatgen-compatible JSON.
+pub fn to_json(crystal: &Crystal, path: &Path) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_json(crystal, &Map::new(), &mut writer)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a structure as a pretty-printed pymatgen `Structure` dictionary. `properties` become
+/// the structure-level `properties` (pymatgen keeps them on `Structure.properties`).
+pub fn write_json<W: Write>(crystal: &Crystal, properties: &Map<String, Value>, mut writer: W) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, &structure_dict(crystal, properties.clone()))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Reads a structure from a pymatgen `Structure` JSON file.
+pub fn from_json(path: &Path) -> Result<Crystal> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read JSON file: {:?}", path))?;
+    from_json_str(&contents).with_context(|| format!("Failed to parse JSON file: {:?}", path))
+}
+
+/// Parses a pymatgen `Structure` dictionary.
+pub fn from_json_str(contents: &str) -> Result<Crystal> {
+    let dict: StructureDict = serde_json::from_str(contents).map_err(|e| anyhow!("Invalid structure JSON: {}", e))?;
+    if !dict.class.is_empty() && !["Structure", "Slab", "IStructure"].contains(&dict.class.as_str()) {
+        return Err(anyhow!("Expected a pymatgen Structure, got '{}'", dict.class));
+    }
+    Crystal::try_from(dict).map_err(|e| anyhow!("Invalid structure JSON: {}", e))
+}
+
+// ============================================================================
+// SERDE HELPERS
+// ============================================================================
+
+/// `#[serde(with = "...")]` adaptor writing a `Matrix3` as the list of its columns, i.e. the
+/// row-vector layout of pymatgen (`[[ax, ay, az], [bx, by, bz], [cx, cy, cz]]`).
+pub mod matrix_columns {
+    use nalgebra::{Matrix3, Scalar, Vector3};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T, S>(matrix: &Matrix3<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Scalar + Serialize,
+        S: Serializer,
+    {
+        let columns: [[T; 3]; 3] = std::array::from_fn(|j| std::array::from_fn(|i| matrix[(i, j)].clone()));
+        columns.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Matrix3<T>, D::Error>
+    where
+        T: Scalar + Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let [a, b, c] = <[[T; 3]; 3]>::deserialize(deserializer)?;
+        Ok(Matrix3::from_columns(&[Vector3::from(a), Vector3::from(b), Vector3::from(c)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::PROP_CHARGE;
+
+    #[test]
+    fn test_pymatgen_round_trip() {
+        let lattice = Lattice::from_parameters(5.0, 6.0, 7.0, 90.0, 100.0, 90.0).unwrap();
+        let mut zn = Atom::new("Zn", Vector3::new(0.1, 0.2, 0.3), ComponentType::MetalNode);
+        zn.set_property(PROP_LABEL, "Zn1");
+        zn.set_property(PROP_CHARGE, 1.25);
+        zn.set_property(PROP_SELECTIVE_DYNAMICS, "T T F");
+        let mut o = Atom::new("O", Vector3::new(0.5, 0.5, 0.5), ComponentType::OrganicLinker);
+        o.occupancy = 0.5;
+        o.disorder_group = Some(2);
+        let crystal = Crystal { lattice, atoms: vec![zn, o] };
+
+        let text = serde_json::to_string(&crystal).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["@class"], "Structure");
+        assert_eq!(value["lattice"]["matrix"][0], serde_json::json!([5.0, 0.0, 0.0]));
+        assert_eq!(value["sites"][0]["label"], "Zn1");
+        assert_eq!(value["sites"][0]["properties"]["selective_dynamics"], serde_json::json!([true, true, false]));
+        assert_eq!(value["sites"][1]["species"], serde_json::json!([{"element": "O", "occu": 0.5}]));
+
+        let reread = from_json_str(&text).unwrap();
+        assert_eq!(reread.atoms, crystal.atoms);
+        assert!((reread.lattice.matrix - crystal.lattice.matrix).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_disordered_pymatgen_site() {
+        let text = r#"{"@module": "pymatgen.core.structure", "@class": "Structure", "charge": 0,
+            "lattice": {"matrix": [[4, 0, 0], [0, 4, 0], [0, 0, 4]], "pbc": [true, true, true]},
+            "properties": {},
+            "sites": [{"species": [{"element": "Fe", "occu": 0.5}, {"element": "Co", "occu": 0.5}],
+                       "abc": [0, 0, 0], "xyz": [0, 0, 0], "label": "Fe:0.500, Co:0.500", "properties": {"magmom": 2.5}}]}"#;
+        let crystal = from_json_str(text).unwrap();
+        assert_eq!(crystal.atoms.len(), 2);
+        assert_eq!(crystal.atoms[1].element, "Co");
+        assert_eq!(crystal.atoms[1].label(), None);
+        assert_eq!(crystal.atoms[0].property("magmom"), Some(&AtomProperty::Float(2.5)));
+    }
+}
@@ -0,0 +1,352 @@
+use crate::core::bonding::BondingRule;
+use crate::core::connectivity::GraphRepresentation;
+use crate::core::elements;
+use crate::core::structure::{ComponentType, Crystal, Lattice};
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use petgraph::graph::NodeIndex;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Atoms closer than this (Å) are treated as duplicates rather than bonded.
+const OVERLAP_DISTANCE: f64 = 0.5;
+
+/// Options for `write_lammps_data`.
+#[derive(Debug, Clone)]
+pub struct LammpsOptions {
+    /// Which atom pairs are bonded; the bonds also define the molecule IDs.
+    pub bonding: BondingRule,
+    /// Write an `Angles` section generated from the bonds.
+    pub angles: bool,
+}
+
+impl Default for LammpsOptions {
+    fn default() -> Self {
+        Self { bonding: BondingRule::default(), angles: true }
+    }
+}
+
+/// A LAMMPS restricted-triclinic box: a along x, b in the xy plane, |tilts| at most half a length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriclinicBox {
+    pub lx: f64,
+    pub ly: f64,
+    pub lz: f64,
+    pub xy: f64,
+    pub xz: f64,
+    pub yz: f64,
+}
+
+impl TriclinicBox {
+    /// Edge vectors as the columns of an upper-triangular matrix.
+    pub fn matrix(&self) -> Matrix3<f64> {
+        Matrix3::new(
+            self.lx, self.xy, self.xz,
+            0.0, self.ly, self.yz,
+            0.0, 0.0, self.lz,
+        )
+    }
+}
+
+/// Re-expresses a lattice as a LAMMPS restricted-triclinic box.
+///
+/// The cell is rotated so that a lies along x and b in the xy plane (a left-handed cell first
+/// has a and b swapped), then the tilt factors are reduced with integer lattice-vector
+/// combinations so LAMMPS accepts them. Returns the box and the transformation `P` with
+/// `frac_box = P * frac_original` (before wrapping).
+pub fn triclinic_box(lattice: &Lattice) -> Result<(TriclinicBox, Matrix3<f64>)> {
+    // 1. Handedness
+    let mut basis_change = Matrix3::identity();
+    let mut matrix = lattice.matrix;
+    if matrix.determinant() < 0.0 {
+        matrix.swap_columns(0, 1);
+        basis_change.swap_rows(0, 1);
+    }
+
+    // 2. Rotate into the a-along-x, b-in-xy frame
+    let (a, b, c) = (matrix.column(0), matrix.column(1), matrix.column(2));
+    let lx = a.norm();
+    let xy = b.dot(&a) / lx;
+    let ly = (b.norm_squared() - xy * xy).sqrt();
+    let xz = c.dot(&a) / lx;
+    let yz = (b.dot(&c) - xy * xz) / ly;
+    let lz = (c.norm_squared() - xz * xz - yz * yz).sqrt();
+    if !(ly > 1e-9 && lz > 1e-9) {
+        bail!("Degenerate cell cannot be written as a LAMMPS box.");
+    }
+    let mut lmp = TriclinicBox { lx, ly, lz, xy, xz, yz };
+
+    // 3. Tilt reduction: c -= n b, c -= m a, b -= p a (in this order, so earlier steps stay valid)
+    let n = (lmp.yz / lmp.ly).round();
+    lmp.yz -= n * lmp.ly;
+    lmp.xz -= n * lmp.xy;
+    let m = (lmp.xz / lmp.lx).round();
+    lmp.xz -= m * lmp.lx;
+    let p = (lmp.xy / lmp.lx).round();
+    lmp.xy -= p * lmp.lx;
+    let reduction = Matrix3::new(
+        1.0, -p, -m,
+        0.0, 1.0, -n,
+        0.0, 0.0, 1.0,
+    );
+
+    // New edges = old edges * reduction, so new fractional = reduction^-1 * old fractional
+    let inverse = reduction.try_inverse().ok_or_else(|| anyhow!("Tilt reduction is singular"))?;
+    Ok((lmp, inverse * basis_change))
+}
+
+/// Writes a LAMMPS data file (`atom_style full`) to disk.
+pub fn to_lammps_data(crystal: &Crystal, path: &Path, options: &LammpsOptions) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_lammps_data(crystal, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a LAMMPS data file (`atom_style full`) to any writer.
+///
+/// Atom types are one per (element, `ComponentType`) pair; bond and angle types one per
+/// (sorted) combination of atom types. Molecule IDs are the connected components of the
+/// bond graph. Charges come from the `charge` property (0.0 when absent).
+pub fn write_lammps_data<W: Write>(crystal: &Crystal, mut writer: W, options: &LammpsOptions) -> Result<()> {
+    let atoms = &crystal.atoms;
+    if atoms.is_empty() {
+        bail!("Cannot write an empty structure to a LAMMPS data file.");
+    }
+
+    // 1. Box and positions in the LAMMPS frame
+    let (lmp_box, to_box) = triclinic_box(&crystal.lattice)?;
+    let box_matrix = lmp_box.matrix();
+    let positions: Vec<Vector3<f64>> = atoms
+        .iter()
+        .map(|a| box_matrix * (to_box * a.fractional_coords).map(|x| x - x.floor()))
+        .collect();
+
+    // 2. Topology
+    let graph = GraphRepresentation::from_crystal_with_rule(crystal, &options.bonding);
+    let mut molecule_ids = vec![0usize; atoms.len()];
+    for (id, component) in graph.find_connected_components().iter().enumerate() {
+        for &i in component {
+            molecule_ids[i] = id + 1;
+        }
+    }
+
+    let bonded = graph.bonds();
+    // LAMMPS bonds join atom IDs under the minimum image: one per pair, none to an atom's own image
+    let mut bonds: Vec<(usize, usize)> = bonded.iter().filter(|b| b.0 != b.1).map(|&(i, j, _)| (i, j)).collect();
+    bonds.dedup();
+
+    // Coincident sites (e.g. periodic duplicates) would give LAMMPS zero-length bonds
+    for &(i, j, image) in &bonded {
+        let d = crystal
+            .lattice
+            .to_cartesian(&(atoms[j].fractional_coords + image.cast::<f64>() - atoms[i].fractional_coords))
+            .norm();
+        if d < OVERLAP_DISTANCE {
+            bail!(
+                "Atoms {} ({}) and {} ({}) overlap ({:.3} Å); remove duplicate sites before writing LAMMPS data.",
+                i + 1, atoms[i].element, j + 1, atoms[j].element, d
+            );
+        }
+    }
+
+    let mut angles: Vec<(usize, usize, usize)> = Vec::new();
+    if options.angles {
+        for center in 0..atoms.len() {
+            let mut neighbors: Vec<usize> =
+                graph.graph.neighbors(NodeIndex::new(center)).map(|n| graph.graph[n]).filter(|&n| n != center).collect();
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            for (x, &i) in neighbors.iter().enumerate() {
+                for &k in &neighbors[x + 1..] {
+                    angles.push((i, center, k));
+                }
+            }
+        }
+    }
+
+    // 3. Type tables (in order of first appearance)
+    let mut atom_types: Vec<(&str, ComponentType)> = Vec::new();
+    let atom_type: Vec<usize> = atoms
+        .iter()
+        .map(|a| type_id(&mut atom_types, (a.element.as_str(), a.component_type)))
+        .collect();
+
+    let mut bond_types: Vec<(usize, usize)> = Vec::new();
+    let bond_type: Vec<usize> = bonds
+        .iter()
+        .map(|&(i, j)| {
+            let (ti, tj) = (atom_type[i], atom_type[j]);
+            type_id(&mut bond_types, (ti.min(tj), ti.max(tj)))
+        })
+        .collect();
+
+    let mut angle_types: Vec<(usize, usize, usize)> = Vec::new();
+    let angle_type: Vec<usize> = angles
+        .iter()
+        .map(|&(i, j, k)| {
+            let (ti, tk) = (atom_type[i], atom_type[k]);
+            type_id(&mut angle_types, (ti.min(tk), atom_type[j], ti.max(tk)))
+        })
+        .collect();
+
+    let type_name = |t: usize| -> String {
+        let (element, component) = atom_types[t - 1];
+        format!("{}_{}", element, component.name())
+    };
+
+    // 4. Header
+    writeln!(writer, "LAMMPS data file generated by Crystal Surface Generator")?;
+    writeln!(writer)?;
+    writeln!(writer, "{} atoms", atoms.len())?;
+    writeln!(writer, "{} bonds", bonds.len())?;
+    writeln!(writer, "{} angles", angles.len())?;
+    writeln!(writer)?;
+    writeln!(writer, "{} atom types", atom_types.len())?;
+    writeln!(writer, "{} bond types", bond_types.len())?;
+    writeln!(writer, "{} angle types", angle_types.len())?;
+    writeln!(writer)?;
+    writeln!(writer, "0.0 {:.8} xlo xhi", lmp_box.lx)?;
+    writeln!(writer, "0.0 {:.8} ylo yhi", lmp_box.ly)?;
+    writeln!(writer, "0.0 {:.8} zlo zhi", lmp_box.lz)?;
+    writeln!(writer, "{:.8} {:.8} {:.8} xy xz yz", lmp_box.xy, lmp_box.xz, lmp_box.yz)?;
+
+    // 5. Sections
+    writeln!(writer, "\nMasses\n")?;
+    for (t, (element, component)) in atom_types.iter().enumerate() {
+        let mass = elements::element(element).map(|e| e.mass).ok_or_else(|| anyhow!("No atomic mass known for element '{}'", element))?;
+        writeln!(writer, "{} {:.4} # {} {}", t + 1, mass, element, component.name())?;
+    }
+
+    writeln!(writer, "\nAtoms # full\n")?;
+    for (i, (atom, pos)) in atoms.iter().zip(&positions).enumerate() {
+        writeln!(
+            writer,
+            "{} {} {} {:.6} {:.8} {:.8} {:.8}",
+            i + 1,
+            molecule_ids[i],
+            atom_type[i],
+            atom.charge().unwrap_or(0.0),
+            pos.x,
+            pos.y,
+            pos.z
+        )?;
+    }
+
+    if !bonds.is_empty() {
+        writeln!(writer, "\nBonds\n")?;
+        for (n, (&(i, j), &t)) in bonds.iter().zip(&bond_type).enumerate() {
+            writeln!(writer, "{} {} {} {}", n + 1, t, i + 1, j + 1)?;
+        }
+    }
+
+    if !angles.is_empty() {
+        writeln!(writer, "\nAngles\n")?;
+        for (n, (&(i, j, k), &t)) in angles.iter().zip(&angle_type).enumerate() {
+            writeln!(writer, "{} {} {} {} {}", n + 1, t, i + 1, j + 1, k + 1)?;
+        }
+    }
+
+    // Type legend for the force-field author (comments only)
+    if !bond_types.is_empty() {
+        writeln!(writer)?;
+        for (t, &(i, j)) in bond_types.iter().enumerate() {
+            writeln!(writer, "# bond type {}: {}-{}", t + 1, type_name(i), type_name(j))?;
+        }
+    }
+    for (t, &(i, j, k)) in angle_types.iter().enumerate() {
+        writeln!(writer, "# angle type {}: {}-{}-{}", t + 1, type_name(i), type_name(j), type_name(k))?;
+    }
+
+    Ok(())
+}
+
+/// 1-based id of `key` in `table`, appending it if new.
+fn type_id<K: PartialEq>(table: &mut Vec<K>, key: K) -> usize {
+    match table.iter().position(|k| *k == key) {
+        Some(i) => i + 1,
+        None => {
+            table.push(key);
+            table.len()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::Atom;
+
+    #[test]
+    fn test_triclinic_box_reduces_tilt_and_keeps_geometry() {
+        // Heavily sheared, left-handed cell
+        let matrix = Matrix3::new(
+            0.0, 10.0, 7.0,
+            5.0, 1.0, 2.0,
+            0.0, 0.0, 6.0,
+        );
+        let lattice = Lattice::new(matrix).unwrap();
+        let (b, to_box) = triclinic_box(&lattice).unwrap();
+        assert!(b.xy.abs() <= b.lx / 2.0 + 1e-9);
+        assert!(b.xz.abs() <= b.lx / 2.0 + 1e-9);
+        assert!(b.yz.abs() <= b.ly / 2.0 + 1e-9);
+        assert!((b.lx * b.ly * b.lz - matrix.determinant().abs()).abs() < 1e-6);
+
+        // Distances between two atoms are unchanged by the rotation and basis change
+        let f1 = Vector3::new(0.1, 0.2, 0.3);
+        let f2 = Vector3::new(0.4, 0.25, 0.35);
+        let before = (matrix * (f2 - f1)).norm();
+        let after = (b.matrix() * (to_box * (f2 - f1))).norm();
+        assert!((before - after).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_water_topology() {
+        let lattice = Lattice::from_parameters(10.0, 10.0, 10.0, 90.0, 90.0, 90.0).unwrap();
+        let cart = [Vector3::new(5.0, 5.0, 5.0), Vector3::new(5.96, 5.0, 5.0), Vector3::new(4.76, 5.93, 5.0)];
+        let atoms = ["O", "H", "H"]
+            .iter()
+            .zip(cart)
+            .map(|(e, r)| Atom::new(*e, lattice.to_fractional(&r), ComponentType::Solvent))
+            .collect();
+        let crystal = Crystal { lattice, atoms };
+
+        let mut buffer = Vec::new();
+        write_lammps_data(&crystal, &mut buffer, &LammpsOptions { bonding: BondingRule::uniform(1.2), angles: true }).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.contains("3 atoms\n2 bonds\n1 angles"));
+        assert!(text.contains("2 atom types\n1 bond types\n1 angle types"));
+        assert!(text.contains("1 15.9990 # O Solvent"));
+        assert!(text.contains("\nAngles\n\n1 1 2 1 3\n"));
+        assert!(text.contains("# angle type 1: H_Solvent-O_Solvent-H_Solvent"));
+    }
+
+    #[test]
+    fn test_rock_salt_supercell_topology() {
+        // 2x2x2 supercell of MgO so every Mg-O bond is a distinct minimum image
+        let lattice = Lattice::from_parameters(4.2316, 4.2316, 4.2316, 90.0, 90.0, 90.0).unwrap();
+        let mut atoms = Vec::new();
+        for t in [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]] {
+            atoms.push(Atom::new("Mg", Vector3::from(t), ComponentType::Unknown));
+            atoms.push(Atom::new("O", Vector3::from(t).map(|x| (x + 0.5) % 1.0), ComponentType::Unknown));
+        }
+        let crystal = Crystal { lattice, atoms }.make_supercell(2, 2, 2).unwrap();
+
+        let mut buffer = Vec::new();
+        write_lammps_data(&crystal, &mut buffer, &LammpsOptions { bonding: BondingRule::uniform(2.2), angles: true }).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        // Octahedral coordination: 6 bonds and 15 angles per atom
+        assert!(text.contains("64 atoms\n192 bonds\n960 angles"));
+        assert!(text.contains("2 atom types\n1 bond types\n2 angle types"));
+        let tilt_line = text.lines().find(|l| l.ends_with("xy xz yz")).unwrap();
+        assert!(tilt_line.split_whitespace().take(3).all(|t| t.parse::<f64>().unwrap().abs() < 1e-6));
+
+        // One periodic framework: every atom in molecule 1
+        let atoms_section = text.split("Atoms # full\n\n").nth(1).unwrap();
+        assert!(atoms_section.lines().take(64).all(|l| l.split_whitespace().nth(1) == Some("1")));
+    }
+}
@@ -1,2 +1,16 @@
+pub mod cif;
+pub mod cp2k;
+pub mod espresso;
+pub mod extxyz;
+pub mod format;
+pub mod gulp;
+pub mod json;
+pub mod lammps;
 pub mod parser;
-pub mod writer;
\ No newline at end of file
+pub mod pdb;
+pub mod poscar;
+pub mod provenance;
+pub mod vasp;
+pub mod volumetric;
+pub mod writer;
+pub mod xsf;
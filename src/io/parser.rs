@@ -1,96 +1,279 @@
-use crate::core::structure::{Atom, Crystal, Lattice, ComponentType};
+use crate::core::elements;
+use crate::core::structure::{Atom, Crystal, Lattice, ComponentType, PROP_CHARGE, PROP_LABEL, PROP_LAYER, PROP_MAGMOM};
+use crate::core::spacegroup::{self, SymOp};
+use crate::io::cif::{self, CifBlock, CifValue};
+use crate::io::provenance::{SlabProvenance, BULK_CELL_NAMES, PROVENANCE_PREFIX, TAG_COMPONENT_TYPE, TAG_LAYER, TAG_SOURCE_LABEL};
 use anyhow::{anyhow, Context, Result};
-use nalgebra::Vector3;
-use std::collections::HashMap;
+use nalgebra::{Matrix3, Vector3};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::Path;
 
-/// Parses a float value from a CIF string, safely removing uncertainty parentheses.
-/// Example: "1.234(5)" -> 1.234
-fn parse_cif_float(s: &str) -> Result<f64> {
-    // Split at '(' and take the first part
-    let clean_s = s.split('(').next().unwrap_or(s);
-    clean_s.parse::<f64>().with_context(|| format!("Failed to parse '{}' as float", s))
+/// How disordered sites (`_atom_site_disorder_group`) are resolved when reading a CIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisorderResolution {
+    /// Keep every site; alternative positions overlap in the resulting cell.
+    KeepAll,
+    /// In each disorder assembly keep the group with the highest mean occupancy.
+    #[default]
+    Majority,
+    /// Keep the given group in every disorder assembly.
+    Group(i32),
+    /// Produce one ordered configuration per disorder group number.
+    Split,
 }
 
+/// Options controlling how a CIF is turned into a `Crystal`.
+#[derive(Debug, Clone)]
+pub struct CifOptions {
+    /// Distance (Å) below which two symmetry images of the same site are merged.
+    /// Sites on special positions are generated several times by the operators.
+    pub symmetry_tolerance: f64,
+    /// Treatment of partially occupied, disordered sites.
+    pub disorder: DisorderResolution,
+}
+
+impl Default for CifOptions {
+    fn default() -> Self {
+        Self { symmetry_tolerance: 0.05, disorder: DisorderResolution::default() }
+    }
+}
+
+/// A crystallographic site removed (fully or partly) while resolving disorder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedSite {
+    pub label: String,
+    pub element: String,
+    pub occupancy: f64,
+    pub assembly: Option<String>,
+    pub group: i32,
+    /// Number of atoms removed from the expanded cell for this site.
+    pub atoms_removed: usize,
+}
+
+/// The sites that were removed to obtain one ordered configuration.
+#[derive(Debug, Clone, Default)]
+pub struct DisorderReport {
+    /// Disorder group the configuration was built from (`Split` and `Group` modes).
+    pub group: Option<i32>,
+    pub dropped: Vec<DroppedSite>,
+}
+
+impl DisorderReport {
+    /// Human-readable listing of the dropped sites.
+    pub fn summary(&self) -> String {
+        if self.dropped.is_empty() {
+            return "No disordered sites dropped.".to_string();
+        }
+        let atoms: usize = self.dropped.iter().map(|d| d.atoms_removed).sum();
+        let mut out = format!("Dropped {} disordered sites ({} atoms):", self.dropped.len(), atoms);
+        for d in &self.dropped {
+            out.push_str(&format!(
+                "\n  • {:<8} {:<3} assembly {:<3} group {:>2}  occ {:.3}  -{} atoms",
+                d.label,
+                d.element,
+                d.assembly.as_deref().unwrap_or("."),
+                d.group,
+                d.occupancy,
+                d.atoms_removed
+            ));
+        }
+        out
+    }
+}
+
+/// One ordered structure read from a CIF, with the disorder resolution that produced it.
+#[derive(Debug, Clone)]
+pub struct CifConfiguration {
+    pub crystal: Crystal,
+    pub disorder: DisorderReport,
+    /// Name of the CIF data block (`None` for other formats).
+    pub block: Option<String>,
+}
+
+/// One structure-bearing data block of a CIF file.
+#[derive(Debug)]
+pub struct CifStructureBlock {
+    pub name: String,
+    /// The block's ordered configurations, or why it could not be read. A bad block does not
+    /// stop the others from being read.
+    pub configurations: Result<Vec<CifConfiguration>>,
+}
+
+/// Tags that may carry the symmetry operators, in order of preference.
+const SYMOP_TAGS: [&str; 2] = ["_space_group_symop_operation_xyz", "_symmetry_equiv_pos_as_xyz"];
+const HALL_TAGS: [&str; 2] = ["_space_group_name_Hall", "_symmetry_space_group_name_Hall"];
+const HM_TAGS: [&str; 3] = [
+    "_space_group_name_H-M_alt",
+    "_symmetry_space_group_name_H-M",
+    "_space_group_name_H-M",
+];
+const NUMBER_TAGS: [&str; 2] = ["_space_group_IT_number", "_symmetry_Int_Tables_number"];
+/// magCIF moment loop (DDLm dotted names first, then the CIF 1 underscore form).
+const MOMENT_LABEL_TAGS: [&str; 2] = ["_atom_site_moment.label", "_atom_site_moment_label"];
+const MOMENT_TAGS: [[&str; 2]; 3] = [
+    ["_atom_site_moment.crystalaxis_x", "_atom_site_moment_crystalaxis_x"],
+    ["_atom_site_moment.crystalaxis_y", "_atom_site_moment_crystalaxis_y"],
+    ["_atom_site_moment.crystalaxis_z", "_atom_site_moment_crystalaxis_z"],
+];
+
+/// Images of a special-position disordered site (negative group) closer than this (Å) to an
+/// already kept atom of the same group are an alternative orientation, not a neighbour.
+const DISORDER_CLASH_DISTANCE: f64 = 1.2;
+
 /// Parses a CIF file into a Crystal structure.
 ///
-/// Note: This is a robust manual parser. For production-grade generic CIF parsing,
-/// consider using a dedicated crate, but this works for 99% of P1/VASP outputs.
+/// The first data block that contains atom sites is used.
 pub fn from_cif(path: &Path) -> Result<Crystal> {
+    from_cif_with_options(path, &CifOptions::default())
+}
+
+/// Parses a CIF file, expanding the asymmetric unit with the space-group operators.
+///
+/// Operators are taken from the `_space_group_symop_operation_xyz` /
+/// `_symmetry_equiv_pos_as_xyz` loop when present; otherwise they are generated from the
+/// Hall symbol, the H-M symbol or the IT number (in that order) via the built-in table.
+/// With `DisorderResolution::Split` only the first configuration is returned; use
+/// `from_cif_configurations` to get all of them.
+pub fn from_cif_with_options(path: &Path, options: &CifOptions) -> Result<Crystal> {
+    Ok(from_cif_configurations(path, options)?.swap_remove(0).crystal)
+}
+
+/// Parses a CIF file into one or more ordered configurations (see `DisorderResolution`).
+pub fn from_cif_configurations(path: &Path, options: &CifOptions) -> Result<Vec<CifConfiguration>> {
     let contents = fs::read_to_string(path).with_context(|| format!("Could not read CIF file: {:?}", path))?;
-    let lines: Vec<&str> = contents.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
-
-    let mut lattice_params: HashMap<&str, f64> = HashMap::new();
-    let mut atoms = Vec::new();
-    
-    let mut i = 0;
-    while i < lines.len() {
-        let line = lines[i];
-        
-        if line.starts_with("_cell_") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                // Handle key-value pairs like "_cell_length_a 10.0"
-                if let Ok(value) = parse_cif_float(parts[1]) {
-                     lattice_params.insert(parts[0], value);
-                }
-            }
-        } else if line.starts_with("loop_") {
-            // Advance past the "loop_" line
-            i += 1;
-            
-            // Collect headers
-            let mut headers = Vec::new();
-            while i < lines.len() && lines[i].starts_with('_') {
-                headers.push(lines[i]);
-                i += 1;
-            }
+    configurations_from_cif_str(&contents, options).with_context(|| format!("Failed to parse CIF file: {:?}", path))
+}
 
-            // Check if this loop contains atom data
-            if headers.contains(&"_atom_site_fract_x") {
-                // Find column indices
-                let symbol_idx = headers.iter().position(|&h| h == "_atom_site_type_symbol")
-                    .context("CIF missing '_atom_site_type_symbol'")?;
-                let x_idx = headers.iter().position(|&h| h == "_atom_site_fract_x")
-                    .context("CIF missing '_atom_site_fract_x'")?;
-                let y_idx = headers.iter().position(|&h| h == "_atom_site_fract_y")
-                    .context("CIF missing '_atom_site_fract_y'")?;
-                let z_idx = headers.iter().position(|&h| h == "_atom_site_fract_z")
-                    .context("CIF missing '_atom_site_fract_z'")?;
-
-                let max_idx = *[symbol_idx, x_idx, y_idx, z_idx].iter().max().unwrap();
-
-                // Parse Data Rows
-                while i < lines.len() && !lines[i].starts_with('_') && !lines[i].starts_with("loop_") {
-                    let atom_line_parts: Vec<&str> = lines[i].split_whitespace().collect();
-                    if atom_line_parts.len() > max_idx {
-                        let element = atom_line_parts[symbol_idx].to_string();
-                        let x = parse_cif_float(atom_line_parts[x_idx])?;
-                        let y = parse_cif_float(atom_line_parts[y_idx])?;
-                        let z = parse_cif_float(atom_line_parts[z_idx])?;
-
-                        atoms.push(Atom {
-                            element,
-                            fractional_coords: Vector3::new(x, y, z),
-                            // When parsing a CIF we don't yet know the semantic role of each atom,
-                            // so default to "Unknown". The semantic tagging pass will update this field
-                            // later based on MOFid fragments.
-                            component_type: ComponentType::Unknown,
-                        });
-                    }
-                    i += 1;
-                }
-                // Step back one, as the outer loop increments i
-                i -= 1;
-            }
+/// Parses CIF text (CIF 1.1 or 2.0) into a Crystal structure.
+pub fn from_cif_str(contents: &str, options: &CifOptions) -> Result<Crystal> {
+    Ok(configurations_from_cif_str(contents, options)?.swap_remove(0).crystal)
+}
+
+/// Parses every structure in a (possibly multi-block) CIF file.
+pub fn from_cif_blocks(path: &Path, options: &CifOptions) -> Result<Vec<CifStructureBlock>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read CIF file: {:?}", path))?;
+    blocks_from_cif_str(&contents, options).with_context(|| format!("Failed to parse CIF file: {:?}", path))
+}
+
+/// Parses every data block with atom sites. Only syntax errors fail the whole file; errors
+/// in one block (missing cell, unknown space group, ...) are returned with that block.
+pub fn blocks_from_cif_str(contents: &str, options: &CifOptions) -> Result<Vec<CifStructureBlock>> {
+    let blocks = cif::parse_document(contents)?;
+    let structures: Vec<CifStructureBlock> = blocks
+        .iter()
+        .filter(|b| has_atom_sites(b))
+        .map(|block| CifStructureBlock {
+            name: block.name.clone(),
+            configurations: configurations_from_block(block, options)
+                .with_context(|| format!("data_{} (line {})", block.name, block.line)),
+        })
+        .collect();
+    if structures.is_empty() {
+        return Err(anyhow!("No atoms found in CIF file."));
+    }
+    Ok(structures)
+}
+
+/// Configurations of every data block, in file order; fails on the first bad block.
+pub fn all_configurations_from_cif_str(contents: &str, options: &CifOptions) -> Result<Vec<CifConfiguration>> {
+    let mut configurations = Vec::new();
+    for block in blocks_from_cif_str(contents, options)? {
+        configurations.extend(block.configurations?);
+    }
+    Ok(configurations)
+}
+
+/// Parses CIF text into one or more ordered configurations of the first structure. Never
+/// returns an empty list.
+pub fn configurations_from_cif_str(contents: &str, options: &CifOptions) -> Result<Vec<CifConfiguration>> {
+    let blocks = cif::parse_document(contents)?;
+
+    let block = blocks
+        .iter()
+        .find(|b| has_atom_sites(b))
+        .ok_or_else(|| anyhow!("No atoms found in CIF file."))?;
+
+    configurations_from_block(block, options)
+}
+
+/// Reads the `_csg_slab_*` provenance items written by `write_cif_with_options`.
+///
+/// Returns `None` when the first structure block carries no provenance.
+pub fn slab_provenance_from_cif_str(contents: &str) -> Result<Option<SlabProvenance>> {
+    let blocks = cif::parse_document(contents)?;
+    let Some(block) = blocks.iter().find(|b| has_atom_sites(b)) else { return Ok(None) };
+    if block.get(&format!("{}miller_index_h", PROVENANCE_PREFIX)).is_none() {
+        return Ok(None);
+    }
+
+    let number = |name: &str| -> Result<f64> {
+        let tag = format!("{}{}", PROVENANCE_PREFIX, name);
+        block.get(&tag).ok_or_else(|| anyhow!("CIF missing tag: {}", tag))?.as_f64(&tag)
+    };
+    let integer = |name: &str| -> Result<i64> {
+        let value = number(name)?;
+        if value.fract() != 0.0 {
+            return Err(anyhow!("{}{}: '{}' is not an integer", PROVENANCE_PREFIX, name, value));
         }
-        i += 1;
+        Ok(value as i64)
+    };
+
+    let mut miller_indices = [0; 3];
+    for (index, axis) in miller_indices.iter_mut().zip(["h", "k", "l"]) {
+        *index = integer(&format!("miller_index_{}", axis))? as i32;
     }
+    let mut bulk_cell = [0.0; 6];
+    for (value, name) in bulk_cell.iter_mut().zip(BULK_CELL_NAMES) {
+        *value = number(&format!("bulk_cell_{}", name))?;
+    }
+    let mut transformation = Matrix3::zeros();
+    for i in 0..3 {
+        for j in 0..3 {
+            transformation[(i, j)] = integer(&format!("transform_matrix_{}{}", i + 1, j + 1))? as i32;
+        }
+    }
+
+    Ok(Some(SlabProvenance {
+        miller_indices,
+        d_hkl: number("d_hkl")?,
+        n_layers: integer("n_layers")? as usize,
+        cut_offset: number("cut_offset")?,
+        vacuum: number("vacuum")?,
+        bulk_cell,
+        transformation,
+    }))
+}
+
+/// An asymmetric-unit site together with the CIF columns that are not stored on `Atom`.
+struct Site {
+    label: String,
+    assembly: Option<String>,
+    atom: Atom,
+}
+
+/// The disorder group kept in each assembly for one configuration.
+struct Selection {
+    group: Option<i32>,
+    /// Kept group per assembly; `None` keeps every group.
+    kept: Option<BTreeMap<Option<String>, i32>>,
+}
+
+impl Selection {
+    fn keeps(&self, site: &Site) -> bool {
+        match (site.atom.disorder_group, &self.kept) {
+            (Some(group), Some(kept)) if group > 0 => kept.get(&site.assembly) == Some(&group),
+            _ => true,
+        }
+    }
+}
 
-    // Extract Lattice Parameters
+/// Builds the ordered configurations of a single parsed data block.
+fn configurations_from_block(block: &CifBlock, options: &CifOptions) -> Result<Vec<CifConfiguration>> {
+    // 1. Lattice
     let get_param = |key: &str| -> Result<f64> {
-        lattice_params.get(key).copied().ok_or_else(|| anyhow!("CIF missing tag: {}", key))
+        let value = block.get(key).ok_or_else(|| anyhow!("CIF missing tag: {}", key))?;
+        value.as_f64(key)
     };
 
     let a = get_param("_cell_length_a")?;
@@ -101,11 +284,686 @@ pub fn from_cif(path: &Path) -> Result<Crystal> {
     let gamma = get_param("_cell_angle_gamma")?;
 
     let lattice = Lattice::from_parameters(a, b, c, alpha, beta, gamma)
-        .map_err(|e| anyhow!(e))?;
+        .map_err(|e| anyhow!("data_{} (line {}): {}", block.name, block.line, e))?;
+
+    // 2. Asymmetric unit
+    let sites = read_sites(block, &lattice)?;
+
+    // 3. Symmetry expansion
+    let ops = resolve_symmetry_operations(block)?;
+    let images: Vec<Vec<(usize, Vector3<f64>)>> = sites
+        .iter()
+        .map(|site| expand_site(&site.atom.fractional_coords, &ops, &lattice, options.symmetry_tolerance))
+        .collect();
+
+    // 4. Disorder resolution
+    let keep_images = if options.disorder == DisorderResolution::KeepAll {
+        images.iter().map(|i| vec![true; i.len()]).collect()
+    } else {
+        resolve_special_position_disorder(&sites, &images, ops.len(), &lattice)
+    };
+
+    let selections = select_disorder_groups(&sites, options.disorder)?;
+
+    let mut configurations = Vec::with_capacity(selections.len());
+    for selection in selections {
+        let mut atoms = Vec::new();
+        let mut dropped = Vec::new();
+
+        for ((site, site_images), keep) in sites.iter().zip(&images).zip(&keep_images) {
+            let kept: Vec<Vector3<f64>> = if selection.keeps(site) {
+                site_images.iter().zip(keep).filter(|(_, &k)| k).map(|((_, frac), _)| *frac).collect()
+            } else {
+                Vec::new()
+            };
+
+            if kept.len() < site_images.len() {
+                dropped.push(DroppedSite {
+                    label: site.label.clone(),
+                    element: site.atom.element.clone(),
+                    occupancy: site.atom.occupancy,
+                    assembly: site.assembly.clone(),
+                    group: site.atom.disorder_group.unwrap_or(0),
+                    atoms_removed: site_images.len() - kept.len(),
+                });
+            }
+
+            atoms.extend(kept.into_iter().map(|frac| Atom {
+                fractional_coords: frac,
+                ..site.atom.clone()
+            }));
+        }
+
+        if atoms.is_empty() {
+            return Err(anyhow!("No atoms left after resolving disorder."));
+        }
+
+        configurations.push(CifConfiguration {
+            crystal: Crystal { lattice: lattice.clone(), atoms },
+            disorder: DisorderReport { group: selection.group, dropped },
+            block: Some(block.name.clone()),
+        });
+    }
+
+    Ok(configurations)
+}
+
+/// True if the block has an atom-site loop with fractional or Cartesian coordinates.
+fn has_atom_sites(block: &CifBlock) -> bool {
+    !block.values("_atom_site_fract_x").is_empty() || !block.values("_atom_site_Cartn_x").is_empty()
+}
+
+/// Reads the `_atom_site_*` loop: positions plus label, occupancy, disorder, charge and
+/// magnetic moment columns. Label, charge and moment go into the atom's property map.
+///
+/// Positions come from `_atom_site_fract_*` or, failing that, `_atom_site_Cartn_*`. The element
+/// comes from `_atom_site_type_symbol` or, failing that, is inferred from `_atom_site_label`.
+fn read_sites(block: &CifBlock, lattice: &Lattice) -> Result<Vec<Site>> {
+    let column = |tag: &str| -> Result<Vec<&CifValue>> {
+        let values = block.values(tag);
+        if values.is_empty() {
+            return Err(anyhow!("CIF missing '{}'", tag));
+        }
+        Ok(values)
+    };
+
+    let (axes, cartesian) = if block.values("_atom_site_fract_x").is_empty() {
+        (["_atom_site_Cartn_x", "_atom_site_Cartn_y", "_atom_site_Cartn_z"], Some(cartesian_to_fractional(block, lattice)?))
+    } else {
+        (["_atom_site_fract_x", "_atom_site_fract_y", "_atom_site_fract_z"], None)
+    };
+    let xs = column(axes[0])?;
+    let ys = column(axes[1])?;
+    let zs = column(axes[2])?;
+
+    let symbols = block.values("_atom_site_type_symbol");
+    let labels = block.values("_atom_site_label");
+    if symbols.is_empty() && labels.is_empty() {
+        return Err(anyhow!("CIF has neither '_atom_site_type_symbol' nor '_atom_site_label'"));
+    }
+
+    // Optional columns
+    let occupancies = block.values("_atom_site_occupancy");
+    let assemblies = block.values("_atom_site_disorder_assembly");
+    let groups = block.values("_atom_site_disorder_group");
+    let charges = block.values("_atom_site_charge");
+    let components = block.values(TAG_COMPONENT_TYPE);
+    let layers = block.values(TAG_LAYER);
+    let source_labels = block.values(TAG_SOURCE_LABEL);
+    let moments = read_moments(block)?;
+
+    let mut sites = Vec::with_capacity(xs.len());
+    for i in 0..xs.len() {
+        let position = Vector3::new(xs[i].as_f64(axes[0])?, ys[i].as_f64(axes[1])?, zs[i].as_f64(axes[2])?);
+        let frac = match &cartesian {
+            Some(to_fractional) => to_fractional * position,
+            None => position,
+        };
+
+        // Type symbol first ("Fe3+" -> Fe), then the label ("Zn1", "O2A" -> Zn, O)
+        let source = optional_value(&symbols, i)
+            .map(|v| ("_atom_site_type_symbol", v))
+            .or_else(|| optional_value(&labels, i).map(|v| ("_atom_site_label", v)))
+            .ok_or_else(|| anyhow!("atom site {} has neither a type symbol nor a label", i + 1))?;
+        let inferred = if source.0 == "_atom_site_type_symbol" {
+            type_symbol_element(&source.1.text)
+        } else {
+            infer_element(&source.1.text)
+        };
+        let element = inferred.ok_or_else(|| {
+            anyhow!("line {}: {}: cannot infer an element from '{}'", source.1.line, source.0, source.1.text)
+        })?;
+
+        // When parsing a CIF we don't yet know the semantic role of each atom,
+        // so default to "Unknown". The semantic tagging pass will update this field
+        // later based on MOFid fragments.
+        let mut atom = Atom::new(element, frac, ComponentType::Unknown);
+
+        if let Some(occupancy) = optional_value(&occupancies, i) {
+            atom.occupancy = occupancy.as_f64("_atom_site_occupancy")?;
+        }
+        if let Some(group) = optional_value(&groups, i) {
+            let value: i32 = group.text.trim().parse().map_err(|_| {
+                anyhow!("line {}: _atom_site_disorder_group: '{}' is not an integer", group.line, group.text)
+            })?;
+            // Group 0 is used by some programs for "not disordered".
+            atom.disorder_group = (value != 0).then_some(value);
+        }
+        if let Some(charge) = optional_value(&charges, i) {
+            atom.set_property(PROP_CHARGE, charge.as_f64("_atom_site_charge")?);
+        }
+        // Columns written by this program (see `write_cif_with_options`)
+        if let Some(component) = optional_value(&components, i) {
+            atom.component_type = ComponentType::from_name(&component.text).ok_or_else(|| {
+                anyhow!("line {}: {}: unknown component '{}'", component.line, TAG_COMPONENT_TYPE, component.text)
+            })?;
+        }
+        if let Some(layer) = optional_value(&layers, i) {
+            let value: i64 = layer.text.trim().parse().map_err(|_| {
+                anyhow!("line {}: {}: '{}' is not an integer", layer.line, TAG_LAYER, layer.text)
+            })?;
+            atom.set_property(PROP_LAYER, value);
+        }
+
+        let label = optional_value(&labels, i).map(|v| v.text.clone());
+        if let Some(label) = &label {
+            let source = optional_value(&source_labels, i).map_or(label.as_str(), |v| v.text.as_str());
+            atom.set_property(PROP_LABEL, source);
+            // Moments are copied unchanged to every symmetry image: the loop gives no
+            // time-reversal information for the operators.
+            if let Some(moment) = moments.get(label) {
+                atom.set_property(PROP_MAGMOM, *moment);
+            }
+        }
+
+        sites.push(Site {
+            label: label.unwrap_or_else(|| format!("{}{}", atom.element, i + 1)),
+            assembly: optional_value(&assemblies, i).map(|v| v.text.clone()),
+            atom,
+        });
+    }
 
-    if atoms.is_empty() {
+    if sites.is_empty() {
         return Err(anyhow!("No atoms found in CIF file."));
     }
+    Ok(sites)
+}
+
+/// Matrix taking `_atom_site_Cartn_*` coordinates to fractional ones.
+///
+/// An explicit `_atom_sites_fract_tran_matrix_ij` (Cartesian -> fractional) or
+/// `_atom_sites_Cartn_tran_matrix_ij` (fractional -> Cartesian) is honoured; otherwise the CIF
+/// default frame (a along x, b in the xy plane) is assumed, which is the one built by
+/// `Lattice::from_parameters`.
+fn cartesian_to_fractional(block: &CifBlock, lattice: &Lattice) -> Result<Matrix3<f64>> {
+    if let Some(m) = read_matrix(block, "_atom_sites_fract_tran_matrix_")? {
+        return Ok(m);
+    }
+    if let Some(m) = read_matrix(block, "_atom_sites_Cartn_tran_matrix_")? {
+        return m.try_inverse().ok_or_else(|| anyhow!("_atom_sites_Cartn_tran_matrix is singular"));
+    }
+    Ok(lattice.reciprocal_matrix.transpose())
+}
+
+/// Reads a 3x3 matrix given as `<prefix>11` ... `<prefix>33`; `None` if none of the items exist.
+fn read_matrix(block: &CifBlock, prefix: &str) -> Result<Option<Matrix3<f64>>> {
+    if block.get(&format!("{}11", prefix)).is_none() {
+        return Ok(None);
+    }
+    let mut m = Matrix3::zeros();
+    for i in 0..3 {
+        for j in 0..3 {
+            let tag = format!("{}{}{}", prefix, i + 1, j + 1);
+            let value = block.get(&tag).ok_or_else(|| anyhow!("CIF missing tag: {}", tag))?;
+            m[(i, j)] = value.as_f64(&tag)?;
+        }
+    }
+    Ok(Some(m))
+}
+
+/// Element of a type symbol ("Fe3+" -> Fe, "CL" -> Cl, "Ow" -> O): leading letters that spell a
+/// symbol in any letter case are taken as is, anything else is inferred as for a label.
+pub(crate) fn type_symbol_element(text: &str) -> Option<String> {
+    let letters: String = text.trim().chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    match elements::normalize_symbol(&letters) {
+        Some(symbol) => Some(symbol.to_string()),
+        None => infer_element(text),
+    }
+}
+
+/// Infers the element from a type symbol or site label: "Zn1" -> Zn, "O2A" -> O, "C12_b" -> C,
+/// "Fe3+" -> Fe, "O2-" -> O, "ZN" -> Zn, "D1" -> H.
+///
+/// The second letter is part of the symbol whenever that makes an element, in either case:
+/// "FE1" is iron and "CA1" calcium. Labels in CIFs name sites, not PDB atoms, so the protein
+/// convention ("CA" for an alpha carbon) does not apply; give a type symbol for those.
+pub(crate) fn infer_element(text: &str) -> Option<String> {
+    let letters: Vec<char> = text.trim().chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    let first = letters.first()?.to_ascii_uppercase();
+
+    let one = first.to_string();
+    let two = letters.get(1).map(|c| format!("{}{}", first, c.to_ascii_lowercase()));
+
+    [two, Some(one)]
+        .into_iter()
+        .flatten()
+        .map(|symbol| if symbol == "D" || symbol == "T" { "H".to_string() } else { symbol })
+        .find(|symbol| elements::element(symbol).is_some())
+}
+
+/// Reads the magCIF `_atom_site_moment` loop into a label -> moment (μB, crystal axes) map.
+fn read_moments(block: &CifBlock) -> Result<HashMap<String, Vector3<f64>>> {
+    let labels = match MOMENT_LABEL_TAGS.iter().map(|t| block.values(t)).find(|v| !v.is_empty()) {
+        Some(labels) => labels,
+        None => return Ok(HashMap::new()),
+    };
+
+    let mut columns = Vec::with_capacity(3);
+    for [dotted, underscore] in MOMENT_TAGS {
+        let (tag, values) = [dotted, underscore]
+            .into_iter()
+            .map(|t| (t, block.values(t)))
+            .find(|(_, v)| v.len() == labels.len())
+            .ok_or_else(|| anyhow!("line {}: moment loop is missing '{}'", labels[0].line, dotted))?;
+        columns.push((tag, values));
+    }
+
+    let mut moments = HashMap::with_capacity(labels.len());
+    for (i, label) in labels.iter().enumerate() {
+        let mut moment = Vector3::zeros();
+        for (axis, (tag, values)) in columns.iter().enumerate() {
+            moment[axis] = values[i].as_f64(tag)?;
+        }
+        moments.insert(label.text.clone(), moment);
+    }
+    Ok(moments)
+}
+
+/// Chooses which positive disorder group survives in each assembly, once per configuration.
+fn select_disorder_groups(sites: &[Site], mode: DisorderResolution) -> Result<Vec<Selection>> {
+    // Mean occupancy of every positive group, per assembly
+    let mut totals: BTreeMap<Option<String>, BTreeMap<i32, (f64, usize)>> = BTreeMap::new();
+    for site in sites {
+        if let Some(group) = site.atom.disorder_group.filter(|&g| g > 0) {
+            let entry = totals.entry(site.assembly.clone()).or_default().entry(group).or_insert((0.0, 0));
+            entry.0 += site.atom.occupancy;
+            entry.1 += 1;
+        }
+    }
+    let assemblies: BTreeMap<Option<String>, BTreeMap<i32, f64>> = totals
+        .into_iter()
+        .map(|(assembly, groups)| {
+            let means = groups.into_iter().map(|(g, (sum, n))| (g, sum / n as f64)).collect();
+            (assembly, means)
+        })
+        .collect();
+
+    // Highest mean occupancy wins; ties go to the lower group number.
+    let majority = |groups: &BTreeMap<i32, f64>| -> i32 {
+        let mut best = (0, f64::NEG_INFINITY);
+        for (&group, &occupancy) in groups {
+            if occupancy > best.1 + 1e-6 {
+                best = (group, occupancy);
+            }
+        }
+        best.0
+    };
+
+    let selections = match mode {
+        DisorderResolution::KeepAll => vec![Selection { group: None, kept: None }],
+        DisorderResolution::Majority => {
+            let kept = assemblies.iter().map(|(a, groups)| (a.clone(), majority(groups))).collect();
+            vec![Selection { group: None, kept: Some(kept) }]
+        }
+        DisorderResolution::Group(group) => {
+            let mut kept = BTreeMap::new();
+            for (assembly, groups) in &assemblies {
+                if !groups.contains_key(&group) {
+                    let available: Vec<String> = groups.keys().map(|g| g.to_string()).collect();
+                    return Err(anyhow!(
+                        "disorder assembly '{}' has no group {} (available: {})",
+                        assembly.as_deref().unwrap_or("."), group, available.join(", ")
+                    ));
+                }
+                kept.insert(assembly.clone(), group);
+            }
+            vec![Selection { group: Some(group), kept: Some(kept) }]
+        }
+        DisorderResolution::Split => {
+            let all_groups: BTreeSet<i32> = assemblies.values().flat_map(|g| g.keys().copied()).collect();
+            if all_groups.is_empty() {
+                vec![Selection { group: None, kept: None }]
+            } else {
+                // Assemblies lacking a group fall back to their majority group.
+                all_groups
+                    .into_iter()
+                    .map(|group| {
+                        let kept = assemblies
+                            .iter()
+                            .map(|(a, groups)| {
+                                let g = if groups.contains_key(&group) { group } else { majority(groups) };
+                                (a.clone(), g)
+                            })
+                            .collect();
+                        Selection { group: Some(group), kept: Some(kept) }
+                    })
+                    .collect()
+            }
+        }
+    };
+    Ok(selections)
+}
+
+/// Negative disorder groups sit across a special position: the operators map the group onto an
+/// overlapping, alternative copy of itself. For each (assembly, group) the operators are taken in
+/// order and a copy is kept only if none of its atoms clashes with an already kept copy.
+/// Returns, per site, whether each symmetry image survives.
+fn resolve_special_position_disorder(
+    sites: &[Site],
+    images: &[Vec<(usize, Vector3<f64>)>],
+    n_ops: usize,
+    lattice: &Lattice,
+) -> Vec<Vec<bool>> {
+    let mut keep: Vec<Vec<bool>> = images.iter().map(|i| vec![true; i.len()]).collect();
+
+    let mut groups: BTreeMap<(Option<String>, i32), Vec<usize>> = BTreeMap::new();
+    for (i, site) in sites.iter().enumerate() {
+        if let Some(group) = site.atom.disorder_group.filter(|&g| g < 0) {
+            groups.entry((site.assembly.clone(), group)).or_default().push(i);
+        }
+    }
+
+    for members in groups.values() {
+        let mut kept_positions: Vec<Vector3<f64>> = Vec::new();
+
+        for op in 0..n_ops {
+            // (site, image) pairs of the copy generated by this operator
+            let copy: Vec<(usize, usize)> = members
+                .iter()
+                .flat_map(|&s| {
+                    images[s].iter().enumerate().filter(move |(_, (o, _))| *o == op).map(move |(k, _)| (s, k))
+                })
+                .collect();
+
+            let clashes = copy.iter().any(|&(s, k)| {
+                kept_positions.iter().any(|kept| {
+                    lattice.get_shortest_distance_vector(kept, &images[s][k].1).norm() < DISORDER_CLASH_DISTANCE
+                })
+            });
+
+            if clashes {
+                for (s, k) in copy {
+                    keep[s][k] = false;
+                }
+            } else {
+                kept_positions.extend(copy.iter().map(|&(s, k)| images[s][k].1));
+            }
+        }
+    }
+    keep
+}
+
+/// Chooses the symmetry operators: explicit loop > Hall symbol > H-M symbol > IT number.
+/// Falls back to P1 (identity only) if the file carries no symmetry information at all.
+fn resolve_symmetry_operations(block: &CifBlock) -> Result<Vec<SymOp>> {
+    if let Some(tag) = SYMOP_TAGS.iter().find(|t| !block.values(t).is_empty()) {
+        return block
+            .values(tag)
+            .into_iter()
+            .map(|v| {
+                SymOp::from_xyz(&v.text).with_context(|| format!("line {}: {}", v.line, tag))
+            })
+            .collect();
+    }
+
+    if let Some((tag, hall)) = first_value(block, &HALL_TAGS) {
+        return spacegroup::operations_from_hall(&hall.text)
+            .with_context(|| format!("line {}: {}: failed to decode Hall symbol '{}'", hall.line, tag, hall.text));
+    }
+
+    if let Some((tag, hm)) = first_value(block, &HM_TAGS) {
+        let setting = spacegroup::find_by_hm(&hm.text).ok_or_else(|| {
+            anyhow!(
+                "line {}: {}: unrecognised space group symbol '{}' and no symmetry operators in CIF",
+                hm.line, tag, hm.text
+            )
+        })?;
+        return spacegroup::operations_from_hall(setting.2);
+    }
+
+    if let Some((tag, number)) = first_value(block, &NUMBER_TAGS) {
+        let n = number.as_f64(tag)? as u16;
+        let setting = spacegroup::find_by_number(n)
+            .ok_or_else(|| anyhow!("line {}: {}: space group number {} is out of range", number.line, tag, n))?;
+        return spacegroup::operations_from_hall(setting.2);
+    }
+
+    Ok(vec![SymOp::identity()])
+}
+
+/// Returns the first of `tags` that carries a real (non-placeholder) value.
+fn first_value<'a>(block: &'a CifBlock, tags: &[&'static str]) -> Option<(&'static str, &'a CifValue)> {
+    tags.iter()
+        .filter_map(|&t| block.get(t).map(|v| (t, v)))
+        .find(|(_, v)| !v.is_missing() && !v.text.trim().is_empty())
+}
+
+/// Value of an optional loop column at row `i`; placeholders ('?' / '.') count as absent.
+fn optional_value<'a>(values: &[&'a CifValue], i: usize) -> Option<&'a CifValue> {
+    values.get(i).copied().filter(|v| !v.is_missing())
+}
+
+/// Applies every operator to one asymmetric-unit position, wraps the images into [0, 1) and
+/// merges images that coincide (special positions). Each image records the operator index
+/// that first generated it.
+fn expand_site(frac: &Vector3<f64>, ops: &[SymOp], lattice: &Lattice, tolerance: f64) -> Vec<(usize, Vector3<f64>)> {
+    let tolerance_sq = tolerance.powi(2);
+    let mut images: Vec<(usize, Vector3<f64>)> = Vec::with_capacity(ops.len());
+
+    for (i, op) in ops.iter().enumerate() {
+        let image = spacegroup::wrap_fractional(&op.apply(frac));
+        let duplicate = images.iter().any(|(_, existing)| {
+            lattice.get_shortest_distance_vector(existing, &image).norm_squared() < tolerance_sq
+        });
+        if !duplicate {
+            images.push((i, image));
+        }
+    }
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CELL: &str = "\
+data_disorder
+_cell_length_a 10
+_cell_length_b 10
+_cell_length_c 10
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+loop_
+_symmetry_equiv_pos_as_xyz
+x,y,z
+-x,-y,-z
+";
+
+    fn options(disorder: DisorderResolution) -> CifOptions {
+        CifOptions { disorder, ..CifOptions::default() }
+    }
+
+    #[test]
+    fn test_disorder_group_resolution() {
+        let source = format!(
+            "{}\
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+_atom_site_disorder_assembly
+_atom_site_disorder_group
+Zn1 Zn 0.0  0.0 0.0 1   . .
+C1A C  0.2  0.2 0.2 0.6 A 1
+C1B C  0.25 0.2 0.2 0.4 A 2
+",
+            CELL
+        );
+
+        let keep_all = configurations_from_cif_str(&source, &options(DisorderResolution::KeepAll)).unwrap();
+        assert_eq!(keep_all.len(), 1);
+        assert_eq!(keep_all[0].crystal.atoms.len(), 5);
+        assert!(keep_all[0].disorder.dropped.is_empty());
+        assert!(keep_all[0].crystal.atoms.iter().any(|a| a.occupancy == 0.4 && a.disorder_group == Some(2)));
+
+        let majority = configurations_from_cif_str(&source, &options(DisorderResolution::Majority)).unwrap();
+        assert_eq!(majority[0].crystal.atoms.len(), 3);
+        assert_eq!(majority[0].disorder.dropped.len(), 1);
+        assert_eq!(majority[0].disorder.dropped[0].label, "C1B");
+        assert_eq!(majority[0].disorder.dropped[0].atoms_removed, 2);
+
+        let chosen = configurations_from_cif_str(&source, &options(DisorderResolution::Group(2))).unwrap();
+        assert_eq!(chosen[0].disorder.dropped[0].label, "C1A");
+        assert!(configurations_from_cif_str(&source, &options(DisorderResolution::Group(3))).is_err());
+
+        let split = configurations_from_cif_str(&source, &options(DisorderResolution::Split)).unwrap();
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0].disorder.group, Some(1));
+        assert_eq!(split[1].disorder.group, Some(2));
+        assert!(split.iter().all(|c| c.crystal.atoms.len() == 3));
+    }
+
+    #[test]
+    fn test_special_position_disorder() {
+        // A water disordered about an inversion centre: the inverted copy shares the O
+        // and its H atoms clash with the original O, so it is removed.
+        let source = format!(
+            "{}\
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+_atom_site_disorder_group
+O1W O  0.0    0.0    0.0 1.0 -1
+H1W H  0.096  0.0    0.0 0.5 -1
+H2W H  -0.024 0.093  0.0 0.5 -1
+",
+            CELL
+        );
+
+        let keep_all = from_cif_str(&source, &options(DisorderResolution::KeepAll)).unwrap();
+        assert_eq!(keep_all.atoms.len(), 5);
+
+        let resolved = configurations_from_cif_str(&source, &CifOptions::default()).unwrap();
+        assert_eq!(resolved[0].crystal.atoms.len(), 3);
+        let dropped: Vec<&str> = resolved[0].disorder.dropped.iter().map(|d| d.label.as_str()).collect();
+        assert_eq!(dropped, vec!["H1W", "H2W"]);
+    }
+
+    #[test]
+    fn test_labels_charges_and_moments() {
+        let source = format!(
+            "{}\
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_charge
+Fe1 Fe 0.1 0.2 0.3 1.25
+O1  O  0.3 0.2 0.1 -0.5
+loop_
+_atom_site_moment.label
+_atom_site_moment.crystalaxis_x
+_atom_site_moment.crystalaxis_y
+_atom_site_moment.crystalaxis_z
+Fe1 0.0 0.0 4.2(1)
+",
+            CELL
+        );
+
+        let crystal = from_cif_str(&source, &CifOptions::default()).unwrap();
+        assert_eq!(crystal.atoms.len(), 4);
+        let fe: Vec<&Atom> = crystal.atoms.iter().filter(|a| a.element == "Fe").collect();
+        assert!(fe.iter().all(|a| a.label() == Some("Fe1") && a.charge() == Some(1.25)));
+        assert!(fe.iter().all(|a| a.magmom() == Some(&Vector3::new(0.0, 0.0, 4.2))));
+        assert!(crystal.atoms.iter().filter(|a| a.element == "O").all(|a| a.magmom().is_none()));
+    }
+
+    #[test]
+    fn test_infer_element() {
+        let cases = [
+            ("Zn1", "Zn"), ("O2A", "O"), ("C12_b", "C"), ("Fe3+", "Fe"), ("O2-", "O"),
+            ("ZN", "Zn"), ("CA1", "Ca"), ("Ca1", "Ca"), ("Ow", "O"), ("OW1", "O"), ("D3", "H"),
+            // All-caps metal labels keep their metal
+            ("FE1", "Fe"), ("CU1", "Cu"), ("ZN1", "Zn"), ("SI1", "Si"), ("NI1", "Ni"), ("CO1", "Co"), ("SN1", "Sn"),
+        ];
+        for (text, element) in cases {
+            assert_eq!(infer_element(text).as_deref(), Some(element), "{}", text);
+        }
+        assert_eq!(infer_element("Xx1"), None);
+        assert_eq!(infer_element("12"), None);
+
+        // Type symbols are symbols first: an all-caps "CL" is chlorine, not carbon
+        assert_eq!(type_symbol_element("CL").as_deref(), Some("Cl"));
+        assert_eq!(type_symbol_element("CA").as_deref(), Some("Ca"));
+        assert_eq!(type_symbol_element("Fe3+").as_deref(), Some("Fe"));
+        assert_eq!(type_symbol_element("Ow").as_deref(), Some("O"));
+        assert_eq!(type_symbol_element("Xx"), None);
+    }
+
+    #[test]
+    fn test_cartesian_sites_without_type_symbol() {
+        let source = "\
+data_cartesian
+_cell_length_a 4
+_cell_length_b 5
+_cell_length_c 10
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+loop_
+_atom_site_label
+_atom_site_Cartn_x
+_atom_site_Cartn_y
+_atom_site_Cartn_z
+Cu1 1.0 2.5 5.0
+O2- 2.0 0.0 2.5
+";
+        let crystal = from_cif_str(source, &CifOptions::default()).unwrap();
+        assert_eq!(crystal.atoms[0].element, "Cu");
+        assert_eq!(crystal.atoms[1].element, "O");
+        assert!((crystal.atoms[0].fractional_coords - Vector3::new(0.25, 0.5, 0.5)).norm() < 1e-9);
+        assert!((crystal.atoms[1].fractional_coords - Vector3::new(0.5, 0.0, 0.25)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_multiple_blocks_with_a_bad_block() {
+        let source = format!(
+            "{}\
+loop_
+_atom_site_label
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+Zn1 0.1 0.1 0.1
+data_broken
+loop_
+_atom_site_label
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+O1 0.0 0.0 0.0
+{}\
+loop_
+_atom_site_label
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+Cu1 0.0 0.0 0.0
+",
+            CELL,
+            CELL.replace("data_disorder", "data_second")
+        );
+
+        let blocks = blocks_from_cif_str(&source, &CifOptions::default()).unwrap();
+        let names: Vec<&str> = blocks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["disorder", "broken", "second"]);
+        assert!(blocks[0].configurations.is_ok());
+        let error = blocks[1].configurations.as_ref().unwrap_err();
+        assert!(format!("{:#}", error).contains("data_broken"));
+        let second = blocks[2].configurations.as_ref().unwrap();
+        assert_eq!(second[0].block.as_deref(), Some("second"));
+        assert_eq!(second[0].crystal.atoms[0].element, "Cu");
+        assert!(all_configurations_from_cif_str(&source, &CifOptions::default()).is_err());
+    }
+}
 
-    Ok(Crystal { lattice, atoms })
-}
\ No newline at end of file
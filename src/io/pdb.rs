@@ -0,0 +1,153 @@
+use crate::core::bonding::BondingRule;
+use crate::core::connectivity::GraphRepresentation;
+use crate::core::structure::{ComponentType, Crystal, Lattice};
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::Vector3;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// PDB serial numbers have five columns.
+const MAX_ATOMS: usize = 99_999;
+/// Chain identifiers, assigned to molecules in turn.
+const CHAIN_IDS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Options for `write_pdb`.
+#[derive(Debug, Clone, Default)]
+pub struct PdbOptions {
+    /// Which atom pairs are bonded; the bonds also define the molecules (chains).
+    pub bonding: BondingRule,
+}
+
+/// Residue name for a component: nodes, linkers and solvent can be coloured separately.
+pub fn residue_name(component: ComponentType) -> &'static str {
+    match component {
+        ComponentType::Unknown => "UNK",
+        ComponentType::MetalNode => "NOD",
+        ComponentType::OrganicLinker => "LNK",
+        ComponentType::Solvent => "SOL",
+        ComponentType::Adsorbate => "ADS",
+    }
+}
+
+/// Writes a structure to a PDB file.
+pub fn to_pdb(crystal: &Crystal, path: &Path, options: &PdbOptions) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_pdb(crystal, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a structure in PDB format to any writer.
+///
+/// Coordinates are in the standard PDB frame of `CRYST1` (a along x, b in the xy plane), and
+/// atoms are not wrapped, so molecules stay whole. Every atom is a `HETATM` whose residue name
+/// comes from its `ComponentType`; each connected molecule gets its own chain and residue
+/// number. `CONECT` records list the bonds of the connectivity graph, except those that cross
+/// the cell boundary in the written coordinates, so viewers never bond across the vacuum.
+pub fn write_pdb<W: Write>(crystal: &Crystal, mut writer: W, options: &PdbOptions) -> Result<()> {
+    let atoms = &crystal.atoms;
+    if atoms.len() > MAX_ATOMS {
+        bail!("PDB files hold at most {} atoms, got {}.", MAX_ATOMS, atoms.len());
+    }
+
+    // 1. Cell in the PDB frame
+    let (a, b, c, alpha, beta, gamma) = crystal.lattice.to_parameters();
+    let frame = Lattice::from_parameters(a, b, c, alpha, beta, gamma).map_err(|e| anyhow!("PDB cell: {}", e))?;
+    let positions: Vec<Vector3<f64>> = atoms.iter().map(|atom| frame.to_cartesian(&atom.fractional_coords)).collect();
+
+    // 2. Molecules
+    let graph = GraphRepresentation::from_crystal_with_rule(crystal, &options.bonding);
+    let mut molecule = vec![0usize; atoms.len()];
+    for (id, component) in graph.find_connected_components().iter().enumerate() {
+        for &i in component {
+            molecule[i] = id;
+        }
+    }
+
+    writeln!(writer, "REMARK   1 Generated by Crystal Surface Generator")?;
+    writeln!(writer, "CRYST1{:9.3}{:9.3}{:9.3}{:7.2}{:7.2}{:7.2} {:<11}{:>4}", a, b, c, alpha, beta, gamma, "P 1", 1)?;
+
+    // 3. Atoms
+    for (i, atom) in atoms.iter().enumerate() {
+        let name = atom.label().map_or_else(|| format!("{}{}", atom.element, i + 1), str::to_string);
+        // One-letter elements start in column 14, as in protein files
+        let name = if atom.element.len() == 1 && name.len() < 4 { format!(" {}", name) } else { name };
+        let name: String = name.chars().take(4).collect();
+        let chain = CHAIN_IDS[molecule[i] % CHAIN_IDS.len()] as char;
+        let p = positions[i];
+        writeln!(
+            writer,
+            "HETATM{:>5} {:<4} {:>3} {}{:>4}    {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}          {:>2}",
+            i + 1,
+            name,
+            residue_name(atom.component_type),
+            chain,
+            (molecule[i] % 9999) + 1,
+            p.x,
+            p.y,
+            p.z,
+            atom.occupancy,
+            0.0,
+            atom.element.to_ascii_uppercase()
+        )?;
+    }
+
+    // 4. Bonds that do not cross the cell boundary
+    let mut neighbors: Vec<Vec<usize>> = vec![Vec::new(); atoms.len()];
+    for (i, j, image) in graph.bonds() {
+        if image == Vector3::zeros() {
+            neighbors[i].push(j);
+            neighbors[j].push(i);
+        }
+    }
+    for (i, bonded) in neighbors.iter_mut().enumerate() {
+        bonded.sort_unstable();
+        bonded.dedup();
+        // Four partners per record
+        for chunk in bonded.chunks(4) {
+            write!(writer, "CONECT{:>5}", i + 1)?;
+            for &j in chunk {
+                write!(writer, "{:>5}", j + 1)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+    writeln!(writer, "END")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::Atom;
+
+    #[test]
+    fn test_residues_chains_and_conect() {
+        let lattice = Lattice::from_parameters(10.0, 10.0, 10.0, 90.0, 90.0, 90.0).unwrap();
+        let cart = [
+            ("O", Vector3::new(5.0, 5.0, 5.0), ComponentType::Solvent),
+            ("H", Vector3::new(5.96, 5.0, 5.0), ComponentType::Solvent),
+            ("H", Vector3::new(4.76, 5.93, 5.0), ComponentType::Solvent),
+            // Bonded to the next atom only through the periodic boundary
+            ("Zn", Vector3::new(0.5, 1.0, 1.0), ComponentType::MetalNode),
+            ("O", Vector3::new(9.4, 1.0, 1.0), ComponentType::OrganicLinker),
+        ];
+        let atoms = cart.iter().map(|(e, r, c)| Atom::new(*e, lattice.to_fractional(r), *c)).collect();
+        let crystal = Crystal { lattice, atoms };
+
+        let mut buffer = Vec::new();
+        write_pdb(&crystal, &mut buffer, &PdbOptions { bonding: BondingRule::uniform(1.2) }).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[1], "CRYST1   10.000   10.000   10.000  90.00  90.00  90.00 P 1           1");
+        assert_eq!(lines[2], "HETATM    1  O1  SOL A   1       5.000   5.000   5.000  1.00  0.00           O");
+        assert_eq!(&lines[5][12..26], "Zn4  NOD B   2");
+        assert_eq!(&lines[6][17..22], "LNK B");
+        assert_eq!(&lines[5][76..78], "ZN");
+        assert!(text.contains("CONECT    1    2    3\nCONECT    2    1\nCONECT    3    1\nEND"));
+    }
+}
@@ -0,0 +1,302 @@
+use crate::core::elements;
+use crate::core::structure::{Atom, AtomProperty, CifRepresentable, ComponentType, Crystal, Lattice, PROP_SELECTIVE_DYNAMICS};
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// ============================================================================
+// READER
+// ============================================================================
+
+/// Parses a VASP POSCAR / CONTCAR file into a Crystal structure.
+pub fn from_poscar(path: &Path) -> Result<Crystal> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read POSCAR file: {:?}", path))?;
+    from_poscar_str(&contents).with_context(|| format!("Failed to parse POSCAR file: {:?}", path))
+}
+
+/// Parses POSCAR text (VASP 4 or 5 layout, Direct or Cartesian coordinates).
+///
+/// The scale line may hold one factor, a negative target volume (Å³) or three per-axis
+/// factors. VASP 4 files have no species line; their species are read from the comment line.
+/// `Selective dynamics` flags are kept in the `selective_dynamics` property ("T T F").
+pub fn from_poscar_str(contents: &str) -> Result<Crystal> {
+    parse_poscar(contents).map(|(crystal, _)| crystal)
+}
+
+/// Parses the POSCAR part of `contents` and returns the crystal with the number of lines
+/// read, so that CHGCAR-style files can continue after the positions.
+pub(crate) fn parse_poscar(contents: &str) -> Result<(Crystal, usize)> {
+    let mut lines = contents.lines().enumerate().map(|(i, l)| (i + 1, l));
+    let mut next_line = |what: &str| -> Result<(usize, &str)> {
+        lines.next().ok_or_else(|| anyhow!("POSCAR ended before the {} line", what))
+    };
+
+    // 1. Header
+    let (_, comment) = next_line("comment")?;
+
+    let (scale_line, scale_text) = next_line("scale")?;
+    let scale_values = parse_floats(scale_text, scale_line)?;
+
+    let mut rows = Matrix3::zeros();
+    for i in 0..3 {
+        let (n, text) = next_line("lattice vector")?;
+        let values = parse_floats(text, n)?;
+        if values.len() < 3 {
+            bail!("line {}: lattice vector needs 3 components", n);
+        }
+        for j in 0..3 {
+            rows[(i, j)] = values[j];
+        }
+    }
+
+    // Columns are the lattice vectors, as in `Lattice::matrix`
+    let unscaled = rows.transpose();
+    let scale = match scale_values.as_slice() {
+        [s] if *s < 0.0 => Vector3::repeat((-s / unscaled.determinant().abs()).cbrt()),
+        [s] => Vector3::repeat(*s),
+        [x, y, z] => Vector3::new(*x, *y, *z),
+        _ => bail!("line {}: expected 1 or 3 scale factors", scale_line),
+    };
+    let matrix = Matrix3::from_diagonal(&scale) * unscaled;
+    let lattice = Lattice::new(matrix).map_err(|e| anyhow!("POSCAR lattice: {}", e))?;
+
+    // 2. Species and counts (VASP 5 has a symbol line before the counts)
+    let (n, text) = next_line("species")?;
+    let (species, counts_line) = if text.split_whitespace().next().is_some_and(|t| t.parse::<usize>().is_err()) {
+        let names: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+        (names, next_line("atom counts")?)
+    } else {
+        (comment.split_whitespace().map(str::to_string).collect(), (n, text))
+    };
+
+    let counts: Vec<usize> = counts_line
+        .1
+        .split_whitespace()
+        .map(|t| t.parse::<usize>().map_err(|_| anyhow!("line {}: bad atom count '{}'", counts_line.0, t)))
+        .collect::<Result<_>>()?;
+    if species.len() < counts.len() {
+        bail!("line {}: {} atom counts but only {} species names", counts_line.0, counts.len(), species.len());
+    }
+
+    let elements: Vec<String> = species
+        .iter()
+        .take(counts.len())
+        .map(|s| potcar_element(s).ok_or_else(|| anyhow!("line {}: '{}' is not an element", counts_line.0, s)))
+        .collect::<Result<_>>()?;
+
+    // 3. Mode lines
+    let (_, mut mode) = next_line("coordinate mode")?;
+    let selective = mode.trim_start().starts_with(['S', 's']);
+    if selective {
+        mode = next_line("coordinate mode")?.1;
+    }
+    let cartesian = mode.trim_start().starts_with(['C', 'c', 'K', 'k']);
+
+    // 4. Positions
+    let mut atoms = Vec::with_capacity(counts.iter().sum());
+    for (element, &count) in elements.iter().zip(&counts) {
+        for _ in 0..count {
+            let (n, text) = next_line("position")?;
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            if tokens.len() < 3 {
+                bail!("line {}: expected 3 coordinates", n);
+            }
+            let mut position = Vector3::zeros();
+            for j in 0..3 {
+                position[j] = tokens[j].parse::<f64>().map_err(|_| anyhow!("line {}: bad coordinate '{}'", n, tokens[j]))?;
+            }
+            let frac = if cartesian {
+                lattice.to_fractional(&position.component_mul(&scale))
+            } else {
+                position
+            };
+
+            let mut atom = Atom::new(element.clone(), frac, ComponentType::Unknown);
+            if selective {
+                let flags = tokens.get(3..6).ok_or_else(|| anyhow!("line {}: missing selective dynamics flags", n))?;
+                atom.set_property(PROP_SELECTIVE_DYNAMICS, flags.join(" "));
+            }
+            atoms.push(atom);
+        }
+    }
+
+    if atoms.is_empty() {
+        bail!("No atoms found in POSCAR file.");
+    }
+
+    let consumed = lines.next().map_or(contents.lines().count(), |(n, _)| n - 1);
+    Ok((Crystal { lattice, atoms }, consumed))
+}
+
+/// "Fe_pv", "O_s", "Zn/abc123" (VASP 6 POTCAR hashes) -> element. The symbol part must be
+/// a bare element so that a VASP 4 comment such as "Generated ..." is not read as Ge.
+pub(crate) fn potcar_element(species: &str) -> Option<String> {
+    let symbol = species.split(['_', '/']).next().unwrap_or(species);
+    elements::normalize_symbol(symbol).map(str::to_string)
+}
+
+fn parse_floats(text: &str, line: usize) -> Result<Vec<f64>> {
+    text.split_whitespace()
+        .take_while(|t| !t.starts_with(['!', '#']))
+        .map(|t| t.parse::<f64>().map_err(|_| anyhow!("line {}: cannot parse '{}' as a number", line, t)))
+        .collect()
+}
+
+// ============================================================================
+// WRITER
+// ============================================================================
+
+/// Elements in order of first appearance.
+fn species_in_order(atoms: &[Atom]) -> Vec<&str> {
+    let mut species: Vec<&str> = Vec::new();
+    for atom in atoms {
+        if !species.contains(&atom.element.as_str()) {
+            species.push(&atom.element);
+        }
+    }
+    species
+}
+
+/// Atom indices in the order `write_poscar` writes them: grouped by species, in order of
+/// first appearance. Atom k of a structure read back from VASP is atom `order[k]`.
+pub fn species_order(atoms: &[Atom]) -> Vec<usize> {
+    species_in_order(atoms)
+        .iter()
+        .flat_map(|s| (0..atoms.len()).filter(move |&i| atoms[i].element == *s))
+        .collect()
+}
+
+/// Options for `write_poscar`.
+#[derive(Debug, Clone, Default)]
+pub struct PoscarOptions {
+    /// First line of the file; defaults to the formula in species order.
+    pub comment: Option<String>,
+    /// Per-atom "free to move" flags, in the structure's atom order. When `None`, flags stored
+    /// in the `selective_dynamics` property (e.g. from a CONTCAR) are written if every atom has them.
+    pub selective_dynamics: Option<Vec<bool>>,
+    /// Write Cartesian instead of Direct coordinates.
+    pub cartesian: bool,
+}
+
+/// Writes a structure to a POSCAR file (VASP 5 layout).
+pub fn to_poscar<T: CifRepresentable>(structure: &T, path: &Path, options: &PoscarOptions) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_poscar(structure, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a structure in POSCAR format to any writer.
+///
+/// Atoms are grouped by species, in order of first appearance, as VASP requires.
+pub fn write_poscar<T: CifRepresentable, W: Write>(structure: &T, mut writer: W, options: &PoscarOptions) -> Result<()> {
+    let atoms = structure.atoms();
+    let lattice = structure.lattice();
+
+    if let Some(flags) = &options.selective_dynamics {
+        if flags.len() != atoms.len() {
+            bail!("{} selective dynamics flags for {} atoms", flags.len(), atoms.len());
+        }
+    }
+
+    // 1. Group by species
+    let species = species_in_order(atoms);
+    let order = species_order(atoms);
+    let counts: Vec<usize> = species.iter().map(|s| atoms.iter().filter(|a| a.element == *s).count()).collect();
+
+    // Explicit flags win; otherwise reuse stored per-axis flags when every atom has them
+    let flags: Option<Vec<String>> = match &options.selective_dynamics {
+        Some(free) => Some(free.iter().map(|&f| if f { "T T T" } else { "F F F" }.to_string()).collect()),
+        None => atoms
+            .iter()
+            .map(|a| a.property(PROP_SELECTIVE_DYNAMICS).and_then(AtomProperty::as_str).map(str::to_string))
+            .collect(),
+    };
+
+    // 2. Header
+    let comment = options.comment.clone().unwrap_or_else(|| {
+        species.iter().zip(&counts).map(|(s, n)| format!("{}{}", s, n)).collect::<Vec<_>>().join(" ")
+    });
+    writeln!(writer, "{}", comment)?;
+    writeln!(writer, "1.0")?;
+    for column in lattice.matrix.column_iter() {
+        writeln!(writer, "  {:>20.12} {:>20.12} {:>20.12}", column.x, column.y, column.z)?;
+    }
+    writeln!(writer, "  {}", species.join("  "))?;
+    writeln!(writer, "  {}", counts.iter().map(|c| c.to_string()).collect::<Vec<_>>().join("  "))?;
+    if flags.is_some() {
+        writeln!(writer, "Selective dynamics")?;
+    }
+    writeln!(writer, "{}", if options.cartesian { "Cartesian" } else { "Direct" })?;
+
+    // 3. Positions
+    for &i in &order {
+        let atom = &atoms[i];
+        let p = if options.cartesian {
+            lattice.to_cartesian(&atom.fractional_coords)
+        } else {
+            atom.fractional_coords
+        };
+        write!(writer, "  {:>18.12} {:>18.12} {:>18.12}", p.x, p.y, p.z)?;
+        if let Some(flags) = &flags {
+            write!(writer, "  {}", flags[i])?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vasp4_cartesian_with_volume_scale() {
+        // VASP 4: species come from the comment line; -64 Å³ rescales the 1 Å cube to 4 Å.
+        let source = "\
+Mg O
+ -64.0
+ 1 0 0
+ 0 1 0
+ 0 0 1
+ 1 1
+Selective dynamics
+Cartesian
+ 0.0 0.0 0.0 F F F
+ 0.5 0.5 0.5 T T F
+";
+        let crystal = from_poscar_str(source).unwrap();
+        assert!((crystal.lattice.matrix[(0, 0)] - 4.0).abs() < 1e-9);
+        assert_eq!(crystal.atoms[1].element, "O");
+        assert!((crystal.atoms[1].fractional_coords - Vector3::repeat(0.5)).norm() < 1e-9);
+        assert_eq!(crystal.atoms[1].property(PROP_SELECTIVE_DYNAMICS).and_then(AtomProperty::as_str), Some("T T F"));
+    }
+
+    #[test]
+    fn test_write_groups_species_and_round_trips() {
+        let lattice = Lattice::from_parameters(5.0, 6.0, 7.0, 90.0, 100.0, 90.0).unwrap();
+        let atoms = vec![
+            Atom::new("O", Vector3::new(0.1, 0.2, 0.3), ComponentType::Unknown),
+            Atom::new("Zn", Vector3::new(0.5, 0.5, 0.5), ComponentType::Unknown),
+            Atom::new("O", Vector3::new(0.7, 0.8, 0.9), ComponentType::Unknown),
+        ];
+        let crystal = Crystal { lattice, atoms };
+        let options = PoscarOptions { selective_dynamics: Some(vec![true, false, true]), ..Default::default() };
+
+        let mut buffer = Vec::new();
+        write_poscar(&crystal, &mut buffer, &options).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("O2 Zn1\n"));
+
+        let read = from_poscar_str(&text).unwrap();
+        let elements: Vec<&str> = read.atoms.iter().map(|a| a.element.as_str()).collect();
+        assert_eq!(elements, vec!["O", "O", "Zn"]);
+        assert!((read.atoms[1].fractional_coords - Vector3::new(0.7, 0.8, 0.9)).norm() < 1e-9);
+        assert_eq!(read.atoms[2].property(PROP_SELECTIVE_DYNAMICS).and_then(AtomProperty::as_str), Some("F F F"));
+        assert!((read.lattice.matrix - crystal.lattice.matrix).norm() < 1e-9);
+    }
+}
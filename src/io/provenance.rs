@@ -0,0 +1,32 @@
+//! This program's CIF extensions: the per-atom columns and the `_csg_slab_*` items recording
+//! how a slab was cut. Written by `writer` and read back by `parser`.
+
+use nalgebra::Matrix3;
+use serde::{Deserialize, Serialize};
+
+/// Per-atom `ComponentType` name ("MetalNode", ...).
+pub const TAG_COMPONENT_TYPE: &str = "_atom_site_csg_component_type";
+/// Per-atom slab layer (0 = bottom layer).
+pub const TAG_LAYER: &str = "_atom_site_csg_layer";
+/// Per-atom label from the source file, written when `_atom_site_label` had to be made unique.
+pub const TAG_SOURCE_LABEL: &str = "_atom_site_csg_source_label";
+/// Prefix of the slab provenance items (`_csg_slab_miller_index_h`, ...).
+pub const PROVENANCE_PREFIX: &str = "_csg_slab_";
+/// Suffixes of the `_csg_slab_bulk_cell_*` items, in `SlabProvenance::bulk_cell` order.
+pub const BULK_CELL_NAMES: [&str; 6] = ["length_a", "length_b", "length_c", "angle_alpha", "angle_beta", "angle_gamma"];
+
+/// How a slab was cut from its bulk: enough to re-derive it from the output file alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlabProvenance {
+    pub miller_indices: [i32; 3],
+    pub d_hkl: f64,
+    pub n_layers: usize,
+    /// Cut offset along the normal (Å).
+    pub cut_offset: f64,
+    pub vacuum: f64,
+    /// Bulk cell parameters (a, b, c, alpha, beta, gamma).
+    pub bulk_cell: [f64; 6],
+    /// Columns: slab a, slab b and the layer stacking vector, in bulk lattice coordinates.
+    #[serde(with = "crate::io::json::matrix_columns")]
+    pub transformation: Matrix3<i32>,
+}
@@ -0,0 +1,272 @@
+use crate::core::structure::{Atom, ComponentType, Crystal, Lattice, PROP_SELECTIVE_DYNAMICS};
+use crate::io::poscar::potcar_element;
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use std::fs;
+use std::path::Path;
+
+// ============================================================================
+// VASPRUN.XML
+// ============================================================================
+
+/// Reads the final structure of a `vasprun.xml`.
+pub fn from_vasprun(path: &Path) -> Result<Crystal> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read vasprun file: {:?}", path))?;
+    from_vasprun_str(&contents).with_context(|| format!("Failed to parse vasprun file: {:?}", path))
+}
+
+/// Parses `vasprun.xml` text: the `finalpos` structure, or the last ionic step's structure if
+/// the run did not finish. Elements come from `<atominfo>`; `selective` flags are kept in the
+/// `selective_dynamics` property.
+pub fn from_vasprun_str(contents: &str) -> Result<Crystal> {
+    let document = roxmltree::Document::parse(contents).context("vasprun.xml is not well-formed XML (was the run killed?)")?;
+    let root = document.root_element();
+
+    // 1. Elements, one per ion
+    let atom_array = root
+        .descendants()
+        .find(|n| n.has_tag_name("array") && n.attribute("name") == Some("atoms"))
+        .ok_or_else(|| anyhow!("vasprun.xml has no <atominfo> atoms array"))?;
+    let elements: Vec<String> = atom_array
+        .descendants()
+        .filter(|n| n.has_tag_name("rc"))
+        .map(|rc| {
+            let symbol = rc.children().find(|c| c.has_tag_name("c")).and_then(|c| c.text()).unwrap_or("").trim();
+            potcar_element(symbol).ok_or_else(|| anyhow!("'{}' is not an element", symbol))
+        })
+        .collect::<Result<_>>()?;
+
+    // 2. Final structure
+    let structures: Vec<roxmltree::Node> = root.descendants().filter(|n| n.has_tag_name("structure")).collect();
+    let structure = structures
+        .iter()
+        .find(|n| n.attribute("name") == Some("finalpos"))
+        .or_else(|| structures.last())
+        .ok_or_else(|| anyhow!("vasprun.xml has no <structure>"))?;
+
+    let varray = |parent: roxmltree::Node, name: &str| -> Option<Vec<Vec<String>>> {
+        let node = parent.descendants().find(|n| n.has_tag_name("varray") && n.attribute("name") == Some(name))?;
+        Some(
+            node.children()
+                .filter(|n| n.has_tag_name("v"))
+                .map(|v| v.text().unwrap_or("").split_whitespace().map(str::to_string).collect())
+                .collect(),
+        )
+    };
+    let numbers = |rows: Vec<Vec<String>>, what: &str| -> Result<Vec<Vector3<f64>>> {
+        rows.iter()
+            .map(|row| {
+                let values: Vec<f64> = row.iter().map(|t| t.parse::<f64>()).collect::<Result<_, _>>().map_err(|_| anyhow!("bad {} row {:?}", what, row))?;
+                match values.as_slice() {
+                    [x, y, z, ..] => Ok(Vector3::new(*x, *y, *z)),
+                    _ => bail!("{} row needs 3 values, found {:?}", what, row),
+                }
+            })
+            .collect()
+    };
+
+    let basis = numbers(varray(*structure, "basis").ok_or_else(|| anyhow!("structure has no basis"))?, "basis")?;
+    if basis.len() != 3 {
+        bail!("basis has {} vectors", basis.len());
+    }
+    let lattice = Lattice::new(Matrix3::from_columns(&basis)).map_err(|e| anyhow!("vasprun lattice: {}", e))?;
+
+    let positions = numbers(varray(*structure, "positions").ok_or_else(|| anyhow!("structure has no positions"))?, "position")?;
+    if positions.len() != elements.len() {
+        bail!("{} positions for {} atoms", positions.len(), elements.len());
+    }
+    let selective = varray(*structure, "selective");
+
+    let atoms = elements
+        .into_iter()
+        .zip(positions)
+        .enumerate()
+        .map(|(i, (element, frac))| {
+            let mut atom = Atom::new(element, frac, ComponentType::Unknown);
+            if let Some(flags) = selective.as_ref().and_then(|rows| rows.get(i)) {
+                atom.set_property(PROP_SELECTIVE_DYNAMICS, flags.join(" "));
+            }
+            atom
+        })
+        .collect();
+
+    Ok(Crystal { lattice, atoms })
+}
+
+// ============================================================================
+// OUTCAR
+// ============================================================================
+
+/// Reads the last geometry of an OUTCAR.
+pub fn from_outcar(path: &Path) -> Result<Crystal> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read OUTCAR file: {:?}", path))?;
+    from_outcar_str(&contents).with_context(|| format!("Failed to parse OUTCAR file: {:?}", path))
+}
+
+/// Parses OUTCAR text: species from the `TITEL` lines and `ions per type`, the last
+/// `direct lattice vectors` block and the last `POSITION ... TOTAL-FORCE` table (Cartesian Å).
+pub fn from_outcar_str(contents: &str) -> Result<Crystal> {
+    let lines: Vec<&str> = contents.lines().collect();
+
+    // 1. Species and counts
+    let species: Vec<String> = lines
+        .iter()
+        .filter_map(|l| l.trim_start().strip_prefix("TITEL"))
+        .map(|rest| {
+            let name = rest.trim_start_matches([' ', '=']).split_whitespace().nth(1).unwrap_or("");
+            potcar_element(name).ok_or_else(|| anyhow!("TITEL '{}' is not an element", rest.trim()))
+        })
+        .collect::<Result<_>>()?;
+    let counts: Vec<usize> = lines
+        .iter()
+        .find_map(|l| l.split_once("ions per type =").map(|(_, counts)| counts))
+        .ok_or_else(|| anyhow!("OUTCAR has no 'ions per type' line"))?
+        .split_whitespace()
+        .map(|t| t.parse::<usize>().map_err(|_| anyhow!("bad ion count '{}'", t)))
+        .collect::<Result<_>>()?;
+    if species.len() < counts.len() {
+        bail!("{} ion counts but {} TITEL lines", counts.len(), species.len());
+    }
+    let elements: Vec<&String> = species.iter().zip(&counts).flat_map(|(s, &n)| std::iter::repeat_n(s, n)).collect();
+
+    // 2. Last cell
+    let cell_line = lines
+        .iter()
+        .rposition(|l| l.contains("direct lattice vectors"))
+        .ok_or_else(|| anyhow!("OUTCAR has no 'direct lattice vectors' block"))?;
+    let mut columns = Vec::with_capacity(3);
+    for n in cell_line + 1..cell_line + 4 {
+        let values = fortran_floats(lines.get(n).copied().unwrap_or(""));
+        if values.len() < 3 {
+            bail!("line {}: expected a lattice vector", n + 1);
+        }
+        columns.push(Vector3::new(values[0], values[1], values[2]));
+    }
+    let lattice = Lattice::new(Matrix3::from_columns(&columns)).map_err(|e| anyhow!("OUTCAR lattice: {}", e))?;
+
+    // 3. Last positions (header, dashes, one row per ion)
+    let table = lines
+        .iter()
+        .rposition(|l| l.contains("POSITION") && l.contains("TOTAL-FORCE"))
+        .ok_or_else(|| anyhow!("OUTCAR has no ionic positions (did the first step finish?)"))?;
+    let mut atoms = Vec::with_capacity(elements.len());
+    for (i, element) in elements.into_iter().enumerate() {
+        let n = table + 2 + i;
+        let values = fortran_floats(lines.get(n).copied().unwrap_or(""));
+        if values.len() < 3 {
+            bail!("line {}: expected the position of ion {}", n + 1, i + 1);
+        }
+        let frac = lattice.to_fractional(&Vector3::new(values[0], values[1], values[2]));
+        atoms.push(Atom::new(element.clone(), frac, ComponentType::Unknown));
+    }
+
+    Ok(Crystal { lattice, atoms })
+}
+
+/// Numbers of a fixed-format Fortran line, where a negative value may touch the previous one
+/// ("4.210000000-0.500000000").
+fn fortran_floats(line: &str) -> Vec<f64> {
+    let mut values = Vec::new();
+    for token in line.split_whitespace() {
+        let mut start = 0;
+        let bytes = token.as_bytes();
+        for i in 1..bytes.len() {
+            if bytes[i] == b'-' && bytes[i - 1].is_ascii_digit() {
+                values.extend(token[start..i].parse::<f64>().ok());
+                start = i;
+            }
+        }
+        values.extend(token[start..].parse::<f64>().ok());
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vasprun_final_structure() {
+        let source = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<modeling>
+ <atominfo>
+  <atoms>       2 </atoms>
+  <array name="atoms" >
+   <dimension dim="1">ion</dimension>
+   <field type="string">element</field>
+   <field type="int">atomtype</field>
+   <set>
+    <rc><c>Mg</c><c>   1</c></rc>
+    <rc><c>O </c><c>   2</c></rc>
+   </set>
+  </array>
+ </atominfo>
+ <structure name="initialpos" >
+  <crystal>
+   <varray name="basis" >
+    <v>       4.00000000       0.00000000       0.00000000 </v>
+    <v>       0.00000000       4.00000000       0.00000000 </v>
+    <v>       0.00000000       0.00000000      20.00000000 </v>
+   </varray>
+  </crystal>
+  <varray name="positions" >
+   <v>       0.00000000       0.00000000       0.10000000 </v>
+   <v>       0.50000000       0.50000000       0.10000000 </v>
+  </varray>
+ </structure>
+ <structure name="finalpos" >
+  <crystal>
+   <varray name="basis" >
+    <v>       4.00000000       0.00000000       0.00000000 </v>
+    <v>       0.00000000       4.00000000       0.00000000 </v>
+    <v>       0.00000000       0.00000000      20.00000000 </v>
+   </varray>
+  </crystal>
+  <varray name="positions" >
+   <v>       0.00000000       0.00000000       0.10500000 </v>
+   <v>       0.50000000       0.50000000       0.09500000 </v>
+  </varray>
+  <varray name="selective" >
+   <v type="logical" >  T  T  T </v>
+   <v type="logical" >  F  F  F </v>
+  </varray>
+ </structure>
+</modeling>
+"#;
+        let crystal = from_vasprun_str(source).unwrap();
+        assert_eq!(crystal.atoms.len(), 2);
+        assert_eq!(crystal.atoms[1].element, "O");
+        assert!((crystal.atoms[0].fractional_coords.z - 0.105).abs() < 1e-12);
+        assert_eq!(crystal.atoms[1].property(PROP_SELECTIVE_DYNAMICS).and_then(|p| p.as_str()), Some("F F F"));
+    }
+
+    #[test]
+    fn test_outcar_last_geometry() {
+        let source = "\
+ vasp.6.3.0 18Jan22 (build Feb 10 2022) complex
+   TITEL  = PAW_PBE Mg_pv 13Apr2007
+   TITEL  = PAW_PBE O 08Apr2002
+   ions per type =               1   1
+      direct lattice vectors                 reciprocal lattice vectors
+     4.000000000  0.000000000  0.000000000     0.250000000  0.000000000  0.000000000
+     0.000000000  4.000000000  0.000000000     0.000000000  0.250000000  0.000000000
+     0.000000000  0.000000000 20.000000000     0.000000000  0.000000000  0.050000000
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      2.00000         0.000000      0.000000      0.100000
+      2.00000      2.00000      2.00000         0.000000      0.000000     -0.100000
+ -----------------------------------------------------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      2.10000         0.000000      0.000000      0.010000
+      2.00000      2.00000      1.90000         0.000000      0.000000-0.010000
+ -----------------------------------------------------------------------------------
+";
+        let crystal = from_outcar_str(source).unwrap();
+        let elements: Vec<&str> = crystal.atoms.iter().map(|a| a.element.as_str()).collect();
+        assert_eq!(elements, vec!["Mg", "O"]);
+        assert!((crystal.atoms[0].fractional_coords.z - 0.105).abs() < 1e-12);
+        assert!((crystal.atoms[1].fractional_coords - Vector3::new(0.5, 0.5, 0.095)).norm() < 1e-12);
+        assert_eq!(fortran_floats("1.5-2.0E-01 -3"), vec![1.5, -0.2, -3.0]);
+    }
+}
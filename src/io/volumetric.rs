@@ -0,0 +1,306 @@
+use crate::analysis::topology::DensityProfile;
+use crate::core::structure::{Atom, ComponentType, Crystal, Lattice, BOHR_RADIUS};
+use crate::core::elements;
+use crate::io::poscar::parse_poscar;
+use crate::math::integer_basis::gcd;
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use std::fs;
+use std::path::Path;
+
+/// Largest differences (relative length, degrees) between a grid's cell and the bulk cell.
+const LENGTH_TOLERANCE: f64 = 1e-2;
+const ANGLE_TOLERANCE: f64 = 0.5;
+
+/// A scalar field (electron density) on a regular grid spanning the unit cell, with the
+/// structure it was computed for.
+#[derive(Debug, Clone)]
+pub struct VolumetricData {
+    pub crystal: Crystal,
+    /// Grid points along a, b and c.
+    pub grid: [usize; 3],
+    /// Values in e/Å³, a index fastest: `data[i + na * (j + nb * k)]` is the value at
+    /// fractional coordinates (i/na, j/nb, k/nc).
+    pub data: Vec<f64>,
+}
+
+impl VolumetricData {
+    pub fn new(crystal: Crystal, grid: [usize; 3], data: Vec<f64>) -> Result<Self> {
+        if grid.contains(&0) {
+            bail!("Volumetric grid {:?} has an empty axis", grid);
+        }
+        if data.len() != grid.iter().product::<usize>() {
+            bail!("Volumetric grid {:?} needs {} values, found {}", grid, grid.iter().product::<usize>(), data.len());
+        }
+        Ok(Self { crystal, grid, data })
+    }
+
+    /// Value at grid point (i, j, k), indices taken modulo the grid.
+    pub fn value(&self, i: usize, j: usize, k: usize) -> f64 {
+        let [na, nb, nc] = self.grid;
+        self.data[i % na + na * (j % nb + nb * (k % nc))]
+    }
+
+    /// Fails unless `lattice` has the cell of the grid (lengths within 1 %, angles within
+    /// 0.5°). Orientation may differ: profiles only use fractional coordinates.
+    pub fn check_cell(&self, lattice: &Lattice) -> Result<()> {
+        let (a, b, c, alpha, beta, gamma) = self.crystal.lattice.to_parameters();
+        let (a2, b2, c2, alpha2, beta2, gamma2) = lattice.to_parameters();
+        let lengths_match = [(a, a2), (b, b2), (c, c2)].iter().all(|(x, y)| (x - y).abs() <= LENGTH_TOLERANCE * x);
+        let angles_match = [(alpha, alpha2), (beta, beta2), (gamma, gamma2)].iter().all(|(x, y)| (x - y).abs() <= ANGLE_TOLERANCE);
+        if !lengths_match || !angles_match {
+            bail!(
+                "Volumetric data cell ({:.3} {:.3} {:.3} {:.2} {:.2} {:.2}) does not match the structure ({:.3} {:.3} {:.3} {:.2} {:.2} {:.2})",
+                a, b, c, alpha, beta, gamma, a2, b2, c2, alpha2, beta2, gamma2
+            );
+        }
+        Ok(())
+    }
+
+    /// Planar average of the data over (h k l) planes, as a function of the height along the
+    /// plane normal (measured from the cell origin, like `VoidCrawler` offsets).
+    ///
+    /// The height of grid point (i, j, k) is `d (h i/na + k j/nb + l k/nc)` modulo `d`, with `d`
+    /// the spacing of the reduced indices, so the points fall exactly on a finite set of planes
+    /// with the same number of points each; those exact averages are then merged onto at most
+    /// `max(na, nb, nc)` samples.
+    pub fn planar_average(&self, miller_indices: [i32; 3]) -> Result<DensityProfile> {
+        let common = gcd(gcd(miller_indices[0], miller_indices[1]), miller_indices[2]);
+        if common == 0 {
+            bail!("Miller indices cannot be (0,0,0).");
+        }
+        let hkl = miller_indices.map(|m| (m / common) as i64);
+        let grid = self.grid.map(|n| n as i64);
+
+        // Number of distinct planes through the grid points
+        let mut planes = 1i64;
+        for axis in 0..3 {
+            if hkl[axis] != 0 {
+                let period = grid[axis] / gcd_i64(hkl[axis], grid[axis]);
+                planes = planes / gcd_i64(planes, period) * period;
+            }
+        }
+        // Plane index step per grid step along each axis: h * planes / na
+        let steps: Vec<i64> = (0..3)
+            .map(|axis| {
+                let g = gcd_i64(hkl[axis], grid[axis]);
+                hkl[axis] / g * (planes / (grid[axis] / g))
+            })
+            .collect();
+
+        let mut sums = vec![0.0; planes as usize];
+        let [na, nb, nc] = self.grid;
+        for k in 0..nc {
+            for j in 0..nb {
+                let base = steps[1] * j as i64 + steps[2] * k as i64;
+                for i in 0..na {
+                    let plane = (base + steps[0] * i as i64).rem_euclid(planes) as usize;
+                    sums[plane] += self.data[i + na * (j + nb * k)];
+                }
+            }
+        }
+
+        // Merge runs of exact planes, centred on each sample
+        let max_samples = *self.grid.iter().max().unwrap_or(&1) as i64;
+        let samples = (1..=max_samples.min(planes)).rev().find(|s| planes % s == 0).unwrap_or(1);
+        let run = planes / samples;
+        let mut values = vec![0.0; samples as usize];
+        for (plane, sum) in sums.iter().enumerate() {
+            let sample = ((plane as f64 / run as f64).round() as i64).rem_euclid(samples) as usize;
+            values[sample] += sum;
+        }
+        let per_sample = self.data.len() as f64 / samples as f64;
+        values.iter_mut().for_each(|v| *v /= per_sample);
+
+        let normal = self.crystal.lattice.reciprocal_matrix * Vector3::new(hkl[0] as f64, hkl[1] as f64, hkl[2] as f64);
+        Ok(DensityProfile { values, period: 1.0 / normal.norm() })
+    }
+}
+
+fn gcd_i64(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// ============================================================================
+// READERS
+// ============================================================================
+
+/// Reads a Gaussian cube file (`.cube`, `.cub`) or, for any other name, a VASP
+/// CHGCAR/PARCHG/AECCAR.
+pub fn from_volumetric(path: &Path) -> Result<VolumetricData> {
+    let is_cube = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("cube") || e.eq_ignore_ascii_case("cub"));
+    if is_cube { from_cube(path) } else { from_chgcar(path) }
+}
+
+/// Reads a VASP CHGCAR, PARCHG or AECCAR file.
+pub fn from_chgcar(path: &Path) -> Result<VolumetricData> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read CHGCAR file: {:?}", path))?;
+    from_chgcar_str(&contents).with_context(|| format!("Failed to parse CHGCAR file: {:?}", path))
+}
+
+/// Parses CHGCAR text: a POSCAR, a blank line, the grid size and ρ·V_cell values with the a
+/// index fastest. Only the first (total) density is read; augmentation occupancies and the
+/// magnetization density of spin-polarized runs are ignored. Values are divided by the cell
+/// volume to give e/Å³.
+pub fn from_chgcar_str(contents: &str) -> Result<VolumetricData> {
+    let (crystal, consumed) = parse_poscar(contents)?;
+    let mut tokens = contents.lines().skip(consumed).flat_map(str::split_whitespace);
+
+    let mut grid = [0usize; 3];
+    for n in grid.iter_mut() {
+        let token = tokens.next().ok_or_else(|| anyhow!("CHGCAR ended before the grid size"))?;
+        *n = token.parse().map_err(|_| anyhow!("bad CHGCAR grid size '{}'", token))?;
+    }
+
+    let volume = crystal.lattice.matrix.determinant().abs();
+    let count: usize = grid.iter().product();
+    let data = tokens
+        .take(count)
+        .map(|t| t.parse::<f64>().map(|v| v / volume).map_err(|_| anyhow!("bad CHGCAR value '{}'", t)))
+        .collect::<Result<Vec<f64>>>()?;
+    VolumetricData::new(crystal, grid, data)
+}
+
+/// Reads a Gaussian cube file.
+pub fn from_cube(path: &Path) -> Result<VolumetricData> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Could not read cube file: {:?}", path))?;
+    from_cube_str(&contents).with_context(|| format!("Failed to parse cube file: {:?}", path))
+}
+
+/// Parses Gaussian cube text. The cell is spanned by the grid (n_i voxel vectors per axis)
+/// and atoms are placed relative to the cube origin. Lengths are in Bohr (values converted
+/// from e/Bohr³ to e/Å³) unless the first grid count is negative, meaning Å. With several
+/// values per point (`NVal` or a `DSET_IDS` line), the first is read.
+pub fn from_cube_str(contents: &str) -> Result<VolumetricData> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let numbers = |index: usize, what: &str| -> Result<Vec<f64>> {
+        let text = lines.get(index).ok_or_else(|| anyhow!("cube file ended before the {} line", what))?;
+        text.split_whitespace()
+            .map(|t| t.parse::<f64>().map_err(|_| anyhow!("line {}: cannot parse '{}' as a number", index + 1, t)))
+            .collect()
+    };
+
+    // 1. Header: two comments, atom count and origin, then the three grid axes
+    let header = numbers(2, "atom count")?;
+    if header.len() < 4 {
+        bail!("line 3: expected the atom count and origin");
+    }
+    let n_atoms = header[0] as i64;
+    let mut values_per_point = header.get(4).map_or(1, |&n| n.max(1.0) as usize);
+
+    let mut grid = [0usize; 3];
+    let mut axes = Matrix3::zeros();
+    let mut unit = BOHR_RADIUS;
+    for (axis, points) in grid.iter_mut().enumerate() {
+        let row = numbers(3 + axis, "grid axis")?;
+        if row.len() < 4 {
+            bail!("line {}: expected a point count and a voxel vector", 4 + axis);
+        }
+        if axis == 0 && row[0] < 0.0 {
+            unit = 1.0;
+        }
+        *points = row[0].abs() as usize;
+        axes.set_column(axis, &(Vector3::new(row[1], row[2], row[3]) * *points as f64));
+    }
+    let lattice = Lattice::new(axes * unit).map_err(|e| anyhow!("cube lattice: {}", e))?;
+    let origin = Vector3::new(header[1], header[2], header[3]) * unit;
+
+    // 2. Atoms
+    let mut atoms = Vec::with_capacity(n_atoms.unsigned_abs() as usize);
+    for index in 6..6 + n_atoms.unsigned_abs() as usize {
+        let row = numbers(index, "atom")?;
+        if row.len() < 5 {
+            bail!("line {}: expected the atomic number, charge and position", index + 1);
+        }
+        let element = elements::from_number(row[0] as usize).map(|e| e.symbol).ok_or_else(|| anyhow!("line {}: unknown atomic number {}", index + 1, row[0]))?;
+        let position = Vector3::new(row[2], row[3], row[4]) * unit - origin;
+        atoms.push(Atom::new(element, lattice.to_fractional(&position), ComponentType::Unknown));
+    }
+
+    // 3. Data, c index fastest
+    let mut tokens = lines[(6 + atoms.len()).min(lines.len())..].iter().flat_map(|l| l.split_whitespace());
+    if n_atoms < 0 {
+        let token = tokens.next().ok_or_else(|| anyhow!("cube file ended before the DSET_IDS line"))?;
+        values_per_point = token.parse().map_err(|_| anyhow!("bad DSET_IDS count '{}'", token))?;
+        tokens.by_ref().take(values_per_point).for_each(drop);
+    }
+
+    let [na, nb, nc] = grid;
+    let scale = 1.0 / unit.powi(3);
+    let mut data = vec![0.0; na * nb * nc];
+    for i in 0..na {
+        for j in 0..nb {
+            for k in 0..nc {
+                let token = tokens.next().ok_or_else(|| anyhow!("cube file has fewer than {} values", na * nb * nc))?;
+                let value: f64 = token.parse().map_err(|_| anyhow!("bad cube value '{}'", token))?;
+                data[i + na * (j + nb * k)] = value * scale;
+                tokens.by_ref().take(values_per_point - 1).for_each(drop);
+            }
+        }
+    }
+
+    VolumetricData::new(Crystal { lattice, atoms }, grid, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::topology::VoidCrawler;
+
+    /// Density along c of a 2 x 2 x 8 grid: low at k = 5, the same everywhere in-plane.
+    const PROFILE: [f64; 8] = [9.0, 7.0, 5.0, 3.0, 2.0, 0.5, 2.0, 6.0];
+
+    #[test]
+    fn test_chgcar_planar_average_and_minima() {
+        let volume = 4.0 * 4.0 * 8.0;
+        let values: Vec<String> = (0..32).map(|n| format!("{:.5E}", PROFILE[n / 4] * volume)).collect();
+        let source = format!(
+            "test\n1.0\n4 0 0\n0 4 0\n0 0 8\nMg\n1\nDirect\n0 0 0\n\n 2 2 8\n{}\naugmentation occupancies 1 1\n 0.1\n",
+            values.chunks(5).map(|c| c.join(" ")).collect::<Vec<_>>().join("\n")
+        );
+
+        let data = from_chgcar_str(&source).unwrap();
+        assert_eq!(data.grid, [2, 2, 8]);
+        assert!((data.value(1, 1, 5) - 0.5).abs() < 1e-9);
+
+        let profile = data.planar_average([0, 0, 2]).unwrap();
+        assert!((profile.period - 8.0).abs() < 1e-9);
+        assert_eq!(profile.values.len(), 8);
+        assert!(profile.values.iter().zip(PROFILE).all(|(a, b)| (a - b).abs() < 1e-9));
+
+        let cuts = VoidCrawler::new(&data.crystal, &Vector3::z()).with_density(profile).find_safe_offsets();
+        assert_eq!(cuts.len(), 1);
+        assert!((cuts[0].offset_z - 5.0).abs() < 0.5);
+        assert!(cuts[0].density.unwrap() <= 0.5);
+
+        // (1 0 1) planes: every grid point still lands on a plane; the mean is preserved
+        let tilted = data.planar_average([1, 0, 1]).unwrap();
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        assert!((mean(&tilted.values) - mean(&PROFILE)).abs() < 1e-9);
+        assert!((tilted.period - 4.0 * 8.0 / 80f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cube_units_and_ordering() {
+        // 1 x 2 x 3 grid of 1 Bohr voxels, origin at (1, 0, 0) Bohr; value = 10 j + k (e/Bohr³)
+        let values: Vec<String> = (0..2).flat_map(|j| (0..3).map(move |k| format!("{}", 10 * j + k))).collect();
+        let source = format!(
+            "comment\ncomment\n 1 1.0 0.0 0.0\n 1 1.0 0.0 0.0\n 2 0.0 1.0 0.0\n 3 0.0 0.0 1.0\n 8 0.0 1.0 1.0 1.5\n{}\n",
+            values.join(" ")
+        );
+
+        let data = from_cube_str(&source).unwrap();
+        assert_eq!(data.grid, [1, 2, 3]);
+        assert!((data.crystal.lattice.matrix[(2, 2)] - 3.0 * BOHR_RADIUS).abs() < 1e-12);
+        assert_eq!(data.crystal.atoms[0].element, "O");
+        assert!((data.crystal.atoms[0].fractional_coords - Vector3::new(0.0, 0.5, 0.5)).norm() < 1e-9);
+        assert!((data.value(0, 1, 2) - 12.0 / BOHR_RADIUS.powi(3)).abs() < 1e-9);
+    }
+}
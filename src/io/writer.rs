@@ -1,22 +1,72 @@
-use crate::core::structure::{CifRepresentable, Lattice};
+use crate::core::bonding::BondingRule;
+use crate::core::connectivity::GraphRepresentation;
+use crate::core::structure::{AtomProperty, CifRepresentable, PROP_LAYER};
+use crate::io::provenance::{SlabProvenance, BULK_CELL_NAMES, PROVENANCE_PREFIX, TAG_COMPONENT_TYPE, TAG_LAYER, TAG_SOURCE_LABEL};
 use anyhow::{Context, Result};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use nalgebra::Vector3;
 
+/// Words that cannot start a bare CIF value (`data_x`, `loop_`, ...).
+const RESERVED_PREFIXES: [&str; 5] = ["data_", "loop_", "save_", "global_", "stop_"];
+
+/// Options for `write_cif_with_options`.
+#[derive(Debug, Clone)]
+pub struct CifWriterOptions {
+    /// Write a `_geom_bond` loop for the pairs this rule bonds; no bonds when `None`.
+    pub bonding: Option<BondingRule>,
+    /// Record how the slab was cut (`_csg_slab_*` items).
+    pub provenance: Option<SlabProvenance>,
+}
+
+impl Default for CifWriterOptions {
+    fn default() -> Self {
+        Self { bonding: Some(BondingRule::default()), provenance: None }
+    }
+}
+
 /// Writes a structure (Crystal or Slab) to a CIF file.
 ///
 /// # Arguments
 /// * `structure` - Anything that implements `CifRepresentable` (has atoms and a lattice).
 /// * `path` - The output file path.
 pub fn to_cif<T: CifRepresentable>(structure: &T, path: &Path) -> Result<()> {
+    to_cif_with_options(structure, path, &CifWriterOptions::default())
+}
+
+/// Writes a structure to a CIF file with bonds and provenance as requested.
+pub fn to_cif_with_options<T: CifRepresentable>(structure: &T, path: &Path, options: &CifWriterOptions) -> Result<()> {
     let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
     let mut writer = BufWriter::new(file);
+    write_cif_with_options(structure, &mut writer, options)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a structure as a P1 CIF data block to any writer.
+pub fn write_cif<T: CifRepresentable, W: Write>(structure: &T, writer: W) -> Result<()> {
+    write_cif_with_options(structure, writer, &CifWriterOptions::default())
+}
 
+/// Writes a structure as a P1 CIF data block to any writer.
+///
+/// Besides the cell and atom sites this writes the `component_type` and layer of every atom,
+/// a `_geom_bond` loop from the connectivity graph (the site symmetry `1_555`-style code gives
+/// the periodic image of the second atom) and, for slabs, the `_csg_slab_*` provenance items.
+/// The transformation matrix `_csg_slab_transform_matrix_ij` satisfies
+/// `[a_slab b_slab w] = [a b c]_bulk * M`, with `w` the bulk vector stacking one layer onto the next.
+pub fn write_cif_with_options<T: CifRepresentable, W: Write>(
+    structure: &T,
+    mut writer: W,
+    options: &CifWriterOptions,
+) -> Result<()> {
+    writeln!(writer, "# Generated by Crystal Surface Generator")?;
     writeln!(writer, "data_generated")?;
     
-    let (a, b, c, alpha, beta, gamma) = structure.lattice().to_parameters();
+    let lattice = structure.lattice();
+    let (a, b, c, alpha, beta, gamma) = lattice.to_parameters();
     
     writeln!(writer, "_cell_length_a               {:.5}", a)?;
     writeln!(writer, "_cell_length_b               {:.5}", b)?;
@@ -25,31 +75,161 @@ pub fn to_cif<T: CifRepresentable>(structure: &T, path: &Path) -> Result<()> {
     writeln!(writer, "_cell_angle_beta             {:.5}", beta)?;
     writeln!(writer, "_cell_angle_gamma            {:.5}", gamma)?;
     writeln!(writer, "_space_group_name_H-M_alt    'P 1'")?;
+
+    if let Some(p) = &options.provenance {
+        let tag = |name: &str| format!("{}{}", PROVENANCE_PREFIX, name);
+        writeln!(writer, "# Slab provenance")?;
+        for (axis, index) in ["h", "k", "l"].iter().zip(p.miller_indices) {
+            writeln!(writer, "{:<36} {}", tag(&format!("miller_index_{}", axis)), index)?;
+        }
+        writeln!(writer, "{:<36} {}", tag("d_hkl"), p.d_hkl)?;
+        writeln!(writer, "{:<36} {}", tag("n_layers"), p.n_layers)?;
+        writeln!(writer, "{:<36} {}", tag("cut_offset"), p.cut_offset)?;
+        writeln!(writer, "{:<36} {}", tag("vacuum"), p.vacuum)?;
+        for (name, value) in BULK_CELL_NAMES.iter().zip(p.bulk_cell) {
+            writeln!(writer, "{:<36} {}", tag(&format!("bulk_cell_{}", name)), value)?;
+        }
+        for i in 0..3 {
+            for j in 0..3 {
+                writeln!(writer, "{:<36} {}", tag(&format!("transform_matrix_{}{}", i + 1, j + 1)), p.transformation[(i, j)])?;
+            }
+        }
+    }
+
+    // Optional columns are only written when at least one atom carries the property.
+    let atoms = structure.atoms();
+    let has_charges = atoms.iter().any(|a| a.charge().is_some());
+    let has_moments = atoms.iter().any(|a| a.magmom().is_some());
+    let has_layers = atoms.iter().any(|a| a.property(PROP_LAYER).is_some());
+
+    // Original site labels are kept when they are unique; atoms without one get a generated
+    // "C1", "H2", ... label. A replicated slab repeats labels, so then every atom gets a
+    // generated label and the original goes into a separate column.
+    let mut labels: Vec<String> = atoms
+        .iter()
+        .enumerate()
+        .map(|(i, atom)| atom.label().map_or_else(|| format!("{}{}", atom.element, i + 1), str::to_string))
+        .collect();
+    let unique = labels.iter().collect::<HashSet<_>>().len() == labels.len();
+    if !unique {
+        labels = atoms.iter().enumerate().map(|(i, atom)| format!("{}{}", atom.element, i + 1)).collect();
+    }
+
     writeln!(writer, "loop_")?;
     writeln!(writer, " _atom_site_label")?;
     writeln!(writer, " _atom_site_type_symbol")?;
     writeln!(writer, " _atom_site_fract_x")?;
     writeln!(writer, " _atom_site_fract_y")?;
     writeln!(writer, " _atom_site_fract_z")?;
+    writeln!(writer, " _atom_site_occupancy")?;
+    writeln!(writer, " _atom_site_disorder_group")?;
+    if has_charges {
+        writeln!(writer, " _atom_site_charge")?;
+    }
+    writeln!(writer, " {}", TAG_COMPONENT_TYPE)?;
+    if has_layers {
+        writeln!(writer, " {}", TAG_LAYER)?;
+    }
+    if !unique {
+        writeln!(writer, " {}", TAG_SOURCE_LABEL)?;
+    }
 
-    for (i, atom) in structure.atoms().iter().enumerate() {
-        // Create unique label: "C1", "H2", etc.
-        let label = format!("{}{}", atom.element, i + 1);
-        writeln!(
+    for (atom, label) in atoms.iter().zip(&labels) {
+        let group = atom.disorder_group.map_or_else(|| ".".to_string(), |g| g.to_string());
+        write!(
             writer, 
-            " {:<8} {:<4} {:<10.5} {:<10.5} {:<10.5}", 
-            label, 
+            " {:<8} {:<4} {:<10.5} {:<10.5} {:<10.5} {:<6.4} {}", 
+            cif_value(label),
             atom.element, 
             atom.fractional_coords.x, 
             atom.fractional_coords.y, 
-            atom.fractional_coords.z
+            atom.fractional_coords.z,
+            atom.occupancy,
+            group
         )?;
+        if has_charges {
+            match atom.charge() {
+                Some(q) => write!(writer, " {:.6}", q)?,
+                None => write!(writer, " ?")?,
+            }
+        }
+        write!(writer, " {}", atom.component_type.name())?;
+        if has_layers {
+            match atom.property(PROP_LAYER).and_then(AtomProperty::as_i64) {
+                Some(layer) => write!(writer, " {}", layer)?,
+                None => write!(writer, " ?")?,
+            }
+        }
+        if !unique {
+            match atom.label() {
+                Some(source) => write!(writer, " {}", cif_value(source))?,
+                None => write!(writer, " ?")?,
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    if has_moments {
+        writeln!(writer, "loop_")?;
+        writeln!(writer, " _atom_site_moment.label")?;
+        writeln!(writer, " _atom_site_moment.crystalaxis_x")?;
+        writeln!(writer, " _atom_site_moment.crystalaxis_y")?;
+        writeln!(writer, " _atom_site_moment.crystalaxis_z")?;
+        for (atom, label) in atoms.iter().zip(&labels) {
+            if let Some(m) = atom.magmom() {
+                writeln!(writer, " {:<8} {:<10.5} {:<10.5} {:<10.5}", cif_value(label), m.x, m.y, m.z)?;
+            }
+        }
+    }
+
+    if let Some(rule) = &options.bonding {
+        let bonds = GraphRepresentation::from_crystal_with_rule(structure, rule).bonds();
+
+        if !bonds.is_empty() {
+            writeln!(writer, "loop_")?;
+            writeln!(writer, " _geom_bond_atom_site_label_1")?;
+            writeln!(writer, " _geom_bond_atom_site_label_2")?;
+            writeln!(writer, " _geom_bond_distance")?;
+            writeln!(writer, " _geom_bond_site_symmetry_2")?;
+            for (i, j, image) in bonds {
+                // `image` is the translation bringing atom j next to atom i
+                let (fi, fj) = (atoms[i].fractional_coords, atoms[j].fractional_coords);
+                let distance = lattice.to_cartesian(&(fj + image.cast::<f64>() - fi)).norm();
+                let symmetry = if image.iter().all(|t| t.abs() <= 4) {
+                    format!("1_{}{}{}", 5 + image.x, 5 + image.y, 5 + image.z)
+                } else {
+                    ".".to_string()
+                };
+                writeln!(writer, " {:<8} {:<8} {:.4} {}", cif_value(&labels[i]), cif_value(&labels[j]), distance, symmetry)?;
+            }
+        }
     }
 
-    writer.flush().context("Failed to flush buffer to file")?;
     Ok(())
 }
 
+/// A text value as it must appear in a CIF: bare when it cannot be mistaken for a tag, comment,
+/// reserved word or `?`/`.`; otherwise single- or double-quoted, or a semicolon text field when
+/// it spans lines or contains both quote characters.
+fn cif_value(text: &str) -> String {
+    let lower = text.to_ascii_lowercase();
+    let bare = !text.is_empty()
+        && text != "?"
+        && text != "."
+        && !text.starts_with(['_', '#', '$', ';', '\'', '"', '[', ']', '{', '}'])
+        && !text.contains(char::is_whitespace)
+        && !RESERVED_PREFIXES.iter().any(|word| lower.starts_with(word));
+    if bare {
+        text.to_string()
+    } else if text.contains(['\n', '\r']) || (text.contains('\'') && text.contains('"')) {
+        format!("\n;{}\n;", text)
+    } else if text.contains('\'') {
+        format!("\"{}\"", text)
+    } else {
+        format!("'{}'", text)
+    }
+}
+
 /// Writes a list of Cartesian coordinates to an XYZ file (for molecular debugging).
 pub fn to_xyz(atoms: &[(String, Vector3<f64>)], path: &Path) -> Result<()> {
     let file = File::create(path).with_context(|| format!("Failed to create XYZ file: {:?}", path))?;
@@ -69,4 +249,52 @@ pub fn to_xyz(atoms: &[(String, Vector3<f64>)], path: &Path) -> Result<()> {
 
     writer.flush()?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType, Crystal, Lattice, PROP_LABEL, PROP_MAGMOM};
+    use crate::io::parser::{self, CifOptions};
+
+    #[test]
+    fn test_awkward_labels_round_trip() {
+        let labels = ["Fe 1", "_Fe2", "#O3", "$O4", ";O5", "O'6", "O\"7", "O'\"8", "?", "data_O9", "O\n10", "loop_"];
+        let lattice = Lattice::from_parameters(20.0, 20.0, 20.0, 90.0, 90.0, 90.0).unwrap();
+        let atoms: Vec<Atom> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                // Fe–O pairs 2 Å apart, so the bond loop carries the labels too
+                let element = if i % 2 == 0 { "Fe" } else { "O" };
+                let p = Vector3::new(0.05 + 0.15 * (i / 2) as f64, 0.1 * (i % 2) as f64, 0.5);
+                let mut atom = Atom::new(element, p, ComponentType::Unknown);
+                atom.set_property(PROP_LABEL, *label);
+                atom
+            })
+            .collect();
+        let mut crystal = Crystal { lattice, atoms };
+        crystal.atoms[7].set_property(PROP_MAGMOM, Vector3::new(0.0, 0.0, 2.5));
+
+        let read_back = |crystal: &Crystal| {
+            let mut buffer = Vec::new();
+            write_cif(crystal, &mut buffer).unwrap();
+            let text = String::from_utf8(buffer).unwrap();
+            assert!(text.contains("_geom_bond_atom_site_label_1"));
+            parser::from_cif_str(&text, &CifOptions::default()).unwrap()
+        };
+
+        // Unique labels are written as site labels (and moment labels)
+        let parsed = read_back(&crystal);
+        assert_eq!(parsed.atoms.len(), labels.len());
+        for (atom, label) in parsed.atoms.iter().zip(labels) {
+            assert_eq!(atom.label(), Some(label));
+        }
+        assert!(parsed.atoms[7].magmom().is_some());
+
+        // Repeated labels go to the source-label column
+        crystal.atoms[1].set_property(PROP_LABEL, "Fe 1");
+        let parsed = read_back(&crystal);
+        assert_eq!(parsed.atoms[1].label(), Some("Fe 1"));
+        assert_eq!(parsed.atoms[7].label(), Some("O'\"8"));
+    }
+}
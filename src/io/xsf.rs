@@ -0,0 +1,62 @@
+use crate::core::structure::CifRepresentable;
+use crate::core::elements;
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writes a structure to an XCrySDen structure file (XSF).
+pub fn to_xsf<T: CifRepresentable>(structure: &T, path: &Path) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    write_xsf(structure, &mut writer)?;
+    writer.flush().context("Failed to flush buffer to file")?;
+    Ok(())
+}
+
+/// Writes a periodic `CRYSTAL` with `PRIMVEC` and `PRIMCOORD` (atomic numbers, Cartesian Å)
+/// to any writer. Readable by XCrySDen and VESTA.
+pub fn write_xsf<T: CifRepresentable, W: Write>(structure: &T, mut writer: W) -> Result<()> {
+    let atoms = structure.atoms();
+    let lattice = structure.lattice();
+
+    writeln!(writer, "CRYSTAL")?;
+    writeln!(writer, "PRIMVEC")?;
+    for column in lattice.matrix.column_iter() {
+        writeln!(writer, "  {:>16.10} {:>16.10} {:>16.10}", column.x, column.y, column.z)?;
+    }
+    writeln!(writer, "PRIMCOORD")?;
+    writeln!(writer, "  {} 1", atoms.len())?;
+    for atom in atoms.iter() {
+        let z = elements::element(&atom.element).map(|e| e.number).ok_or_else(|| anyhow!("Unknown element '{}' for XSF", atom.element))?;
+        let r = lattice.to_cartesian(&atom.fractional_coords);
+        writeln!(writer, "  {:>3} {:>16.10} {:>16.10} {:>16.10}", z, r.x, r.y, r.z)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{Atom, ComponentType, Crystal, Lattice};
+    use nalgebra::Vector3;
+
+    #[test]
+    fn test_primcoord_uses_atomic_numbers() {
+        let lattice = Lattice::from_parameters(4.0, 4.0, 4.0, 90.0, 90.0, 90.0).unwrap();
+        let atoms = vec![
+            Atom::new("Mg", Vector3::new(0.0, 0.0, 0.0), ComponentType::Unknown),
+            Atom::new("O", Vector3::new(0.5, 0.5, 0.5), ComponentType::Unknown),
+        ];
+        let crystal = Crystal { lattice, atoms };
+
+        let mut buffer = Vec::new();
+        write_xsf(&crystal, &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.starts_with("CRYSTAL\nPRIMVEC\n"));
+        assert!(text.contains("PRIMCOORD\n  2 1\n   12"));
+        assert!(text.ends_with("    8     2.0000000000     2.0000000000     2.0000000000\n"));
+    }
+}
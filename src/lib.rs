@@ -7,23 +7,33 @@ pub mod math;
 pub mod analysis;
 pub mod synthesis;
 pub mod chemistry; // NEW: Export the chemistry module
+pub mod batch;
 
 // ============================================================================
 // RE-EXPORTS (Public API)
 // ============================================================================
-pub use crate::core::structure::{Atom, Crystal, Lattice, Molecule, ComponentType};
-pub use crate::core::connectivity::MoleculeFinder;
-pub use crate::io::{parser, writer};
+pub use crate::core::structure::{Atom, AtomProperty, Crystal, Lattice, Molecule, ComponentType};
+pub use crate::core::bonding::BondingRule;
+pub use crate::core::connectivity::{Dimensionality, MoleculeFinder};
+pub use crate::core::primitive::{ReducedCell, DEFAULT_SYMPREC};
+pub use crate::core::symmetry::{CrystalSystem, SymmetryDataset};
+pub use crate::io::{cp2k, espresso, extxyz, format, gulp, json, lammps, parser, pdb, poscar, vasp, volumetric, writer, xsf};
+pub use crate::io::format::{FormatRegistry, StructureFrame, StructureReader, StructureWriter};
+pub use crate::io::volumetric::VolumetricData;
 
-pub use crate::synthesis::builder::SlabBuilder;
+pub use crate::synthesis::builder::{SlabBuilder, SlabGeometry};
+pub use crate::io::provenance::SlabProvenance;
 pub use crate::synthesis::population::SlabPopulator;
 pub use crate::synthesis::ionic::{IonicReconstructor, ReconstructionMode};
-pub use crate::analysis::topology::VoidCrawler;
+pub use crate::analysis::relaxation::{RelaxationOptions, RelaxationReport};
+pub use crate::analysis::report::{PhaseReport, SurfaceReport};
+pub use crate::analysis::topology::{DensityProfile, SafeCut, VoidCrawler};
 pub use crate::chemistry::tagging::SemanticTagger; // NEW
 
 use anyhow::{Result, Context};
 use std::path::PathBuf;
-use nalgebra::Vector3;
+use crate::core::structure::{embed_in_plane, PROP_BULK_IMAGE, PROP_BULK_INDEX};
+use nalgebra::{Matrix2, Vector3};
 
 // ============================================================================
 // HIGH-LEVEL INTERFACE
@@ -37,6 +47,14 @@ pub struct SurfaceConfig {
     pub vacuum: f64,
     pub offset: Option<f64>,
     pub reconstruct: bool,
+    /// Electron density of the bulk (CHGCAR/cube); when set, cuts are ranked by the minima of
+    /// its planar average instead of hard-sphere gaps.
+    pub density: Option<VolumetricData>,
+    /// Which atom pairs are bonded, for molecule detection (`find_molecules`) and bond output.
+    pub bonding: BondingRule,
+    /// Reduce the bulk to its Niggli-reduced primitive cell (with this site tolerance, Å) before
+    /// cutting. `miller_indices` stay in the input cell and are mapped to the primitive one.
+    pub primitive: Option<f64>,
 
     // --- NEW: Semantic Configuration ---
     /// Path to the original input CIF (required for MOFid execution).
@@ -47,14 +65,105 @@ pub struct SurfaceConfig {
     pub mofid_output_root: Option<PathBuf>,
 }
 
+impl Default for SurfaceConfig {
+    /// A (001) slab of 15 Å with 15 Å of vacuum, the CLI defaults; everything optional is off.
+    fn default() -> Self {
+        Self {
+            miller_indices: [0, 0, 1],
+            thickness: 15.0,
+            vacuum: 15.0,
+            offset: None,
+            reconstruct: false,
+            density: None,
+            bonding: BondingRule::default(),
+            primitive: None,
+            input_cif_path: None,
+            enable_mofid: false,
+            mofid_output_root: None,
+        }
+    }
+}
+
+impl SurfaceConfig {
+    /// Discrete molecules of the bulk under the configured bonding rule, as passed to
+    /// `generate_surface` (none for extended networks).
+    pub fn find_molecules(&self, crystal: &Crystal) -> Result<Vec<Molecule>> {
+        MoleculeFinder::with_rule(self.bonding.clone()).find_discrete_molecules(crystal)
+    }
+
+    /// Periodicity of the bulk's bonded network under the configured bonding rule
+    /// (molecular crystal, chains, layers or framework).
+    pub fn dimensionality(&self, crystal: &Crystal) -> Dimensionality {
+        MoleculeFinder::with_rule(self.bonding.clone()).dimensionality(crystal)
+    }
+}
+
+/// Output of `generate_surface_with_provenance`.
+#[derive(Debug, Clone)]
+pub struct GeneratedSurface {
+    pub slab: Crystal,
+    /// Geometry the slab was built with (layer spacing, layer count; `basis` is the slab cell),
+    /// for layer-based outputs such as relax masks and k-point meshes.
+    pub geometry: SlabGeometry,
+    /// How the slab was cut from the bulk (written to output CIFs).
+    pub provenance: SlabProvenance,
+    /// Summary of every phase (`to_string()` gives the text report).
+    pub report: SurfaceReport,
+}
+
+impl GeneratedSurface {
+    /// Repeats the slab in-plane (`in_plane` acts on slab a and b, e.g. `diag(2, 2)` or
+    /// [[2, -1], [1, 1]] for √3×√3 on a hexagonal surface), keeping the provenance and the
+    /// slab atoms' bulk images consistent.
+    pub fn in_plane_supercell(&self, in_plane: &Matrix2<i32>) -> Result<GeneratedSurface> {
+        let (mut slab, images) = self.slab.transform_with_images(&embed_in_plane(in_plane), false)?;
+
+        // Slab translations in bulk lattice coordinates
+        let to_bulk = self.provenance.transformation;
+        for (atom, image) in slab.atoms.iter_mut().zip(&images) {
+            if let Some((index, bulk_image)) = atom.bulk_site() {
+                atom.set_property(PROP_BULK_INDEX, index as i64);
+                atom.set_property(PROP_BULK_IMAGE, (bulk_image + to_bulk * image).cast::<f64>());
+            }
+        }
+
+        let geometry = SlabGeometry { basis: slab.lattice.matrix, ..self.geometry.clone() };
+        let mut provenance = self.provenance.clone();
+        provenance.transformation = to_bulk * embed_in_plane(in_plane);
+        let mut report = self.report.clone();
+        report.n_atoms = slab.atoms.len();
+        report.add_phase(
+            "Supercell",
+            format!("[[{}, {}], [{}, {}]] in-plane, {} cells.",
+                in_plane[(0, 0)], in_plane[(0, 1)], in_plane[(1, 0)], in_plane[(1, 1)], images.len() / self.slab.atoms.len().max(1)),
+        );
+        Ok(GeneratedSurface { slab, geometry, provenance, report })
+    }
+}
+
 /// The Master Pipeline function.
 pub fn generate_surface(
     crystal: &mut Crystal, // CHANGED: Mutable to allow tagging
     molecules: &[Molecule], 
     config: &SurfaceConfig
 ) -> Result<(Crystal, String)> {
+    let generated = generate_surface_with_provenance(crystal, molecules, config)?;
+    Ok((generated.slab, generated.report.to_string()))
+}
+
+/// Runs the pipeline and also records the slab's provenance (Miller indices, layers, cut offset,
+/// bulk cell and integer transformation from the bulk).
+pub fn generate_surface_with_provenance(
+    crystal: &mut Crystal,
+    molecules: &[Molecule],
+    config: &SurfaceConfig
+) -> Result<GeneratedSurface> {
     
-    let mut report_buffer = String::new();
+    let mut report = SurfaceReport {
+        requested_thickness: config.thickness,
+        vacuum: config.vacuum,
+        ..Default::default()
+    };
 
     // 0. SEMANTIC PHASE (The Integration Logic)
     if config.enable_mofid {
@@ -68,72 +177,110 @@ pub fn generate_surface(
             
             // Map Semantics to Geometry
             let tag_msg = SemanticTagger::tag_structure(crystal, &artifacts)?;
-            report_buffer.push_str(&format!("--- Chemistry ---\n{}\n", tag_msg));
+            report.add_phase("Chemistry", tag_msg);
         } else {
-            report_buffer.push_str("Warning: --with-mofid requested but input path lost. Skipping semantics.\n");
+            report.warnings.push("--with-mofid requested but input path lost. Skipping semantics.".to_string());
         }
     }
 
-    // 1. MATH PHASE
+    // 1. REDUCTION PHASE (optional)
+    // Molecules found in the input cell index its atoms, so they are found again in the new one
+    let reduced = match config.primitive {
+        Some(_) if config.enable_mofid || config.density.is_some() => {
+            anyhow::bail!("Reduction to the primitive cell cannot be combined with MOFid or a density grid, which refer to the input cell.");
+        }
+        Some(symprec) => {
+            let reduced = crystal.find_primitive(symprec)?;
+            let hkl = reduced.miller_to_reduced(config.miller_indices);
+            let molecules = if molecules.is_empty() { Vec::new() } else { config.find_molecules(&reduced.crystal)? };
+            report.add_phase(
+                "Reduction",
+                format!(
+                    "Primitive cell of {} atoms; ({} {} {}) is ({} {} {}) in it.",
+                    reduced.crystal.atoms.len(),
+                    config.miller_indices[0], config.miller_indices[1], config.miller_indices[2],
+                    hkl[0], hkl[1], hkl[2]
+                ),
+            );
+            Some((reduced.crystal, hkl, molecules))
+        }
+        None => None,
+    };
+    let (bulk, miller_indices, molecules) = match &reduced {
+        Some((bulk, hkl, molecules)) => (bulk, *hkl, molecules.as_slice()),
+        None => (&*crystal, config.miller_indices, molecules),
+    };
+    report.miller_indices = miller_indices;
+
+    // 2. MATH PHASE
     let builder = SlabBuilder::new(
-        config.miller_indices[0], 
-        config.miller_indices[1], 
-        config.miller_indices[2], 
+        miller_indices[0], 
+        miller_indices[1], 
+        miller_indices[2], 
         config.thickness, 
         config.vacuum
     );
-    let geometry = builder.compute_geometry(crystal)?;
+    let geometry = builder.compute_geometry(bulk)?;
 
-    // 2. TOPOLOGY PHASE
+    // 3. TOPOLOGY PHASE
     let offset = if let Some(u) = config.offset {
+        report.add_phase("Topology", "Cut offset given in the configuration.");
         u
     } else {
         let hkl = Vector3::new(
-            config.miller_indices[0] as f64, 
-            config.miller_indices[1] as f64, 
-            config.miller_indices[2] as f64
+            miller_indices[0] as f64, 
+            miller_indices[1] as f64, 
+            miller_indices[2] as f64
         );
-        let normal: Vector3<f64> = crystal.lattice.reciprocal_matrix * hkl;
+        let normal: Vector3<f64> = bulk.lattice.reciprocal_matrix * hkl;
         
-        let crawler = VoidCrawler::new(crystal, &normal);
-        crawler.find_safe_offsets().first().map(|c| c.offset_z).unwrap_or(0.0)
+        let mut crawler = VoidCrawler::new(bulk, &normal);
+        if let Some(grid) = &config.density {
+            grid.check_cell(&bulk.lattice)?;
+            crawler = crawler.with_density(grid.planar_average(miller_indices)?);
+        }
+        report.safe_cuts = crawler.find_safe_offsets();
+        match report.safe_cuts.first().copied() {
+            Some(best) => {
+                let message = match best.density {
+                    Some(rho) => format!("Lowest of {} density minima (ρ = {:.4} e/Å³).", report.safe_cuts.len(), rho),
+                    None => format!("Best of {} candidate cuts (gap {:.3} Å).", report.safe_cuts.len(), best.gap_size),
+                };
+                report.add_phase("Topology", message);
+                best.offset_z
+            }
+            None => {
+                report.warnings.push("No void found along the normal; cutting at offset 0.".to_string());
+                0.0
+            }
+        }
     };
 
-    // 3. SYNTHESIS PHASE
+    // 4. SYNTHESIS PHASE
     // Note: SlabPopulator reads `component_type` from atoms. 
     // If tagged in Phase 0, it can now make smarter decisions (future upgrade).
-    let mut slab_atoms = SlabPopulator::populate(crystal, &geometry, molecules, offset)?;
+    let mut slab_atoms = SlabPopulator::populate(bulk, &geometry, molecules, offset)?;
 
-    // 4. PHYSICS PHASE
+    // 5. PHYSICS PHASE
     let slab_lattice = crate::core::structure::Lattice::new(geometry.basis)
         .map_err(|e| anyhow::anyhow!(e))?;
         
     let mode = if config.reconstruct { ReconstructionMode::DipoleCorrection } else { ReconstructionMode::None };
     
     let phys_report = IonicReconstructor::stabilize(&mut slab_atoms, &slab_lattice, mode)?;
+    report.add_phase("Physics", phys_report);
 
-    // 5. REPORT GENERATION
-    let actual_material_thickness = geometry.n_layers as f64 * geometry.d_hkl;
-    
-    let verbose_output = format!(
-        "{}\n--- Surface Generation Report ---\n\
-         • Plane:           ({} {} {})\n\
-         • Interplanar Spacing: {:.4} Å\n\
-         • Quantization:    Requested {:.2} Å → {} Full Layers\n\
-         • Final Thickness: {:.4} Å (Material) + {:.2} Å (Vacuum)\n\
-         • Cut Offset:      {:.4} Å\n\
-         • Physics:         {}", 
-        report_buffer,
-        config.miller_indices[0], config.miller_indices[1], config.miller_indices[2],
-        geometry.d_hkl,
-        config.thickness, geometry.n_layers,
-        actual_material_thickness, config.vacuum,
-        offset,
-        phys_report
-    );
+    // 6. REPORT GENERATION
+    report.d_hkl = geometry.d_hkl;
+    report.n_layers = geometry.n_layers;
+    report.material_thickness = geometry.n_layers as f64 * geometry.d_hkl;
+    report.cut_offset = offset;
+    report.n_atoms = slab_atoms.len();
 
-    Ok((
-        Crystal { lattice: slab_lattice, atoms: slab_atoms },
-        verbose_output
-    ))
+    Ok(GeneratedSurface {
+        slab: Crystal { lattice: slab_lattice, atoms: slab_atoms },
+        provenance: geometry.provenance(&bulk.lattice, miller_indices, offset),
+        geometry,
+        report,
+    })
 }
\ No newline at end of file
@@ -1,11 +1,26 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
+use nalgebra::Matrix2;
 
 use crystal_surface_generator::{
-    parser, writer, generate_surface, SurfaceConfig, MoleculeFinder
+    generate_surface_with_provenance, volumetric, BondingRule, Crystal, SurfaceConfig
 };
+use crystal_surface_generator::format::{
+    CifFormat, Cp2kFormat, EspressoFormat, FormatRegistry, GulpFormat, StructureWriter, WriteContext,
+};
+use crystal_surface_generator::batch::{self, BatchOptions, BatchStatus};
+use crystal_surface_generator::core::bonding;
+use crystal_surface_generator::core::primitive::DEFAULT_SYMPREC;
+use crystal_surface_generator::parser::{self, CifOptions, DisorderResolution};
+use crystal_surface_generator::analysis::relaxation::{analyze_relaxation, RelaxationOptions};
+use crystal_surface_generator::espresso::EspressoOptions;
+use crystal_surface_generator::cp2k::Cp2kOptions;
+use crystal_surface_generator::gulp::GulpOptions;
 
 #[derive(Parser)]
 #[command(author, version, about = "Ultimate Crystal Surface Generator")]
@@ -16,18 +31,29 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Generates a surface slab from a CIF file.
+    /// Generates a surface slab from a structure file (CIF, POSCAR, extended XYZ, pymatgen JSON, VASP or pw.x output).
+    /// Formats are detected from the file names (and the input's contents); "-" reads from
+    /// stdin / writes to stdout.
     Generate {
         #[arg(short, long)]
         input: PathBuf,
 
         #[arg(short, long)]
         output: PathBuf,
-        
-        h: i32, 
-        k: i32, 
+
+        h: i32,
+        k: i32,
         l: i32,
 
+        /// Input format (cif, poscar, extxyz, json), overriding detection.
+        #[arg(long)]
+        input_format: Option<String>,
+
+        /// Output format (cif, poscar, extxyz, json, lammps, espresso, cp2k, gulp, pdb, xsf), overriding detection.
+        /// Needed when the output name does not tell the format; stdout defaults to CIF.
+        #[arg(long)]
+        output_format: Option<String>,
+
         #[arg(long, default_value_t = 15.0)]
         thickness: f64,
 
@@ -37,6 +63,11 @@ enum Commands {
         #[arg(long)]
         offset: Option<f64>,
 
+        /// Rank cuts by the planar-averaged electron density of this CHGCAR/PARCHG or cube
+        /// file (same cell as the input) instead of atomic radii.
+        #[arg(long)]
+        density: Option<PathBuf>,
+
         /// Enable Tasker III dipole reconstruction (Physics).
         #[arg(long)]
         reconstruct: bool,
@@ -56,7 +87,224 @@ enum Commands {
         /// Prefer linker-terminated surfaces (cap nodes)
         #[arg(long)]
         expose_linkers: bool,
+
+        /// Disordered sites: keep the majority group, keep all sites, or split into one slab per group.
+        #[arg(long, value_enum, default_value_t = DisorderMode::Majority)]
+        disorder: DisorderMode,
+
+        /// Keep this disorder group in every assembly (overrides --disorder).
+        #[arg(long)]
+        disorder_group: Option<i32>,
+
+        /// POSCAR / pw.x / CP2K output: relax this many layers at each surface and freeze the rest.
+        /// GULP output: this many layers below the top surface form region 1.
+        #[arg(long)]
+        relax_layers: Option<usize>,
+
+        /// pw.x / CP2K output: target k-point spacing in 1/Å (2π included, as VASP's KSPACING).
+        #[arg(long, default_value_t = 0.25)]
+        k_spacing: f64,
+
+        /// GULP output: potential library to load (e.g. "catlow").
+        #[arg(long)]
+        gulp_library: Option<String>,
+
+        /// Also write the generation report (phases, warnings, numbers) as JSON to this file.
+        #[arg(long)]
+        report_json: Option<PathBuf>,
+
+        /// Repeat the slab in-plane: "2x2", or the matrix "a1,b1,a2,b2" whose columns are the
+        /// new a and b in slab a, b coordinates (e.g. "2,-1,1,1" for √3×√3 on a hexagonal surface).
+        #[arg(long)]
+        supercell: Option<String>,
+
+        /// Reduce the bulk to its Niggli-reduced primitive cell before cutting. The Miller
+        /// indices stay in the input cell's setting and are mapped to the primitive cell.
+        #[arg(long)]
+        primitive: bool,
+
+        /// Site tolerance (Å) for finding the primitive cell.
+        #[arg(long, default_value_t = DEFAULT_SYMPREC)]
+        symprec: f64,
+
+        #[command(flatten)]
+        bonding: BondingArgs,
     },
+
+    /// Generates slabs for every structure in a directory or glob and every given plane.
+    /// Slabs are written to <output>/<name>/<hkl>/<termination>.cif, with manifest.csv and
+    /// manifest.json listing every success and failure. A bad input does not stop the batch.
+    Batch {
+        /// Directory (every recognised structure file in it) or glob pattern, e.g. "mofs/*.cif".
+        #[arg(short, long)]
+        input: String,
+
+        /// Output root directory.
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Miller indices, e.g. "1 0 0", "1,-1,0" or "111"; repeat the flag or list several.
+        #[arg(long = "hkl", required = true, num_args = 1..)]
+        hkl: Vec<String>,
+
+        #[arg(long, default_value_t = 15.0)]
+        thickness: f64,
+
+        #[arg(long, default_value_t = 15.0)]
+        vacuum: f64,
+
+        /// Enable Tasker III dipole reconstruction (Physics).
+        #[arg(long)]
+        reconstruct: bool,
+
+        /// Terminations per plane (t0, t1, ...), from the best-ranked safe cuts.
+        #[arg(long, default_value_t = 1)]
+        terminations: usize,
+
+        /// Disordered sites: keep the majority group, keep all sites, or split into one slab per group.
+        #[arg(long, value_enum, default_value_t = DisorderMode::Majority)]
+        disorder: DisorderMode,
+
+        /// Keep this disorder group in every assembly (overrides --disorder).
+        #[arg(long)]
+        disorder_group: Option<i32>,
+
+        #[command(flatten)]
+        bonding: BondingArgs,
+    },
+
+    /// Compares a relaxed slab (vasprun.xml, OUTCAR, pw.x output, CONTCAR, ...) with the
+    /// generated one: per-layer displacement along the normal, interlayer spacing changes
+    /// relative to d_hkl and the rumpling of each plane. Atoms are matched by index.
+    Relaxation {
+        /// The slab as generated: the CIF (layers and d_hkl are read from it) or the DFT input.
+        #[arg(long)]
+        ideal: PathBuf,
+
+        /// The relaxed structure.
+        #[arg(long)]
+        relaxed: PathBuf,
+
+        /// Interplanar spacing (Å); read from the ideal CIF's slab provenance when omitted.
+        #[arg(long)]
+        d_hkl: Option<f64>,
+
+        /// Also write the report as JSON to this file ("-" for stdout instead of the table).
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+
+    /// Reports the space group of a structure: number and symbols, the transformation to the
+    /// standard conventional cell, and the site symmetry and Wyckoff multiplicity of every atom (Wyckoff letters are not assigned yet).
+    Symmetry {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Site tolerance (Å): how far an atom may sit from the image of another.
+        #[arg(long, default_value_t = DEFAULT_SYMPREC)]
+        symprec: f64,
+    },
+}
+
+/// Bonding criteria for molecule detection and the bonds written to CIF, LAMMPS and PDB files.
+#[derive(Args, Debug)]
+struct BondingArgs {
+    /// Bonded when closer than the sum of covalent radii plus this (Å).
+    #[arg(long, default_value_t = BondingRule::default().tolerance)]
+    bond_tolerance: f64,
+
+    /// Tolerance (Å) for pairs involving a metal.
+    #[arg(long, default_value_t = BondingRule::default().metal_tolerance)]
+    metal_bond_tolerance: f64,
+
+    /// Fixed cutoff for an element pair, e.g. "Zr-O=2.6"; repeat for several pairs.
+    #[arg(long = "bond-pair")]
+    bond_pairs: Vec<String>,
+
+    /// Ignore the elements and bond every pair closer than this (Å).
+    #[arg(long)]
+    bond_cutoff: Option<f64>,
+}
+
+impl BondingArgs {
+    fn rule(&self) -> Result<BondingRule> {
+        let mut rule = BondingRule {
+            tolerance: self.bond_tolerance,
+            metal_tolerance: self.metal_bond_tolerance,
+            uniform_cutoff: self.bond_cutoff,
+            ..BondingRule::default()
+        };
+        for text in &self.bond_pairs {
+            let (a, b, cutoff) = bonding::parse_pair_cutoff(text)?;
+            rule = rule.with_pair_cutoff(&a, &b, cutoff)?;
+        }
+        Ok(rule)
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DisorderMode {
+    Majority,
+    KeepAll,
+    Split,
+}
+
+/// CIF disorder handling from `--disorder` / `--disorder-group`.
+fn disorder_resolution(mode: DisorderMode, group: Option<i32>) -> DisorderResolution {
+    match (group, mode) {
+        (Some(group), _) => DisorderResolution::Group(group),
+        (None, DisorderMode::Majority) => DisorderResolution::Majority,
+        (None, DisorderMode::KeepAll) => DisorderResolution::KeepAll,
+        (None, DisorderMode::Split) => DisorderResolution::Split,
+    }
+}
+
+/// Output formats that can hold fixed atoms (`--relax-layers`).
+const RELAX_FORMATS: [&str; 4] = ["poscar", "espresso", "cp2k", "gulp"];
+
+/// `--supercell`: "2x3" or "a1,b1,a2,b2" (the 2×2 matrix row by row).
+fn parse_in_plane_supercell(text: &str) -> Result<Matrix2<i32>> {
+    let integer = |s: &str| s.trim().parse::<i32>().with_context(|| format!("Invalid supercell '{}'", text));
+    if let Some((na, nb)) = text.split_once(['x', 'X']) {
+        return Ok(Matrix2::new(integer(na)?, 0, 0, integer(nb)?));
+    }
+    let entries = text.split(',').map(integer).collect::<Result<Vec<_>>>()?;
+    match entries[..] {
+        [a1, b1, a2, b2] => Ok(Matrix2::new(a1, b1, a2, b2)),
+        _ => Err(anyhow!("Expected NAxNB or four comma-separated integers, got '{}'", text)),
+    }
+}
+
+/// "-" stands for stdin / stdout.
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Output path for one of several configurations: "slab.cif" -> "slab_group2.cif", or
+/// "slab_<block>.cif" / "slab_<block>_group2.cif" for multi-block CIF inputs.
+fn configuration_path(output: &Path, block: Option<&str>, group: Option<i32>, index: usize) -> PathBuf {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let group = group.map(|g| format!("group{}", g));
+    let suffix = match (block, group) {
+        (Some(block), Some(group)) => format!("{}_{}", block, group),
+        (Some(block), None) => block.to_string(),
+        (None, Some(group)) => group,
+        (None, None) => format!("conf{}", index + 1),
+    };
+    let name = match output.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, suffix, ext),
+        None => format!("{}_{}", stem, suffix),
+    };
+    output.with_file_name(name)
+}
+
+/// Opens an output file, or stdout for "-".
+fn open_output(path: &Path) -> Result<Box<dyn Write>> {
+    if is_stdio(path) {
+        return Ok(Box::new(BufWriter::new(io::stdout().lock())));
+    }
+    let file = File::create(path).with_context(|| format!("Failed to create output file: {:?}", path))?;
+    Ok(Box::new(BufWriter::new(file)))
 }
 
 fn main() -> Result<()> {
@@ -64,38 +312,115 @@ fn main() -> Result<()> {
     let start_time = Instant::now();
 
     match cli.command {
-        Commands::Generate { 
-            input, output, h, k, l, 
-            thickness, vacuum, offset, reconstruct, 
+        Commands::Generate {
+            input, output, h, k, l,
+            input_format, output_format,
+            thickness, vacuum, offset, density, reconstruct,
             with_mofid, mofid_work_dir,
             expose_nodes, expose_linkers,
+            disorder, disorder_group, relax_layers, k_spacing,
+            gulp_library, report_json, supercell, primitive, symprec, bonding,
         } => {
-            println!("--- Crystal Surface Generator ---");
+            // Progress goes to stderr when the structure itself goes to stdout
+            let to_stdout = is_stdio(&output);
+            macro_rules! status {
+                ($($arg:tt)*) => {
+                    if to_stdout { eprintln!($($arg)*) } else { println!($($arg)*) }
+                };
+            }
+
+            status!("--- Crystal Surface Generator ---");
+            let in_plane_supercell = supercell.as_deref().map(parse_in_plane_supercell).transpose()?;
+
+            // Formats
+            let mut registry = FormatRegistry::default();
+            registry.register_reader(CifFormat {
+                options: CifOptions {
+                    disorder: disorder_resolution(disorder, disorder_group),
+                    ..CifOptions::default()
+                },
+                ..Default::default()
+            });
+            registry.register_writer(EspressoFormat { options: EspressoOptions { k_spacing, ..Default::default() } });
+            registry.register_writer(Cp2kFormat { options: Cp2kOptions { k_spacing, ..Default::default() } });
+            registry.register_writer(GulpFormat {
+                options: GulpOptions { title: format!("({} {} {}) slab", h, k, l), library: gulp_library.clone(), ..Default::default() },
+            });
+
+            let writer: &dyn StructureWriter = match &output_format {
+                Some(name) => registry.writer(name).ok_or_else(|| anyhow!("Unknown output format '{}'", name))?,
+                None if to_stdout => registry.writer("cif").expect("CIF writer is built in"),
+                None => registry.detect_writer(&output).ok_or_else(|| {
+                    anyhow!(
+                        "Cannot tell the output format from {:?}; use one of {} or set --output-format.",
+                        output, registry.writer_patterns().join(", ")
+                    )
+                })?,
+            };
+
+            let bonding = bonding.rule()?;
 
             // Hard validation
             if expose_nodes && expose_linkers {
                 anyhow::bail!("--expose-nodes and --expose-linkers cannot be used together.");
             }
+            if relax_layers.is_some() && !RELAX_FORMATS.contains(&writer.name()) {
+                anyhow::bail!("--relax-layers needs a POSCAR (POSCAR or *.vasp), pw.x (*.pwi), CP2K (*.inp) or GULP (*.gin) output.");
+            }
+            if gulp_library.is_some() && writer.name() != "gulp" {
+                anyhow::bail!("--gulp-library needs a GULP (*.gin) output.");
+            }
+            if primitive && (with_mofid || density.is_some()) {
+                anyhow::bail!("--primitive cannot be combined with --with-mofid or --density, which refer to the input cell.");
+            }
 
             // 1. Parsing
-            println!("Reading structure from {:?}...", input);
-            let mut crystal = parser::from_cif(&input)?;
-            println!("-> Loaded {} atoms.", crystal.atoms.len());
-
-            // 2. Molecule Analysis
-            println!("Analyzing connectivity...");
-            let finder = MoleculeFinder::new(2.0);
-            let molecules = finder.find_molecules(&crystal)?;
-            if !molecules.is_empty() {
-                println!("-> Detected {} discrete molecules.", molecules.len());
+            let (contents, source) = if is_stdio(&input) {
+                status!("Reading structure from stdin...");
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text).context("Could not read stdin")?;
+                (text, None)
+            } else {
+                status!("Reading structure from {:?}...", input);
+                let text = std::fs::read_to_string(&input).with_context(|| format!("Could not read structure file: {:?}", input))?;
+                (text, Some(input.as_path()))
+            };
+            let reader = match &input_format {
+                Some(name) => registry.reader(name).ok_or_else(|| anyhow!("Unknown input format '{}'", name))?,
+                None => registry.detect_reader(source, &contents)?,
+            };
+            let frames = reader
+                .read_str(&contents)
+                .with_context(|| format!("Failed to parse {} input {:?}", reader.name(), input))?;
+            let n_configurations = frames.len();
+            let n_blocks = frames.iter().map(|f| &f.name).collect::<HashSet<_>>().len();
+            let multi_block = n_blocks > 1;
+            if multi_block {
+                status!("-> {} structures ({} data blocks).", n_configurations, n_blocks);
+            } else if n_configurations > 1 {
+                status!("-> Disorder split into {} ordered configurations.", n_configurations);
+                if to_stdout && !writer.multi_frame() {
+                    anyhow::bail!("{} configurations cannot share stdout as {}; write to a file or use extxyz.", n_configurations, writer.name());
+                }
             }
+            let density_grid = match &density {
+                Some(path) => {
+                    status!("Reading volumetric data from {:?}...", path);
+                    let grid = volumetric::from_volumetric(path)?;
+                    status!("-> {}×{}×{} grid.", grid.grid[0], grid.grid[1], grid.grid[2]);
+                    if offset.is_some() {
+                        status!("Warning: --offset is set; the density is not used.");
+                    }
+                    Some(grid)
+                }
+                None => None,
+            };
 
-            // 3. MOFid
-            if with_mofid {
-                println!("-> MOFid integration enabled.");
-            }
+            // Multi-frame formats collect every configuration into one output
+            let mut shared_output = if writer.multi_frame() { Some(open_output(&output)?) } else { None };
+            let mut n_frames = 0;
 
-            println!(
+            status!(
                 "Surface mode: {}",
                 if expose_nodes {
                     "node-terminated"
@@ -106,45 +431,220 @@ fn main() -> Result<()> {
                 }
             );
 
-            // 4. Execution
-            println!("Generating ({} {} {}) slab...", h, k, l);
+            for (index, frame) in frames.into_iter().enumerate() {
+                // Block names only tell structures apart in multi-block inputs
+                let block = frame.name.clone().filter(|_| multi_block);
+                let mut crystal = frame.crystal;
+                if n_configurations > 1 {
+                    status!("\n=== Configuration {}/{} ===", index + 1, n_configurations);
+                }
+                status!("-> Loaded {} atoms.", crystal.atoms.len());
+                let config = SurfaceConfig {
+                    miller_indices: [h, k, l],
+                    thickness,
+                    vacuum,
+                    offset,
+                    reconstruct,
+                    density: density_grid.clone(),
+                    bonding: bonding.clone(),
+                    primitive: primitive.then_some(symprec),
+
+                    // MOFid integration
+                    input_cif_path: source.map(Path::to_path_buf),
+                    enable_mofid: with_mofid,
+                    mofid_output_root: mofid_work_dir.clone(),
 
-            let config = SurfaceConfig {
-                miller_indices: [h, k, l],
+                    // NOTE:
+                    // Your current SurfaceConfig DOES NOT expose:
+                    //   expose_nodes / expose_linkers
+                    //
+                    // So we DO NOT set them here.
+                    // The CLI flags are parsed and validated,
+                    // and you can wire them into SurfaceConfig later
+                    // when you add the fields in lib.rs.
+                };
+
+                // 2. Molecule Analysis
+                status!("Analyzing connectivity...");
+                let molecules = config.find_molecules(&crystal)?;
+                if !molecules.is_empty() {
+                    status!("-> Detected {} discrete molecules.", molecules.len());
+                } else if !crystal.atoms.is_empty() {
+                    status!("-> Extended bonded network ({}); cutting atom by atom.", config.dimensionality(&crystal));
+                }
+
+                // 3. MOFid
+                if with_mofid {
+                    status!("-> MOFid integration enabled.");
+                }
+
+                // 4. Execution
+                status!("Generating ({} {} {}) slab...", h, k, l);
+
+                let mut generated = generate_surface_with_provenance(&mut crystal, &molecules, &config)?;
+                if let Some(in_plane) = &in_plane_supercell {
+                    generated = generated.in_plane_supercell(in_plane)?;
+                }
+                let slab = generated.slab;
+
+                status!("\nSuccess!");
+                if let Some(summary) = &frame.disorder_summary {
+                    status!("--- Disorder ---\n{}", summary);
+                }
+                status!("{}", generated.report);
+                if let Some(report_path) = &report_json {
+                    let report_path = if n_configurations > 1 {
+                        configuration_path(report_path, block.as_deref(), frame.disorder_group, index)
+                    } else {
+                        report_path.clone()
+                    };
+                    let mut out = open_output(&report_path)?;
+                    serde_json::to_writer_pretty(&mut out, &generated.report).context("Failed to write JSON report")?;
+                    writeln!(out)?;
+                    out.flush().context("Failed to flush JSON report")?;
+                }
+
+                let mut context = WriteContext {
+                    geometry: Some(generated.geometry),
+                    relax_layers,
+                    provenance: Some(generated.provenance),
+                    bonding: Some(config.bonding.clone()),
+                    ..Default::default()
+                };
+                context.info.insert("miller".to_string(), format!("{} {} {}", h, k, l));
+                if let Some(group) = frame.disorder_group {
+                    context.info.insert("disorder_group".to_string(), group.to_string());
+                }
+                if let Some(mask) = context.relax_mask(&slab)? {
+                    status!(
+                        "-> Selective dynamics: {} of {} atoms free to relax.",
+                        mask.iter().filter(|&&free| free).count(),
+                        mask.len()
+                    );
+                }
+
+                if let Some(out) = shared_output.as_mut() {
+                    writer.write(&slab, &context, out)?;
+                    n_frames += 1;
+                    continue;
+                }
+
+                let output_path = if n_configurations > 1 {
+                    configuration_path(&output, block.as_deref(), frame.disorder_group, index)
+                } else {
+                    output.clone()
+                };
+                status!("Writing {} output to {:?}...", writer.name(), output_path);
+                let mut out = open_output(&output_path)?;
+                writer.write(&slab, &context, &mut out)?;
+                out.flush().context("Failed to flush output")?;
+            }
+
+            if let Some(mut out) = shared_output {
+                status!("Wrote {} frame(s) to {:?}.", n_frames, output);
+                out.flush().context("Failed to flush output")?;
+            }
+
+            status!(
+                "Done in {:.2?}",
+                start_time.elapsed()
+            );
+        }
+
+        Commands::Batch {
+            input, output, hkl,
+            thickness, vacuum, reconstruct, terminations,
+            disorder, disorder_group, bonding,
+        } => {
+            println!("--- Crystal Surface Generator (batch) ---");
+
+            let options = BatchOptions {
+                miller_indices: hkl.iter().map(|text| batch::parse_miller(text)).collect::<Result<_>>()?,
                 thickness,
                 vacuum,
-                offset,
                 reconstruct,
-
-                // MOFid integration
-                input_cif_path: Some(input),
-                enable_mofid: with_mofid,
-                mofid_output_root: mofid_work_dir,
-
-                // NOTE:
-                // Your current SurfaceConfig DOES NOT expose:
-                //   expose_nodes / expose_linkers
-                //
-                // So we DO NOT set them here.
-                // The CLI flags are parsed and validated,
-                // and you can wire them into SurfaceConfig later
-                // when you add the fields in lib.rs.
+                terminations,
+                cif: CifOptions { disorder: disorder_resolution(disorder, disorder_group), ..CifOptions::default() },
+                bonding: bonding.rule()?,
             };
+            let registry = FormatRegistry::default();
+            let inputs = batch::collect_inputs(&input, &registry)?;
+            println!("{} input file(s) × {} plane(s) -> {:?}", inputs.len(), options.miller_indices.len(), output);
+            std::fs::create_dir_all(&output).with_context(|| format!("Could not create {:?}", output))?;
 
-            let (slab, report) = generate_surface(&mut crystal, &molecules, &config)?;
-
-            println!("\nSuccess!");
-            println!("{}", report);
-
-            println!("Writing output to {:?}...", output);
-            writer::to_cif(&slab, &output)?;
+            let manifest = batch::run_batch(&inputs, &output, &options, &registry, &mut |entry| match entry.status {
+                BatchStatus::Ok => println!(
+                    "  ok    {} ({}) {}: {} atoms",
+                    entry.structure, entry.hkl, entry.termination, entry.n_atoms.unwrap_or(0)
+                ),
+                BatchStatus::Error => println!(
+                    "  FAIL  {} {}: {}",
+                    entry.structure, entry.hkl, entry.error.as_deref().unwrap_or("unknown error")
+                ),
+            });
 
+            batch::write_manifest_csv(&manifest, &output.join("manifest.csv"))?;
+            batch::write_manifest_json(&manifest, &output.join("manifest.json"))?;
+            let failures = manifest.iter().filter(|e| e.status == BatchStatus::Error).count();
             println!(
-                "Done in {:.2?}",
+                "\n{} slab(s) written, {} failure(s); manifest in {:?}. Done in {:.2?}",
+                manifest.len() - failures,
+                failures,
+                output.join("manifest.csv"),
                 start_time.elapsed()
             );
         }
+
+        Commands::Relaxation { ideal, relaxed, d_hkl, json } => {
+            let registry = FormatRegistry::default();
+            let read_first = |path: &Path| -> Result<(Crystal, String)> {
+                let contents = std::fs::read_to_string(path).with_context(|| format!("Could not read structure file: {:?}", path))?;
+                let reader = registry.detect_reader(Some(path), &contents)?;
+                let frame = reader
+                    .read_str(&contents)
+                    .with_context(|| format!("Failed to parse {} file {:?}", reader.name(), path))?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow!("No structure in {:?}", path))?;
+                Ok((frame.crystal, contents))
+            };
+
+            let (ideal_slab, ideal_text) = read_first(&ideal)?;
+            let (relaxed_slab, _) = read_first(&relaxed)?;
+            let d_hkl = match d_hkl {
+                Some(d) => Some(d),
+                None => parser::slab_provenance_from_cif_str(&ideal_text).ok().flatten().map(|p| p.d_hkl),
+            };
+
+            let options = RelaxationOptions { d_hkl, ..RelaxationOptions::default() };
+            let report = analyze_relaxation(&ideal_slab, &relaxed_slab, &options)?;
+
+            if json.as_deref().is_none_or(|path| !is_stdio(path)) {
+                print!("{}", report);
+            }
+            if let Some(path) = &json {
+                let mut out = open_output(path)?;
+                serde_json::to_writer_pretty(&mut out, &report).context("Failed to write JSON report")?;
+                writeln!(out)?;
+                out.flush().context("Failed to flush JSON report")?;
+            }
+        }
+
+        Commands::Symmetry { input, symprec } => {
+            let registry = FormatRegistry::default();
+            let contents = std::fs::read_to_string(&input).with_context(|| format!("Could not read structure file: {:?}", input))?;
+            let reader = registry.detect_reader(Some(&input), &contents)?;
+            let frames = reader
+                .read_str(&contents)
+                .with_context(|| format!("Failed to parse {} file {:?}", reader.name(), input))?;
+            for frame in &frames {
+                if let Some(name) = frame.name.as_deref().filter(|_| frames.len() > 1) {
+                    println!("=== {} ===", name);
+                }
+                println!("{}", frame.crystal.symmetry(symprec)?);
+            }
+        }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
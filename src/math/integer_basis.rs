@@ -2,7 +2,7 @@ use nalgebra::Vector3;
 use anyhow::{Result, anyhow};
 
 /// Calculates the Greatest Common Divisor (Euclidean algorithm).
-fn gcd(a: i32, b: i32) -> i32 {
+pub(crate) fn gcd(a: i32, b: i32) -> i32 {
     let mut a = a.abs();
     let mut b = b.abs();
     while b != 0 {
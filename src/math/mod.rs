@@ -1,2 +1,3 @@
 pub mod lll;
-pub mod integer_basis;
\ No newline at end of file
+pub mod integer_basis;
+pub mod reduction;
@@ -0,0 +1,167 @@
+use nalgebra::{Matrix3, Vector3};
+use anyhow::{Result, bail};
+
+/// Iteration cap for the reduction loops (a valid cell converges in a few dozen steps).
+const MAX_ITERATIONS: usize = 1000;
+
+/// Niggli reduction (Křivý & Gruber 1976, with the tolerances of Grosse-Kunstleve et al. 2004).
+///
+/// `basis` holds the lattice vectors as columns. Returns the integer transformation `P`
+/// (determinant +1) such that `basis · P` is the Niggli-reduced basis. `tolerance` is relative
+/// to the squared cell edge, V^(2/3).
+pub fn niggli_reduce(basis: &Matrix3<f64>, tolerance: f64) -> Result<Matrix3<i32>> {
+    let volume = basis.determinant().abs();
+    if volume < 1e-12 {
+        bail!("Cannot reduce a cell with zero volume.");
+    }
+    let eps = tolerance * volume.powf(2.0 / 3.0);
+    let sign = |x: f64| if x > eps { 1 } else if x < -eps { -1 } else { 0 };
+
+    let mut p = Matrix3::<i32>::identity();
+    for _ in 0..MAX_ITERATIONS {
+        let b = basis * p.cast::<f64>();
+        let (a_, b_, c_) = (b.column(0).norm_squared(), b.column(1).norm_squared(), b.column(2).norm_squared());
+        let xi = 2.0 * b.column(1).dot(&b.column(2));
+        let eta = 2.0 * b.column(0).dot(&b.column(2));
+        let zeta = 2.0 * b.column(0).dot(&b.column(1));
+        let (l, m, n) = (sign(xi), sign(eta), sign(zeta));
+
+        let step = if a_ > b_ + eps || ((a_ - b_).abs() <= eps && xi.abs() > eta.abs() + eps) {
+            // A1: order a, b
+            Matrix3::new(0, -1, 0, -1, 0, 0, 0, 0, -1)
+        } else if b_ > c_ + eps || ((b_ - c_).abs() <= eps && eta.abs() > zeta.abs() + eps) {
+            // A2: order b, c
+            Matrix3::new(-1, 0, 0, 0, 0, -1, 0, -1, 0)
+        } else if l * m * n == 1 {
+            // A3: make all three angles acute
+            Matrix3::from_diagonal(&Vector3::new(l, m, n))
+        } else {
+            // A4: make all three angles obtuse (or right), using a right angle to keep det = +1
+            let flip = |s: i32| if s == 1 { -1 } else { 1 };
+            let mut flips = Vector3::new(flip(l), flip(m), flip(n));
+            if flips.x * flips.y * flips.z == -1 {
+                if let Some(k) = [l, m, n].iter().rposition(|&s| s == 0) {
+                    flips[k] = -1;
+                }
+            }
+            Matrix3::from_diagonal(&flips)
+        };
+        if step != Matrix3::identity() {
+            p *= step;
+            continue;
+        }
+
+        let step = if xi.abs() > b_ + eps || ((xi - b_).abs() <= eps && 2.0 * eta < zeta - eps) || ((xi + b_).abs() <= eps && zeta < -eps) {
+            // A5: shorten c with b
+            Matrix3::new(1, 0, 0, 0, 1, -xi.signum() as i32, 0, 0, 1)
+        } else if eta.abs() > a_ + eps || ((eta - a_).abs() <= eps && 2.0 * xi < zeta - eps) || ((eta + a_).abs() <= eps && zeta < -eps) {
+            // A6: shorten c with a
+            Matrix3::new(1, 0, -eta.signum() as i32, 0, 1, 0, 0, 0, 1)
+        } else if zeta.abs() > a_ + eps || ((zeta - a_).abs() <= eps && 2.0 * xi < eta - eps) || ((zeta + a_).abs() <= eps && eta < -eps) {
+            // A7: shorten b with a
+            Matrix3::new(1, -zeta.signum() as i32, 0, 0, 1, 0, 0, 0, 1)
+        } else {
+            let sum = xi + eta + zeta + a_ + b_;
+            if sum < -eps || (sum.abs() <= eps && 2.0 * (a_ + eta) + zeta > eps) {
+                // A8: shorten c with a + b
+                Matrix3::new(1, 0, 1, 0, 1, 1, 0, 0, 1)
+            } else {
+                return Ok(p);
+            }
+        };
+        p *= step;
+    }
+    bail!("Niggli reduction did not converge.")
+}
+
+/// Delaunay (Selling) reduction: makes the four vectors a, b, c and -(a + b + c) pairwise
+/// obtuse, then picks the three shortest non-coplanar vectors among them and their pair sums.
+///
+/// Returns the integer transformation `P` (determinant +1) such that `basis · P` is the
+/// reduced basis. `tolerance` is relative to V^(2/3), as in `niggli_reduce`.
+pub fn delaunay_reduce(basis: &Matrix3<f64>, tolerance: f64) -> Result<Matrix3<i32>> {
+    let volume = basis.determinant().abs();
+    if volume < 1e-12 {
+        bail!("Cannot reduce a cell with zero volume.");
+    }
+    let eps = tolerance * volume.powf(2.0 / 3.0);
+
+    // Superbase as integer coefficients of the input vectors
+    let mut superbase = [
+        Vector3::new(1, 0, 0),
+        Vector3::new(0, 1, 0),
+        Vector3::new(0, 0, 1),
+        Vector3::new(-1, -1, -1),
+    ];
+    let cart = |v: &Vector3<i32>| basis * v.cast::<f64>();
+
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        let acute = (0..4)
+            .flat_map(|i| (i + 1..4).map(move |j| (i, j)))
+            .find(|&(i, j)| cart(&superbase[i]).dot(&cart(&superbase[j])) > eps);
+        let Some((i, j)) = acute else {
+            converged = true;
+            break;
+        };
+        for k in (0..4).filter(|&k| k != i && k != j) {
+            superbase[k] += superbase[i];
+        }
+        superbase[i] = -superbase[i];
+    }
+    if !converged {
+        bail!("Delaunay reduction did not converge.");
+    }
+
+    let [b1, b2, b3, b4] = superbase;
+    let mut candidates = [b1, b2, b3, b4, b1 + b2, b2 + b3, b3 + b1];
+    candidates.sort_by(|u, v| cart(u).norm_squared().total_cmp(&cart(v).norm_squared()));
+
+    for (x, u) in candidates.iter().enumerate() {
+        for (y, v) in candidates.iter().enumerate().skip(x + 1) {
+            for w in candidates.iter().skip(y + 1) {
+                let p = Matrix3::from_columns(&[*u, *v, *w]);
+                match integer_determinant(&p) {
+                    1 => return Ok(p),
+                    -1 => return Ok(-p),
+                    _ => {}
+                }
+            }
+        }
+    }
+    bail!("Delaunay reduction found no basis among the reduced vectors.")
+}
+
+/// Determinant of an integer matrix, in exact arithmetic.
+pub(crate) fn integer_determinant(m: &Matrix3<i32>) -> i64 {
+    let m = m.cast::<i64>();
+    m[(0, 0)] * (m[(1, 1)] * m[(2, 2)] - m[(1, 2)] * m[(2, 1)])
+        - m[(0, 1)] * (m[(1, 0)] * m[(2, 2)] - m[(1, 2)] * m[(2, 0)])
+        + m[(0, 2)] * (m[(1, 0)] * m[(2, 1)] - m[(1, 1)] * m[(2, 0)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduction_of_sheared_cells() {
+        // A cube of edge 3 Å given by the skewed basis (a, a + b, a + b + c)
+        let skewed = Matrix3::new(3.0, 3.0, 3.0, 0.0, 3.0, 3.0, 0.0, 0.0, 3.0);
+        for p in [niggli_reduce(&skewed, 1e-5).unwrap(), delaunay_reduce(&skewed, 1e-5).unwrap()] {
+            assert_eq!(integer_determinant(&p), 1);
+            let reduced = skewed * p.cast::<f64>();
+            let metric = reduced.transpose() * reduced;
+            assert!((metric - Matrix3::from_diagonal_element(9.0)).abs().max() < 1e-9, "{}", metric);
+        }
+
+        // Niggli cell of fcc (a = 4 Å) from its body-diagonal-heavy basis: edges a/√2, angles 60°
+        let fcc = Matrix3::new(0.0, 2.0, 2.0, 2.0, 0.0, 2.0, 2.0, 2.0, 0.0);
+        let sheared = fcc * Matrix3::new(1.0, 2.0, 0.0, 0.0, 1.0, 3.0, 0.0, 0.0, 1.0);
+        let reduced = sheared * niggli_reduce(&sheared, 1e-5).unwrap().cast::<f64>();
+        let metric = reduced.transpose() * reduced;
+        assert!((metric.diagonal() - Vector3::repeat(8.0)).abs().max() < 1e-9);
+        assert!((metric[(0, 1)].abs() - 4.0).abs() < 1e-9);
+        assert!(niggli_reduce(&Matrix3::zeros(), 1e-5).is_err());
+    }
+}
@@ -1,19 +1,81 @@
-use crate::core::structure::{Crystal, Lattice};
+use crate::core::structure::{Atom, Crystal, Lattice};
+use crate::io::provenance::SlabProvenance;
 use crate::math::{integer_basis, lll};
-use nalgebra::{Matrix3, Vector3};
+use nalgebra::{Matrix2, Matrix3, Vector2, Vector3};
 use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
 
 // --- STRICT TYPE DEFINITIONS FOR CLARITY ---
 type Cartesian3 = Vector3<f64>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlabGeometry {
+    /// Columns: slab a, b and c (Å); serialized as row vectors.
+    #[serde(with = "crate::io::json::matrix_columns")]
     pub basis: Matrix3<f64>,
     pub d_hkl: f64,
     pub n_layers: usize,
     pub vacuum_thickness: f64,
 }
 
+impl SlabGeometry {
+    /// Flags the slab atoms that should relax: those within `relax_layers` interplanar spacings
+    /// of either surface. The centre of the slab is held at its bulk positions.
+    /// Heights are measured along the slab normal, so this also works after reconstruction.
+    pub fn surface_mask(&self, atoms: &[Atom], relax_layers: usize) -> Vec<bool> {
+        let normal = self.basis.column(2).normalize();
+        let heights: Vec<f64> = atoms.iter().map(|a| (self.basis * a.fractional_coords).dot(&normal)).collect();
+
+        let bottom = heights.iter().copied().fold(f64::INFINITY, f64::min);
+        let top = heights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let depth = relax_layers as f64 * self.d_hkl - 1e-3;
+
+        heights.iter().map(|&h| h < bottom + depth || h > top - depth).collect()
+    }
+
+    /// Monkhorst-Pack mesh with at most `k_spacing` (Å⁻¹, 2π included as in VASP's KSPACING)
+    /// between in-plane k-points and a single k-point along the vacuum direction.
+    pub fn kpoint_mesh(&self, k_spacing: f64) -> [usize; 3] {
+        let reciprocal = self.basis.try_inverse().map(|m| m.transpose()).unwrap_or_else(Matrix3::zeros);
+        let divisions = |i: usize| {
+            let length = 2.0 * std::f64::consts::PI * reciprocal.column(i).norm();
+            ((length / k_spacing).ceil() as usize).max(1)
+        };
+        [divisions(0), divisions(1), 1]
+    }
+
+    /// How this slab was cut from `bulk`: recovers the integer in-plane vectors from the slab
+    /// basis and picks the stacking vector (h·w = gcd(h, k, l)) with the smallest in-plane component.
+    pub fn provenance(&self, bulk: &Lattice, miller_indices: [i32; 3], cut_offset: f64) -> SlabProvenance {
+        let [h, k, l] = miller_indices;
+        let to_integer = |cart: Vector3<f64>| bulk.to_fractional(&cart).map(|x| x.round() as i32);
+        let u = to_integer(self.basis.column(0).into_owned());
+        let v = to_integer(self.basis.column(1).into_owned());
+
+        // Remove the in-plane part of w (nearest-plane rounding in the u, v basis)
+        let mut w = integer_basis::find_stacking_vector(h, k, l);
+        let cart = |n: &Vector3<i32>| bulk.to_cartesian(&n.map(|x| x as f64));
+        let (uc, vc) = (cart(&u), cart(&v));
+        let gram = Matrix2::new(uc.dot(&uc), uc.dot(&vc), uc.dot(&vc), vc.dot(&vc));
+        if let Some(inverse) = gram.try_inverse() {
+            let wc = cart(&w);
+            let coefficients = inverse * Vector2::new(wc.dot(&uc), wc.dot(&vc));
+            w -= u * coefficients.x.round() as i32 + v * coefficients.y.round() as i32;
+        }
+
+        let (a, b, c, alpha, beta, gamma) = bulk.to_parameters();
+        SlabProvenance {
+            miller_indices,
+            d_hkl: self.d_hkl,
+            n_layers: self.n_layers,
+            cut_offset,
+            vacuum: self.vacuum_thickness,
+            bulk_cell: [a, b, c, alpha, beta, gamma],
+            transformation: Matrix3::from_columns(&[u, v, w]),
+        }
+    }
+}
+
 pub struct SlabBuilder {
     miller_indices: [i32; 3],
     target_thickness: f64,
@@ -1,3 +1,4 @@
+use crate::core::elements::{self, Element};
 use crate::core::structure::{Atom, Lattice};
 use nalgebra::Vector3;
 use anyhow::Result;
@@ -12,7 +13,7 @@ pub struct IonicReconstructor;
 
 impl IonicReconstructor {
     pub fn stabilize(
-        atoms: &mut Vec<Atom>, 
+        atoms: &mut [Atom], 
         lattice: &Lattice, 
         mode: ReconstructionMode
     ) -> Result<String> {
@@ -51,7 +52,11 @@ impl IonicReconstructor {
         planes.push(current_plane);
 
         // 2. Dipole Check
-        let charges = Self::guess_charges(atoms);
+        // Prefer the charges carried over from the input file; fall back to formal charges.
+        let (charges, charge_source) = match atoms.iter().map(Atom::charge).collect::<Option<Vec<f64>>>() {
+            Some(charges) => (charges, "input charges"),
+            None => (Self::guess_charges(atoms), "formal charges"),
+        };
         let dipole_z: f64 = atoms.iter().zip(&charges)
             .map(|(a, q)| q * lattice.to_cartesian(&a.fractional_coords).z)
             .sum();
@@ -62,23 +67,10 @@ impl IonicReconstructor {
                 let num_to_move = top_plane.len() / 2;
                 
                 if num_to_move > 0 && planes.len() > 1 {
-                    // INTELLIGENT PLACEMENT LOGIC:
-                    // Instead of guessing Z, we calculate the vector from Top Layer -> Second Layer.
-                    // This vector represents the "Stacking Shift" in reverse.
-                    
+                    // Moved atoms go to a ghost plane one stacking step below the bottom
+                    // plane, keeping their in-plane offsets from the top plane centre.
                     let top_z_avg = Self::average_pos(lattice, top_plane, atoms);
-                    let second_z_avg = Self::average_pos(lattice, &planes[planes.len() - 2], atoms);
-                    
-                    // Vector pointing 'down' one layer in the stack
-                    let stacking_vector = second_z_avg - top_z_avg;
-                    
-                    // To go from Top to "New Bottom" (which is below the current Bottom),
-                    // we need to apply this vector (N_planes - 1) times.
-                    // Or more simply: New_Pos = Old_Pos + (stacking_vector * (planes.len() - 1))?
-                    // No, that assumes linear spacing. Safe bet: 
-                    // Calculate vector from Plane[1] to Plane[0] (Bottom to Bottom-most).
-                    // Apply that vector to Plane[0] to find "Ghost Plane[-1]".
-                    
+
                     // Let's use the Bottom -> Bottom+1 vector reversed.
                     let bottom_plane = &planes[0];
                     let bottom_next_plane = &planes[1];
@@ -106,14 +98,14 @@ impl IonicReconstructor {
                     }
                     
                     return Ok(format!(
-                        "Dipole detected ({:.3} eA). Moved {} atoms to crystallographic bottom sites.", 
-                        dipole_z, num_to_move
+                        "Dipole detected ({:.3} eA, {}). Moved {} atoms to crystallographic bottom sites.", 
+                        dipole_z, charge_source, num_to_move
                     ));
                 }
             }
         }
 
-        Ok(format!("Surface is stable (Dipole: {:.3} eA).", dipole_z))
+        Ok(format!("Surface is stable (Dipole: {:.3} eA, {}).", dipole_z, charge_source))
     }
 
     fn average_pos(lattice: &Lattice, indices: &[usize], atoms: &[Atom]) -> Vector3<f64> {
@@ -124,10 +116,8 @@ impl IonicReconstructor {
         sum / (indices.len() as f64)
     }
 
+    /// Formal charge of each atom's element (its most common oxidation state); 0 if unknown.
     fn guess_charges(atoms: &[Atom]) -> Vec<f64> {
-        atoms.iter().map(|a| match a.element.as_str() {
-            "Li"|"Na"|"K"|"H" => 1.0, "Mg"|"Ca"|"Zn"|"Fe" => 2.0, "Al" => 3.0,
-            "F"|"Cl"|"Br"|"I" => -1.0, "O"|"S" => -2.0, "N" => -3.0, _ => 0.0,
-        }).collect()
+        atoms.iter().map(|a| elements::element(&a.element).map_or(0.0, Element::formal_charge)).collect()
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::core::structure::{Atom, Crystal, Molecule, ComponentType};
+use crate::core::structure::{Atom, Crystal, Molecule, PROP_BULK_IMAGE, PROP_BULK_INDEX, PROP_LAYER};
 use crate::synthesis::builder::SlabGeometry;
 use nalgebra::Vector3;
 use anyhow::{Result, anyhow};
@@ -6,6 +6,13 @@ use anyhow::{Result, anyhow};
 pub struct SlabPopulator;
 
 impl SlabPopulator {
+    /// Fills the slab cell of `geometry` with the bulk atoms (or whole molecules, kept by their
+    /// centre of mass) whose height lies in `n_layers` interplanar spacings from `offset_z`.
+    ///
+    /// Every atom appears once: lattice translations are limited to those that can reach the
+    /// slab cell, and an image is kept only when its in-plane slab coordinates lie in [0, 1).
+    /// An atom on a lattice plane belongs to the layer above it, so with a cut on a plane every
+    /// layer holds the same atoms.
     pub fn populate(
         crystal: &Crystal, 
         geometry: &SlabGeometry, 
@@ -13,11 +20,10 @@ impl SlabPopulator {
         offset_z: f64
     ) -> Result<Vec<Atom>> {
         
-        // Store interim atoms along with their semantic component type. The third
-        // entry preserves the ComponentType from the bulk structure or assigns
-        // Unknown for molecules. This allows downstream capping logic to
-        // distinguish metal nodes from organic linkers when reconstructing the
-        // slab surfaces.
+        // Store interim atoms as (source atom, Cartesian position). The source atom
+        // preserves the ComponentType, occupancy and disorder group from the bulk
+        // structure, in molecular mode too. This allows downstream capping logic to
+        // distinguish metal nodes from organic linkers when reconstructing the slab surfaces.
         let mut final_atoms = Vec::new();
         
         // 1. Calculate Miller Plane Projection
@@ -42,67 +48,105 @@ impl SlabPopulator {
         let min_idx = offset_idx - epsilon;
         let max_idx = offset_idx + n_layers_float - epsilon;
 
-        // 2. Dynamic Bounding (High Index Safe)
-        // Project bulk vectors onto normal to determine sweep range
-        let proj_a = crystal.lattice.matrix.column(0).dot(&slab_normal).abs();
-        let proj_b = crystal.lattice.matrix.column(1).dot(&slab_normal).abs();
-        let proj_c = crystal.lattice.matrix.column(2).dot(&slab_normal).abs();
-        
-        // This is the height of one unit cell in Angstroms
-        let cell_height_ang = proj_a.max(proj_b).max(proj_c);
-        
-        if cell_height_ang < 1e-9 { return Err(anyhow!("Degenerate unit cell (zero height).")); }
+        // Layer an atom (or molecule COM) falls in, counted from the bottom of the slab.
+        // Atoms on a lattice plane belong to the layer above it (same epsilon as the bounds).
+        let layer_index = |layer_val: f64| -> i64 {
+            ((layer_val - offset_idx + epsilon).floor() as i64).clamp(0, geometry.n_layers as i64 - 1)
+        };
 
-        // Convert total slab height to bulk cell repeats
-        let total_slab_height = geometry.n_layers as f64 * geometry.d_hkl;
-        
-        // Multiplier 2.5 is sufficient because we check Com/Bounds later
-        let repeats = (total_slab_height / cell_height_ang).ceil() as i32 + 3;
+        // 2. Dynamic Bounding (High Index Safe)
+        // The slab cell is the prism spanned by the in-plane vectors and the slab height along
+        // the normal. Its corners in bulk fractional coordinates bound the lattice translations
+        // that can place an atom (or molecule COM) inside it.
+        let (z_min, z_max) = (min_idx * geometry.d_hkl, max_idx * geometry.d_hkl);
+        let mut lower = Vector3::repeat(f64::INFINITY);
+        let mut upper = Vector3::repeat(f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let x = (corner & 1) as f64;
+            let y = ((corner >> 1) & 1) as f64;
+            let z = if corner & 4 == 0 { z_min } else { z_max };
+            let cart = geometry.basis.column(0) * x + geometry.basis.column(1) * y + slab_normal * z;
+            let frac = crystal.lattice.to_fractional(&cart);
+            lower = lower.inf(&frac);
+            upper = upper.sup(&frac);
+        }
+        if !lower.iter().chain(upper.iter()).all(|x| x.is_finite()) {
+            return Err(anyhow!("Degenerate unit cell (zero height)."));
+        }
+        // Bulk positions lie in [0, 1), with a margin for unwrapped atoms and molecule COMs
+        let ranges: [(i32, i32); 3] = std::array::from_fn(|i| (lower[i].floor() as i32 - 2, upper[i].ceil() as i32 + 1));
 
-        let range_iter = (-repeats..=repeats).flat_map(|i| 
-            (-repeats..=repeats).flat_map(move |j| 
-                (-repeats..=repeats).map(move |k| Vector3::new(i as f64, j as f64, k as f64))
+        let range_iter = (ranges[0].0..=ranges[0].1).flat_map(|i|
+            (ranges[1].0..=ranges[1].1).flat_map(move |j|
+                (ranges[2].0..=ranges[2].1).map(move |k| Vector3::new(i as f64, j as f64, k as f64))
             )
         );
 
-        if !molecules.is_empty() {
-            // --- Molecular Mode ---
-            for cell_shift_frac in range_iter {
-                let cell_shift_cart = crystal.lattice.to_cartesian(&cell_shift_frac);
-                
-                for mol in molecules {
-                    let shifted_com = mol.center_of_mass + cell_shift_cart;
-                    // Project COM onto Normal
-                    let z_ang = shifted_com.dot(&slab_normal);
-
-                    // Convert to Layer Index Space
-                    let layer_val = z_ang / geometry.d_hkl;
-
-                    if layer_val >= min_idx && layer_val < max_idx {
-                        for (element, rel_pos) in &mol.atoms {
-                             let final_pos = rel_pos + cell_shift_cart;
-                             // Molecule atoms lack semantic tagging; default to Unknown
-                             final_atoms.push((element.clone(), final_pos, ComponentType::Unknown));
-                        }
+        // Each atom (or molecule) is kept once: in-plane fractional coordinates in [0, 1)
+        let slab_basis_inv = geometry.basis.try_inverse().ok_or(anyhow!("Slab basis singular"))?;
+        let in_plane_epsilon = 1e-6;
+        let in_cell = |pos: &Vector3<f64>| -> bool {
+            let frac = slab_basis_inv * pos;
+            (0..2).all(|i| frac[i] >= -in_plane_epsilon && frac[i] < 1.0 - in_plane_epsilon)
+        };
+
+        // Bulk atoms covered by a molecule; the others (all of them without molecules, the
+        // framework of a mixed structure) are placed one by one
+        let mut in_molecule = vec![false; crystal.atoms.len()];
+        for &i in molecules.iter().flat_map(|mol| &mol.indices) {
+            *in_molecule.get_mut(i).ok_or_else(|| anyhow!("Molecule atom {} is not in the bulk structure.", i))? = true;
+        }
+
+        // Lattice translation of each unwrapped molecule atom from its bulk site
+        let molecule_images: Vec<Vec<Vector3<i32>>> = molecules
+            .iter()
+            .map(|mol| {
+                mol.atoms.iter().zip(&mol.indices).map(|((_, pos), &i)| {
+                    (crystal.lattice.to_fractional(pos) - crystal.atoms[i].fractional_coords).map(|x| x.round() as i32)
+                }).collect()
+            })
+            .collect();
+
+        // The slab atom is a copy of its bulk atom (semantic tag, occupancy, label, charge, ...)
+        // that records which bulk atom and which periodic image it was cut from
+        let slab_atom = |index: usize, image: Vector3<i32>, layer_val: f64| -> Atom {
+            let mut atom = crystal.atoms[index].clone();
+            atom.set_property(PROP_LAYER, layer_index(layer_val));
+            atom.set_property(PROP_BULK_INDEX, index as i64);
+            atom.set_property(PROP_BULK_IMAGE, image.cast::<f64>());
+            atom
+        };
+
+        for cell_shift_frac in range_iter {
+            let cell_shift = cell_shift_frac.map(|x| x as i32);
+            let cell_shift_cart = crystal.lattice.to_cartesian(&cell_shift_frac);
+
+            // --- Molecular Mode: whole molecules, kept or dropped by their COM ---
+            for (mol, images) in molecules.iter().zip(&molecule_images) {
+                let shifted_com = mol.center_of_mass + cell_shift_cart;
+                // Project COM onto Normal
+                let z_ang = shifted_com.dot(&slab_normal);
+
+                // Convert to Layer Index Space
+                let layer_val = z_ang / geometry.d_hkl;
+
+                if layer_val >= min_idx && layer_val < max_idx && in_cell(&shifted_com) {
+                    for (((_, rel_pos), &index), image) in mol.atoms.iter().zip(&mol.indices).zip(images) {
+                        final_atoms.push((slab_atom(index, image + cell_shift, layer_val), rel_pos + cell_shift_cart));
                     }
                 }
             }
-        } else {
+
             // --- Atomic Mode (High Precision) ---
-            for cell_shift_frac in range_iter {
-                let cell_shift_cart = crystal.lattice.to_cartesian(&cell_shift_frac);
-                
-                for atom in &crystal.atoms {
-                    let pos_cart = crystal.lattice.to_cartesian(&atom.fractional_coords) + cell_shift_cart;
-                    let z_ang = pos_cart.dot(&slab_normal);
-                    
-                    // Layer Index Check (The Fix for High Indices)
-                    let layer_val = z_ang / geometry.d_hkl;
-
-                    if layer_val >= min_idx && layer_val < max_idx {
-                        // Preserve the semantic tag from the bulk atom
-                        final_atoms.push((atom.element.clone(), pos_cart, atom.component_type));
-                    }
+            for (index, atom) in crystal.atoms.iter().enumerate().filter(|&(i, _)| !in_molecule[i]) {
+                let pos_cart = crystal.lattice.to_cartesian(&atom.fractional_coords) + cell_shift_cart;
+                let z_ang = pos_cart.dot(&slab_normal);
+
+                // Layer Index Check (The Fix for High Indices)
+                let layer_val = z_ang / geometry.d_hkl;
+
+                if layer_val >= min_idx && layer_val < max_idx && in_cell(&pos_cart) {
+                    final_atoms.push((slab_atom(index, cell_shift, layer_val), pos_cart));
                 }
             }
         }
@@ -116,7 +160,7 @@ impl SlabPopulator {
         let mut min_z = f64::INFINITY;
         let mut max_z = f64::NEG_INFINITY;
 
-        for (_, pos, _) in &final_atoms {
+        for (_, pos) in &final_atoms {
             let z = pos.dot(&slab_normal);
             if z < min_z { min_z = z; }
             if z > max_z { max_z = z; }
@@ -130,19 +174,63 @@ impl SlabPopulator {
         let shift_val = target_z_start - min_z;
         
         let shift_vec = slab_normal * shift_val;
-        let slab_basis_inv = geometry.basis.try_inverse().ok_or(anyhow!("Slab basis singular"))?;
 
-        let result_atoms: Vec<Atom> = final_atoms.into_iter().map(|(el, pos, comp_type)| {
+        let result_atoms: Vec<Atom> = final_atoms.into_iter().map(|(atom, pos)| {
             let shifted_cart = pos + shift_vec;
             let fractional = slab_basis_inv * shifted_cart;
             Atom {
-                element: el,
                 fractional_coords: fractional,
                 // Preserve the component type for semantic aware capping
-                component_type: comp_type,
+                ..atom
             }
         }).collect();
 
         Ok(result_atoms)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::structure::{ComponentType, Lattice};
+    use crate::synthesis::builder::SlabBuilder;
+
+    #[test]
+    fn test_each_atom_once_per_slab_cell() {
+        // Rock salt with its atoms on the (001) planes, and a triclinic cell for a high index
+        let mut rocksalt = Vec::new();
+        for t in [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]] {
+            rocksalt.push(Atom::new("Mg", Vector3::from(t), ComponentType::Unknown));
+            rocksalt.push(Atom::new("O", Vector3::from([t[0] + 0.5, t[1], t[2]].map(|x: f64| x % 1.0)), ComponentType::Unknown));
+        }
+        let cubic = Crystal { lattice: Lattice::from_parameters(4.2, 4.2, 4.2, 90.0, 90.0, 90.0).unwrap(), atoms: rocksalt };
+        let triclinic = Crystal {
+            lattice: Lattice::from_parameters(4.0, 5.0, 6.0, 80.0, 95.0, 105.0).unwrap(),
+            atoms: vec![
+                Atom::new("Zn", Vector3::new(0.1, 0.2, 0.3), ComponentType::Unknown),
+                Atom::new("S", Vector3::new(0.6, 0.7, 0.9), ComponentType::Unknown),
+            ],
+        };
+
+        for (crystal, hkl) in [(&cubic, [0, 0, 1]), (&cubic, [1, 1, 1]), (&cubic, [2, 1, 0]), (&triclinic, [3, 2, 1])] {
+            let geometry = SlabBuilder::new(hkl[0], hkl[1], hkl[2], 12.0, 10.0).compute_geometry(crystal).unwrap();
+            let atoms = SlabPopulator::populate(crystal, &geometry, &[], 0.0).unwrap();
+            let lattice = Lattice::new(geometry.basis).unwrap();
+            // Bulk cells per layer of the slab cell (the in-plane cell need not be primitive)
+            let area = geometry.basis.column(0).cross(&geometry.basis.column(1)).norm();
+            let per_layer = crystal.atoms.len() * (area * geometry.d_hkl / crystal.lattice.matrix.determinant().abs()).round() as usize;
+
+            // No two atoms on the same site of the periodic slab, and equally filled layers
+            for (i, a) in atoms.iter().enumerate() {
+                for b in &atoms[i + 1..] {
+                    assert!(lattice.minimum_image(&a.fractional_coords, &b.fractional_coords).1.norm() > 0.1, "{:?}", hkl);
+                }
+            }
+            assert_eq!(atoms.len(), geometry.n_layers * per_layer, "{:?}", hkl);
+            for layer in 0..geometry.n_layers as i64 {
+                let count = atoms.iter().filter(|a| a.property(PROP_LAYER).and_then(|p| p.as_i64()) == Some(layer)).count();
+                assert_eq!(count, per_layer, "{:?} layer {}", hkl, layer);
+            }
+        }
+    }
+}
@@ -1,6 +1,23 @@
-use crystal_surface_generator::{parser, generate_surface, SurfaceConfig, MoleculeFinder};
+use crystal_surface_generator::{BondingRule, format, gulp, parser, poscar, writer, generate_surface, generate_surface_with_provenance, SurfaceConfig, MoleculeFinder, DEFAULT_SYMPREC};
+use crystal_surface_generator::analysis::relaxation::{analyze_relaxation, RelaxationOptions};
+use crystal_surface_generator::{Atom, Crystal, VolumetricData};
+use crystal_surface_generator::core::structure::PROP_LAYER;
 use std::path::PathBuf;
 
+/// Path of a file in the repository ("A_sample_inputs/mgo.cif").
+fn sample(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path)
+}
+
+fn read_sample(path: &str) -> Crystal {
+    parser::from_cif(&sample(path)).expect("Failed to parse CIF")
+}
+
+/// A slab of `thickness` Å with 10 Å of vacuum, the rest of the configuration left at its defaults.
+fn slab_config(miller_indices: [i32; 3], thickness: f64) -> SurfaceConfig {
+    SurfaceConfig { miller_indices, thickness, vacuum: 10.0, ..Default::default() }
+}
+
 #[test]
 fn test_generate_surface_all_samples() {
     let inputs = vec![
@@ -13,10 +30,8 @@ fn test_generate_surface_all_samples() {
         "A_sample_inputs/mgo.cif",
     ];
 
-    let root_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-
     for input_filename in inputs {
-        let input_path = root_dir.join(input_filename);
+        let input_path = sample(input_filename);
         println!("Testing: {:?}", input_path);
 
         // Ensure file exists
@@ -28,7 +43,7 @@ fn test_generate_surface_all_samples() {
         // 2. Molecule Analysis
         // Using a bond tolerance of 2.0 Angstroms
         let finder = MoleculeFinder::new(2.0);
-        let molecules = finder.find_molecules(&crystal).expect("Failed to find molecules");
+        let molecules = finder.find_discrete_molecules(&crystal).expect("Failed to find molecules");
 
         // 3. Configuration
         // Testing a standard (1 0 0) surface generation
@@ -36,11 +51,8 @@ fn test_generate_surface_all_samples() {
             miller_indices: [1, 0, 0],
             thickness: 15.0,
             vacuum: 15.0,
-            offset: None,
-            reconstruct: false,
             input_cif_path: Some(input_path.clone()),
-            enable_mofid: false,
-            mofid_output_root: None,
+            ..Default::default()
         };
 
         // 4. Execution
@@ -54,9 +66,282 @@ fn test_generate_surface_all_samples() {
         let (slab, report) = result.unwrap();
 
         // Basic assertions on output
-        assert!(slab.atoms.len() > 0, "Generated slab has no atoms");
+        assert!(!slab.atoms.is_empty(), "Generated slab has no atoms");
         assert!(!report.is_empty(), "Report is empty");
 
         println!("Successfully generated surface for {:?}. Atoms: {}", input_filename, slab.atoms.len());
     }
 }
+
+#[test]
+fn test_symmetry_expansion_mgo() {
+    // Fm-3m rock salt: 2 asymmetric sites expand to 4 Mg + 4 O in the conventional cell.
+    let crystal = read_sample("A_sample_inputs/mgo.cif");
+
+    assert_eq!(crystal.atoms.len(), 8);
+    assert_eq!(crystal.atoms.iter().filter(|a| a.element == "Mg").count(), 4);
+    for atom in &crystal.atoms {
+        assert!(atom.fractional_coords.iter().all(|&x| (0.0..1.0).contains(&x)));
+    }
+}
+
+#[test]
+fn test_atom_properties_survive_slab_and_writer() {
+    // RASPA-style P1 CIF with partial charges: labels and charges must reach the written slab.
+    let mut crystal = read_sample("A_sample_inputs/5.cif");
+    assert!(crystal.atoms.iter().all(|a| a.label().is_some() && a.charge().is_some()));
+
+    let config = SurfaceConfig { reconstruct: true, ..slab_config([0, 0, 1], 10.0) };
+    // Atomic mode (no molecules) keeps the bulk atoms' properties.
+    let (slab, report) = generate_surface(&mut crystal, &[], &config).expect("Failed to generate surface");
+    assert!(report.contains("input charges"), "Reconstruction ignored the CIF charges:\n{}", report);
+    assert!(slab.atoms.iter().all(|a| a.charge().is_some()));
+
+    let output_path = std::env::temp_dir().join("csg_test_properties_slab.cif");
+    writer::to_cif(&slab, &output_path).expect("Failed to write CIF");
+    let reread = parser::from_cif(&output_path).expect("Failed to re-read written CIF");
+    std::fs::remove_file(&output_path).ok();
+
+    assert_eq!(reread.atoms.len(), slab.atoms.len());
+    for (written, read) in slab.atoms.iter().zip(&reread.atoms) {
+        assert_eq!(written.label(), read.label());
+        assert!((written.charge().unwrap() - read.charge().unwrap()).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_label_only_cif() {
+    // UiO-66 has no _atom_site_type_symbol; elements come from the labels ("Zr", "O1", "C11").
+    let crystal = read_sample("1_mofid_to_rust/0_MOF_inputs/16_UiO-66.cif");
+
+    assert_eq!(crystal.atoms.iter().filter(|a| a.element == "Zr").count(), 24);
+    assert!(crystal.atoms.iter().all(|a| ["Zr", "O", "C"].contains(&a.element.as_str())));
+}
+
+#[test]
+fn test_poscar_slab_with_selective_dynamics() {
+    let mut crystal = read_sample("A_sample_inputs/mgo.cif");
+    let generated = generate_surface_with_provenance(&mut crystal, &[], &slab_config([1, 0, 0], 20.0)).expect("Failed to generate surface");
+    let slab = &generated.slab;
+
+    // 5 layers of MgO(100): the outer layer on each side relaxes, the middle 3 stay frozen
+    assert_eq!(generated.geometry.n_layers, 5);
+    let mask = generated.geometry.surface_mask(&slab.atoms, 1);
+    let free = mask.iter().filter(|&&f| f).count();
+    assert_eq!(free * 5, slab.atoms.len() * 2);
+
+    let output_path = std::env::temp_dir().join("csg_test_selective_POSCAR");
+    let options = poscar::PoscarOptions { selective_dynamics: Some(mask), ..Default::default() };
+    poscar::to_poscar(slab, &output_path, &options).expect("Failed to write POSCAR");
+    let reread = poscar::from_poscar(&output_path).expect("Failed to re-read POSCAR");
+    std::fs::remove_file(&output_path).ok();
+
+    assert_eq!(reread.atoms.len(), slab.atoms.len());
+    let frozen = reread
+        .atoms
+        .iter()
+        .filter(|a| a.property("selective_dynamics").and_then(|p| p.as_str()) == Some("F F F"))
+        .count();
+    assert_eq!(frozen, slab.atoms.len() - free);
+}
+
+#[test]
+fn test_gulp_slab_regions_mgo() {
+    let mut crystal = read_sample("A_sample_inputs/mgo.cif");
+    let generated = generate_surface_with_provenance(&mut crystal, &[], &slab_config([1, 0, 0], 20.0)).expect("Failed to generate surface");
+    let slab = &generated.slab;
+
+    // Top two of the five layers relax
+    let options = gulp::GulpOptions { region1_depth: Some(2.0 * generated.geometry.d_hkl - 1e-3), ..Default::default() };
+    let mut buffer = Vec::new();
+    gulp::write_gulp(slab, &mut buffer, &options).expect("Failed to write GULP input");
+    let text = String::from_utf8(buffer).unwrap();
+
+    let region1 = text.split("sfractional region 1\n").nth(1).unwrap().split("sfractional region 2\n").next().unwrap();
+    let region2 = text.split("sfractional region 2\n").nth(1).unwrap().split("species\n").next().unwrap();
+    assert_eq!(region1.lines().count() * 5, slab.atoms.len() * 2);
+    assert_eq!(region2.lines().count() * 5, slab.atoms.len() * 3);
+    assert!(region1.lines().chain(region2.lines()).all(|l| l.split_whitespace().nth(4).unwrap().parse::<f64>().unwrap() <= 1e-6));
+    assert!(text.contains("species\nMg  core   2.0000\nO   core  -2.0000\n") || text.contains("species\nO   core  -2.0000\nMg  core   2.0000\n"));
+}
+
+#[test]
+fn test_cif_bonds_and_provenance_round_trip() {
+    let mut crystal = read_sample("A_sample_inputs/mgo.cif");
+    let generated = generate_surface_with_provenance(&mut crystal, &[], &slab_config([1, 1, 0], 8.0)).expect("Failed to generate surface");
+    let provenance = &generated.provenance;
+
+    // Slab a, b and the stacking vector span the bulk lattice and stack (110) planes
+    assert_eq!(provenance.transformation.map(|x| x as f64).determinant().abs().round(), 1.0);
+    let hkl = nalgebra::RowVector3::new(1, 1, 0);
+    assert_eq!(hkl * provenance.transformation, nalgebra::RowVector3::new(0, 0, 1));
+
+    let options = writer::CifWriterOptions { bonding: Some(BondingRule::uniform(2.5)), provenance: Some(provenance.clone()) };
+    let mut buffer = Vec::new();
+    writer::write_cif_with_options(&generated.slab, &mut buffer, &options).expect("Failed to write CIF");
+    let text = String::from_utf8(buffer).unwrap();
+    assert!(text.contains("_csg_slab_miller_index_h"));
+    assert!(text.contains("_geom_bond_site_symmetry_2"));
+    assert!(text.contains(" Mg1 ") && text.contains(" O2 ") || text.contains(" O1 ") && text.contains(" Mg2 "));
+
+    let reread = parser::from_cif_str(&text, &parser::CifOptions::default()).expect("Failed to re-read written CIF");
+    assert_eq!(parser::slab_provenance_from_cif_str(&text).unwrap().as_ref(), Some(provenance));
+    assert_eq!(reread.atoms.len(), generated.slab.atoms.len());
+    for (written, read) in generated.slab.atoms.iter().zip(&reread.atoms) {
+        assert!(written.label().is_none_or(|label| read.label() == Some(label)));
+        assert_eq!(written.component_type, read.component_type);
+        assert_eq!(written.property(PROP_LAYER), read.property(PROP_LAYER));
+    }
+}
+
+#[test]
+fn test_density_profile_selects_cut() {
+    let mut crystal = read_sample("A_sample_inputs/mgo.cif");
+
+    // Density along c with its lowest minimum at z = 3c/4 (between the z = c/2 and z = c layers)
+    let grid = [4, 4, 16];
+    let data: Vec<f64> = (0..256)
+        .map(|n| {
+            let f = (n / 16) as f64 / 16.0;
+            let tau = std::f64::consts::TAU;
+            2.0 + (2.0 * tau * f).cos() + 0.1 * (tau * f).sin()
+        })
+        .collect();
+    let density = VolumetricData::new(crystal.clone(), grid, data).unwrap();
+
+    let config = SurfaceConfig { density: Some(density), ..slab_config([0, 0, 1], 10.0) };
+    let generated = generate_surface_with_provenance(&mut crystal, &[], &config).expect("Failed to generate surface");
+
+    let c = crystal.lattice.matrix.column(2).norm();
+    assert!((generated.report.cut_offset - 0.75 * c).abs() < 0.05, "cut at {}", generated.report.cut_offset);
+    assert_eq!(generated.report.safe_cuts.len(), 2);
+    assert!(generated.report.phase("Topology").unwrap().contains("density minima"));
+    assert!(!generated.slab.atoms.is_empty());
+}
+
+#[test]
+fn test_relaxation_against_poscar_round_trip() {
+    let mut crystal = read_sample("A_sample_inputs/mgo.cif");
+    let generated = generate_surface_with_provenance(&mut crystal, &[], &slab_config([0, 0, 1], 12.0)).expect("Failed to generate surface");
+    let slab = generated.slab;
+
+    // "Relax" by moving every atom of the top layer out by 0.1 Å, then read back as VASP would
+    let top = slab.atoms.iter().filter_map(|a| a.property(PROP_LAYER).and_then(|p| p.as_i64())).max().unwrap();
+    let c = slab.lattice.matrix.column(2).norm();
+    let mut relaxed = slab.clone();
+    for atom in relaxed.atoms.iter_mut().filter(|a| a.property(PROP_LAYER).and_then(|p| p.as_i64()) == Some(top)) {
+        atom.fractional_coords.z += 0.1 / c;
+    }
+    let mut buffer = Vec::new();
+    poscar::write_poscar(&relaxed, &mut buffer, &poscar::PoscarOptions::default()).unwrap();
+    let contcar = poscar::from_poscar_str(&String::from_utf8(buffer).unwrap()).unwrap();
+
+    let options = RelaxationOptions { d_hkl: Some(generated.provenance.d_hkl), ..Default::default() };
+    let report = analyze_relaxation(&slab, &contcar, &options).expect("Failed to analyze relaxation");
+    assert_eq!(report.layers.len(), generated.provenance.n_layers);
+    assert!(report.layers[..top as usize].iter().all(|l| l.max_abs_dz < 1e-6));
+    assert!((report.layers[top as usize].mean_dz - 0.1).abs() < 1e-6);
+    let last = report.spacings.last().unwrap();
+    assert!((last.change_percent - 10.0 / generated.provenance.d_hkl).abs() < 1e-4);
+    assert!(report.planes.iter().all(|p| p.rumpling < 1e-6));
+}
+
+#[test]
+fn test_molecular_slab_keeps_bulk_sites() {
+    // With a 2.0 Å cutoff sample 5 falls apart into discrete molecules, which are placed whole
+    let bulk = read_sample("A_sample_inputs/5.cif");
+    let molecules = MoleculeFinder::new(2.0).find_discrete_molecules(&bulk).unwrap();
+    assert!(!molecules.is_empty());
+
+    let (slab, _) = generate_surface(&mut bulk.clone(), &molecules, &slab_config([0, 0, 1], 10.0)).expect("Failed to generate surface");
+
+    // Every slab atom is a copy of its bulk atom's image, all shifted by the same centring vector
+    let mut shift = None;
+    for atom in &slab.atoms {
+        let (index, image) = atom.bulk_site().expect("slab atom without a bulk site");
+        let source = &bulk.atoms[index];
+        assert_eq!(atom.element, source.element);
+        assert_eq!(atom.label(), source.label());
+        assert_eq!(atom.charge(), source.charge());
+
+        let bulk_cart = bulk.lattice.to_cartesian(&(source.fractional_coords + image.cast::<f64>()));
+        let offset = slab.lattice.to_cartesian(&atom.fractional_coords) - bulk_cart;
+        let shift = *shift.get_or_insert(offset);
+        assert!((offset - shift).norm() < 1e-6, "atom {} is not at its bulk image", index);
+    }
+}
+
+#[test]
+fn test_in_plane_supercell_of_slab() {
+    let bulk = read_sample("A_sample_inputs/mgo.cif");
+    let generated = generate_surface_with_provenance(&mut bulk.clone(), &[], &slab_config([1, 1, 1], 8.0)).expect("Failed to generate surface");
+
+    // √3×√3 of the hexagonal (111) surface: three times the atoms, same height and vacuum
+    let root3 = generated.in_plane_supercell(&nalgebra::Matrix2::new(2, -1, 1, 1)).unwrap();
+    assert_eq!(root3.slab.atoms.len(), 3 * generated.slab.atoms.len());
+    assert_eq!(root3.report.n_atoms, root3.slab.atoms.len());
+    assert_eq!(root3.slab.lattice.matrix.column(2), generated.slab.lattice.matrix.column(2));
+
+    // The provenance still maps the slab cell onto the bulk lattice ...
+    let to_bulk = root3.provenance.transformation.map(|x| x as f64);
+    for k in 0..2 {
+        let expected = bulk.lattice.to_cartesian(&to_bulk.column(k).into_owned());
+        assert!((root3.slab.lattice.matrix.column(k) - expected).norm() < 1e-6);
+    }
+    // ... and every atom still sits at its bulk image, up to the common centring shift
+    let shift = |atom: &Atom| {
+        let (index, image) = atom.bulk_site().unwrap();
+        let bulk_cart = bulk.lattice.to_cartesian(&(bulk.atoms[index].fractional_coords + image.cast::<f64>()));
+        root3.slab.lattice.to_cartesian(&atom.fractional_coords) - bulk_cart
+    };
+    let first = shift(&root3.slab.atoms[0]);
+    assert!(root3.slab.atoms.iter().all(|a| (shift(a) - first).norm() < 1e-6));
+}
+
+#[test]
+fn test_primitive_bulk_slab() {
+    let bulk = read_sample("A_sample_inputs/mgo.cif");
+    let config = |miller_indices, primitive| SurfaceConfig { primitive, ..slab_config(miller_indices, 8.0) };
+
+    // (111) cut from the primitive cell: the same plane, so a slab of the same thickness
+    let conventional = generate_surface_with_provenance(&mut bulk.clone(), &[], &config([1, 1, 1], None)).unwrap();
+    let primitive = generate_surface_with_provenance(&mut bulk.clone(), &[], &config([1, 1, 1], Some(DEFAULT_SYMPREC))).unwrap();
+    let reduced = bulk.find_primitive(DEFAULT_SYMPREC).unwrap();
+    assert_eq!(primitive.report.miller_indices, reduced.miller_to_reduced([1, 1, 1]));
+    assert_eq!(primitive.provenance.miller_indices, primitive.report.miller_indices);
+    assert!(primitive.report.phase("Reduction").is_some());
+    assert!((conventional.report.material_thickness - primitive.report.material_thickness).abs() < 1e-6);
+    assert_eq!(conventional.slab.atoms.len() % primitive.slab.atoms.len(), 0);
+
+    // (100): layers are spaced by the mapped plane's d, a/2 for rock salt, and the relax mask
+    // frees one layer at each surface
+    let generated = generate_surface_with_provenance(&mut bulk.clone(), &[], &config([1, 0, 0], Some(DEFAULT_SYMPREC))).unwrap();
+    let a = bulk.lattice.matrix.column(0).norm();
+    assert!((generated.geometry.d_hkl - a / 2.0).abs() < 1e-6);
+    let per_layer = generated.slab.atoms.len() / generated.geometry.n_layers;
+    assert_eq!(per_layer * generated.geometry.n_layers, generated.slab.atoms.len());
+    let context = format::WriteContext { geometry: Some(generated.geometry.clone()), relax_layers: Some(1), ..Default::default() };
+    let mask = context.relax_mask(&generated.slab).unwrap().unwrap();
+    assert_eq!(mask.iter().filter(|&&free| free).count(), 2 * per_layer);
+}
+
+#[test]
+fn test_symmetry_of_samples_and_slab() {
+    // The space groups declared by the sample CIFs are found again from the expanded P1 atoms
+    let zeolite = read_sample("A_sample_inputs/1.cif");
+    let dataset = zeolite.symmetry(DEFAULT_SYMPREC).unwrap();
+    assert_eq!((dataset.number, dataset.international.as_str()), (218, "P -4 3 n"));
+    assert_eq!(dataset.operations.len(), 24);
+    for (i, &representative) in dataset.equivalent_atoms.iter().enumerate() {
+        assert_eq!(zeolite.atoms[i].element, zeolite.atoms[representative].element);
+        let orbit = dataset.equivalent_atoms.iter().filter(|&&r| r == representative).count();
+        assert_eq!(orbit, dataset.multiplicities[i]);
+    }
+
+    // An MgO (001) slab keeps the four-fold axis, and its two faces are equivalent
+    let mgo = read_sample("A_sample_inputs/mgo.cif");
+    assert_eq!(mgo.symmetry(DEFAULT_SYMPREC).unwrap().number, 225);
+    let generated = generate_surface_with_provenance(&mut mgo.clone(), &[], &slab_config([0, 0, 1], 8.0)).unwrap();
+    let slab = generated.slab.symmetry(DEFAULT_SYMPREC).unwrap();
+    assert_eq!(slab.point_group, "4/mmm");
+}